@@ -0,0 +1,82 @@
+//! Compares the generators the search can draw its random numbers from, on an MDP whose long stochastic rollouts make the search RNG-bound.
+//!
+//! Run with `cargo bench --bench rng --features xoshiro,wyrand` to include the optional generators
+
+use mct_rs::{
+    action::Action,
+    bench,
+    budget::Budget,
+    mcts::MCTS,
+    mdp::{ExplicitMDP, GenerativeModel},
+    policy::RandomRollout,
+    rand::{Rng, SplitMix64},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Left,
+    Right,
+}
+
+impl Action for Step {}
+
+/// A random walk over `0..=length` starting in the middle, where every step has a chance of slipping the other way
+struct LongWalk {
+    length: i32,
+}
+
+impl GenerativeModel<i32, Step> for LongWalk {
+    fn get_actions(&self, _state: &i32) -> Vec<Step> {
+        vec![Step::Left, Step::Right]
+    }
+
+    fn execute(&self, state: &i32, action: &Step, rng: &mut dyn Rng) -> (i32, f64, bool) {
+        self.sample_transition(state, action, rng)
+    }
+
+    fn is_terminal(&self, state: &i32) -> bool {
+        *state <= 0 || *state >= self.length
+    }
+
+    fn get_initial_state(&self) -> i32 {
+        self.length / 2
+    }
+}
+
+impl ExplicitMDP<i32, Step> for LongWalk {
+    fn get_states(&self) -> Vec<i32> {
+        (0..=self.length).collect()
+    }
+
+    fn get_transitions(&self, state: &i32, action: &Step) -> Vec<(i32, f64)> {
+        let direction = match action {
+            Step::Left => -1,
+            Step::Right => 1,
+        };
+        vec![(state + direction, 0.7), (state - direction, 0.3)]
+    }
+
+    fn get_reward(&self, _state: &i32, _action: &Step, next_state: &i32) -> f64 {
+        if *next_state == self.length { 1.0 } else { 0.0 }
+    }
+
+    fn get_goal_states(&self) -> Vec<i32> {
+        vec![self.length]
+    }
+}
+
+fn run<R: Rng + 'static>(name: &str, rng: R) {
+    let mut mcts = MCTS::new(LongWalk { length: 60 }, RandomRollout::new()).with_rng(rng);
+    let report = bench::run(&mut mcts, Budget::Time(2_000));
+    println!("rng={name} {report}");
+}
+
+fn main() {
+    run("splitmix64", SplitMix64::seed(42));
+
+    #[cfg(feature = "xoshiro")]
+    run("xoshiro256++", mct_rs::rand::Xoshiro256PlusPlus::seed(42));
+
+    #[cfg(feature = "wyrand")]
+    run("wyrand", mct_rs::rand::WyRand::seed(42));
+}
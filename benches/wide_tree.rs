@@ -0,0 +1,78 @@
+//! Measures search throughput on wide trees, where selection has to pick among hundreds of children at every level.
+//!
+//! Run with `cargo bench --bench wide_tree`
+
+use mct_rs::{
+    action::{Action, hash_action},
+    bench,
+    budget::Budget,
+    mcts::MCTS,
+    mdp::GenerativeModel,
+    policy::RandomRollout,
+    rand::Rng,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Pick(u16);
+
+impl Action for Pick {
+    fn key(&self) -> Option<u64> {
+        Some(hash_action(self))
+    }
+}
+
+/// A deterministic MDP where every state offers `width` actions and episodes end after `depth` moves
+struct WideMDP {
+    width: u16,
+    depth: u32,
+}
+
+impl GenerativeModel<(u32, u64), Pick> for WideMDP {
+    fn get_actions(&self, _state: &(u32, u64)) -> Vec<Pick> {
+        (0..self.width).map(Pick).collect()
+    }
+
+    fn is_terminal(&self, state: &(u32, u64)) -> bool {
+        state.0 >= self.depth
+    }
+
+    fn get_initial_state(&self) -> (u32, u64) {
+        (0, 0)
+    }
+
+    fn execute(
+        &self,
+        state: &(u32, u64),
+        action: &Pick,
+        _rng: &mut dyn Rng,
+    ) -> ((u32, u64), f64, bool) {
+        let next = self.next(state, action);
+        // a few of the actions are better than the others
+        let reward = if action.0.is_multiple_of(7) { 1.0 } else { 0.0 };
+        let done = self.is_terminal(&next);
+        (next, reward, done)
+    }
+}
+
+impl WideMDP {
+    fn next(&self, state: &(u32, u64), action: &Pick) -> (u32, u64) {
+        (
+            state.0 + 1,
+            state.1.wrapping_mul(31).wrapping_add(action.0 as u64),
+        )
+    }
+}
+
+fn main() {
+    const BUDGET: Budget = Budget::Time(2_000);
+
+    for width in [50, 200, 500] {
+        let mdp = WideMDP { width, depth: 4 };
+        let mut mcts = MCTS::new(mdp, RandomRollout::new())
+            .with_seed(42)
+            .with_profiling();
+
+        let report = bench::run(&mut mcts, BUDGET);
+        println!("width={width} {report}");
+    }
+}
@@ -1,4 +1,12 @@
-use mct_rs::{action::Action, mcts::MCTS, mdp::MDP, policy::RandomRollout, strategy::Strategy};
+use mct_rs::{
+    action::Action,
+    mcts::MCTS,
+    mdp::{GenerativeModel, PlayerId},
+    policy::RandomRollout,
+    rand::Rng,
+    strategy::Strategy,
+    zobrist::ZobristTable,
+};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 enum Player {
@@ -11,6 +19,8 @@ enum Player {
 pub(crate) struct TicTacToeState {
     board: [[Option<Player>; 3]; 3],
     current: Player,
+    /// Zobrist hash of the board, updated on every move
+    hash: u64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,10 +30,18 @@ pub(crate) enum TicTacToeAction {
 
 impl Action for TicTacToeAction {}
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub(crate) struct TicTacToeMDP {
-    player: Player,
-    state: TicTacToeState,
+    zobrist: ZobristTable,
+}
+
+impl Default for TicTacToeMDP {
+    fn default() -> Self {
+        Self {
+            // 9 cells, each of which can hold an O or an X
+            zobrist: ZobristTable::new(9, 2),
+        }
+    }
 }
 
 impl TicTacToeMDP {
@@ -48,17 +66,20 @@ impl TicTacToeMDP {
                 state.board[a.0][a.1],
                 state.board[b.0][b.1],
                 state.board[c.0][c.1],
-            ) {
-                if p1 == p2 && p2 == p3 {
-                    return Some(p1);
-                }
+            ) && p1 == p2
+                && p2 == p3
+            {
+                return Some(p1);
             }
         }
         None
     }
 }
 
-impl MDP<TicTacToeState, TicTacToeAction> for TicTacToeMDP {
+/// Maps a cell (row, column) to where it ends up after rotating/reflecting the board
+type Symmetry = fn(usize, usize) -> (usize, usize);
+
+impl GenerativeModel<TicTacToeState, TicTacToeAction> for TicTacToeMDP {
     fn get_initial_state(&self) -> TicTacToeState {
         TicTacToeState::default()
     }
@@ -80,6 +101,7 @@ impl MDP<TicTacToeState, TicTacToeAction> for TicTacToeMDP {
         &self,
         state: &TicTacToeState,
         action: &TicTacToeAction,
+        _rng: &mut dyn Rng,
     ) -> (TicTacToeState, f64, bool) {
         let mut new_state = state.clone();
 
@@ -93,6 +115,11 @@ impl MDP<TicTacToeState, TicTacToeAction> for TicTacToeMDP {
             Player::X => Player::O,
             Player::O => Player::X,
         };
+        new_state.hash = self.zobrist.toggle_side(self.zobrist.toggle(
+            state.hash,
+            i * 3 + j,
+            state.current as usize,
+        ));
 
         let terminal = self.is_terminal(&new_state);
         let reward = match terminal {
@@ -117,56 +144,83 @@ impl MDP<TicTacToeState, TicTacToeAction> for TicTacToeMDP {
                 .all(|row| row.iter().all(|c| c.is_some()))
     }
 
-    fn get_discount_factor(&self) -> f64 {
-        1.0
+    fn player_to_move(&self, state: &TicTacToeState) -> PlayerId {
+        state.current as PlayerId
     }
 
-    fn get_goal_states(&self) -> Vec<TicTacToeState> {
-        vec![]
+    fn state_key(&self, state: &TicTacToeState) -> Option<u64> {
+        Some(state.hash)
     }
 
-    fn get_reward(
-        &self,
-        _state: &TicTacToeState,
-        _action: &TicTacToeAction,
-        next_state: &TicTacToeState,
-    ) -> f64 {
-        match self.get_winner(next_state) {
-            Some(p) if p == self.player => 1.0, // current player wins
-            Some(_) => -1.0,                    // current player loses
-            None => 0.0,                        // draw or ongoing
-        }
-    }
+    fn canonicalize(&self, state: &TicTacToeState) -> Option<TicTacToeState> {
+        // The 8 symmetries of the board: the 4 rotations, each with and without a reflection
+        let symmetries: [Symmetry; 8] = [
+            |i, j| (i, j),
+            |i, j| (j, 2 - i),
+            |i, j| (2 - i, 2 - j),
+            |i, j| (2 - j, i),
+            |i, j| (i, 2 - j),
+            |i, j| (j, i),
+            |i, j| (2 - i, j),
+            |i, j| (2 - j, 2 - i),
+        ];
 
-    fn get_states(&self) -> Vec<TicTacToeState> {
-        vec![self.get_initial_state()]
-    }
+        // Pick the symmetry whose board, read as a base-3 number, is the smallest
+        let encode = |board: &[[Option<Player>; 3]; 3]| {
+            board
+                .iter()
+                .flatten()
+                .fold(0u32, |code, c| code * 3 + c.map_or(0, |p| p as u32 + 1))
+        };
 
-    fn get_transitions(
-        &self,
-        state: &TicTacToeState,
-        action: &TicTacToeAction,
-    ) -> Vec<(TicTacToeState, f64)> {
-        // Tic-Tac-Toe is deterministic: only one outcome per action
-        let (next_state, _, _) = self.execute(state, action);
-        vec![(next_state, 1.0)]
+        let board = symmetries
+            .iter()
+            .map(|transform| {
+                let mut board = [[None; 3]; 3];
+                for i in 0..3 {
+                    for j in 0..3 {
+                        let (x, y) = transform(i, j);
+                        board[x][y] = state.board[i][j];
+                    }
+                }
+                board
+            })
+            .min_by_key(encode)?;
+
+        let pieces =
+            (0..9).filter_map(|cell| board[cell / 3][cell % 3].map(|p| (cell, p as usize)));
+        let mut hash = self.zobrist.hash(pieces);
+        if state.current == Player::X {
+            hash = self.zobrist.toggle_side(hash);
+        }
+
+        Some(TicTacToeState {
+            board,
+            current: state.current,
+            hash,
+        })
     }
 }
 
 fn main() {
     let mdp = TicTacToeMDP::default();
-    let mut mcts = MCTS::new(mdp, RandomRollout::new());
-
-    // The player from whom's perspective we want to run MCTS
+    // The same board can be reached through different orders of moves (or be a reflection/rotation of another),
+    // so let those share statistics.
+    // A win pays 1 to whoever made the winning move; values are reported for O, who moves first,
+    // whichever player's turn it is at the root
+    let mut mcts = MCTS::new(mdp, RandomRollout::new())
+        .with_transpositions()
+        .for_player(Player::O as PlayerId);
 
     // Run MCTS for 100ms
     mcts.mcts(100);
 
-    // this would return the best possible move from the perspective of mdp.player
-    // if you need to check the best move for a different player X, change player to X in mdp, and test from it's perspective.
-    // Pick best child from root (most visits)
+    // The search always picks the best move for the player to move at the root
     let best_child = mcts
         .best_action(Strategy::Probabilistic)
         .expect("No children found");
-    println!("Best action: {:?}", best_child);
+    let value = mcts
+        .action_stats(&best_child)
+        .map_or(0.0, |stats| stats.value);
+    println!("Best action: {:?}, worth {:.2} to O", best_child, value);
 }
@@ -1,3 +1,47 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    hash::{DefaultHasher, Hash, Hasher},
+};
 
-pub trait Action: Debug + Eq + PartialEq + Clone + Copy {}
+/// An action of an MDP.
+///
+/// Actions only have to be `Clone`, so that they can carry owned data (e.g. the parameters of a move).
+/// The search clones them when it stores them in the tree, which is a plain copy for `Copy` types
+pub trait Action: Debug + Eq + PartialEq + Clone {
+    /// An optional hash of this action.
+    /// When provided, a node finds the child reached through this action with a hash lookup instead of scanning all of its children,
+    /// which matters for large branching factors.
+    ///
+    /// This is opt-in, even for actions that implement `Hash`: the default can't depend on whether they do
+    /// (that would take specialization), so it returns `None` and children are found by scanning. Such actions opt in with
+    ///
+    /// ```
+    /// use mct_rs::action::{Action, hash_action};
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    /// struct Place(u16);
+    ///
+    /// impl Action for Place {
+    ///     fn key(&self) -> Option<u64> {
+    ///         Some(hash_action(self))
+    ///     }
+    /// }
+    /// ```
+    fn key(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Actions that are plain indices, e.g. those of [`crate::tabular::TabularMDP`]
+impl Action for usize {
+    fn key(&self) -> Option<u64> {
+        Some(*self as u64)
+    }
+}
+
+/// Hashes an action with the standard library's default hasher, for use in [`Action::key`]
+pub fn hash_action<A: Hash>(action: &A) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    action.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,98 @@
+use std::{fmt::Display, time::Duration};
+
+use crate::{
+    action::Action, budget::Budget, mcts::MCTS, mdp::GenerativeModel, policy::RolloutPolicy,
+    stats::PhaseTimings,
+};
+
+/// How fast a search runs over a given MDP, see [`run`].
+///
+/// The fields of this struct are stable, so users and CI scripts can track the performance of their MDP implementations over time
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BenchReport {
+    /// Wall-clock time the search actually ran for
+    pub elapsed: Duration,
+    /// Number of select/expand/simulate/backpropagate iterations
+    pub iterations: usize,
+    pub iterations_per_sec: f64,
+    /// Number of actions executed on the MDP, by both expansions and rollouts
+    pub env_steps: usize,
+    pub env_steps_per_sec: f64,
+    /// Average number of actions executed per rollout
+    pub avg_rollout_length: f64,
+    /// Number of nodes the tree grew by during the search
+    pub nodes_added: usize,
+    /// Tree growth rate, in nodes per second
+    pub nodes_per_sec: f64,
+    /// Time spent in each phase of the search, if the searcher was built [`MCTS::with_profiling`]
+    pub phases: Option<PhaseTimings>,
+}
+
+impl Display for BenchReport {
+    /// A single line of `key=value` pairs, easy to grep out of CI logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "elapsed_ms={} iterations={} iterations_per_sec={:.1} env_steps={} env_steps_per_sec={:.1} avg_rollout_length={:.2} nodes_added={} nodes_per_sec={:.1}",
+            self.elapsed.as_millis(),
+            self.iterations,
+            self.iterations_per_sec,
+            self.env_steps,
+            self.env_steps_per_sec,
+            self.avg_rollout_length,
+            self.nodes_added,
+            self.nodes_per_sec
+        )?;
+
+        if let Some(phases) = self.phases {
+            write!(
+                f,
+                " select_ms={} expand_ms={} simulate_ms={} backpropagate_ms={}",
+                phases.select.as_millis(),
+                phases.expand.as_millis(),
+                phases.simulate.as_millis(),
+                phases.backpropagate.as_millis()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs a search for the given budget and reports how fast it went. Panics if the MDP fails
+pub fn run<M, S, A, P>(mcts: &mut MCTS<M, S, A, P>, budget: Budget) -> BenchReport
+where
+    M: GenerativeModel<S, A>,
+    A: Action,
+    S: Clone + Eq + PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    let nodes_before = mcts.tree_stats().nodes;
+
+    let start = mcts.clock().now();
+    let counters = mcts
+        .run(budget, MCTS::simulate)
+        .unwrap_or_else(|error| panic!("search failed: {error}"));
+    let elapsed = mcts.clock().now().saturating_sub(start);
+
+    let nodes_added = mcts.tree_stats().nodes.saturating_sub(nodes_before);
+    let env_steps = counters.expansions + counters.rollout_steps;
+    let per_sec = |count: usize| count as f64 / elapsed.as_secs_f64().max(f64::MIN_POSITIVE);
+
+    BenchReport {
+        elapsed,
+        iterations: counters.iterations,
+        iterations_per_sec: per_sec(counters.iterations),
+        env_steps,
+        env_steps_per_sec: per_sec(env_steps),
+        avg_rollout_length: if counters.rollouts == 0 {
+            0.0
+        } else {
+            counters.rollout_steps as f64 / counters.rollouts as f64
+        },
+        nodes_added,
+        nodes_per_sec: per_sec(nodes_added),
+        phases: counters.phases,
+    }
+}
@@ -0,0 +1,288 @@
+//! Runs searches over the built-in environments from the command line (behind the `cli` feature):
+//!
+//! ```text
+//! mct search race --iterations 5000 --seed 7
+//! mct bench chain --time-ms 200
+//! mct search race --config search.toml
+//! ```
+//!
+//! `search` prints the action picked, its statistics and the principal variation; `bench` prints the
+//! `key=value` line of [`mct_rs::bench::BenchReport`]. The budget, exploration constant, strategy and seed come from
+//! the configuration file if one is given (see [`mct_rs::config`]), then from the flags, which take precedence
+
+use std::{env, fmt::Debug, process::ExitCode};
+
+use mct_rs::{
+    action::Action,
+    bench,
+    budget::Budget,
+    builder::MctsBuilder,
+    config::SearchConfig,
+    envs::{
+        blackjack::{Blackjack, Hand},
+        cliff_walking::CliffWalking,
+        connect_four::ConnectFour,
+        frozen_lake::FrozenLake,
+        nim::Nim,
+        taxi::Taxi,
+        twenty_forty_eight::{Board, TwentyFortyEight},
+    },
+    game::{Game, GameMdp},
+    mdp::{GenerativeModel, PlayerId},
+    policy::RandomRollout,
+    rand::SplitMix64,
+    tabular::TabularMDP,
+};
+
+const USAGE: &str = "usage: mct <search|bench> <environment> [--config FILE] [--iterations N] [--time-ms N] [--seed N]
+
+environments:
+  race     two players take turns adding 1 or 2 to a total, whoever reaches 10 wins
+  chain    a corridor of 5 cells, with a small reward for going back to the start and a large one at the end
+  blackjack
+           hitting or sticking on a hand of blackjack dealt by the seed
+  cliff-walking
+           walking along the edge of a cliff, every step costing 1 and a fall 100
+  connect-four
+           dropping discs into a grid of 7 columns, whoever lines up 4 wins
+  frozen-lake, frozen-lake-8x8
+           walking across a slippery frozen lake to its goal without falling into a hole
+  2048     sliding tiles so that equal ones merge, from a start dealt by the seed
+  nim      taking objects from heaps of 3, 4 and 5, whoever takes the last one wins
+  taxi     picking a passenger up and driving them to their destination, from a start dealt by the seed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Search,
+    Bench,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Args {
+    command: Command,
+    environment: String,
+    config: Option<String>,
+    budget: Option<Budget>,
+    seed: Option<u64>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let command = match args.next().as_deref() {
+            Some("search") => Command::Search,
+            Some("bench") => Command::Bench,
+            Some(other) => return Err(format!("unknown command {other:?}")),
+            None => return Err("missing command".to_string()),
+        };
+        let environment = args.next().ok_or("missing environment")?;
+        let mut parsed = Self {
+            command,
+            environment,
+            config: None,
+            budget: None,
+            seed: None,
+        };
+
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {flag}"))?;
+            let number = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| format!("{flag} expects a number, got {value:?}"))
+            };
+            match flag.as_str() {
+                "--config" => parsed.config = Some(value.clone()),
+                "--iterations" => parsed.budget = Some(Budget::Iterations(number()? as usize)),
+                "--time-ms" => parsed.budget = Some(Budget::Time(u128::from(number()?))),
+                "--seed" => parsed.seed = Some(number()?),
+                _ => return Err(format!("unknown flag {flag}")),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Players take turns adding 1 or 2 to a total, and whoever reaches 10 wins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Race {
+    total: usize,
+    player: PlayerId,
+}
+
+impl Game for Race {
+    type Move = usize;
+
+    fn legal_moves(&self) -> Vec<usize> {
+        match self.winner() {
+            Some(_) => vec![],
+            None => vec![1, 2],
+        }
+    }
+
+    fn make_move(&mut self, step: &usize) {
+        self.total += step;
+        self.player = 1 - self.player;
+    }
+
+    fn winner(&self) -> Option<PlayerId> {
+        (self.total >= 10).then_some(1 - self.player)
+    }
+
+    fn current_player(&self) -> PlayerId {
+        self.player
+    }
+}
+
+/// A corridor of 5 cells: action 0 goes back to the first cell for a reward of 0.2,
+/// action 1 moves a cell forward, and reaching the last cell is worth 10. Discounted, so that the end is worth reaching
+fn chain() -> TabularMDP {
+    const CELLS: usize = 5;
+    let mut transitions = vec![vec![vec![0.0; CELLS]; 2]; CELLS];
+    let mut rewards = vec![vec![0.0; 2]; CELLS];
+    for cell in 0..CELLS - 1 {
+        transitions[cell][0][0] = 1.0;
+        rewards[cell][0] = 0.2;
+        transitions[cell][1][cell + 1] = 1.0;
+    }
+    rewards[CELLS - 2][1] = 10.0;
+
+    TabularMDP::new(transitions, rewards)
+        .with_terminal_states([CELLS - 1])
+        .with_discount_factor(0.9)
+}
+
+fn run<M, S, A>(args: &Args, config: &SearchConfig, mdp: M) -> Result<(), String>
+where
+    M: GenerativeModel<S, A>,
+    S: Clone + Eq,
+    A: Action,
+{
+    let mut builder = MctsBuilder::new(mdp, RandomRollout::new()).with_config(config);
+    if let Some(budget) = args.budget {
+        builder = builder.with_budget(budget);
+    }
+    if let Some(seed) = args.seed {
+        builder = builder.with_seed(seed);
+    }
+    let mut mcts = builder
+        .try_build::<S, A>()
+        .map_err(|error| error.to_string())?;
+
+    match args.command {
+        Command::Search => {
+            let result = mcts
+                .try_search()
+                .map_err(|error| format!("search failed: {error}"))?
+                .ok_or("the environment starts in a terminal state")?;
+            println!("action: {:?}", result.action);
+            println!("value: {:.4} ± {:.4}", result.value, result.std_error);
+            println!("visits: {}", result.visits);
+            println!("iterations: {}", result.iterations);
+            if let Some(elapsed) = result.elapsed {
+                println!("elapsed_ms: {}", elapsed.as_millis());
+            }
+            for (action, stats) in mcts.root_action_stats() {
+                println!("  {action:?}: {stats:?}");
+            }
+            println!("pv: {}", pv(&mcts.root().principal_variation()));
+        }
+        Command::Bench => {
+            let budget = args
+                .budget
+                .or(config.budget.map(Budget::from))
+                .unwrap_or(Budget::Iterations(10_000));
+            println!("{}", bench::run(&mut mcts, budget));
+        }
+    }
+
+    Ok(())
+}
+
+fn pv<A: Debug>(actions: &[A]) -> String {
+    actions
+        .iter()
+        .map(|action| format!("{action:?}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let config = match &args.config {
+        Some(path) => match SearchConfig::from_file(path) {
+            Ok(config) => config,
+            Err(error) => {
+                eprintln!("couldn't load {path}: {error}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => SearchConfig::default(),
+    };
+
+    let outcome = match args.environment.as_str() {
+        "race" => run(&args, &config, GameMdp::new(Race::default())),
+        "chain" => run(&args, &config, chain()),
+        "blackjack" => {
+            let hand = Hand::deal(&mut SplitMix64::seed(args.seed.unwrap_or(0)));
+            run(&args, &config, Blackjack::new(hand))
+        }
+        "cliff-walking" => run(&args, &config, CliffWalking::new()),
+        "connect-four" => run(&args, &config, GameMdp::new(ConnectFour::new())),
+        "frozen-lake" => run(&args, &config, FrozenLake::four_by_four()),
+        "frozen-lake-8x8" => run(&args, &config, FrozenLake::eight_by_eight()),
+        "2048" => {
+            let start = Board::random_start(&mut SplitMix64::seed(args.seed.unwrap_or(0)));
+            run(&args, &config, TwentyFortyEight::new(start))
+        }
+        "nim" => run(&args, &config, GameMdp::new(Nim::new([3, 4, 5]))),
+        "taxi" => {
+            let start = Taxi::random_start(&mut SplitMix64::seed(args.seed.unwrap_or(0)));
+            run(&args, &config, Taxi::new(start))
+        }
+        other => Err(format!("unknown environment {other:?}\n\n{USAGE}")),
+    };
+    match outcome {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Args, String> {
+        Args::parse(line.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn test_arguments_are_parsed() {
+        let args = parse("bench chain --iterations 500 --seed 3 --config a.toml").unwrap();
+        assert_eq!(args.command, Command::Bench);
+        assert_eq!(args.environment, "chain");
+        assert_eq!(args.budget, Some(Budget::Iterations(500)));
+        assert_eq!(args.seed, Some(3));
+        assert_eq!(args.config.as_deref(), Some("a.toml"));
+
+        let args = parse("search race --time-ms 20").unwrap();
+        assert_eq!(args.budget, Some(Budget::Time(20)));
+
+        assert!(parse("solve race").is_err());
+        assert!(parse("search").is_err());
+        assert!(parse("search race --seed").is_err());
+        assert!(parse("search race --seed x").is_err());
+        assert!(parse("search race --depth 3").is_err());
+    }
+}
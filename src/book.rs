@@ -0,0 +1,116 @@
+//! Opening books: moves known before any search, e.g. from theory or from the records of strong games.
+//!
+//! A [`Book`] is looked up for the root before every search of [`crate::mcts::MCTS::search`] (see [`crate::mcts::MCTS::with_book`]).
+//! It either knows the move to play, which is then played without searching, or what games through each move were worth,
+//! which the search starts from as if it had already taken the moves that many times
+//!
+//! ```
+//! use std::collections::HashMap;
+//!
+//! use mct_rs::{
+//!     book::BookEntry,
+//!     envs::nim::{Nim, Take},
+//!     game::GameMdp,
+//!     mcts::MCTS,
+//!     policy::RandomRollout,
+//! };
+//!
+//! let start = Nim::new([3, 4, 5]);
+//! let winning = Take { heap: 0, count: 2 };
+//! let book = HashMap::from([(start.clone(), BookEntry::Move(winning))]);
+//!
+//! let mut mcts = MCTS::new(GameMdp::new(start), RandomRollout::new()).with_book(book);
+//! let result = mcts.search().unwrap();
+//! assert_eq!(result.action, winning);
+//! assert_eq!(result.iterations, 0);
+//! ```
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::stats::ActionStats;
+
+/// What a book knows about a state, see the [module documentation](self)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BookEntry<A> {
+    /// The move to play, without searching
+    Move(A),
+    /// The moves to start the search with, each as if the search had already taken it `visits` times
+    /// for a mean return of `value`, from the perspective of the player to move
+    Stats(Vec<(A, ActionStats)>),
+}
+
+/// Knows moves of some states, see the [module documentation](self)
+pub trait Book<S, A> {
+    /// What the book knows about the state, `None` if it's out of book
+    fn lookup(&self, state: &S) -> Option<BookEntry<A>>;
+}
+
+impl<S, A, F> Book<S, A> for F
+where
+    F: Fn(&S) -> Option<BookEntry<A>>,
+{
+    fn lookup(&self, state: &S) -> Option<BookEntry<A>> {
+        self(state)
+    }
+}
+
+impl<S: Eq + Hash, A: Clone> Book<S, A> for HashMap<S, BookEntry<A>> {
+    fn lookup(&self, state: &S) -> Option<BookEntry<A>> {
+        self.get(state).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        budget::Budget,
+        envs::nim::{Nim, Take},
+        game::GameMdp,
+        mcts::MCTS,
+        policy::RandomRollout,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_book_statistics_seed_fresh_roots() {
+        let start = Nim::new([2, 3]);
+        let stats = |visits, value| ActionStats { visits, value };
+        let book = HashMap::from([(
+            start.clone(),
+            BookEntry::Stats(vec![
+                (Take { heap: 1, count: 1 }, stats(40, 0.9)),
+                (Take { heap: 0, count: 1 }, stats(10, -0.5)),
+                // no such heap
+                (Take { heap: 2, count: 1 }, stats(100, 1.0)),
+            ]),
+        )]);
+        let mut mcts = MCTS::new(GameMdp::new(start), RandomRollout::new())
+            .with_seed(1)
+            .with_budget(Budget::Iterations(0))
+            .with_book(book);
+
+        for _ in 0..2 {
+            let result = mcts.search().unwrap();
+            assert_eq!(result.action, Take { heap: 1, count: 1 });
+            assert_eq!((result.visits, result.value), (40, 0.9));
+            assert_eq!(mcts.root_action_stats().len(), 2);
+        }
+        assert_eq!(mcts.root().visits(), 50);
+    }
+
+    #[test]
+    fn test_moves_out_of_book_are_searched() {
+        let book = |nim: &Nim| {
+            let illegal = Take { heap: 0, count: 9 };
+            (nim.heaps()[0] > 0).then_some(BookEntry::Move(illegal))
+        };
+        let mut mcts = MCTS::new(GameMdp::new(Nim::new([2])), RandomRollout::new())
+            .with_seed(1)
+            .with_book(book);
+        let result = mcts.search().unwrap();
+        assert_eq!(result.action, Take { heap: 0, count: 2 });
+        assert!(result.iterations > 0);
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use crate::clock::Clock;
+
+/// How long a search is allowed to run for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Budget {
+    /// Stop after this many milliseconds
+    Time(u128),
+    /// Stop after this many iterations. Unlike a time budget, the amount of work done doesn't depend on the machine,
+    /// so seeded searches (see [`crate::mcts::MCTS::with_seed`]) with this budget are reproducible
+    Iterations(usize),
+}
+
+/// Keeps track of a running search's budget
+#[derive(Clone, Copy)]
+pub(crate) struct Tracker<'a> {
+    budget: Budget,
+    clock: &'a dyn Clock,
+    /// Only read for time budgets, so iteration-bounded searches work without a working clock
+    start_time: Duration,
+}
+
+impl<'a> Tracker<'a> {
+    pub(crate) fn start(budget: Budget, clock: &'a dyn Clock) -> Self {
+        let start_time = match budget {
+            Budget::Time(_) => clock.now(),
+            Budget::Iterations(_) => Duration::ZERO,
+        };
+
+        Self {
+            budget,
+            clock,
+            start_time,
+        }
+    }
+
+    /// Returns true once the search has used up its budget after `iterations` iterations
+    pub(crate) fn exhausted(&self, iterations: usize) -> bool {
+        match self.budget {
+            Budget::Time(_) => self.out_of_time(),
+            Budget::Iterations(max) => iterations >= max,
+        }
+    }
+
+    /// Returns true if the time budget has run out, which also cuts the ongoing rollout short
+    pub(crate) fn out_of_time(&self) -> bool {
+        match self.budget {
+            Budget::Time(timeout) => {
+                self.clock.now().saturating_sub(self.start_time).as_millis() >= timeout
+            }
+            Budget::Iterations(_) => false,
+        }
+    }
+}
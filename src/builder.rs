@@ -0,0 +1,194 @@
+//! Step-by-step configuration of a [`MCTS`] searcher, so that new options don't change the signature of [`MCTS::new`]
+
+use crate::{
+    action::Action,
+    budget::Budget,
+    error::McstError,
+    mcts::MCTS,
+    mdp::GenerativeModel,
+    observer::Observer,
+    policy::RolloutPolicy,
+    rand::{Rng, SplitMix64},
+    strategy::Strategy,
+    ucb1::UCB1,
+};
+
+/// How the searcher stores the states it reaches.
+///
+/// A plain tree is the cheapest to grow, but searches a state once for every order of actions that reaches it.
+/// Transpositions pool those searches, at the cost of a lookup for every new node and of a key for every state,
+/// and only pay off when the MDP does reach states through different paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TreeBackend {
+    /// Every path gets its own nodes, even when it reaches a state another path already reached.
+    /// The only choice for MDPs without [`GenerativeModel::state_key`]
+    #[default]
+    Tree,
+    /// States reached through different orders of actions share their node, see [`MCTS::with_transpositions`]
+    Transpositions,
+}
+
+/// Collects the options of a searcher, and builds it with [`MctsBuilder::build`].
+/// Every option defaults to the value [`MCTS::new`] uses
+///
+/// ```
+/// use mct_rs::{budget::Budget, builder::TreeBackend, mcts::MCTS, mdp::FnMdp, policy::RandomRollout, rand::SplitMix64};
+///
+/// let mdp = FnMdp::new(
+///     || 0u32,
+///     |_state: &u32| vec![1usize, 2],
+///     |state, step, _rng| (state + *step as u32, *step as f64, state + *step as u32 >= 10),
+///     |state| *state >= 10,
+/// );
+///
+/// let mut mcts = MCTS::builder(mdp, RandomRollout::new())
+///     .with_exploration_constant(10.0)
+///     .with_budget(Budget::Iterations(200))
+///     // same as `.with_seed(1)`: any generator works, e.g. a faster one from the `xoshiro` or `wyrand` features
+///     .with_rng(SplitMix64::seed(1))
+///     .with_backend(TreeBackend::Transpositions)
+///     .build();
+/// assert!(mcts.search().is_some());
+/// ```
+pub struct MctsBuilder<M, P> {
+    mdp: M,
+    policy: P,
+    exploration: f64,
+    budget: Budget,
+    strategy: Strategy,
+    max_rollout_depth: Option<usize>,
+    robust_child: Option<(usize, Strategy)>,
+    rng: Option<Box<dyn Rng>>,
+    backend: TreeBackend,
+    chance_nodes: bool,
+    observers: Vec<Box<dyn Observer>>,
+}
+
+impl<M, P> MctsBuilder<M, P> {
+    /// Starts from the options [`MCTS::new`] uses, e.g. a budget of 1000 iterations and no seed.
+    /// Same as [`MCTS::builder`], which reads better at the head of a chain
+    pub fn new(mdp: M, policy: P) -> Self {
+        Self {
+            mdp,
+            policy,
+            exploration: UCB1::C,
+            budget: Budget::Iterations(1000),
+            strategy: Strategy::default(),
+            max_rollout_depth: None,
+            robust_child: None,
+            rng: None,
+            backend: TreeBackend::default(),
+            chance_nodes: false,
+            observers: vec![],
+        }
+    }
+
+    /// See [`MCTS::with_exploration_constant`]
+    pub fn with_exploration_constant(mut self, c: f64) -> Self {
+        self.exploration = c;
+        self
+    }
+
+    /// See [`MCTS::with_budget`]
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// See [`MCTS::with_strategy`]
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// See [`MCTS::with_robust_child`]
+    pub fn with_robust_child(mut self, extension: usize, fallback: Strategy) -> Self {
+        self.robust_child = Some((extension, fallback));
+        self
+    }
+
+    /// See [`MCTS::with_max_rollout_depth`]
+    pub fn with_max_rollout_depth(mut self, depth: usize) -> Self {
+        self.max_rollout_depth = Some(depth);
+        self
+    }
+
+    /// See [`MCTS::with_rng`]. Without a generator or a seed, the searcher draws from a [`SplitMix64`] seeded from the system's entropy
+    pub fn with_rng<R: Rng + 'static>(mut self, rng: R) -> Self {
+        self.rng = Some(Box::new(rng));
+        self
+    }
+
+    /// See [`MCTS::with_seed`]. Replaces the generator given to [`MctsBuilder::with_rng`], as that one would replace the seed
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(SplitMix64::seed(seed))
+    }
+
+    /// Chooses between a plain tree and one that shares the nodes of transposed states, see [`TreeBackend`] for the trade-offs.
+    /// Defaults to [`TreeBackend::Tree`]
+    pub fn with_backend(mut self, backend: TreeBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// See [`MCTS::with_chance_nodes`]
+    pub fn with_chance_nodes(mut self) -> Self {
+        self.chance_nodes = true;
+        self
+    }
+
+    /// See [`MCTS::with_observer`]
+    pub fn with_observer<O: Observer + 'static>(mut self, observer: O) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Panics if the searcher isn't seeded and the operating system's entropy source fails, see [`MctsBuilder::try_build`]
+    pub fn build<S, A>(self) -> MCTS<M, S, A, P>
+    where
+        M: GenerativeModel<S, A>,
+        A: Action,
+        S: Clone + Eq,
+        P: RolloutPolicy<M, S, A>,
+    {
+        self.try_build()
+            .unwrap_or_else(|error| panic!("couldn't build the searcher: {error}"))
+    }
+
+    /// Same as [`MctsBuilder::build`], but reports a failure of the entropy source rather than panicking
+    pub fn try_build<S, A>(self) -> Result<MCTS<M, S, A, P>, McstError>
+    where
+        M: GenerativeModel<S, A>,
+        A: Action,
+        S: Clone + Eq,
+        P: RolloutPolicy<M, S, A>,
+    {
+        let rng: Box<dyn Rng> = match self.rng {
+            Some(rng) => rng,
+            None => Box::new(SplitMix64::try_from_entropy()?),
+        };
+        let mut mcts = MCTS::with_generator(self.mdp, self.policy, rng)
+            .with_exploration_constant(self.exploration)
+            .with_budget(self.budget)
+            .with_strategy(self.strategy);
+
+        if let Some(depth) = self.max_rollout_depth {
+            mcts = mcts.with_max_rollout_depth(depth);
+        }
+        if let Some((extension, fallback)) = self.robust_child {
+            mcts = mcts.with_robust_child(extension, fallback);
+        }
+        if self.backend == TreeBackend::Transpositions {
+            mcts = mcts.with_transpositions();
+        }
+        if self.chance_nodes {
+            mcts = mcts.with_chance_nodes();
+        }
+
+        for observer in self.observers {
+            mcts.observers.get_mut().push(observer);
+        }
+
+        Ok(mcts)
+    }
+}
@@ -0,0 +1,32 @@
+//! Where time-bounded searches (and profiling) read the time from.
+//!
+//! `std::time::Instant::now` panics on `wasm32-unknown-unknown`, so searches there need another source of time,
+//! e.g. `performance.now()` through `web-sys`, handed to [`crate::mcts::MCTS::with_clock`].
+//! Searches bounded by a number of iterations never read the clock, so they work on any target without one
+
+use std::{sync::OnceLock, time::Duration};
+
+/// A monotonic source of time
+pub trait Clock {
+    /// Time elapsed since some fixed point. Only the difference between two readings is ever used, so that point can be anything
+    fn now(&self) -> Duration;
+}
+
+/// Any closure returning the current time is a clock, e.g. `|| Duration::from_secs_f64(performance.now() / 1000.0)`
+impl<F: Fn() -> Duration> Clock for F {
+    fn now(&self) -> Duration {
+        self()
+    }
+}
+
+/// The standard library's monotonic clock, the default of every searcher.
+/// Not available on `wasm32-unknown-unknown`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        static ORIGIN: OnceLock<std::time::Instant> = OnceLock::new();
+        ORIGIN.get_or_init(std::time::Instant::now).elapsed()
+    }
+}
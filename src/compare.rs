@@ -0,0 +1,497 @@
+//! A harness comparing planners on the same MDPs: each of them plays the same number of episodes of every MDP of a suite,
+//! deciding every action with the same budget, and is scored by its average return, its decision time,
+//! and its regret against the optimal value where that's known (e.g. by value iteration, see [`Case::solved`]).
+//!
+//! The environment draws its randomness from a generator seeded the same for every planner, so all of them face
+//! the same luck as far as their actions allow. The planners keep their own generators, see their `with_seed`
+//!
+//! ```
+//! use mct_rs::{
+//!     budget::Budget,
+//!     compare::{Case, Comparison, FlatMonteCarlo},
+//!     mcts::MCTS,
+//!     policy::RandomRollout,
+//!     tabular::TabularMDP,
+//! };
+//!
+//! // action 0 ends the episode with 1, action 1 pays 2 but only ends it half of the time
+//! let mdp = TabularMDP::new(
+//!     vec![vec![vec![0.0, 1.0], vec![0.5, 0.5]], vec![vec![0.0, 1.0], vec![0.0, 0.0]]],
+//!     vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+//! )
+//! .with_terminal_states([1]);
+//!
+//! let reports = Comparison::new(Budget::Iterations(100))
+//!     .with_episodes(20)
+//!     .with_seed(1)
+//!     .with_planner("uct", |mdp| MCTS::new(mdp, RandomRollout::new()).with_seed(1))
+//!     .with_planner("flat", |mdp| FlatMonteCarlo::new(mdp, RandomRollout::new()).with_seed(1))
+//!     .run(&[Case::solved("gamble", &mdp)]);
+//!
+//! for report in &reports {
+//!     println!("{report}");
+//! }
+//! assert_eq!(reports.len(), 2);
+//! ```
+
+use std::{fmt::Display, hash::Hash, rc::Rc, time::Duration};
+
+use crate::{
+    action::Action,
+    budget::{Budget, Tracker},
+    clock::{Clock, SystemClock},
+    error::{McstError, MdpError},
+    mcts::MCTS,
+    mdp::{ExplicitMDP, GenerativeModel, checked_execute},
+    policy::RolloutPolicy,
+    rand::{Rng, SplitMix64},
+    rtdp::RTDP,
+    solvers::value_iteration::{Solution, ValueIteration},
+    stats::ActionStats,
+};
+
+/// Anything that picks actions: the searchers of this crate, or a policy computed ahead of time
+pub trait Planner<S, A> {
+    /// Picks the action to take in the state, planning within the budget. None if there's none to take there
+    fn plan(&mut self, state: &S, budget: Budget) -> Result<Option<A>, McstError>;
+
+    /// What the last plan found about the actions it weighed, for planners that search (see [`MCTS::root_action_stats`]).
+    /// Empty by default
+    fn root_stats(&self) -> Vec<(A, ActionStats)> {
+        vec![]
+    }
+}
+
+impl<M, S, A, P> Planner<S, A> for MCTS<M, S, A, P>
+where
+    M: GenerativeModel<S, A>,
+    A: Action,
+    S: Clone + Eq + PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    fn plan(&mut self, state: &S, budget: Budget) -> Result<Option<A>, McstError> {
+        match self.try_search_from(state, budget) {
+            Ok(result) => Ok(result.map(|result| result.action)),
+            Err(McstError::TerminalRoot { .. } | McstError::NoLegalActions) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn root_stats(&self) -> Vec<(A, ActionStats)> {
+        self.root_action_stats()
+    }
+}
+
+impl<M, S, A> Planner<S, A> for RTDP<M, S, A>
+where
+    M: GenerativeModel<S, A>,
+    S: Clone + Eq + Hash,
+    A: Action,
+{
+    fn plan(&mut self, state: &S, budget: Budget) -> Result<Option<A>, McstError> {
+        self.set_root_state(state.clone());
+        match self.try_search(budget) {
+            Ok(()) => Ok(self.best_action()),
+            Err(McstError::TerminalRoot { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Plays the solution's policy, without planning
+impl<S, A> Planner<S, A> for Solution<S, A>
+where
+    S: Eq + Hash,
+    A: Clone,
+{
+    fn plan(&mut self, state: &S, _budget: Budget) -> Result<Option<A>, McstError> {
+        Ok(self.action(state).cloned())
+    }
+}
+
+/// Flat Monte Carlo: tries the actions of the current state in turn, one rollout per iteration,
+/// and picks the one with the best mean return. No tree, so a baseline for what the tree adds
+pub struct FlatMonteCarlo<M, P> {
+    mdp: M,
+    policy: P,
+    max_rollout_depth: usize,
+    clock: Rc<dyn Clock>,
+    rng: Box<dyn Rng>,
+}
+
+impl<M, P> FlatMonteCarlo<M, P> {
+    /// Rollouts are cut short after 1000 actions
+    pub fn new(mdp: M, policy: P) -> Self {
+        Self {
+            mdp,
+            policy,
+            max_rollout_depth: 1000,
+            clock: Rc::new(SystemClock),
+            rng: Box::new(SplitMix64::from_entropy()),
+        }
+    }
+
+    /// Draws all the randomness of the planner from the given generator, see [`crate::mcts::MCTS::with_rng`]
+    pub fn with_rng<R: Rng + 'static>(mut self, rng: R) -> Self {
+        self.rng = Box::new(rng);
+        self
+    }
+
+    /// Makes the planner deterministic, see [`crate::mcts::MCTS::with_seed`]
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(SplitMix64::seed(seed))
+    }
+
+    /// Reads the time from the given clock, for time budgets, see [`crate::mcts::MCTS::with_clock`]
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Rc::new(clock);
+        self
+    }
+
+    pub fn with_max_rollout_depth(mut self, depth: usize) -> Self {
+        self.max_rollout_depth = depth;
+        self
+    }
+}
+
+impl<M, S, A, P> Planner<S, A> for FlatMonteCarlo<M, P>
+where
+    M: GenerativeModel<S, A>,
+    S: Clone,
+    A: Action,
+    P: RolloutPolicy<M, S, A>,
+{
+    fn plan(&mut self, state: &S, budget: Budget) -> Result<Option<A>, McstError> {
+        if self.mdp.is_terminal(state) {
+            return Ok(None);
+        }
+        let actions = self.mdp.get_actions(state);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions.into());
+        }
+
+        let discount = self.mdp.get_discount_factor();
+        let tracker = Tracker::start(budget, &*self.clock);
+        let rng = &mut *self.rng;
+        // (total return, rollouts) of every action
+        let mut totals = vec![(0.0, 0); actions.len()];
+        let mut iterations = 0;
+        while !tracker.exhausted(iterations) {
+            let i = iterations % actions.len();
+            iterations += 1;
+
+            let (mut state, reward, mut done) =
+                checked_execute(&self.mdp, state, &actions[i], rng)?;
+            let mut ret = reward;
+            let mut factor = discount;
+            let mut depth = 1;
+            while !done && !self.mdp.is_terminal(&state) && depth < self.max_rollout_depth {
+                let actions = self.mdp.get_actions(&state);
+                if actions.is_empty() {
+                    return Err(MdpError::NoActions.into());
+                }
+                let action = self.policy.pick(&state, &actions, rng);
+                let (next, reward, end) = checked_execute(&self.mdp, &state, &action, rng)?;
+                ret += factor * reward;
+                factor *= discount;
+                (state, done) = (next, end);
+                depth += 1;
+            }
+
+            totals[i].0 += ret;
+            totals[i].1 += 1;
+        }
+
+        let best = totals
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, count))| *count > 0)
+            .map(|(i, (total, count))| (i, total / *count as f64))
+            .fold(None, |best: Option<(usize, f64)>, (i, mean)| match best {
+                Some(best) if best.1 >= mean => Some(best),
+                _ => Some((i, mean)),
+            });
+        Ok(best.map(|(i, _)| actions[i].clone()))
+    }
+}
+
+/// An MDP of the suite, with its optimal value from the initial state if it's known
+#[derive(Debug, Clone, Copy)]
+pub struct Case<'a, M> {
+    pub name: &'a str,
+    pub mdp: &'a M,
+    pub optimal: Option<f64>,
+}
+
+impl<'a, M> Case<'a, M> {
+    pub fn new(name: &'a str, mdp: &'a M) -> Self {
+        Self {
+            name,
+            mdp,
+            optimal: None,
+        }
+    }
+
+    /// The optimal expected return from the initial state, for the regret of the planners
+    pub fn with_optimal_value(mut self, value: f64) -> Self {
+        self.optimal = Some(value);
+        self
+    }
+
+    /// Same as [`Case::new`], with the optimal value found by [`ValueIteration`]
+    pub fn solved<S, A>(name: &'a str, mdp: &'a M) -> Self
+    where
+        M: ExplicitMDP<S, A>,
+        S: Clone + Eq + Hash,
+        A: Clone,
+    {
+        let optimal = ValueIteration::new()
+            .solve(mdp)
+            .value(&mdp.get_initial_state());
+        Self { name, mdp, optimal }
+    }
+}
+
+/// How a planner did on an MDP, see [`Comparison::run`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Report {
+    pub planner: String,
+    /// The name of the MDP
+    pub case: String,
+    pub episodes: usize,
+    /// The mean discounted return of the episodes
+    pub mean_return: f64,
+    /// The standard error of `mean_return`
+    pub std_error: f64,
+    /// The mean time the planner took to pick an action
+    pub mean_decision_time: Duration,
+    /// The number of actions taken over all the episodes
+    pub decisions: usize,
+    /// How much less than the optimal value the planner got on average, if the case knows it
+    pub regret: Option<f64>,
+}
+
+impl Display for Report {
+    /// A single line of `key=value` pairs, like [`crate::bench::BenchReport`]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "case={} planner={} episodes={} mean_return={:.4} std_error={:.4} decision_us={} decisions={}",
+            self.case,
+            self.planner,
+            self.episodes,
+            self.mean_return,
+            self.std_error,
+            self.mean_decision_time.as_micros(),
+            self.decisions,
+        )?;
+        if let Some(regret) = self.regret {
+            write!(f, " regret={regret:.4}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds a fresh planner for every MDP of the suite
+type PlannerFactory<'a, M, S, A> = dyn Fn(&'a M) -> Box<dyn Planner<S, A> + 'a> + 'a;
+
+/// A planner of the comparison, by name
+type NamedPlanner<'a, M, S, A> = (String, Box<PlannerFactory<'a, M, S, A>>);
+
+/// Compares planners over a suite of MDPs, see the [module documentation](self)
+pub struct Comparison<'a, M, S, A> {
+    planners: Vec<NamedPlanner<'a, M, S, A>>,
+    budget: Budget,
+    episodes: usize,
+    max_episode_length: usize,
+    seed: u64,
+    clock: Rc<dyn Clock>,
+}
+
+impl<'a, M, S, A> Comparison<'a, M, S, A>
+where
+    M: GenerativeModel<S, A>,
+    S: Clone,
+    A: Action,
+{
+    /// Every planner gets this budget for every decision. Plays 100 episodes of every MDP, of at most 1000 actions
+    pub fn new(budget: Budget) -> Self {
+        Self {
+            planners: vec![],
+            budget,
+            episodes: 100,
+            max_episode_length: 1000,
+            seed: 0,
+            clock: Rc::new(SystemClock),
+        }
+    }
+
+    /// Adds a planner, built by `create` for every MDP of the suite
+    pub fn with_planner<P, F>(mut self, name: &str, create: F) -> Self
+    where
+        P: Planner<S, A> + 'a,
+        F: Fn(&'a M) -> P + 'a,
+    {
+        let create = move |mdp: &'a M| Box::new(create(mdp)) as Box<dyn Planner<S, A> + 'a>;
+        self.planners.push((name.to_string(), Box::new(create)));
+        self
+    }
+
+    pub fn with_episodes(mut self, episodes: usize) -> Self {
+        self.episodes = episodes;
+        self
+    }
+
+    /// Ends episodes after this many actions, for MDPs whose episodes are long or endless
+    pub fn with_max_episode_length(mut self, actions: usize) -> Self {
+        self.max_episode_length = actions;
+        self
+    }
+
+    /// Seeds the environment's generator, the same for every planner
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Reads the time from the given clock, to time the decisions, see [`crate::mcts::MCTS::with_clock`]
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Rc::new(clock);
+        self
+    }
+
+    /// Plays every planner on every MDP, and reports on them in that order (all the planners of the first MDP first).
+    ///
+    /// Panics if an MDP fails, see [`Comparison::try_run`]
+    pub fn run(&self, suite: &[Case<'a, M>]) -> Vec<Report> {
+        self.try_run(suite)
+            .unwrap_or_else(|error| panic!("comparison failed: {error}"))
+    }
+
+    /// Same as [`Comparison::run`], but stops at the first failure of an MDP and returns it
+    pub fn try_run(&self, suite: &[Case<'a, M>]) -> Result<Vec<Report>, McstError> {
+        let mut reports = vec![];
+        for case in suite {
+            for (name, create) in &self.planners {
+                let mut planner = create(case.mdp);
+                let mut report = self.evaluate(case.mdp, &mut *planner)?;
+                report.planner = name.clone();
+                report.case = case.name.to_string();
+                report.regret = case.optimal.map(|optimal| optimal - report.mean_return);
+                reports.push(report);
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Plays the episodes of an MDP with a planner
+    fn evaluate(&self, mdp: &M, planner: &mut dyn Planner<S, A>) -> Result<Report, McstError> {
+        let mut rng = SplitMix64::seed(self.seed);
+        let discount = mdp.get_discount_factor();
+        let mut returns = Vec::with_capacity(self.episodes);
+        let mut decisions = 0;
+        let mut thinking = Duration::ZERO;
+
+        for _ in 0..self.episodes {
+            let mut state = mdp.get_initial_state();
+            let mut ret = 0.0;
+            let mut factor = 1.0;
+            for _ in 0..self.max_episode_length {
+                if mdp.is_terminal(&state) {
+                    break;
+                }
+                let start = self.clock.now();
+                let action = planner.plan(&state, self.budget)?;
+                thinking += self.clock.now().saturating_sub(start);
+                let Some(action) = action else {
+                    break;
+                };
+                decisions += 1;
+
+                let (next, reward, done) = checked_execute(mdp, &state, &action, &mut rng)?;
+                ret += factor * reward;
+                factor *= discount;
+                if done {
+                    break;
+                }
+                state = next;
+            }
+            returns.push(ret);
+        }
+
+        let n = returns.len().max(1) as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+        Ok(Report {
+            planner: String::new(),
+            case: String::new(),
+            episodes: self.episodes,
+            mean_return: mean,
+            std_error: (variance / n).sqrt(),
+            mean_decision_time: thinking / decisions.max(1) as u32,
+            decisions,
+            regret: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{policy::RandomRollout, tabular::TabularMDP};
+
+    use super::*;
+
+    #[test]
+    fn test_planners_are_compared_on_the_same_episodes() {
+        // a chain 0 -> 1 -> 2 -> 3, where action 0 walks right for nothing and action 1 ends the episode at once,
+        // paying the index of the state it's taken in. Discounted, the best is to walk to 2 and stop there: V*(0) = 0.72
+        let transitions = (0..4)
+            .map(|s| {
+                let right = (0..4)
+                    .map(|t| f64::from(u8::from(t == (s + 1).min(3))))
+                    .collect();
+                let stop = (0..4).map(|t| f64::from(u8::from(t == 3))).collect();
+                if s == 3 {
+                    vec![vec![0.0; 4], vec![0.0; 4]]
+                } else {
+                    vec![right, stop]
+                }
+            })
+            .collect();
+        let rewards = (0..4).map(|s| vec![0.0, s as f64]).collect();
+        let mdp = TabularMDP::new(transitions, rewards)
+            .with_terminal_states([3])
+            .with_discount_factor(0.6);
+
+        let reports = Comparison::new(Budget::Iterations(200))
+            .with_episodes(5)
+            .with_seed(3)
+            .with_planner("uct", |mdp| {
+                MCTS::new(mdp, RandomRollout::new()).with_seed(1)
+            })
+            .with_planner("flat", |mdp| {
+                FlatMonteCarlo::new(mdp, RandomRollout::new()).with_seed(1)
+            })
+            .with_planner("rtdp", |mdp: &TabularMDP| {
+                RTDP::explicit(mdp.clone())
+                    .with_seed(1)
+                    .with_heuristic(|_: &usize| 3.0)
+            })
+            .with_planner("vi", |mdp| ValueIteration::new().solve(mdp))
+            .run(&[Case::solved("chain", &mdp)]);
+
+        assert_eq!(reports.len(), 4);
+        for report in [&reports[0], &reports[2], &reports[3]] {
+            assert_eq!(report.case, "chain");
+            assert_eq!(report.decisions, 15);
+            // deterministic, so every planner that finds the best policy has no regret
+            assert!(report.regret.unwrap().abs() < 1e-9, "{report}");
+        }
+        // random rollouts undervalue walking on, so flat Monte Carlo stops at 1
+        assert_eq!(reports[1].decisions, 10);
+        assert!((reports[1].regret.unwrap() - 0.12).abs() < 1e-9);
+        assert_eq!(reports[3].planner, "vi");
+        assert!(reports[0].to_string().contains("regret=0.0000"));
+    }
+}
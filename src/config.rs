@@ -0,0 +1,232 @@
+//! Search configurations loaded from TOML or JSON (behind the `config` feature),
+//! so that deployments can tune a searcher without recompiling it.
+//!
+//! Every setting is optional, and the ones left out keep the builder's values:
+//!
+//! ```toml
+//! exploration_constant = 2.0
+//! max_rollout_depth = 50
+//! seed = 7
+//! threads = 4
+//! budget = { iterations = 5000 }   # or { time_ms = 100 }
+//!
+//! [strategy]
+//! name = "robust_child"            # most_visited, highest_q_value, probabilistic, heuristic_win or robust_child
+//! extension = 1000                 # robust_child only, see `MCTS::with_robust_child`
+//! fallback = "highest_q_value"
+//! ```
+
+use std::{error::Error, fmt::Display, path::Path};
+
+use serde::Deserialize;
+
+use crate::{budget::Budget, builder::MctsBuilder, strategy::Strategy};
+
+/// The settings of a searcher, see the [module documentation](self) for the format
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    pub budget: Option<BudgetConfig>,
+    pub exploration_constant: Option<f64>,
+    pub strategy: Option<StrategyConfig>,
+    pub max_rollout_depth: Option<usize>,
+    pub seed: Option<u64>,
+    /// The searchers of this crate are single-threaded, so this is left to the caller,
+    /// e.g. to run that many independent searchers and combine their statistics (root parallelization)
+    pub threads: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum BudgetConfig {
+    Iterations(usize),
+    TimeMs(u64),
+}
+
+impl From<BudgetConfig> for Budget {
+    fn from(budget: BudgetConfig) -> Self {
+        match budget {
+            BudgetConfig::Iterations(iterations) => Budget::Iterations(iterations),
+            BudgetConfig::TimeMs(millis) => Budget::Time(u128::from(millis)),
+        }
+    }
+}
+
+/// The final-move strategy, and the parameters of the robust child
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[serde(deny_unknown_fields)]
+pub struct StrategyConfig {
+    pub name: Strategy,
+    /// See [`crate::mcts::MCTS::with_robust_child`]
+    #[serde(default)]
+    pub extension: usize,
+    #[serde(default)]
+    pub fallback: Strategy,
+}
+
+/// Why a configuration couldn't be loaded
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    /// The file's extension is neither `.toml` nor `.json`
+    UnknownFormat,
+    /// The configuration parsed, but doesn't make sense
+    Invalid(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "couldn't read the configuration: {error}"),
+            Self::Toml(error) => write!(f, "invalid TOML configuration: {error}"),
+            Self::Json(error) => write!(f, "invalid JSON configuration: {error}"),
+            Self::UnknownFormat => write!(f, "configuration files must end in .toml or .json"),
+            Self::Invalid(message) => write!(f, "invalid configuration: {message}"),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Toml(error) => Some(error),
+            Self::Json(error) => Some(error),
+            Self::UnknownFormat | Self::Invalid(_) => None,
+        }
+    }
+}
+
+impl SearchConfig {
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        toml::from_str::<Self>(text)
+            .map_err(ConfigError::Toml)?
+            .validated()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, ConfigError> {
+        serde_json::from_str::<Self>(text)
+            .map_err(ConfigError::Json)?
+            .validated()
+    }
+
+    /// Reads a `.toml` or `.json` file, going by its extension
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&text),
+            Some("json") => Self::from_json(&text),
+            _ => Err(ConfigError::UnknownFormat),
+        }
+    }
+
+    /// Catches the settings the builder would otherwise panic on
+    fn validated(self) -> Result<Self, ConfigError> {
+        if let Some(strategy) = &self.strategy
+            && strategy.fallback == Strategy::RobustChild
+        {
+            return Err(ConfigError::Invalid(
+                "the robust child can't be its own fallback".to_string(),
+            ));
+        }
+
+        if self.threads == Some(0) {
+            return Err(ConfigError::Invalid(
+                "threads must be at least 1".to_string(),
+            ));
+        }
+
+        Ok(self)
+    }
+}
+
+impl<M, P> MctsBuilder<M, P> {
+    /// Applies every setting of the configuration, keeping the current value of the others
+    pub fn with_config(mut self, config: &SearchConfig) -> Self {
+        if let Some(budget) = config.budget {
+            self = self.with_budget(budget.into());
+        }
+        if let Some(c) = config.exploration_constant {
+            self = self.with_exploration_constant(c);
+        }
+        if let Some(strategy) = config.strategy {
+            self = self.with_strategy(strategy.name);
+            if strategy.name == Strategy::RobustChild {
+                self = self.with_robust_child(strategy.extension, strategy.fallback);
+            }
+        }
+        if let Some(depth) = config.max_rollout_depth {
+            self = self.with_max_rollout_depth(depth);
+        }
+        if let Some(seed) = config.seed {
+            self = self.with_seed(seed);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+
+    use super::*;
+
+    #[test]
+    fn test_toml_and_json_configure_the_builder() {
+        let toml = r#"
+            exploration_constant = 2.0
+            seed = 7
+            threads = 4
+            budget = { iterations = 50 }
+
+            [strategy]
+            name = "robust_child"
+            extension = 100
+            fallback = "highest_q_value"
+        "#;
+        let json = r#"{
+            "exploration_constant": 2.0,
+            "seed": 7,
+            "threads": 4,
+            "budget": { "iterations": 50 },
+            "strategy": { "name": "robust_child", "extension": 100, "fallback": "highest_q_value" }
+        }"#;
+
+        let config = SearchConfig::from_toml(toml).unwrap();
+        assert_eq!(config, SearchConfig::from_json(json).unwrap());
+        assert_eq!(
+            config.budget.map(Budget::from),
+            Some(Budget::Iterations(50))
+        );
+        assert_eq!(config.threads, Some(4));
+
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_state: &u32| vec![1usize, 2],
+            |state, step, _rng| (state + *step as u32, 0.0, state + *step as u32 >= 10),
+            |state| *state >= 10,
+        );
+        let mut mcts = MCTS::builder(mdp, RandomRollout::new())
+            .with_config(&config)
+            .build();
+        assert!(mcts.search().unwrap().iterations >= 50);
+
+        assert!(matches!(
+            SearchConfig::from_toml("seed = 1\nthreds = 2"),
+            Err(ConfigError::Toml(_))
+        ));
+        assert!(matches!(
+            SearchConfig::from_json(
+                r#"{ "strategy": { "name": "robust_child", "fallback": "robust_child" } }"#
+            ),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+}
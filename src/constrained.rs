@@ -0,0 +1,586 @@
+//! Constrained MCTS, for MDPs that incur costs alongside their rewards, where the expected total of every cost has to stay within a budget
+//! (e.g. the risk of collision of a robot).
+//!
+//! The search maximizes the Lagrangian `reward - λ·costs` (Lee et al., 2018): the multipliers `λ` start at 0 and, after every iteration,
+//! grow for the costs the current best root action is expected to exceed the budgets of, and shrink for the others (down to 0).
+//! Costs whose budgets are met end up ignored, while the others weigh in just enough to keep them within budget
+
+use crate::{
+    action::Action,
+    budget::{Budget, Tracker},
+    error::{McstError, MdpError},
+    mdp::GenerativeModel,
+    policy::RolloutPolicy,
+    rand::Rng,
+    setup::{Configurable, SearchSetup},
+    ucb1::UCB1,
+};
+
+/// A single-agent MDP whose transitions also incur costs, each of which has its own budget
+pub trait ConstrainedMDP<S, A>: GenerativeModel<S, A> {
+    /// Number of costs, i.e. the length of every cost vector
+    fn costs(&self) -> usize;
+
+    /// Same as [`GenerativeModel::try_execute`], but also returns the costs incurred by the transition
+    fn execute_with_costs(
+        &self,
+        state: &S,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, f64, Vec<f64>, bool), MdpError>;
+}
+
+#[derive(Debug)]
+struct ConstrainedNode<S, A> {
+    state: S,
+    visits: usize,
+    edges: Vec<ConstrainedEdge<S, A>>,
+}
+
+#[derive(Debug)]
+struct ConstrainedEdge<S, A> {
+    action: A,
+    visits: usize,
+    /// Total discounted return and total discounted costs, transitions included
+    score: f64,
+    total_costs: Vec<f64>,
+    /// One child per state the action was seen to lead to, as the transition is sampled anew on every visit
+    outcomes: Vec<ConstrainedNode<S, A>>,
+}
+
+impl<S, A> ConstrainedEdge<S, A> {
+    fn q_value(&self) -> f64 {
+        self.score / self.visits.max(1) as f64
+    }
+
+    /// The expected total of every cost
+    fn mean_costs(&self) -> Vec<f64> {
+        let visits = self.visits.max(1) as f64;
+        self.total_costs
+            .iter()
+            .map(|total| total / visits)
+            .collect()
+    }
+
+    /// The mean of `reward - λ·costs`
+    fn lagrangian(&self, multipliers: &[f64]) -> f64 {
+        let penalty = self
+            .mean_costs()
+            .iter()
+            .zip(multipliers)
+            .map(|(cost, lambda)| cost * lambda)
+            .sum::<f64>();
+        self.q_value() - penalty
+    }
+}
+
+impl<S, A> ConstrainedNode<S, A>
+where
+    S: Clone + PartialEq,
+    A: Action,
+{
+    fn new(state: S) -> Self {
+        Self {
+            state,
+            visits: 0,
+            edges: vec![],
+        }
+    }
+
+    /// Runs one iteration below this node and returns the discounted return and the discounted total of every cost.
+    /// Descends the tree, sampling the transition of every action it takes, until the first untried action or outcome,
+    /// which is followed by a rollout
+    fn iterate<M, P>(
+        &mut self,
+        mdp: &M,
+        policy: &P,
+        multipliers: &[f64],
+        tracker: &Tracker<'_>,
+        rng: &mut dyn Rng,
+    ) -> Result<(f64, Vec<f64>), MdpError>
+    where
+        M: ConstrainedMDP<S, A>,
+        P: RolloutPolicy<M, S, A>,
+    {
+        self.visits += 1;
+        if mdp.is_terminal(&self.state) {
+            return Ok((0.0, vec![0.0; mdp.costs()]));
+        }
+
+        let actions = mdp.get_actions(&self.state);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions);
+        }
+
+        let untried = actions
+            .into_iter()
+            .filter(|a| self.edges.iter().all(|edge| edge.action != *a))
+            .collect::<Vec<_>>();
+
+        let index = match untried.is_empty() {
+            true => self.select(multipliers, rng),
+            false => {
+                self.edges.push(ConstrainedEdge {
+                    action: policy.pick(&self.state, &untried, rng),
+                    visits: 0,
+                    score: 0.0,
+                    total_costs: vec![0.0; mdp.costs()],
+                    outcomes: vec![],
+                });
+                self.edges.len() - 1
+            }
+        };
+
+        let edge = &mut self.edges[index];
+        let (next_state, reward, step_costs, _) =
+            mdp.execute_with_costs(&self.state, &edge.action, rng)?;
+        let outcome = edge
+            .outcomes
+            .iter()
+            .position(|child| child.state == next_state);
+        let (future, future_costs) = match outcome {
+            Some(outcome) => {
+                edge.outcomes[outcome].iterate(mdp, policy, multipliers, tracker, rng)?
+            }
+            None => {
+                let returns = rollout(mdp, policy, next_state.clone(), tracker, rng)?;
+                let mut child = ConstrainedNode::new(next_state);
+                child.visits += 1;
+                edge.outcomes.push(child);
+                returns
+            }
+        };
+
+        let discount = mdp.get_discount_factor();
+        let value = reward + discount * future;
+        let costs = step_costs
+            .iter()
+            .zip(&future_costs)
+            .map(|(cost, future)| cost + discount * future)
+            .collect::<Vec<_>>();
+
+        edge.visits += 1;
+        edge.score += value;
+        edge.total_costs
+            .iter_mut()
+            .zip(&costs)
+            .for_each(|(total, c)| *total += c);
+        Ok((value, costs))
+    }
+
+    /// UCB1 over the Lagrangian of the children
+    fn select(&self, multipliers: &[f64], rng: &mut dyn Rng) -> usize {
+        let exploration = UCB1::C * (self.visits.max(1) as f64).ln().sqrt();
+        let scores = self
+            .edges
+            .iter()
+            .map(|edge| {
+                let inv_sqrt_visits = (edge.visits.max(1) as f64).sqrt().recip();
+                edge.lagrangian(multipliers) + exploration * inv_sqrt_visits
+            })
+            .collect::<Vec<_>>();
+        let best = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        //  if there are multiple actions with the highest value choose one randomly
+        let best_edges = (0..scores.len())
+            .filter(|i| scores[*i] == best)
+            .collect::<Vec<_>>();
+        best_edges[rng.gen_range(0, best_edges.len())]
+    }
+}
+
+/// Plays the rollout policy from `state` until the end of the episode, and returns the discounted return and the discounted total of every cost
+fn rollout<M, S, A, P>(
+    mdp: &M,
+    policy: &P,
+    mut state: S,
+    tracker: &Tracker<'_>,
+    rng: &mut dyn Rng,
+) -> Result<(f64, Vec<f64>), MdpError>
+where
+    M: ConstrainedMDP<S, A>,
+    A: Action,
+    P: RolloutPolicy<M, S, A>,
+{
+    let mut value = 0.0;
+    let mut costs = vec![0.0; mdp.costs()];
+    let discount = mdp.get_discount_factor();
+    let mut weight = 1.0;
+
+    while !mdp.is_terminal(&state) && !tracker.out_of_time() {
+        let actions = mdp.get_actions(&state);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions);
+        }
+
+        let action = policy.pick(&state, &actions, rng);
+        let (next_state, reward, step_costs, _) = mdp.execute_with_costs(&state, &action, rng)?;
+        value += weight * reward;
+        costs
+            .iter_mut()
+            .zip(&step_costs)
+            .for_each(|(c, cost)| *c += weight * cost);
+        weight *= discount;
+        state = next_state;
+    }
+
+    Ok((value, costs))
+}
+
+/// Searches a [`ConstrainedMDP`] for the action from its initial state with the highest expected return,
+/// among those whose expected costs stay within the budgets
+pub struct ConstrainedMCTS<M, S, A, P>
+where
+    M: ConstrainedMDP<S, A>,
+    A: Action,
+    S: Clone + PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    mdp: M,
+    policy: P,
+    root: ConstrainedNode<S, A>,
+    /// The bound on the expected total of every cost
+    budgets: Vec<f64>,
+    /// λ - How much every cost weighs against the reward
+    multipliers: Vec<f64>,
+    learning_rate: f64,
+    setup: SearchSetup,
+}
+
+impl<M, S, A, P> Configurable for ConstrainedMCTS<M, S, A, P>
+where
+    M: ConstrainedMDP<S, A>,
+    A: Action,
+    S: Clone + PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    fn setup(&mut self) -> &mut SearchSetup {
+        &mut self.setup
+    }
+}
+
+impl<M, S, A, P> ConstrainedMCTS<M, S, A, P>
+where
+    M: ConstrainedMDP<S, A>,
+    A: Action,
+    S: Clone + PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    /// `budgets` bounds the expected total of each of the MDP's costs, in the same order.
+    /// The multipliers move by 0.05 times the excess cost after every iteration, see [`ConstrainedMCTS::with_learning_rate`]
+    ///
+    /// Panics if there isn't one budget per cost
+    pub fn new(mdp: M, policy: P, budgets: Vec<f64>) -> Self {
+        assert_eq!(
+            budgets.len(),
+            mdp.costs(),
+            "there must be one budget per cost"
+        );
+
+        let state = mdp.get_initial_state();
+        Self {
+            root: ConstrainedNode::new(state),
+            multipliers: vec![0.0; budgets.len()],
+            budgets,
+            learning_rate: 0.05,
+            mdp,
+            policy,
+            setup: SearchSetup::default(),
+        }
+    }
+
+    /// How fast the multipliers adapt to the excess (or slack) of the costs.
+    /// Larger rates enforce the budgets sooner, but keep the multipliers swinging around their right values
+    pub fn with_learning_rate(mut self, learning_rate: f64) -> Self {
+        self.learning_rate = learning_rate;
+        self
+    }
+
+    /// Runs iterations until the budget is used up, adapting the multipliers after each of them
+    ///
+    /// Panics if the MDP fails, see [`ConstrainedMCTS::try_search`]
+    pub fn search(&mut self, budget: Budget) {
+        match self.try_search(budget) {
+            Ok(()) | Err(McstError::TerminalRoot { .. } | McstError::NoLegalActions) => {}
+            Err(error) => panic!("search failed: {error}"),
+        }
+    }
+
+    /// Same as [`ConstrainedMCTS::search`], but stops at the first failure of the MDP and returns it.
+    /// Fails up front if the initial state is terminal or has no actions, see [`crate::mcts::MCTS::try_mcts`]
+    pub fn try_search(&mut self, budget: Budget) -> Result<(), McstError> {
+        if self.mdp.is_terminal(&self.root.state) {
+            // nothing is left to earn, nor to spend
+            return Err(McstError::TerminalRoot { value: 0.0 });
+        }
+        if self.root.edges.is_empty() && self.mdp.get_actions(&self.root.state).is_empty() {
+            return Err(McstError::NoLegalActions);
+        }
+
+        self.setup.run(budget, |tracker, rng| {
+            self.root
+                .iterate(&self.mdp, &self.policy, &self.multipliers, tracker, rng)?;
+
+            // the costs of the action the search currently favours tell which constraints bind
+            let favoured = self.root.edges.iter().max_by(|a, b| {
+                a.lagrangian(&self.multipliers)
+                    .total_cmp(&b.lagrangian(&self.multipliers))
+            });
+            if let Some(edge) = favoured {
+                let costs = edge.mean_costs();
+                for ((lambda, cost), budget) in
+                    self.multipliers.iter_mut().zip(costs).zip(&self.budgets)
+                {
+                    *lambda = (*lambda + self.learning_rate * (cost - budget)).max(0.0);
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Returns the explored root action with the highest expected return among those expected to stay within the budgets,
+    /// or the one that exceeds them the least if none does
+    pub fn best_action(&self) -> Option<A> {
+        let excess = |edge: &ConstrainedEdge<S, A>| {
+            edge.mean_costs()
+                .iter()
+                .zip(&self.budgets)
+                .map(|(cost, budget)| (cost - budget).max(0.0))
+                .sum::<f64>()
+        };
+
+        let edges = &self.root.edges;
+        edges
+            .iter()
+            .filter(|edge| excess(edge) == 0.0)
+            .max_by(|a, b| a.q_value().total_cmp(&b.q_value()))
+            .or_else(|| edges.iter().min_by(|a, b| excess(a).total_cmp(&excess(b))))
+            .map(|edge| edge.action.clone())
+    }
+
+    /// Returns the expected return and costs of each of the root's explored actions, with the number of times it was taken
+    pub fn action_values(&self) -> Vec<(A, f64, Vec<f64>, usize)> {
+        self.root
+            .edges
+            .iter()
+            .map(|edge| {
+                (
+                    edge.action.clone(),
+                    edge.q_value(),
+                    edge.mean_costs(),
+                    edge.visits,
+                )
+            })
+            .collect()
+    }
+
+    /// The current weight λ of every cost against the reward
+    pub fn multipliers(&self) -> &[f64] {
+        &self.multipliers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::policy::RandomRollout;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Move {
+        Safe,
+        Risky,
+    }
+
+    impl Action for Move {}
+
+    /// A single move: the risky one pays 3 but costs 1, the safe one pays 1 for free
+    struct Gamble;
+
+    impl GenerativeModel<bool, Move> for Gamble {
+        fn get_actions(&self, _state: &bool) -> Vec<Move> {
+            vec![Move::Safe, Move::Risky]
+        }
+
+        fn execute(&self, _state: &bool, action: &Move, _rng: &mut dyn Rng) -> (bool, f64, bool) {
+            let reward = match action {
+                Move::Safe => 1.0,
+                Move::Risky => 3.0,
+            };
+            (true, reward, true)
+        }
+
+        fn is_terminal(&self, done: &bool) -> bool {
+            *done
+        }
+
+        fn get_initial_state(&self) -> bool {
+            false
+        }
+    }
+
+    impl ConstrainedMDP<bool, Move> for Gamble {
+        fn costs(&self) -> usize {
+            1
+        }
+
+        fn execute_with_costs(
+            &self,
+            state: &bool,
+            action: &Move,
+            rng: &mut dyn Rng,
+        ) -> Result<(bool, f64, Vec<f64>, bool), MdpError> {
+            let (next_state, reward, done) = self.execute(state, action, rng);
+            let cost = if *action == Move::Risky { 1.0 } else { 0.0 };
+            Ok((next_state, reward, vec![cost], done))
+        }
+    }
+
+    #[test]
+    fn test_constrained_search_keeps_within_budget() {
+        let mut tight = ConstrainedMCTS::new(Gamble, RandomRollout::new(), vec![0.5]).with_seed(1);
+        tight.search(Budget::Iterations(500));
+        assert_eq!(tight.best_action(), Some(Move::Safe));
+        // the risky move is worth 2 more but costs 1, so the cost must weigh at least 2 to keep it out
+        assert!(tight.multipliers()[0] > 1.5);
+
+        let mut loose = ConstrainedMCTS::new(Gamble, RandomRollout::new(), vec![2.0]).with_seed(1);
+        loose.search(Budget::Iterations(500));
+        assert_eq!(loose.best_action(), Some(Move::Risky));
+        assert_eq!(loose.multipliers(), &[0.0]);
+    }
+
+    /// The risky move pays 3, but crashes (ending in state 2 rather than 1) with a cost of 1 three times out of ten
+    struct Hazard;
+
+    impl GenerativeModel<u8, Move> for Hazard {
+        fn get_actions(&self, _state: &u8) -> Vec<Move> {
+            vec![Move::Safe, Move::Risky]
+        }
+
+        fn execute(&self, _state: &u8, action: &Move, rng: &mut dyn Rng) -> (u8, f64, bool) {
+            match action {
+                Move::Safe => (1, 1.0, true),
+                Move::Risky => (if rng.gen_f64() < 0.3 { 2 } else { 1 }, 3.0, true),
+            }
+        }
+
+        fn is_terminal(&self, state: &u8) -> bool {
+            *state != 0
+        }
+
+        fn get_initial_state(&self) -> u8 {
+            0
+        }
+    }
+
+    impl ConstrainedMDP<u8, Move> for Hazard {
+        fn costs(&self) -> usize {
+            1
+        }
+
+        fn execute_with_costs(
+            &self,
+            state: &u8,
+            action: &Move,
+            rng: &mut dyn Rng,
+        ) -> Result<(u8, f64, Vec<f64>, bool), MdpError> {
+            let (next_state, reward, done) = self.execute(state, action, rng);
+            let cost = if next_state == 2 { 1.0 } else { 0.0 };
+            Ok((next_state, reward, vec![cost], done))
+        }
+    }
+
+    #[test]
+    fn test_constrained_search_resamples_stochastic_costs() {
+        let mut search = ConstrainedMCTS::new(Hazard, RandomRollout::new(), vec![0.5]).with_seed(3);
+        search.search(Budget::Iterations(1000));
+
+        // the expected cost of the risky move, not the cost of its first crash (or lack of one)
+        let (_, _, costs, visits) = search
+            .action_values()
+            .into_iter()
+            .find(|(action, ..)| *action == Move::Risky)
+            .unwrap();
+        assert!(visits > 300, "visits={visits}");
+        assert!((costs[0] - 0.3).abs() < 0.1, "cost={}", costs[0]);
+        assert_eq!(search.best_action(), Some(Move::Risky));
+
+        // one child per outcome
+        let risky = search
+            .root
+            .edges
+            .iter()
+            .find(|edge| edge.action == Move::Risky);
+        assert_eq!(risky.unwrap().outcomes.len(), 2);
+    }
+
+    /// Two steps down a corridor, each paying 1 and costing 1
+    struct Corridor;
+
+    impl GenerativeModel<u8, Move> for Corridor {
+        fn get_actions(&self, _state: &u8) -> Vec<Move> {
+            vec![Move::Safe]
+        }
+
+        fn execute(&self, state: &u8, _action: &Move, _rng: &mut dyn Rng) -> (u8, f64, bool) {
+            (state + 1, 1.0, *state == 1)
+        }
+
+        fn is_terminal(&self, state: &u8) -> bool {
+            *state == 2
+        }
+
+        fn get_initial_state(&self) -> u8 {
+            0
+        }
+
+        fn get_discount_factor(&self) -> f64 {
+            0.5
+        }
+    }
+
+    impl ConstrainedMDP<u8, Move> for Corridor {
+        fn costs(&self) -> usize {
+            1
+        }
+
+        fn execute_with_costs(
+            &self,
+            state: &u8,
+            action: &Move,
+            rng: &mut dyn Rng,
+        ) -> Result<(u8, f64, Vec<f64>, bool), MdpError> {
+            let (next_state, reward, done) = self.execute(state, action, rng);
+            Ok((next_state, reward, vec![1.0], done))
+        }
+    }
+
+    #[test]
+    fn test_constrained_search_discounts_rewards_and_costs() {
+        let mut search =
+            ConstrainedMCTS::new(Corridor, RandomRollout::new(), vec![2.0]).with_seed(1);
+        search.search(Budget::Iterations(10));
+
+        assert_eq!(
+            search.action_values(),
+            vec![(Move::Safe, 1.5, vec![1.5], 10)]
+        );
+    }
+
+    #[test]
+    fn test_constrained_search_of_a_terminal_root_fails_up_front() {
+        let mut search =
+            ConstrainedMCTS::new(Corridor, RandomRollout::new(), vec![2.0]).with_seed(1);
+        search.root = ConstrainedNode::new(2);
+
+        assert_eq!(
+            search.try_search(Budget::Iterations(10)),
+            Err(McstError::TerminalRoot { value: 0.0 })
+        );
+        assert!(search.action_values().is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+//! MDPs whose actions can't be enumerated, e.g. real-valued controls.
+//!
+//! Such MDPs are searched with [`crate::mcts::MCTS::mcts_continuous`], which samples the actions of every node rather than listing them,
+//! and grows the number of actions tried at a node with the number of times it was visited (progressive widening)
+
+use crate::{mdp::GenerativeModel, rand::Rng};
+
+/// An MDP whose actions are sampled rather than enumerated.
+///
+/// The continuous search never calls [`GenerativeModel::get_actions`], which can return an empty list.
+/// Actions still have to implement [`crate::action::Action`], whose `Eq` bound real numbers don't meet:
+/// wrap them in a type that compares them by value (as long as the MDP never samples a NaN)
+pub trait ContinuousActions<S, A>: GenerativeModel<S, A> {
+    /// Samples an action available in the state, e.g. uniformly over the range of the controls
+    fn sample_action(&self, state: &S, rng: &mut dyn Rng) -> A;
+
+    /// Samples an action close to `action`, to search around actions that did well so far.
+    /// Returns `None` (the default) to always sample from [`ContinuousActions::sample_action`] instead
+    fn refine_action(&self, _state: &S, _action: &A, _rng: &mut dyn Rng) -> Option<A> {
+        None
+    }
+}
+
+/// How many actions a node may try given how many times it was visited: `ceil(k * N^alpha)`, and at least one.
+/// The lower `alpha`, the more the search focuses on the actions it already tried rather than sampling new ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressiveWidening {
+    pub k: f64,
+    pub alpha: f64,
+}
+
+impl ProgressiveWidening {
+    pub fn new(k: f64, alpha: f64) -> Self {
+        Self { k, alpha }
+    }
+
+    /// The number of actions a node visited `visits` times may have tried
+    pub(crate) fn limit(&self, visits: usize) -> usize {
+        ((self.k * (visits as f64).powf(self.alpha)).ceil() as usize).max(1)
+    }
+}
+
+impl Default for ProgressiveWidening {
+    /// One action per visit at first, growing with the square root of the visits
+    fn default() -> Self {
+        Self::new(1.0, 0.5)
+    }
+}
@@ -0,0 +1,201 @@
+//! Comparisons of two searches of the same position, e.g. with different exploration constants or rollout policies,
+//! for tuning search parameters.
+//!
+//! [`TreeDiff`] matches the nodes of the two trees by the actions leading to them from the root,
+//! and reports how the statistics of every matched action differ. Visits are compared as shares of the parent's visits,
+//! so that searches with different budgets can still be compared
+
+use crate::{action::Action, stats::ActionStats, tree::TreeNode};
+
+/// How an action was explored in each of two searches
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Divergence<A> {
+    /// The actions from the root to the node the action is taken in, followed by the action itself
+    pub path: Vec<A>,
+    /// The action's statistics in the first search, zero if it wasn't explored there
+    pub left: ActionStats,
+    /// The action's statistics in the second search, zero if it wasn't explored there
+    pub right: ActionStats,
+    /// The action's share of the visits of the node it's taken in, in the first search
+    pub left_share: f64,
+    /// The action's share of the visits of the node it's taken in, in the second search
+    pub right_share: f64,
+}
+
+impl<A> Divergence<A> {
+    /// How differently the searches spent their visits on the action, between 0 and 1
+    pub fn share_gap(&self) -> f64 {
+        (self.left_share - self.right_share).abs()
+    }
+
+    /// How differently the searches value the action, or None if one of them didn't explore it
+    pub fn value_gap(&self) -> Option<f64> {
+        (self.left.visits > 0 && self.right.visits > 0)
+            .then(|| (self.left.value - self.right.value).abs())
+    }
+}
+
+/// Compares two search trees, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{diff::TreeDiff, mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+///
+/// let mdp = || FnMdp::new(
+///     || 0u32,
+///     |_state: &u32| vec![1usize, 2],
+///     |state, step, _rng| (state + *step as u32, *step as f64, state + *step as u32 >= 10),
+///     |state| *state >= 10,
+/// );
+/// let mut greedy = MCTS::new(mdp(), RandomRollout::new()).with_seed(1).with_exploration_constant(0.1);
+/// let mut curious = MCTS::new(mdp(), RandomRollout::new()).with_seed(1).with_exploration_constant(5.0);
+/// greedy.run_iterations(200);
+/// curious.run_iterations(200);
+///
+/// let divergences = TreeDiff::new().with_max_depth(3).compare(&greedy.root(), &curious.root());
+/// for divergence in divergences.iter().take(5) {
+///     println!("{:?}: {:.2} vs {:.2}", divergence.path, divergence.left_share, divergence.right_share);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TreeDiff {
+    max_depth: Option<usize>,
+    min_visits: usize,
+}
+
+impl TreeDiff {
+    /// Compares the whole trees
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves out the actions taken this many actions below the nodes the comparison starts from, or deeper
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Leaves out the actions taken less than this many times in both searches, and everything below them
+    pub fn with_min_visits(mut self, visits: usize) -> Self {
+        self.min_visits = visits;
+        self
+    }
+
+    /// Compares the actions explored in either tree below nodes reached by the same actions in both,
+    /// the most diverging first: by [`Divergence::share_gap`], then by [`Divergence::value_gap`].
+    /// With chance nodes (see [`crate::mcts::MCTS::with_chance_nodes`]) only the first outcome of every action is followed
+    pub fn compare<S, A>(&self, left: &TreeNode<S, A>, right: &TreeNode<S, A>) -> Vec<Divergence<A>>
+    where
+        S: Eq,
+        A: Action,
+    {
+        let mut divergences = Vec::new();
+
+        // iterative, so that very deep trees don't overflow the stack
+        let mut stack = vec![(left.clone(), right.clone(), Vec::new())];
+        while let Some((left, right, path)) = stack.pop() {
+            if self.max_depth.is_some_and(|max| path.len() >= max) {
+                continue;
+            }
+
+            let (left_edges, right_edges) = (left.edges(), right.edges());
+            let share = |stats: &ActionStats, node: &TreeNode<S, A>| {
+                if node.visits() == 0 {
+                    0.0
+                } else {
+                    stats.visits as f64 / node.visits() as f64
+                }
+            };
+
+            let unmatched = right_edges
+                .iter()
+                .filter(|edge| !left_edges.iter().any(|l| l.action() == edge.action()))
+                .map(|edge| (None, Some(edge)));
+            let edges = left_edges
+                .iter()
+                .map(|edge| {
+                    let matched = right_edges.iter().find(|r| r.action() == edge.action());
+                    (Some(edge), matched)
+                })
+                .chain(unmatched);
+
+            for (l, r) in edges {
+                let l_stats = l.map(|edge| edge.stats()).unwrap_or_default();
+                let r_stats = r.map(|edge| edge.stats()).unwrap_or_default();
+                if l_stats.visits.max(r_stats.visits) < self.min_visits {
+                    continue;
+                }
+
+                let mut path = path.clone();
+                path.push(l.or(r).map(|edge| edge.action().clone()).unwrap());
+                if let (Some(l), Some(r)) = (l, r) {
+                    stack.push((l.child().clone(), r.child().clone(), path.clone()));
+                }
+
+                divergences.push(Divergence {
+                    path,
+                    left_share: share(&l_stats, &left),
+                    right_share: share(&r_stats, &right),
+                    left: l_stats,
+                    right: r_stats,
+                });
+            }
+        }
+
+        divergences.sort_by(|a, b| {
+            b.share_gap().total_cmp(&a.share_gap()).then_with(|| {
+                match (a.value_gap(), b.value_gap()) {
+                    (Some(a), Some(b)) => b.total_cmp(&a),
+                    (a, b) => b.is_some().cmp(&a.is_some()),
+                }
+            })
+        });
+        divergences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+
+    use super::*;
+
+    #[test]
+    fn test_diff_finds_where_searches_diverge() {
+        let mdp = || {
+            FnMdp::new(
+                || 0u32,
+                |_state: &u32| vec![1usize, 2],
+                |state, step, _rng| {
+                    (
+                        state + *step as u32,
+                        *step as f64,
+                        state + *step as u32 >= 6,
+                    )
+                },
+                |state| *state >= 6,
+            )
+        };
+        let mut mcts = MCTS::new(mdp(), RandomRollout::new()).with_seed(1);
+        mcts.run_iterations(200);
+
+        let same = TreeDiff::new().compare(&mcts.root(), &mcts.root());
+        assert!(!same.is_empty());
+        assert!(
+            same.iter()
+                .all(|d| d.share_gap() == 0.0 && d.value_gap() == Some(0.0))
+        );
+
+        // a single iteration only explores one action of the root
+        let mut shallow = MCTS::new(mdp(), RandomRollout::new()).with_seed(1);
+        shallow.run_iterations(1);
+        let diff = TreeDiff::new()
+            .with_max_depth(1)
+            .compare(&mcts.root(), &shallow.root());
+        assert_eq!(diff.len(), 2);
+        assert!(diff.iter().all(|d| d.path.len() == 1));
+        assert!(diff[0].share_gap() >= diff[1].share_gap());
+        assert_eq!(diff.iter().filter(|d| d.right.visits == 0).count(), 1);
+        assert!(diff.iter().any(|d| d.value_gap().is_none()));
+    }
+}
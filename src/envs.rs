@@ -0,0 +1,11 @@
+//! Ready-made environments: classic benchmark problems for demos and tests,
+//! and for comparing the search against the exact and learning-based [`crate::solvers`] on well-known ground
+
+pub mod bandit;
+pub mod blackjack;
+pub mod cliff_walking;
+pub mod connect_four;
+pub mod frozen_lake;
+pub mod nim;
+pub mod taxi;
+pub mod twenty_forty_eight;
@@ -0,0 +1,230 @@
+//! A k-armed bandit: a single decision, pulling one of k arms, each paying a reward drawn from its own distribution.
+//!
+//! Searching it is running the search's selection rule as a plain bandit algorithm, every iteration a pull,
+//! so selection policies (UCB1, [`crate::node_stats::Ucb1Tuned`] or any [`crate::node_stats::SelectionPolicy`])
+//! can be tested in isolation, through the same API as tree search. The means of the arms are known,
+//! so how often the search pulled each arm gives its regret, see [`Bandit::regret`].
+//!
+//! Search bandits with chance nodes (see [`crate::mcts::MCTS::with_chance_nodes`]), or every arm is credited
+//! with whatever it paid the first time it was pulled
+
+use crate::{mdp::GenerativeModel, rand::Rng};
+
+/// The distribution of the rewards of an arm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arm {
+    /// Pays 1 with this probability, and 0 otherwise
+    Bernoulli(f64),
+    Gaussian {
+        mean: f64,
+        std_dev: f64,
+    },
+    /// Pays a reward uniformly distributed between the bounds
+    Uniform {
+        low: f64,
+        high: f64,
+    },
+    Constant(f64),
+}
+
+impl Arm {
+    pub fn mean(&self) -> f64 {
+        match *self {
+            Arm::Bernoulli(p) => p,
+            Arm::Gaussian { mean, .. } => mean,
+            Arm::Uniform { low, high } => (low + high) / 2.0,
+            Arm::Constant(reward) => reward,
+        }
+    }
+
+    pub fn sample(&self, rng: &mut dyn Rng) -> f64 {
+        match *self {
+            Arm::Bernoulli(p) => f64::from(rng.gen_f64() < p),
+            Arm::Gaussian { mean, std_dev } => {
+                // Box-Muller, with the first uniform in (0, 1] for the logarithm
+                let (u, v) = (1.0 - rng.gen_f64(), rng.gen_f64());
+                mean + std_dev * (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+            }
+            Arm::Uniform { low, high } => low + (high - low) * rng.gen_f64(),
+            Arm::Constant(reward) => reward,
+        }
+    }
+}
+
+/// Before the pull, or after it with the bits of the reward it paid, so that every distinct payout is an outcome of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pull {
+    Pending,
+    Paid(u64),
+}
+
+impl Pull {
+    /// What the pull paid, if it's done
+    pub fn reward(&self) -> Option<f64> {
+        match self {
+            Pull::Pending => None,
+            Pull::Paid(bits) => Some(f64::from_bits(*bits)),
+        }
+    }
+}
+
+/// The bandit, whose arms are pulled by their index, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{envs::bandit::{Arm, Bandit}, mcts::MCTS, policy::RandomRollout, strategy::Strategy};
+///
+/// let bandit = Bandit::new([Arm::Bernoulli(0.2), Arm::Bernoulli(0.5), Arm::Bernoulli(0.6)]);
+/// let mut mcts = MCTS::new(bandit.clone(), RandomRollout::new())
+///     .with_seed(1)
+///     .with_chance_nodes();
+/// mcts.run_iterations(5000);
+/// assert_eq!(mcts.best_action(Strategy::MostVisited), Some(bandit.best_arm()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bandit {
+    arms: Vec<Arm>,
+}
+
+impl Bandit {
+    /// Panics without arms
+    pub fn new(arms: impl IntoIterator<Item = Arm>) -> Self {
+        let arms = arms.into_iter().collect::<Vec<_>>();
+        assert!(!arms.is_empty(), "a bandit needs at least one arm");
+        Self { arms }
+    }
+
+    pub fn arms(&self) -> &[Arm] {
+        &self.arms
+    }
+
+    /// The arm with the highest mean, the first of them on ties
+    pub fn best_arm(&self) -> usize {
+        (0..self.arms.len())
+            .reduce(
+                |best, arm| match self.arms[arm].mean() > self.arms[best].mean() {
+                    true => arm,
+                    false => best,
+                },
+            )
+            .unwrap()
+    }
+
+    /// The expected loss of pulling each arm this many times rather than always the best one,
+    /// e.g. from the visits of the search's root actions
+    pub fn regret(&self, pulls: impl IntoIterator<Item = (usize, usize)>) -> f64 {
+        let best = self.arms[self.best_arm()].mean();
+        pulls
+            .into_iter()
+            .map(|(arm, times)| (best - self.arms[arm].mean()) * times as f64)
+            .sum()
+    }
+}
+
+impl GenerativeModel<Pull, usize> for Bandit {
+    fn get_actions(&self, state: &Pull) -> Vec<usize> {
+        match state {
+            Pull::Pending => (0..self.arms.len()).collect(),
+            Pull::Paid(_) => vec![],
+        }
+    }
+
+    fn execute(&self, _state: &Pull, arm: &usize, rng: &mut dyn Rng) -> (Pull, f64, bool) {
+        let reward = self.arms[*arm].sample(rng);
+        (Pull::Paid(reward.to_bits()), reward, true)
+    }
+
+    fn is_terminal(&self, state: &Pull) -> bool {
+        *state != Pull::Pending
+    }
+
+    fn get_initial_state(&self) -> Pull {
+        Pull::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mcts::MCTS, node_stats::Ucb1Tuned, policy::RandomRollout, rand::SplitMix64,
+        strategy::Strategy,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_arms_pay_around_their_means() {
+        let mut rng = SplitMix64::seed(1);
+        for arm in [
+            Arm::Bernoulli(0.3),
+            Arm::Gaussian {
+                mean: 2.0,
+                std_dev: 0.5,
+            },
+            Arm::Uniform {
+                low: -1.0,
+                high: 0.0,
+            },
+            Arm::Constant(4.0),
+        ] {
+            let mean = (0..20_000).map(|_| arm.sample(&mut rng)).sum::<f64>() / 20_000.0;
+            assert!((mean - arm.mean()).abs() < 0.02, "{arm:?} {mean}");
+        }
+    }
+
+    #[test]
+    fn test_selection_policies_pull_the_best_arm_most() {
+        let bandit = Bandit::new([
+            Arm::Bernoulli(0.4),
+            Arm::Bernoulli(0.5),
+            Arm::Bernoulli(0.7),
+            Arm::Bernoulli(0.45),
+        ]);
+        assert_eq!(bandit.best_arm(), 2);
+        assert!((bandit.regret([(0, 10), (2, 100)]) - 3.0).abs() < 1e-9);
+
+        let ucb1 = {
+            let mut mcts = MCTS::new(bandit.clone(), RandomRollout::new())
+                .with_seed(1)
+                .with_chance_nodes();
+            mcts.run_iterations(3000);
+            mcts.root_action_stats()
+        };
+        let tuned = {
+            let mut mcts = MCTS::new(bandit.clone(), RandomRollout::new())
+                .with_seed(1)
+                .with_chance_nodes()
+                .with_selection(Ucb1Tuned);
+            mcts.run_iterations(3000);
+            mcts.root_action_stats()
+        };
+        for stats in [&ucb1, &tuned] {
+            let most_pulled = stats.iter().max_by_key(|(_, stats)| stats.visits).unwrap();
+            assert_eq!(most_pulled.0, 2);
+        }
+
+        // far less than pulling the arms in turn would lose
+        let uniform = bandit.regret((0..4).map(|arm| (arm, 750)));
+        for stats in [&ucb1, &tuned] {
+            let regret = bandit.regret(stats.iter().map(|(arm, stats)| (*arm, stats.visits)));
+            assert!(regret < uniform / 4.0, "{regret} {uniform}");
+        }
+
+        // every pull of a continuous arm pays something new
+        let gaussian = Bandit::new([
+            Arm::Gaussian {
+                mean: 0.0,
+                std_dev: 1.0,
+            },
+            Arm::Gaussian {
+                mean: 0.5,
+                std_dev: 1.0,
+            },
+        ]);
+        let mut mcts = MCTS::new(gaussian, RandomRollout::new())
+            .with_seed(1)
+            .with_chance_nodes();
+        mcts.run_iterations(2000);
+        assert_eq!(mcts.tree_stats().nodes, 1 + 2000);
+        assert_eq!(mcts.best_action(Strategy::MostVisited), Some(1));
+    }
+}
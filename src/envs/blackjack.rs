@@ -0,0 +1,349 @@
+//! Blackjack, as in Sutton and Barto's *Reinforcement Learning* (example 5.1) and Gymnasium's `Blackjack-v1`:
+//! the player draws cards (hits) until they stick or go over 21, then the dealer draws until they reach 17 or more,
+//! and whoever is closer to 21 wins. The player wins if the dealer goes over 21, and loses if they do.
+//!
+//! Cards are drawn from an infinite deck: aces, 2 to 9, and four times as many cards worth 10 (10s and face cards).
+//! An ace is worth 11 unless that takes the hand over 21 (a usable ace), and 1 otherwise.
+//! The player sees their sum, the dealer's face-up card and whether they hold a usable ace, which is all that matters.
+//! Rounds end with a reward of 1 for a win, -1 for a loss and 0 for a draw.
+//!
+//! The model is explicit (see [`ExplicitMDP`]), so value iteration finds the optimal policy,
+//! which is also given in closed form by [`optimal_decision`] to check the search against
+
+use crate::{
+    action::Action,
+    error::MdpError,
+    mdp::{ExplicitMDP, GenerativeModel},
+    rand::Rng,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decision {
+    Stick,
+    Hit,
+}
+
+impl Action for Decision {}
+
+/// What the player knows about a round in play
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hand {
+    /// The sum of the player's cards, from 4 to 21, with a usable ace counted as 11
+    pub sum: u8,
+    /// The dealer's face-up card, 1 for an ace
+    pub dealer: u8,
+    pub usable_ace: bool,
+}
+
+impl Hand {
+    /// Two cards for the player and one for the dealer
+    pub fn deal(rng: &mut dyn Rng) -> Self {
+        let first = draw(rng);
+        let hand = Self {
+            sum: 0,
+            dealer: draw(rng),
+            usable_ace: false,
+        };
+        hand.add(first).add(draw(rng))
+    }
+
+    /// The hand after drawing the card
+    fn add(self, card: u8) -> Self {
+        let (mut sum, mut usable_ace) = (self.sum + card, self.usable_ace);
+        if card == 1 && sum + 10 <= 21 {
+            (sum, usable_ace) = (sum + 10, true);
+        }
+        if sum > 21 && usable_ace {
+            (sum, usable_ace) = (sum - 10, false);
+        }
+        Self {
+            sum,
+            usable_ace,
+            ..self
+        }
+    }
+}
+
+/// A state of the round
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Round {
+    Playing(Hand),
+    Won,
+    Drawn,
+    Lost,
+}
+
+/// The optimal decision in every hand, from Sutton and Barto's figure 5.2:
+/// with a usable ace, stick on 19 or more, or on 18 unless the dealer shows a 9, a 10 or an ace.
+/// Without one, stick on 17 or more, on 13 to 16 if the dealer shows 2 to 6, and on 12 if they show 4 to 6
+pub fn optimal_decision(hand: &Hand) -> Decision {
+    let stick = match (hand.usable_ace, hand.sum) {
+        (true, 19..) => true,
+        (true, 18) => (2..=8).contains(&hand.dealer),
+        (true, _) => false,
+        (false, 17..) => true,
+        (false, 13..=16) => (2..=6).contains(&hand.dealer),
+        (false, 12) => (4..=6).contains(&hand.dealer),
+        (false, _) => false,
+    };
+    match stick {
+        true => Decision::Stick,
+        false => Decision::Hit,
+    }
+}
+
+/// The probability of drawing a card of this value
+fn card_probability(card: u8) -> f64 {
+    match card {
+        10 => 4.0 / 13.0,
+        _ => 1.0 / 13.0,
+    }
+}
+
+fn draw(rng: &mut dyn Rng) -> u8 {
+    (rng.gen_range(1, 14) as u8).min(10)
+}
+
+/// The probabilities that the dealer ends on 17, 18, 19, 20 or 21, or goes over 21, from a hand of theirs
+fn dealer_outcomes(hand: Hand) -> [f64; 6] {
+    if hand.sum > 21 {
+        return [0.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+    }
+    if hand.sum >= 17 {
+        let mut outcomes = [0.0; 6];
+        outcomes[usize::from(hand.sum - 17)] = 1.0;
+        return outcomes;
+    }
+
+    let mut outcomes = [0.0; 6];
+    for card in 1..=10 {
+        let drawn = dealer_outcomes(hand.add(card));
+        for (outcome, p) in outcomes.iter_mut().zip(drawn) {
+            *outcome += card_probability(card) * p;
+        }
+    }
+    outcomes
+}
+
+/// Rounds of blackjack from a given deal, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{
+///     envs::blackjack::{Blackjack, Decision, Hand},
+///     mcts::MCTS,
+///     policy::RandomRollout,
+/// };
+///
+/// // 20 against a 10
+/// let hand = Hand { sum: 20, dealer: 10, usable_ace: false };
+/// let mut mcts = MCTS::new(Blackjack::new(hand), RandomRollout::new())
+///     .with_seed(1)
+///     .with_chance_nodes();
+/// assert_eq!(mcts.search().unwrap().action, Decision::Stick);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blackjack {
+    start: Hand,
+    /// The outcomes of the dealer's play, by their face-up card
+    dealer: [[f64; 6]; 10],
+}
+
+impl Blackjack {
+    /// Rounds start from `start`, e.g. [`Hand::deal`]
+    pub fn new(start: Hand) -> Self {
+        let dealer = std::array::from_fn(|card| {
+            let hand = Hand {
+                sum: 0,
+                dealer: 0,
+                usable_ace: false,
+            };
+            dealer_outcomes(hand.add(card as u8 + 1))
+        });
+        Self { start, dealer }
+    }
+}
+
+impl GenerativeModel<Round, Decision> for Blackjack {
+    fn get_actions(&self, state: &Round) -> Vec<Decision> {
+        match state {
+            Round::Playing(_) => vec![Decision::Stick, Decision::Hit],
+            _ => vec![],
+        }
+    }
+
+    fn execute(&self, state: &Round, action: &Decision, rng: &mut dyn Rng) -> (Round, f64, bool) {
+        self.sample_transition(state, action, rng)
+    }
+
+    fn try_execute(
+        &self,
+        state: &Round,
+        action: &Decision,
+        rng: &mut dyn Rng,
+    ) -> Result<(Round, f64, bool), MdpError> {
+        self.try_sample_transition(state, action, rng)
+    }
+
+    fn is_terminal(&self, state: &Round) -> bool {
+        !matches!(state, Round::Playing(_))
+    }
+
+    fn get_initial_state(&self) -> Round {
+        Round::Playing(self.start)
+    }
+
+    fn transition_probability(
+        &self,
+        state: &Round,
+        action: &Decision,
+        next_state: &Round,
+    ) -> Option<f64> {
+        let transitions = self.get_transitions(state, action);
+        Some(
+            transitions
+                .iter()
+                .find(|(next, _)| next == next_state)
+                .map_or(0.0, |(_, p)| *p),
+        )
+    }
+}
+
+impl ExplicitMDP<Round, Decision> for Blackjack {
+    fn get_states(&self) -> Vec<Round> {
+        let mut states = vec![Round::Won, Round::Drawn, Round::Lost];
+        for dealer in 1..=10 {
+            for sum in 4..=21 {
+                states.push(Round::Playing(Hand {
+                    sum,
+                    dealer,
+                    usable_ace: false,
+                }));
+            }
+            for sum in 12..=21 {
+                states.push(Round::Playing(Hand {
+                    sum,
+                    dealer,
+                    usable_ace: true,
+                }));
+            }
+        }
+        states
+    }
+
+    fn get_transitions(&self, state: &Round, action: &Decision) -> Vec<(Round, f64)> {
+        let Round::Playing(hand) = state else {
+            return vec![];
+        };
+
+        let mut transitions: Vec<(Round, f64)> = vec![];
+        let mut add = |round: Round, p: f64| match transitions.iter_mut().find(|(r, _)| *r == round)
+        {
+            Some((_, total)) => *total += p,
+            None => transitions.push((round, p)),
+        };
+        match action {
+            Decision::Hit => {
+                for card in 1..=10 {
+                    let next = hand.add(card);
+                    let round = match next.sum > 21 {
+                        true => Round::Lost,
+                        false => Round::Playing(next),
+                    };
+                    add(round, card_probability(card));
+                }
+            }
+            Decision::Stick => {
+                let outcomes = self.dealer[usize::from(hand.dealer - 1)];
+                for (total, p) in (17..=22).zip(outcomes) {
+                    let round = match total {
+                        22 => Round::Won,
+                        total if hand.sum > total => Round::Won,
+                        total if hand.sum == total => Round::Drawn,
+                        _ => Round::Lost,
+                    };
+                    add(round, p);
+                }
+            }
+        }
+        transitions.retain(|(_, p)| *p > 0.0);
+        transitions
+    }
+
+    fn get_reward(&self, _state: &Round, _action: &Decision, next_state: &Round) -> f64 {
+        match next_state {
+            Round::Won => 1.0,
+            Round::Lost => -1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn get_goal_states(&self) -> Vec<Round> {
+        vec![Round::Won]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mcts::MCTS, policy::RandomRollout, rand::SplitMix64,
+        solvers::value_iteration::ValueIteration, strategy::Strategy,
+    };
+
+    use super::*;
+
+    fn hand(sum: u8, dealer: u8, usable_ace: bool) -> Hand {
+        Hand {
+            sum,
+            dealer,
+            usable_ace,
+        }
+    }
+
+    #[test]
+    fn test_blackjack_hands_count_aces_both_ways() {
+        assert_eq!(hand(4, 2, false).add(1), hand(15, 2, true));
+        assert_eq!(hand(15, 2, true).add(10), hand(15, 2, false));
+        assert_eq!(hand(11, 2, false).add(1), hand(12, 2, false));
+
+        let mut rng = SplitMix64::seed(1);
+        for _ in 0..100 {
+            let dealt = Hand::deal(&mut rng);
+            assert!((4..=21).contains(&dealt.sum) && (1..=10).contains(&dealt.dealer));
+        }
+
+        let game = Blackjack::new(hand(20, 10, false));
+        for state in game.get_states() {
+            for action in game.get_actions(&state) {
+                let total = game
+                    .get_transitions(&state, &action)
+                    .iter()
+                    .map(|(_, p)| p)
+                    .sum::<f64>();
+                assert!((total - 1.0).abs() < 1e-12, "{state:?} {action:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_optimal_blackjack_policy_matches_value_iteration() {
+        let game = Blackjack::new(hand(20, 10, false));
+        let solution = ValueIteration::new().solve(&game);
+        assert!(solution.converged());
+        for state in game.get_states() {
+            if let Round::Playing(hand) = state {
+                assert_eq!(
+                    solution.action(&state),
+                    Some(&optimal_decision(&hand)),
+                    "{hand:?}"
+                );
+            }
+        }
+
+        // a clear-cut hit
+        let mut mcts = MCTS::new(Blackjack::new(hand(11, 6, false)), RandomRollout::new())
+            .with_seed(2)
+            .with_chance_nodes();
+        mcts.run_iterations(2000);
+        assert_eq!(mcts.best_action(Strategy::MostVisited), Some(Decision::Hit));
+    }
+}
@@ -0,0 +1,244 @@
+//! CliffWalking: walking along the edge of a cliff, from one end of it to the other.
+//!
+//! The grid has 4 rows and 12 columns. The walker starts in the bottom-left corner and the goal is the bottom-right one,
+//! with the cliff in between. Every step costs 1, and stepping off the cliff costs 100 and sends the walker back to the start.
+//! The shortest walk runs right along the cliff, which exploring policies keep falling off: Q-learning learns it all the same,
+//! while SARSA, which learns the value of the policy it follows, settles for a safer walk further away from the edge.
+//! The search has to rank actions whose values are all negative, and far apart.
+//!
+//! States are the indices of the cells, row by row from the top-left corner, as in Gymnasium's `CliffWalking-v0`
+
+use crate::{
+    action::Action,
+    error::MdpError,
+    mdp::{ExplicitMDP, GenerativeModel},
+    rand::Rng,
+};
+
+pub const ROWS: usize = 4;
+pub const COLUMNS: usize = 12;
+
+/// The reward of every step that doesn't end off the cliff
+pub const STEP_REWARD: f64 = -1.0;
+/// The reward of stepping off the cliff
+pub const CLIFF_REWARD: f64 = -100.0;
+
+/// Where the walker goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Move {
+    /// In Gymnasium's order, so that the index of a move is its action number there
+    pub const ALL: [Move; 4] = [Move::Up, Move::Right, Move::Down, Move::Left];
+}
+
+impl Action for Move {}
+
+/// The cliff, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{envs::cliff_walking::{CliffWalking, Move}, mcts::MCTS, policy::RandomRollout};
+///
+/// let mut mcts = MCTS::new(CliffWalking::new(), RandomRollout::new())
+///     .with_seed(1)
+///     .with_max_rollout_depth(30);
+/// mcts.run_iterations(2000);
+/// // the only other ways out of the corner are off the cliff or into the wall
+/// assert_eq!(mcts.search().unwrap().action, Move::Up);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CliffWalking {
+    discount: f64,
+}
+
+impl Default for CliffWalking {
+    fn default() -> Self {
+        Self { discount: 1.0 }
+    }
+}
+
+impl CliffWalking {
+    /// Undiscounted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_discount_factor(mut self, discount: f64) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    /// The bottom-left cell
+    pub fn start() -> usize {
+        (ROWS - 1) * COLUMNS
+    }
+
+    /// The bottom-right cell
+    pub fn goal() -> usize {
+        ROWS * COLUMNS - 1
+    }
+
+    /// Whether the cell is part of the cliff, i.e. on the bottom row between the start and the goal
+    pub fn is_cliff(state: usize) -> bool {
+        state > Self::start() && state < Self::goal()
+    }
+
+    /// The cell the move leads to, the same one against the edge of the grid, before falling off the cliff
+    fn step(state: usize, direction: Move) -> usize {
+        let (row, column) = (state / COLUMNS, state % COLUMNS);
+        let (row, column) = match direction {
+            Move::Up => (row.saturating_sub(1), column),
+            Move::Right => (row, (column + 1).min(COLUMNS - 1)),
+            Move::Down => ((row + 1).min(ROWS - 1), column),
+            Move::Left => (row, column.saturating_sub(1)),
+        };
+        row * COLUMNS + column
+    }
+}
+
+impl GenerativeModel<usize, Move> for CliffWalking {
+    fn get_actions(&self, state: &usize) -> Vec<Move> {
+        match self.is_terminal(state) {
+            true => vec![],
+            false => Move::ALL.to_vec(),
+        }
+    }
+
+    fn execute(&self, state: &usize, action: &Move, rng: &mut dyn Rng) -> (usize, f64, bool) {
+        self.sample_transition(state, action, rng)
+    }
+
+    fn try_execute(
+        &self,
+        state: &usize,
+        action: &Move,
+        rng: &mut dyn Rng,
+    ) -> Result<(usize, f64, bool), MdpError> {
+        self.try_sample_transition(state, action, rng)
+    }
+
+    fn is_terminal(&self, state: &usize) -> bool {
+        *state == Self::goal()
+    }
+
+    fn get_initial_state(&self) -> usize {
+        Self::start()
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.discount
+    }
+
+    fn state_key(&self, state: &usize) -> Option<u64> {
+        Some(*state as u64)
+    }
+
+    fn transition_probability(
+        &self,
+        state: &usize,
+        action: &Move,
+        next_state: &usize,
+    ) -> Option<f64> {
+        Some(f64::from(
+            self.get_transitions(state, action)[0].0 == *next_state,
+        ))
+    }
+}
+
+impl ExplicitMDP<usize, Move> for CliffWalking {
+    fn get_states(&self) -> Vec<usize> {
+        (0..ROWS * COLUMNS).collect()
+    }
+
+    fn get_transitions(&self, state: &usize, action: &Move) -> Vec<(usize, f64)> {
+        let next = Self::step(*state, *action);
+        match Self::is_cliff(next) {
+            true => vec![(Self::start(), 1.0)],
+            false => vec![(next, 1.0)],
+        }
+    }
+
+    /// Depends on where the walker stepped rather than where it ended up, as falling off the cliff ends up at the start
+    fn get_reward(&self, state: &usize, action: &Move, _next_state: &usize) -> f64 {
+        match Self::is_cliff(Self::step(*state, *action)) {
+            true => CLIFF_REWARD,
+            false => STEP_REWARD,
+        }
+    }
+
+    fn get_goal_states(&self) -> Vec<usize> {
+        vec![Self::goal()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        rand::SplitMix64,
+        solvers::{
+            q_learning::QLearning, q_table::QTable, sarsa::Sarsa, value_iteration::ValueIteration,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_walks_along_the_cliff() {
+        let cliff = CliffWalking::new();
+        let mut rng = SplitMix64::seed(1);
+        assert_eq!(cliff.get_initial_state(), 36);
+        assert_eq!(
+            cliff.execute(&36, &Move::Right, &mut rng),
+            (36, CLIFF_REWARD, false)
+        );
+        assert_eq!(
+            cliff.execute(&36, &Move::Down, &mut rng),
+            (36, STEP_REWARD, false)
+        );
+        assert_eq!(
+            cliff.execute(&35, &Move::Down, &mut rng),
+            (47, STEP_REWARD, true)
+        );
+        assert!(cliff.get_actions(&47).is_empty());
+
+        // up, 11 steps right and down
+        let solution = ValueIteration::new().solve(&cliff);
+        assert!(solution.converged());
+        assert_eq!(solution.value(&36), Some(-13.0));
+        assert_eq!(solution.action(&36), Some(&Move::Up));
+    }
+
+    #[test]
+    fn test_sarsa_walks_further_from_the_cliff_than_q_learning() {
+        /// The row the greedy walk from the start is on once it has gone halfway across
+        fn halfway(table: &QTable<usize, Move>) -> usize {
+            let cliff = CliffWalking::new();
+            let mut state = CliffWalking::start();
+            for _ in 0..30 {
+                if state % COLUMNS == COLUMNS / 2 {
+                    return state / COLUMNS;
+                }
+                state = cliff.get_transitions(&state, table.best_action(&state).unwrap())[0].0;
+            }
+            panic!("the greedy walk doesn't get halfway");
+        }
+
+        for seed in 0..3 {
+            let q_learning = QLearning::new()
+                .with_episodes(500)
+                .with_learning_rate(0.5)
+                .train(&CliffWalking::new(), &mut SplitMix64::seed(seed));
+            let sarsa = Sarsa::new()
+                .with_episodes(500)
+                .with_learning_rate(0.5)
+                .train(&CliffWalking::new(), &mut SplitMix64::seed(seed));
+            assert_eq!(halfway(&q_learning), ROWS - 2);
+            assert!(halfway(&sarsa) < ROWS - 2);
+        }
+    }
+}
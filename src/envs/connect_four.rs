@@ -0,0 +1,243 @@
+//! Connect Four: two players take turns dropping discs into the 7 columns of an upright grid of 6 rows,
+//! and the first to line up 4 of their discs, across, up or diagonally, wins. The game is a draw once the grid is full.
+//!
+//! Positions are bitboards, one per player, so that moves and the check for four in a row are a few bit operations.
+//! Every position has a unique [`Game::key`], for transpositions (see [`crate::mcts::MCTS::with_transpositions`]),
+//! and positions are canonicalized to the smaller of themselves and their mirror image (see [`Game::canonical`]).
+//! With a branching factor of 7 and games of up to 42 moves, it's a step up from tic-tac-toe
+//! for two-player backups and transpositions
+
+use std::fmt::Display;
+
+use crate::{game::Game, mdp::PlayerId};
+
+pub const ROWS: usize = 6;
+pub const COLUMNS: usize = 7;
+
+/// Every column takes 7 bits of a bitboard, from the bottom row up,
+/// the topmost one always empty so that four in a row never wraps around to the next column
+const HEIGHT: usize = ROWS + 1;
+/// The bottom cell of every column
+const BOTTOM: u64 = {
+    let mut bottom = 0;
+    let mut column = 0;
+    while column < COLUMNS {
+        bottom |= 1 << (column * HEIGHT);
+        column += 1;
+    }
+    bottom
+};
+
+/// A position, with player 0 to move first, see the [module documentation](self).
+/// Moves are the columns the discs are dropped into, numbered from 0 on the left
+///
+/// ```
+/// use mct_rs::{envs::connect_four::ConnectFour, game::GameMdp, mcts::MCTS, policy::RandomRollout};
+///
+/// // player 0 has three discs in a row on the bottom row, open at both ends, and player 1 three stacked in column 4
+/// let position = ConnectFour::from_moves(&[1, 4, 2, 4, 3, 4]);
+/// let mut mcts = MCTS::new(GameMdp::new(position), RandomRollout::new()).with_seed(1);
+/// let action = mcts.search().unwrap().action;
+/// assert!(action == 0 || action == 4);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ConnectFour {
+    /// The discs of each player
+    boards: [u64; 2],
+    /// The number of discs in each column
+    heights: [u8; COLUMNS],
+    moves: u8,
+}
+
+impl ConnectFour {
+    /// The empty grid
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The position after these moves from the empty grid.
+    ///
+    /// Panics if a move is illegal, i.e. into a full column or after the game is over
+    pub fn from_moves(moves: &[usize]) -> Self {
+        let mut position = Self::new();
+        for (index, column) in moves.iter().enumerate() {
+            assert!(
+                position.legal_moves().contains(column),
+                "illegal move. move={index} column={column}"
+            );
+            position.make_move(column);
+        }
+        position
+    }
+
+    /// The player whose disc is in the cell, with rows numbered from 0 at the bottom
+    pub fn cell(&self, row: usize, column: usize) -> Option<PlayerId> {
+        let bit = 1 << (column * HEIGHT + row);
+        (0..2).find(|player| self.boards[*player] & bit != 0)
+    }
+
+    /// The number of discs dropped so far
+    pub fn moves_played(&self) -> usize {
+        usize::from(self.moves)
+    }
+
+    fn occupied(&self) -> u64 {
+        self.boards[0] | self.boards[1]
+    }
+
+    fn four_in_a_row(board: u64) -> bool {
+        // up, across, and both diagonals
+        [1, HEIGHT, HEIGHT - 1, HEIGHT + 1].iter().any(|shift| {
+            let pairs = board & (board >> shift);
+            pairs & (pairs >> (2 * shift)) != 0
+        })
+    }
+
+    /// The position reflected left to right
+    fn mirrored(&self) -> Self {
+        let mirror = |board: u64| {
+            (0..COLUMNS).fold(0, |mirrored, column| {
+                let discs = (board >> (column * HEIGHT)) & ((1 << HEIGHT) - 1);
+                mirrored | discs << ((COLUMNS - 1 - column) * HEIGHT)
+            })
+        };
+        let mut heights = self.heights;
+        heights.reverse();
+        Self {
+            boards: [mirror(self.boards[0]), mirror(self.boards[1])],
+            heights,
+            moves: self.moves,
+        }
+    }
+
+    /// Player 0's discs, over a disc above the top of every column, which tells the heights of the columns apart
+    fn unique_key(&self) -> u64 {
+        self.boards[0] + self.occupied() + BOTTOM
+    }
+}
+
+impl Game for ConnectFour {
+    type Move = usize;
+
+    fn legal_moves(&self) -> Vec<usize> {
+        if self.winner().is_some() {
+            return vec![];
+        }
+        (0..COLUMNS)
+            .filter(|column| usize::from(self.heights[*column]) < ROWS)
+            .collect()
+    }
+
+    fn make_move(&mut self, column: &usize) {
+        let player = self.current_player();
+        self.boards[player] |= 1 << (column * HEIGHT + usize::from(self.heights[*column]));
+        self.heights[*column] += 1;
+        self.moves += 1;
+    }
+
+    fn winner(&self) -> Option<PlayerId> {
+        (0..2).find(|player| Self::four_in_a_row(self.boards[*player]))
+    }
+
+    fn current_player(&self) -> PlayerId {
+        usize::from(self.moves % 2)
+    }
+
+    fn key(&self) -> Option<u64> {
+        Some(self.unique_key())
+    }
+
+    fn canonical(&self) -> Option<Self> {
+        let mirrored = self.mirrored();
+        (mirrored.unique_key() < self.unique_key()).then_some(mirrored)
+    }
+}
+
+impl Display for ConnectFour {
+    /// The grid from the top row down, `X` for player 0's discs and `O` for player 1's
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in (0..ROWS).rev() {
+            for column in 0..COLUMNS {
+                let disc = match self.cell(row, column) {
+                    Some(0) => 'X',
+                    Some(_) => 'O',
+                    None => '.',
+                };
+                write!(f, "{disc}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::GameMdp, mcts::MCTS, mdp::GenerativeModel, policy::RandomRollout, rand::SplitMix64,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_connect_four_finds_every_four_in_a_row() {
+        // across, up, and both diagonals
+        let across = ConnectFour::from_moves(&[0, 0, 1, 1, 2, 2, 3]);
+        let up = ConnectFour::from_moves(&[6, 5, 6, 5, 6, 5, 0, 5]);
+        let rising = ConnectFour::from_moves(&[0, 1, 1, 2, 2, 3, 2, 3, 3, 6, 3]);
+        let falling = ConnectFour::from_moves(&[6, 5, 5, 4, 4, 3, 4, 3, 3, 0, 3]);
+        assert_eq!(across.winner(), Some(0));
+        assert_eq!(up.winner(), Some(1));
+        assert_eq!(rising.winner(), Some(0));
+        assert_eq!(falling.winner(), Some(0));
+        assert!(across.legal_moves().is_empty());
+
+        // player 0's discs at the top of column 0 and the bottom of column 1 aren't four in a row up
+        let wrapped = ConnectFour::from_moves(&[0, 0, 2, 0, 0, 2, 0, 2, 0, 6, 1]);
+        assert_eq!(wrapped.cell(5, 0), Some(0));
+        assert_eq!(wrapped.winner(), None);
+        assert_eq!(wrapped.legal_moves(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(wrapped.to_string().lines().next(), Some("X......"));
+
+        // a full grid without four in a row is a draw
+        let mdp = GameMdp::new(ConnectFour::new());
+        let mut rng = SplitMix64::seed(1);
+        let mut position = ConnectFour::new();
+        for column in [
+            0, 1, 2, 3, 4, 5, 6, 0, 1, 2, 3, 4, 5, 6, 0, 1, 2, 3, 4, 5, 6, 1, 0, 3, 2, 5, 4, 0, 6,
+            1, 2, 3, 4, 5, 6, 0, 1, 2, 3, 4, 5, 6,
+        ] {
+            let reward;
+            (position, reward, _) = mdp.execute(&position, &column, &mut rng);
+            assert_eq!(reward, 0.0);
+        }
+        assert_eq!(position.moves_played(), ROWS * COLUMNS);
+        assert_eq!(position.winner(), None);
+        assert!(mdp.is_terminal(&position));
+    }
+
+    #[test]
+    fn test_connect_four_positions_share_keys_and_mirrors() {
+        let position = ConnectFour::from_moves(&[3, 2, 4]);
+        let transposed = ConnectFour::from_moves(&[4, 2, 3]);
+        assert_eq!(position, transposed);
+        assert_eq!(position.key(), transposed.key());
+        assert_ne!(position.key(), ConnectFour::from_moves(&[3, 4, 2]).key());
+
+        // the mirror image of one of the two is the other
+        let left = ConnectFour::from_moves(&[0, 1]);
+        let right = ConnectFour::from_moves(&[6, 5]);
+        let canonical = left.canonical().unwrap_or(left);
+        assert_ne!(left, right);
+        assert_eq!(right.canonical().unwrap_or(right), canonical);
+        assert!(canonical.canonical().is_none());
+        assert!(ConnectFour::new().canonical().is_none());
+
+        let mut mcts = MCTS::new(GameMdp::new(ConnectFour::new()), RandomRollout::new())
+            .with_seed(1)
+            .with_transpositions();
+        mcts.run_iterations(3000);
+        // the middle column is the best start
+        assert_eq!(mcts.search().unwrap().action, 3);
+    }
+}
@@ -0,0 +1,303 @@
+//! FrozenLake: walking from the start to the goal across a frozen lake, without falling into any of its holes.
+//!
+//! The lake is a grid of tiles, `S` for the start, `F` for frozen tiles, `H` for holes and `G` for the goal.
+//! Episodes end in a hole or at the goal, and reaching the goal is the only reward (1).
+//! On a slippery lake, the walker only goes where it meant to a third of the time,
+//! and slides to either side of that direction otherwise. Walking into the edge of the lake leaves it where it was.
+//!
+//! States are the indices of the tiles, row by row from the top-left corner, as in Gymnasium's `FrozenLake-v1`
+
+use crate::{
+    action::Action,
+    error::MdpError,
+    mdp::{ExplicitMDP, GenerativeModel},
+    rand::Rng,
+};
+
+/// The 4x4 lake of Gymnasium
+pub const MAP_4X4: [&str; 4] = ["SFFF", "FHFH", "FFFH", "HFFG"];
+
+/// The 8x8 lake of Gymnasium
+pub const MAP_8X8: [&str; 8] = [
+    "SFFFFFFF", "FFFFFFFF", "FFFHFFFF", "FFFFFHFF", "FFFHFFFF", "FHHFFFHF", "FHFFHFHF", "FFFHFFFG",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Start,
+    Frozen,
+    Hole,
+    Goal,
+}
+
+/// Where the walker means to go
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    Left,
+    Down,
+    Right,
+    Up,
+}
+
+impl Move {
+    /// In Gymnasium's order, so that the index of a move is its action number there
+    pub const ALL: [Move; 4] = [Move::Left, Move::Down, Move::Right, Move::Up];
+
+    /// The moves a slippery lake can turn this one into: itself, and the two at right angles to it
+    fn slides(self) -> [Move; 3] {
+        match self {
+            Move::Left | Move::Right => [self, Move::Up, Move::Down],
+            Move::Down | Move::Up => [self, Move::Left, Move::Right],
+        }
+    }
+}
+
+impl Action for Move {}
+
+/// A frozen lake, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{envs::frozen_lake::{FrozenLake, Move}, mcts::MCTS, policy::RandomRollout};
+///
+/// // discounted, so that shorter walks are better
+/// let lake = FrozenLake::four_by_four().with_slippery(false).with_discount_factor(0.9);
+/// let mut mcts = MCTS::new(lake, RandomRollout::new()).with_seed(1);
+/// // both ways around the hole next to the start are as short
+/// let action = mcts.search().unwrap().action;
+/// assert!(action == Move::Down || action == Move::Right);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrozenLake {
+    tiles: Vec<Tile>,
+    columns: usize,
+    start: usize,
+    slippery: bool,
+    discount: f64,
+}
+
+impl FrozenLake {
+    /// A slippery lake, undiscounted, from rows of `S`, `F`, `H` and `G` tiles.
+    ///
+    /// Panics if the rows aren't all as long, if a tile is none of those, or if there isn't exactly one start
+    pub fn from_map(rows: &[&str]) -> Self {
+        assert!(!rows.is_empty(), "a lake needs at least one row");
+        let columns = rows[0].len();
+        let mut tiles = vec![];
+        for (r, row) in rows.iter().enumerate() {
+            assert_eq!(
+                row.len(),
+                columns,
+                "every row of the lake must be as long. row={r}"
+            );
+            for tile in row.chars() {
+                tiles.push(match tile {
+                    'S' => Tile::Start,
+                    'F' => Tile::Frozen,
+                    'H' => Tile::Hole,
+                    'G' => Tile::Goal,
+                    _ => panic!("unknown tile {tile:?}, expected S, F, H or G. row={r}"),
+                });
+            }
+        }
+
+        let starts = tiles.iter().filter(|tile| **tile == Tile::Start).count();
+        assert_eq!(starts, 1, "a lake needs exactly one start");
+        let start = tiles.iter().position(|tile| *tile == Tile::Start).unwrap();
+
+        Self {
+            tiles,
+            columns,
+            start,
+            slippery: true,
+            discount: 1.0,
+        }
+    }
+
+    /// See [`MAP_4X4`]
+    pub fn four_by_four() -> Self {
+        Self::from_map(&MAP_4X4)
+    }
+
+    /// See [`MAP_8X8`]
+    pub fn eight_by_eight() -> Self {
+        Self::from_map(&MAP_8X8)
+    }
+
+    /// Slippery lakes (the default) make the walker slide sideways two thirds of the time
+    pub fn with_slippery(mut self, slippery: bool) -> Self {
+        self.slippery = slippery;
+        self
+    }
+
+    pub fn with_discount_factor(mut self, discount: f64) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    pub fn rows(&self) -> usize {
+        self.tiles.len() / self.columns
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn tile(&self, state: usize) -> Tile {
+        self.tiles[state]
+    }
+
+    /// The row and column of the tile
+    pub fn position(&self, state: usize) -> (usize, usize) {
+        (state / self.columns, state % self.columns)
+    }
+
+    /// The tile reached by moving from this one, the same one at the edge of the lake
+    fn step(&self, state: usize, direction: Move) -> usize {
+        let (row, column) = self.position(state);
+        let (row, column) = match direction {
+            Move::Left => (row, column.saturating_sub(1)),
+            Move::Down => ((row + 1).min(self.rows() - 1), column),
+            Move::Right => (row, (column + 1).min(self.columns - 1)),
+            Move::Up => (row.saturating_sub(1), column),
+        };
+        row * self.columns + column
+    }
+}
+
+impl GenerativeModel<usize, Move> for FrozenLake {
+    fn get_actions(&self, state: &usize) -> Vec<Move> {
+        match self.is_terminal(state) {
+            true => vec![],
+            false => Move::ALL.to_vec(),
+        }
+    }
+
+    fn execute(&self, state: &usize, action: &Move, rng: &mut dyn Rng) -> (usize, f64, bool) {
+        self.sample_transition(state, action, rng)
+    }
+
+    fn try_execute(
+        &self,
+        state: &usize,
+        action: &Move,
+        rng: &mut dyn Rng,
+    ) -> Result<(usize, f64, bool), MdpError> {
+        self.try_sample_transition(state, action, rng)
+    }
+
+    fn is_terminal(&self, state: &usize) -> bool {
+        matches!(self.tiles[*state], Tile::Hole | Tile::Goal)
+    }
+
+    fn get_initial_state(&self) -> usize {
+        self.start
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.discount
+    }
+
+    fn state_key(&self, state: &usize) -> Option<u64> {
+        Some(*state as u64)
+    }
+
+    fn transition_probability(
+        &self,
+        state: &usize,
+        action: &Move,
+        next_state: &usize,
+    ) -> Option<f64> {
+        let transitions = self.get_transitions(state, action);
+        Some(
+            transitions
+                .iter()
+                .find(|(next, _)| next == next_state)
+                .map_or(0.0, |(_, p)| *p),
+        )
+    }
+}
+
+impl ExplicitMDP<usize, Move> for FrozenLake {
+    fn get_states(&self) -> Vec<usize> {
+        (0..self.tiles.len()).collect()
+    }
+
+    fn get_transitions(&self, state: &usize, action: &Move) -> Vec<(usize, f64)> {
+        if !self.slippery {
+            return vec![(self.step(*state, *action), 1.0)];
+        }
+
+        // slides into the same tile (e.g. along an edge) add up
+        let mut transitions: Vec<(usize, f64)> = vec![];
+        for direction in action.slides() {
+            let next = self.step(*state, direction);
+            match transitions.iter_mut().find(|(tile, _)| *tile == next) {
+                Some((_, p)) => *p += 1.0 / 3.0,
+                None => transitions.push((next, 1.0 / 3.0)),
+            }
+        }
+        transitions
+    }
+
+    fn get_reward(&self, _state: &usize, _action: &Move, next_state: &usize) -> f64 {
+        f64::from(self.tiles[*next_state] == Tile::Goal)
+    }
+
+    fn get_goal_states(&self) -> Vec<usize> {
+        self.get_states()
+            .into_iter()
+            .filter(|state| self.tiles[*state] == Tile::Goal)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mcts::MCTS, policy::RandomRollout, solvers::value_iteration::ValueIteration,
+        strategy::Strategy,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_frozen_lakes_follow_gymnasium() {
+        let lake = FrozenLake::four_by_four();
+        assert_eq!((lake.rows(), lake.columns()), (4, 4));
+        assert_eq!(lake.get_initial_state(), 0);
+        assert_eq!(lake.tile(5), Tile::Hole);
+        assert!(lake.is_terminal(&5) && lake.is_terminal(&15));
+        assert_eq!(lake.get_goal_states(), vec![15]);
+        assert_eq!(FrozenLake::eight_by_eight().get_goal_states(), vec![63]);
+
+        // from the top-left corner, moving left slides down a third of the time and stays put otherwise
+        let transitions = lake.get_transitions(&0, &Move::Left);
+        assert_eq!(transitions.len(), 2);
+        assert!((transitions[0].1 - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(transitions[1].0, 4);
+        let lake = lake.with_slippery(false);
+        assert_eq!(lake.get_transitions(&14, &Move::Right), vec![(15, 1.0)]);
+        assert_eq!(lake.get_reward(&14, &Move::Right, &15), 1.0);
+    }
+
+    #[test]
+    fn test_search_agrees_with_value_iteration_on_frozen_lake() {
+        let lake = FrozenLake::four_by_four().with_discount_factor(0.9);
+        let solution = ValueIteration::new().solve(&lake);
+        assert!(solution.converged());
+        // the best policy of the slippery lake still falls into a hole now and then
+        let start = solution.value(&0).unwrap();
+        assert!(start > 0.0 && start < 1.0);
+
+        // on ice that doesn't slip, the goal is 6 moves away whichever way the walker starts
+        let lake = lake.with_slippery(false);
+        let solution = ValueIteration::new().solve(&lake);
+        assert!((solution.value(&0).unwrap() - 0.9f64.powi(5)).abs() < 1e-9);
+
+        let mut mcts = MCTS::new(lake.clone(), RandomRollout::new()).with_seed(3);
+        mcts.run_iterations(2000);
+        let action = mcts.best_action(Strategy::MostVisited).unwrap();
+        let next = lake.get_transitions(&0, &action)[0].0;
+        assert!((0.9 * solution.value(&next).unwrap() - solution.value(&0).unwrap()).abs() < 1e-9);
+    }
+}
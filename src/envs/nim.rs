@@ -0,0 +1,162 @@
+//! Nim: two players take turns taking any number of objects from a single heap, and whoever takes the last object wins.
+//!
+//! Nim is solved in closed form (Bouton's theorem): the player to move loses against perfect play
+//! if and only if the nim-sum of the heaps, the exclusive or of their sizes, is 0, and the winning moves
+//! are exactly those leaving a nim-sum of 0. [`Nim::winning_moves`] gives them, so that tests can check what the search
+//! (its backups, and each final-move strategy) finds against ground truth, on positions of any size.
+//! Positions with larger heaps are notoriously hard for plain UCT, whose random rollouts say little about them
+
+use crate::{action::Action, game::Game, mdp::PlayerId};
+
+/// Takes `count` objects from the heap at index `heap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Take {
+    pub heap: usize,
+    pub count: u32,
+}
+
+impl Action for Take {}
+
+/// A position, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{envs::nim::Nim, game::GameMdp, mcts::MCTS, policy::RandomRollout, strategy::Strategy};
+///
+/// let nim = Nim::new([1, 3, 5]);
+/// let mut mcts = MCTS::new(GameMdp::new(nim.clone()), RandomRollout::new()).with_seed(1);
+/// mcts.run_iterations(5000);
+/// let action = mcts.best_action(Strategy::MostVisited).unwrap();
+/// assert!(nim.winning_moves().contains(&action));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Nim {
+    heaps: Vec<u32>,
+    player: PlayerId,
+}
+
+impl Nim {
+    /// Heaps of these sizes, with player 0 to move
+    pub fn new(heaps: impl IntoIterator<Item = u32>) -> Self {
+        Self {
+            heaps: heaps.into_iter().collect(),
+            player: 0,
+        }
+    }
+
+    pub fn heaps(&self) -> &[u32] {
+        &self.heaps
+    }
+
+    /// The exclusive or of the sizes of the heaps, 0 if and only if the player to move loses against perfect play
+    pub fn nim_sum(&self) -> u32 {
+        self.heaps.iter().fold(0, |sum, heap| sum ^ heap)
+    }
+
+    /// The moves that win against any defence, i.e. those leaving a nim-sum of 0. None if the player to move is lost
+    pub fn winning_moves(&self) -> Vec<Take> {
+        let sum = self.nim_sum();
+        self.heaps
+            .iter()
+            .enumerate()
+            .filter(|(_, size)| *size ^ sum < **size)
+            .map(|(heap, size)| Take {
+                heap,
+                count: size - (size ^ sum),
+            })
+            .collect()
+    }
+}
+
+impl Game for Nim {
+    type Move = Take;
+
+    fn legal_moves(&self) -> Vec<Take> {
+        self.heaps
+            .iter()
+            .enumerate()
+            .flat_map(|(heap, size)| (1..=*size).map(move |count| Take { heap, count }))
+            .collect()
+    }
+
+    fn make_move(&mut self, take: &Take) {
+        self.heaps[take.heap] -= take.count;
+        self.player = 1 - self.player;
+    }
+
+    fn winner(&self) -> Option<PlayerId> {
+        // the player who took the last object
+        self.heaps
+            .iter()
+            .all(|size| *size == 0)
+            .then_some(1 - self.player)
+    }
+
+    fn current_player(&self) -> PlayerId {
+        self.player
+    }
+
+    /// The order of the heaps doesn't matter, so positions are canonical with their heaps sorted
+    fn canonical(&self) -> Option<Self> {
+        let mut heaps = self.heaps.clone();
+        heaps.sort_unstable();
+        let sorted = heaps != self.heaps;
+        sorted.then_some(Self {
+            heaps,
+            player: self.player,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{game::GameMdp, mcts::MCTS, policy::RandomRollout, strategy::Strategy};
+
+    use super::*;
+
+    #[test]
+    fn test_nim_winning_moves_leave_a_nim_sum_of_zero() {
+        let nim = Nim::new([3, 4, 5]);
+        assert_eq!(nim.nim_sum(), 2);
+        assert_eq!(nim.winning_moves(), vec![Take { heap: 0, count: 2 }]);
+        assert_eq!(nim.legal_moves().len(), 12);
+
+        let mut lost = nim.clone();
+        lost.make_move(&Take { heap: 0, count: 2 });
+        assert_eq!(lost.nim_sum(), 0);
+        assert!(lost.winning_moves().is_empty());
+        assert_eq!(lost.current_player(), 1);
+        assert_eq!(lost.canonical(), None);
+        assert_eq!(Nim::new([4, 1]).canonical(), Some(Nim::new([1, 4])));
+
+        let mut over = Nim::new([0, 1]);
+        over.make_move(&Take { heap: 1, count: 1 });
+        assert_eq!(over.winner(), Some(0));
+        assert!(over.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_search_finds_the_winning_nim_moves() {
+        for heaps in [[1, 2, 4], [2, 3, 6], [1, 3, 5]] {
+            let nim = Nim::new(heaps);
+            let mut mcts = MCTS::new(GameMdp::new(nim.clone()), RandomRollout::new())
+                .with_seed(7)
+                .with_transpositions();
+            mcts.run_iterations(5000);
+            for strategy in [Strategy::MostVisited, Strategy::HighestQValue] {
+                let action = mcts.best_action(strategy).unwrap();
+                assert!(
+                    nim.winning_moves().contains(&action),
+                    "{heaps:?} {strategy:?}"
+                );
+            }
+        }
+
+        // every move loses against perfect play, which the search sees coming
+        let mut mcts = MCTS::new(GameMdp::new(Nim::new([1, 2, 3])), RandomRollout::new())
+            .with_seed(7)
+            .with_transpositions();
+        assert!(mcts.search().unwrap().value < 0.0);
+    }
+}
@@ -0,0 +1,306 @@
+//! Taxi, as in Gymnasium's `Taxi-v3` (after Dietterich's MAXQ paper): a taxi drives around a 5x5 grid with walls,
+//! picks a passenger up at one of four stands and drops them off at another.
+//!
+//! ```text
+//! +---------+
+//! |R: | : :G|
+//! | : | : : |
+//! | : : : : |
+//! | | : | : |
+//! |Y| : |B: |
+//! +---------+
+//! ```
+//!
+//! Every action costs 1, and so does driving into a wall (the taxi stays put). Picking up or dropping off
+//! where the passenger can't be costs 10 instead, and dropping them off at their destination pays 20 and ends the ride.
+//! Dropping them off at another stand leaves them there.
+//!
+//! The 500 states (25 cells, 5 places for the passenger counting the taxi, and 4 destinations)
+//! are numbered as in Gymnasium, see [`Taxi::encode`]; many of them are reached through different orders of moves,
+//! which makes Taxi a good test for transpositions (see [`crate::mcts::MCTS::with_transpositions`])
+
+use crate::{
+    action::Action,
+    error::MdpError,
+    mdp::{ExplicitMDP, GenerativeModel},
+    rand::Rng,
+};
+
+/// The rows of the map, with `|` for the walls between cells, as drawn in the [module documentation](self)
+const MAP: [&str; 5] = [
+    "|R: | : :G|",
+    "| : | : : |",
+    "| : : : : |",
+    "| | : | : |",
+    "|Y| : |B: |",
+];
+
+/// The cells of the stands R, G, Y and B
+pub const STANDS: [(usize, usize); 4] = [(0, 0), (0, 4), (4, 0), (4, 3)];
+
+/// Where the passenger is when they're in the taxi
+pub const IN_TAXI: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    South,
+    North,
+    East,
+    West,
+    Pickup,
+    Dropoff,
+}
+
+impl Command {
+    /// In Gymnasium's order, so that the index of a command is its action number there
+    pub const ALL: [Command; 6] = [
+        Command::South,
+        Command::North,
+        Command::East,
+        Command::West,
+        Command::Pickup,
+        Command::Dropoff,
+    ];
+}
+
+impl Action for Command {}
+
+/// A state, decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ride {
+    pub row: usize,
+    pub column: usize,
+    /// The stand the passenger waits at (an index of [`STANDS`]), or [`IN_TAXI`]
+    pub passenger: usize,
+    /// The stand the passenger goes to
+    pub destination: usize,
+}
+
+/// The taxi, from a given state, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{envs::taxi::{Command, Ride, Taxi, IN_TAXI}, mcts::MCTS, policy::RandomRollout};
+///
+/// // the taxi is at B with the passenger, who goes there
+/// let start = Taxi::encode(Ride { row: 4, column: 3, passenger: IN_TAXI, destination: 3 });
+/// let mut mcts = MCTS::new(Taxi::new(start), RandomRollout::new())
+///     .with_seed(1)
+///     .with_max_rollout_depth(20);
+/// assert_eq!(mcts.search().unwrap().action, Command::Dropoff);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Taxi {
+    start: usize,
+    discount: f64,
+}
+
+impl Taxi {
+    /// Undiscounted rides from `start`, e.g. [`Taxi::random_start`]. Panics if there's no such state
+    pub fn new(start: usize) -> Self {
+        assert!(start < 500, "no such state. state={start}");
+        Self {
+            start,
+            discount: 1.0,
+        }
+    }
+
+    pub fn with_discount_factor(mut self, discount: f64) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    /// A state as Gymnasium deals them: anywhere, with the passenger waiting at one stand to go to another
+    pub fn random_start(rng: &mut dyn Rng) -> usize {
+        let passenger = rng.gen_range(0, 4);
+        let destination = (passenger + rng.gen_range(1, 4)) % 4;
+        Self::encode(Ride {
+            row: rng.gen_range(0, 5),
+            column: rng.gen_range(0, 5),
+            passenger,
+            destination,
+        })
+    }
+
+    pub fn encode(ride: Ride) -> usize {
+        ((ride.row * 5 + ride.column) * 5 + ride.passenger) * 4 + ride.destination
+    }
+
+    pub fn decode(state: usize) -> Ride {
+        Ride {
+            row: state / 100,
+            column: state / 20 % 5,
+            passenger: state / 4 % 5,
+            destination: state % 4,
+        }
+    }
+
+    /// The next state and the reward of the command
+    fn step(state: usize, command: Command) -> (usize, f64) {
+        let mut ride = Self::decode(state);
+        let stand = STANDS
+            .iter()
+            .position(|cell| *cell == (ride.row, ride.column));
+        let open = |column: usize| MAP[ride.row].as_bytes()[2 * column + 2] == b':';
+
+        let mut reward = -1.0;
+        match command {
+            Command::South => ride.row = (ride.row + 1).min(4),
+            Command::North => ride.row = ride.row.saturating_sub(1),
+            Command::East if ride.column < 4 && open(ride.column) => ride.column += 1,
+            Command::West if ride.column > 0 && open(ride.column - 1) => ride.column -= 1,
+            Command::East | Command::West => {}
+            Command::Pickup => match stand {
+                Some(stand) if ride.passenger == stand => ride.passenger = IN_TAXI,
+                _ => reward = -10.0,
+            },
+            Command::Dropoff => match stand {
+                Some(stand) if ride.passenger == IN_TAXI => {
+                    ride.passenger = stand;
+                    if stand == ride.destination {
+                        reward = 20.0;
+                    }
+                }
+                _ => reward = -10.0,
+            },
+        }
+        (Self::encode(ride), reward)
+    }
+}
+
+impl GenerativeModel<usize, Command> for Taxi {
+    fn get_actions(&self, state: &usize) -> Vec<Command> {
+        match self.is_terminal(state) {
+            true => vec![],
+            false => Command::ALL.to_vec(),
+        }
+    }
+
+    fn execute(&self, state: &usize, action: &Command, rng: &mut dyn Rng) -> (usize, f64, bool) {
+        self.sample_transition(state, action, rng)
+    }
+
+    fn try_execute(
+        &self,
+        state: &usize,
+        action: &Command,
+        rng: &mut dyn Rng,
+    ) -> Result<(usize, f64, bool), MdpError> {
+        self.try_sample_transition(state, action, rng)
+    }
+
+    /// The passenger is at their destination
+    fn is_terminal(&self, state: &usize) -> bool {
+        let ride = Self::decode(*state);
+        ride.passenger == ride.destination
+    }
+
+    fn get_initial_state(&self) -> usize {
+        self.start
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.discount
+    }
+
+    fn state_key(&self, state: &usize) -> Option<u64> {
+        Some(*state as u64)
+    }
+
+    fn transition_probability(
+        &self,
+        state: &usize,
+        action: &Command,
+        next_state: &usize,
+    ) -> Option<f64> {
+        Some(f64::from(Self::step(*state, *action).0 == *next_state))
+    }
+}
+
+impl ExplicitMDP<usize, Command> for Taxi {
+    fn get_states(&self) -> Vec<usize> {
+        (0..500).collect()
+    }
+
+    fn get_transitions(&self, state: &usize, action: &Command) -> Vec<(usize, f64)> {
+        vec![(Self::step(*state, *action).0, 1.0)]
+    }
+
+    fn get_reward(&self, state: &usize, action: &Command, _next_state: &usize) -> f64 {
+        Self::step(*state, *action).1
+    }
+
+    fn get_goal_states(&self) -> Vec<usize> {
+        self.get_states()
+            .into_iter()
+            .filter(|state| self.is_terminal(state))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mcts::MCTS, policy::RandomRollout, rand::SplitMix64,
+        solvers::value_iteration::ValueIteration, strategy::Strategy,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_taxi_drives_around_walls() {
+        let ride = Ride {
+            row: 0,
+            column: 1,
+            passenger: 0,
+            destination: 1,
+        };
+        let state = Taxi::encode(ride);
+        assert_eq!(Taxi::decode(state), ride);
+        assert_eq!(Taxi::decode(499).destination, 3);
+
+        // a wall east of the second column of the top row, none west of it
+        assert_eq!(Taxi::step(state, Command::East), (state, -1.0));
+        let (west, _) = Taxi::step(state, Command::West);
+        assert_eq!(Taxi::decode(west).column, 0);
+        assert_eq!(Taxi::step(state, Command::Pickup), (state, -10.0));
+
+        // at R with the passenger, who goes to G
+        let (picked, reward) = Taxi::step(west, Command::Pickup);
+        assert_eq!((Taxi::decode(picked).passenger, reward), (IN_TAXI, -1.0));
+        let (dropped, reward) = Taxi::step(picked, Command::Dropoff);
+        assert_eq!((dropped, reward), (west, -1.0));
+
+        let mut rng = SplitMix64::seed(1);
+        for _ in 0..50 {
+            let start = Taxi::random_start(&mut rng);
+            assert!(!Taxi::new(start).is_terminal(&start));
+        }
+    }
+
+    #[test]
+    fn test_search_drives_taxis_the_shortest_way() {
+        let start = Taxi::encode(Ride {
+            row: 2,
+            column: 2,
+            passenger: 3,
+            destination: 2,
+        });
+        let taxi = Taxi::new(start);
+        let solution = ValueIteration::new().solve(&taxi);
+        assert!(solution.converged());
+        // 3 moves to B, pick up, 7 moves around the walls to Y, drop off
+        assert_eq!(solution.value(&start), Some(20.0 - 11.0));
+
+        let mut mcts = MCTS::new(taxi, RandomRollout::new())
+            .with_seed(1)
+            .with_transpositions()
+            .with_max_rollout_depth(20);
+        mcts.run_iterations(5000);
+        let action = mcts.best_action(Strategy::MostVisited).unwrap();
+        let (next, reward) = Taxi::step(start, action);
+        assert_eq!(
+            Some(reward + solution.value(&next).unwrap()),
+            solution.value(&start)
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! 2048: sliding numbered tiles across a 4x4 board, so that equal tiles merge into one of twice their value.
+//!
+//! Every slide moves all the tiles as far as they go in one direction, merging each pair of equal tiles that meet
+//! (a tile merges at most once per slide), and its reward is the sum of the merged tiles. Then a new tile appears
+//! on an empty cell picked uniformly at random: a 2 nine times out of ten, a 4 otherwise.
+//! Slides that move nothing aren't allowed, and the game is over once no slide is.
+//!
+//! Each step is a deterministic slide, to the afterstate (see [`Board::slide`]), followed by a random spawn,
+//! whose probabilities the model gives (see [`GenerativeModel::transition_probability`]), so that
+//! [`crate::mcts::MCTS::with_chance_nodes`] weights the outcomes of a slide exactly. With up to 30 outcomes per slide
+//! and games lasting thousands of moves, it's the usual stress test for single-player stochastic search
+
+use crate::{action::Action, mdp::GenerativeModel, rand::Rng};
+
+/// The probability that a new tile is a 2 rather than a 4
+pub const TWO_PROBABILITY: f64 = 0.9;
+
+/// The exponent of the largest tile, 32768: the largest a cell's 4 bits hold
+const MAX_EXPONENT: u8 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slide {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Slide {
+    pub const ALL: [Slide; 4] = [Slide::Up, Slide::Down, Slide::Left, Slide::Right];
+
+    /// The cells of every row or column, in the order the tiles slide towards the first one
+    fn lines(self) -> [[usize; 4]; 4] {
+        std::array::from_fn(|line| {
+            std::array::from_fn(|i| match self {
+                Slide::Left => line * 4 + i,
+                Slide::Right => line * 4 + 3 - i,
+                Slide::Up => i * 4 + line,
+                Slide::Down => (3 - i) * 4 + line,
+            })
+        })
+    }
+}
+
+impl Action for Slide {}
+
+/// The 16 cells of a board, row by row from the top-left corner, as 4 bits each: 0 for an empty cell, `n` for a tile of 2ⁿ.
+/// Tiles go up to 32768, which no game has been seen to go past, and two of them don't merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Board(pub u64);
+
+impl Board {
+    /// A board from the values of its tiles, 0 for empty cells.
+    ///
+    /// Panics if a value is neither 0 nor a power of two from 2 to 2¹⁵
+    pub fn from_rows(rows: [[u32; 4]; 4]) -> Self {
+        let mut board = Self::default();
+        for (cell, value) in rows.iter().flatten().enumerate() {
+            assert!(
+                *value == 0 || (value.is_power_of_two() && *value > 1 && *value < 1 << 16),
+                "tiles must be powers of two from 2 to 32768. cell={cell} value={value}"
+            );
+            if *value > 0 {
+                board = board.with_exponent(cell, value.trailing_zeros() as u8);
+            }
+        }
+        board
+    }
+
+    /// Two tiles on random cells, as a game starts
+    pub fn random_start(rng: &mut dyn Rng) -> Self {
+        Self::default().spawn(rng).spawn(rng)
+    }
+
+    /// The value of the tile in the cell, 0 if it's empty
+    pub fn tile(&self, row: usize, column: usize) -> u32 {
+        match self.exponent(row * 4 + column) {
+            0 => 0,
+            exponent => 1 << exponent,
+        }
+    }
+
+    /// The value of the largest tile
+    pub fn max_tile(&self) -> u32 {
+        let exponent = (0..16).map(|cell| self.exponent(cell)).max().unwrap_or(0);
+        match exponent {
+            0 => 0,
+            exponent => 1 << exponent,
+        }
+    }
+
+    pub fn empty_cells(&self) -> Vec<usize> {
+        (0..16).filter(|cell| self.exponent(*cell) == 0).collect()
+    }
+
+    /// The afterstate of the slide, before a new tile appears, and its reward. None if the slide moves nothing.
+    /// Two 32768 tiles stay side by side, as their merge wouldn't fit in a cell
+    pub fn slide(&self, slide: Slide) -> Option<(Board, f64)> {
+        let mut board = Self::default();
+        let mut reward = 0.0;
+        for line in slide.lines() {
+            let mut slid = line
+                .iter()
+                .map(|cell| self.exponent(*cell))
+                .filter(|e| *e > 0);
+            let mut target = line.iter();
+            let mut next = slid.next();
+            while let Some(exponent) = next {
+                next = slid.next();
+                let exponent = match next == Some(exponent) && exponent < MAX_EXPONENT {
+                    true => {
+                        next = slid.next();
+                        reward += f64::from(1u32 << (exponent + 1));
+                        exponent + 1
+                    }
+                    false => exponent,
+                };
+                board = board.with_exponent(*target.next().unwrap(), exponent);
+            }
+        }
+        (board != *self).then_some((board, reward))
+    }
+
+    /// Adds a new tile on a random empty cell
+    fn spawn(self, rng: &mut dyn Rng) -> Self {
+        let empty = self.empty_cells();
+        if empty.is_empty() {
+            return self;
+        }
+        let cell = empty[rng.gen_range(0, empty.len())];
+        let exponent = match rng.gen_f64() < TWO_PROBABILITY {
+            true => 1,
+            false => 2,
+        };
+        self.with_exponent(cell, exponent)
+    }
+
+    fn exponent(&self, cell: usize) -> u8 {
+        ((self.0 >> (cell * 4)) & 0xf) as u8
+    }
+
+    fn with_exponent(self, cell: usize, exponent: u8) -> Self {
+        Self(self.0 & !(0xf << (cell * 4)) | u64::from(exponent) << (cell * 4))
+    }
+}
+
+/// The game, played from a given board, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{
+///     envs::twenty_forty_eight::{Board, TwentyFortyEight},
+///     mcts::MCTS,
+///     policy::RandomRollout,
+/// };
+///
+/// let game = TwentyFortyEight::new(Board::from_rows([[2, 2, 0, 0], [0, 0, 0, 0], [0, 0, 4, 0], [0, 0, 4, 0]]));
+/// let mut mcts = MCTS::new(game, RandomRollout::new())
+///     .with_seed(1)
+///     .with_chance_nodes()
+///     .with_max_rollout_depth(20);
+/// mcts.run_iterations(500);
+/// assert!(mcts.search().is_some());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwentyFortyEight {
+    start: Board,
+}
+
+impl TwentyFortyEight {
+    /// Searches start from `start`, e.g. [`Board::random_start`]
+    pub fn new(start: Board) -> Self {
+        Self { start }
+    }
+}
+
+impl GenerativeModel<Board, Slide> for TwentyFortyEight {
+    fn get_actions(&self, state: &Board) -> Vec<Slide> {
+        Slide::ALL
+            .into_iter()
+            .filter(|slide| state.slide(*slide).is_some())
+            .collect()
+    }
+
+    /// Panics if the slide moves nothing
+    fn execute(&self, state: &Board, action: &Slide, rng: &mut dyn Rng) -> (Board, f64, bool) {
+        let (afterstate, reward) = state
+            .slide(*action)
+            .unwrap_or_else(|| panic!("{action:?} moves nothing on {state:?}"));
+        let next = afterstate.spawn(rng);
+        (next, reward, self.is_terminal(&next))
+    }
+
+    fn is_terminal(&self, state: &Board) -> bool {
+        Slide::ALL.iter().all(|slide| state.slide(*slide).is_none())
+    }
+
+    fn get_initial_state(&self) -> Board {
+        self.start
+    }
+
+    fn state_key(&self, state: &Board) -> Option<u64> {
+        Some(state.0)
+    }
+
+    /// The chance that the new tile appears where, and as what, it did after the slide
+    fn transition_probability(
+        &self,
+        state: &Board,
+        action: &Slide,
+        next_state: &Board,
+    ) -> Option<f64> {
+        let Some((afterstate, _)) = state.slide(*action) else {
+            return Some(0.0);
+        };
+        let empty = afterstate.empty_cells();
+        let spawned = empty.iter().find(|cell| {
+            afterstate.with_exponent(**cell, next_state.exponent(**cell)) == *next_state
+        });
+        let probability = match spawned.map(|cell| next_state.exponent(*cell)) {
+            Some(1) => TWO_PROBABILITY,
+            Some(2) => 1.0 - TWO_PROBABILITY,
+            _ => 0.0,
+        };
+        Some(probability / empty.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, policy::RandomRollout, rand::SplitMix64};
+
+    use super::*;
+
+    #[test]
+    fn test_tiles_slide_and_merge_once() {
+        let board = Board::from_rows([[2, 2, 2, 2], [2, 2, 4, 0], [0, 4, 0, 4], [8, 0, 0, 0]]);
+        let (left, reward) = board.slide(Slide::Left).unwrap();
+        assert_eq!(
+            left,
+            Board::from_rows([[4, 4, 0, 0], [4, 4, 0, 0], [8, 0, 0, 0], [8, 0, 0, 0]])
+        );
+        assert_eq!(reward, 8.0 + 4.0 + 8.0);
+        let (up, _) = board.slide(Slide::Up).unwrap();
+        assert_eq!(up.tile(0, 0), 4);
+        assert_eq!(up.tile(1, 0), 8);
+        assert_eq!(left.max_tile(), 8);
+
+        // nothing moves left on a board packed to the left without equal neighbours
+        let stuck = Board::from_rows([[2, 4, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]]);
+        assert_eq!(stuck.slide(Slide::Left), None);
+        let game = TwentyFortyEight::new(stuck);
+        assert_eq!(game.get_actions(&stuck), vec![Slide::Down, Slide::Right]);
+
+        let over = Board::from_rows([[2, 4, 2, 4], [4, 2, 4, 2], [2, 4, 2, 4], [4, 2, 4, 2]]);
+        assert!(game.is_terminal(&over));
+        assert!(game.get_actions(&over).is_empty());
+
+        // two 32768 tiles would make a tile too large for a cell, and spill into the next one
+        let top = 1 << 15;
+        let full = Board::from_rows([[top, top, 0, 2], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]]);
+        let (left, reward) = full.slide(Slide::Left).unwrap();
+        assert_eq!(
+            left,
+            Board::from_rows([[top, top, 2, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]])
+        );
+        assert_eq!(reward, 0.0);
+        assert_eq!(left.slide(Slide::Left), None);
+        assert_eq!(left.max_tile(), top);
+    }
+
+    #[test]
+    fn test_spawns_follow_their_probabilities() {
+        let start = Board::random_start(&mut SplitMix64::seed(1));
+        assert_eq!(start.empty_cells().len(), 14);
+
+        let game = TwentyFortyEight::new(start);
+        let mut rng = SplitMix64::seed(2);
+        let action = game.get_actions(&start)[0];
+        let (afterstate, _) = start.slide(action).unwrap();
+        let (next, _, _) = game.execute(&start, &action, &mut rng);
+        assert_eq!(next.empty_cells().len(), afterstate.empty_cells().len() - 1);
+
+        // every cell the new tile can appear on, as a 2 or a 4
+        let total = afterstate
+            .empty_cells()
+            .into_iter()
+            .flat_map(|cell| [1, 2].map(|exponent| afterstate.with_exponent(cell, exponent)))
+            .map(|outcome| {
+                game.transition_probability(&start, &action, &outcome)
+                    .unwrap()
+            })
+            .sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert_eq!(
+            game.transition_probability(&start, &action, &start),
+            Some(0.0)
+        );
+
+        let mut mcts = MCTS::new(game, RandomRollout::new())
+            .with_seed(3)
+            .with_chance_nodes()
+            .with_max_rollout_depth(30);
+        mcts.run_iterations(300);
+        assert!(
+            game.get_actions(&start)
+                .contains(&mcts.search().unwrap().action)
+        );
+    }
+}
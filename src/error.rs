@@ -0,0 +1,85 @@
+use std::{error::Error, fmt::Display};
+
+/// Why a model couldn't produce the next step of the search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MdpError {
+    /// A non-terminal state offered no actions
+    NoActions,
+    /// [`crate::mdp::ExplicitMDP::get_transitions`] returned no transitions for an action
+    NoTransitions,
+    /// The model broke one of the rules of [`crate::mdp::GenerativeModel`], as caught by [`crate::wrappers::Checked`],
+    /// or its states don't replay as [`crate::incremental::IncrementalState`] requires
+    ContractViolation(String),
+    /// None of the states believed possible could have produced the observation, see [`crate::pomcp::POMCP::update`]
+    EmptyBelief,
+    /// Raised by the model itself, from [`crate::mdp::GenerativeModel::try_execute`]
+    Custom(String),
+}
+
+impl Display for MdpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoActions => write!(f, "no actions available from a non-terminal state"),
+            Self::NoTransitions => write!(f, "no transitions for this action"),
+            Self::EmptyBelief => write!(
+                f,
+                "no state of the belief is consistent with the observation"
+            ),
+            Self::ContractViolation(message) => write!(f, "contract violation: {message}"),
+            Self::Custom(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl Error for MdpError {}
+
+/// Why a search failed, for every searcher of the crate.
+/// Each panicking method has a `try_` twin returning this instead, so the search can run where a panic isn't an option
+#[derive(Debug, Clone, PartialEq)]
+pub enum McstError {
+    /// The model failed, see [`MdpError`]
+    Mdp(MdpError),
+    /// The state to search from is terminal, so there is no move to make, only the `value` of the state
+    /// to the searcher (see [`crate::mcts::MCTS::terminal_value`])
+    TerminalRoot { value: f64 },
+    /// The state to search from isn't terminal, but the model has no action for it
+    NoLegalActions,
+    /// The operating system's entropy source, used to seed unseeded searchers, failed
+    Rng(getrandom::Error),
+    /// A value that should have been a number wasn't, e.g. a reward of the model.
+    /// Left alone, it would spread to every statistic it's averaged into
+    NotANumber(&'static str),
+}
+
+impl Display for McstError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mdp(error) => write!(f, "{error}"),
+            Self::TerminalRoot { value } => {
+                write!(f, "can't search from a terminal state, worth {value}")
+            }
+            Self::NoLegalActions => write!(f, "the state to search from has no actions"),
+            Self::Rng(error) => write!(f, "the entropy source failed: {error}"),
+            Self::NotANumber(what) => write!(f, "{what} is NaN"),
+        }
+    }
+}
+
+impl Error for McstError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Mdp(error) => Some(error),
+            // getrandom's error only implements `Error` with its `std` feature, and is already in the message
+            Self::TerminalRoot { .. }
+            | Self::NoLegalActions
+            | Self::Rng(_)
+            | Self::NotANumber(_) => None,
+        }
+    }
+}
+
+impl From<MdpError> for McstError {
+    fn from(error: MdpError) -> Self {
+        Self::Mdp(error)
+    }
+}
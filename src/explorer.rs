@@ -0,0 +1,372 @@
+//! An interactive explorer of a search tree in the terminal (behind the `explorer` feature), for teaching
+//! and for finding out why the search likes a move.
+//!
+//! [`TreeExplorer`] keeps which nodes are expanded and which one is selected, and lays the visible nodes out as rows.
+//! [`TreeExplorer::explore`] drives it with one-letter commands read line by line, which works in any terminal;
+//! frontends with their own event loop and widgets can call the navigation methods and draw [`TreeExplorer::rows`] themselves.
+//! There is no ratatui frontend, as the crate isn't available to this build: this line-based one stands in for it.
+//!
+//! The explorer shares the nodes of the tree (see [`crate::tree`]), so it shows a live search's statistics as they change
+
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    io::{self, BufRead, Write},
+};
+
+use crate::{action::Action, tree::TreeNode};
+
+/// A step down the tree: the index of an edge of a node, and of one of the outcomes of that edge
+type Step = (usize, usize);
+
+/// The order the children of a node are listed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortBy {
+    /// The order the actions were first tried in
+    #[default]
+    Added,
+    /// The most visited first
+    Visits,
+    /// The highest value first
+    Value,
+}
+
+impl SortBy {
+    fn next(self) -> Self {
+        match self {
+            Self::Added => Self::Visits,
+            Self::Visits => Self::Value,
+            Self::Value => Self::Added,
+        }
+    }
+}
+
+/// A visible node of the tree, as the explorer lists it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplorerRow {
+    /// The number of actions between the node the explorer started from and this node
+    pub indent: usize,
+    /// The action leading to the node, with the index of its outcome if it has several
+    pub label: String,
+    /// The visits and value of the action leading to the node (of the node itself for the first row and for chance outcomes)
+    pub visits: usize,
+    pub value: f64,
+    pub expanded: bool,
+    pub is_leaf: bool,
+    pub selected: bool,
+}
+
+struct Entry<S, A> {
+    path: Vec<Step>,
+    node: TreeNode<S, A>,
+    label: String,
+    visits: usize,
+    value: f64,
+}
+
+/// Browses a search tree, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{explorer::TreeExplorer, mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+///
+/// let mdp = FnMdp::new(
+///     || 0u32,
+///     |_state: &u32| vec![1usize, 2],
+///     |state, step, _rng| (state + *step as u32, *step as f64, state + *step as u32 >= 10),
+///     |state| *state >= 10,
+/// );
+/// let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+/// mcts.run_iterations(100);
+///
+/// let mut explorer = TreeExplorer::new(mcts.root());
+/// explorer.jump_to_pv();
+/// print!("{}", explorer.render());
+/// ```
+pub struct TreeExplorer<S, A> {
+    root: TreeNode<S, A>,
+    expanded: HashSet<Vec<Step>>,
+    selected: Vec<Step>,
+    sort: SortBy,
+}
+
+impl<S, A> TreeExplorer<S, A>
+where
+    S: Eq,
+    A: Action,
+{
+    /// Starts with `root` expanded and selected
+    pub fn new(root: TreeNode<S, A>) -> Self {
+        Self {
+            root,
+            expanded: HashSet::from([Vec::new()]),
+            selected: Vec::new(),
+            sort: SortBy::default(),
+        }
+    }
+
+    pub fn with_sort(mut self, sort: SortBy) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn set_sort(&mut self, sort: SortBy) {
+        self.sort = sort;
+    }
+
+    pub fn sort(&self) -> SortBy {
+        self.sort
+    }
+
+    /// The node of the selected row
+    pub fn selected(&self) -> TreeNode<S, A> {
+        self.entries().swap_remove(self.cursor()).node
+    }
+
+    /// The visible nodes, parents before their children
+    pub fn rows(&self) -> Vec<ExplorerRow> {
+        self.entries()
+            .into_iter()
+            .map(|entry| ExplorerRow {
+                indent: entry.path.len(),
+                selected: entry.path == self.selected,
+                expanded: self.expanded.contains(&entry.path),
+                is_leaf: entry.node.is_leaf(),
+                label: entry.label,
+                visits: entry.visits,
+                value: entry.value,
+            })
+            .collect()
+    }
+
+    /// Selects the row above the selected one
+    pub fn up(&mut self) {
+        let cursor = self.cursor();
+        if cursor > 0 {
+            self.selected = self.entries().swap_remove(cursor - 1).path;
+        }
+    }
+
+    /// Selects the row below the selected one
+    pub fn down(&mut self) {
+        let mut entries = self.entries();
+        let cursor = self.cursor();
+        if cursor + 1 < entries.len() {
+            self.selected = entries.swap_remove(cursor + 1).path;
+        }
+    }
+
+    /// Lists the children of the selected node
+    pub fn expand(&mut self) {
+        self.expanded.insert(self.selected.clone());
+    }
+
+    /// Hides the children of the selected node, or selects its parent if they're already hidden
+    pub fn collapse(&mut self) {
+        if !self.expanded.remove(&self.selected) {
+            self.selected.pop();
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        if !self.expanded.remove(&self.selected) {
+            self.expand();
+        }
+    }
+
+    /// Expands the principal variation below the selected node, following the most visited action down to a leaf,
+    /// and selects the leaf
+    pub fn jump_to_pv(&mut self) {
+        let mut node = self.selected_node();
+        let mut seen = HashSet::new();
+        // transpositions can lead back to a node of the variation
+        while seen.insert(node.address()) {
+            self.expanded.insert(self.selected.clone());
+            let edges = node.edges();
+            let Some((index, edge)) = edges
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, edge)| edge.stats().visits)
+            else {
+                break;
+            };
+            self.selected.push((index, 0));
+            node = edge.child().clone();
+        }
+    }
+
+    /// Lays the visible rows out as text, one per line, marking the selected one
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for row in self.rows() {
+            let marker = match (row.is_leaf, row.expanded) {
+                (true, _) => ' ',
+                (false, true) => '-',
+                (false, false) => '+',
+            };
+            text.push_str(&format!(
+                "{}{}{marker} {}  visits {}  value {:.3}\n",
+                if row.selected { '>' } else { ' ' },
+                "  ".repeat(row.indent),
+                row.label,
+                row.visits,
+                row.value
+            ));
+        }
+        text
+    }
+
+    /// Runs the explorer until `q` or the end of `input`, writing the tree to `output` after every line of commands.
+    /// Every character of a line is a command:
+    /// - `j`/`k` select the next/previous row
+    /// - `l`/`h` expand/collapse the selected node, `t` toggles it
+    /// - `p` jumps along the principal variation
+    /// - `s` sorts the children by the next of [`SortBy`]
+    ///
+    /// e.g. `explorer.explore(std::io::stdin().lock(), std::io::stdout())`
+    pub fn explore<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        const HELP: &str = "j/k: down/up  l/h: expand/collapse  t: toggle  p: principal variation  s: sort  q: quit";
+
+        writeln!(output, "{}{HELP}", self.render())?;
+        for line in input.lines() {
+            for command in line?.chars() {
+                match command {
+                    'j' => self.down(),
+                    'k' => self.up(),
+                    'l' => self.expand(),
+                    'h' => self.collapse(),
+                    't' => self.toggle(),
+                    'p' => self.jump_to_pv(),
+                    's' => self.sort = self.sort.next(),
+                    'q' => return Ok(()),
+                    _ => {}
+                }
+            }
+            writeln!(output, "{}sorted by {:?}  {HELP}", self.render(), self.sort)?;
+        }
+        Ok(())
+    }
+
+    /// The index of the selected row
+    fn cursor(&self) -> usize {
+        self.entries()
+            .iter()
+            .position(|entry| entry.path == self.selected)
+            .unwrap_or(0)
+    }
+
+    fn selected_node(&self) -> TreeNode<S, A> {
+        let mut node = self.root.clone();
+        for &(edge, outcome) in &self.selected {
+            node = node.edges()[edge].outcomes()[outcome].0.clone();
+        }
+        node
+    }
+
+    /// The visible nodes, parents before their children
+    fn entries(&self) -> Vec<Entry<S, A>> {
+        let mut entries = Vec::new();
+        // iterative, so that very deep trees don't overflow the stack.
+        // Only the finitely many expanded paths are listed, so transpositions can't loop forever
+        let mut stack = vec![Entry {
+            path: Vec::new(),
+            node: self.root.clone(),
+            label: String::from("root"),
+            visits: self.root.visits(),
+            value: self.root.value(),
+        }];
+        while let Some(entry) = stack.pop() {
+            if self.expanded.contains(&entry.path) {
+                // reversed, so that the first child is listed first
+                let start = stack.len();
+                stack.extend(self.children(&entry));
+                stack[start..].reverse();
+            }
+            entries.push(entry);
+        }
+        entries
+    }
+
+    fn children(&self, parent: &Entry<S, A>) -> Vec<Entry<S, A>> {
+        let mut children = Vec::new();
+        for (index, edge) in parent.node.edges().iter().enumerate() {
+            let outcomes = edge.outcomes();
+            for (outcome, (node, _)) in outcomes.iter().enumerate() {
+                let mut path = parent.path.clone();
+                path.push((index, outcome));
+                let (label, visits, value) = if outcomes.len() > 1 {
+                    let label = format!("{:?} #{outcome}", edge.action());
+                    (label, node.visits(), node.value())
+                } else {
+                    let stats = edge.stats();
+                    (format!("{:?}", edge.action()), stats.visits, stats.value)
+                };
+                children.push(Entry {
+                    path,
+                    node: node.clone(),
+                    label,
+                    visits,
+                    value,
+                });
+            }
+        }
+
+        match self.sort {
+            SortBy::Added => {}
+            SortBy::Visits => children.sort_by_key(|child| Reverse(child.visits)),
+            SortBy::Value => children.sort_by(|a, b| b.value.total_cmp(&a.value)),
+        }
+        children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+
+    use super::*;
+
+    #[test]
+    fn test_explorer_navigates_the_tree() {
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_state: &u32| vec![1usize, 2],
+            |state, step, _rng| {
+                (
+                    state + *step as u32,
+                    *step as f64,
+                    state + *step as u32 >= 6,
+                )
+            },
+            |state| *state >= 6,
+        );
+        let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+        mcts.run_iterations(200);
+
+        let mut explorer = TreeExplorer::new(mcts.root()).with_sort(SortBy::Visits);
+        let rows = explorer.rows();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].selected && rows[0].expanded);
+        assert!(rows[1].visits >= rows[2].visits);
+
+        explorer.jump_to_pv();
+        let mut leaf = mcts.root();
+        while let Some(edge) = leaf.edges().into_iter().max_by_key(|e| e.stats().visits) {
+            leaf = edge.child().clone();
+        }
+        assert_eq!(explorer.selected().depth(), leaf.depth());
+        assert!(
+            explorer
+                .rows()
+                .iter()
+                .any(|row| row.selected && row.is_leaf)
+        );
+
+        // back up to the root, which hides everything below it
+        let mut output = Vec::new();
+        let commands = "h".repeat(2 * leaf.depth()) + "\nh\nq\nj\n";
+        explorer.explore(commands.as_bytes(), &mut output).unwrap();
+        assert_eq!(explorer.rows().len(), 1);
+        assert_eq!(explorer.selected().depth(), 0);
+        assert!(String::from_utf8(output).unwrap().contains(">+ root"));
+    }
+}
@@ -0,0 +1,503 @@
+//! Exports of the search tree for external tools, starting from any [`TreeNode`] (see [`crate::mcts::MCTS::root`]).
+//!
+//! [`JsonSnapshot`] writes the tree as nested JSON objects, for notebooks and visualizers,
+//! and [`CsvExport`] as a flat table with a row per node, for dataframes and spreadsheets.
+//! [`TreeSnapshot`] copies the tree with its states and actions, in the same order as the CSV rows,
+//! to log or persist it with serde (behind the `serde` feature).
+//! Nodes shared through transpositions (see [`crate::mcts::MCTS::with_transpositions`]) are written once under every edge leading to them in JSON,
+//! but only get one row in CSV and one entry in snapshots
+
+use std::{
+    collections::HashSet,
+    fmt::{Display, Write},
+};
+
+use crate::{action::Action, tree::TreeNode};
+
+/// How [`JsonSnapshot`] writes the states of the nodes
+enum StateFormat<'f, S> {
+    /// As a JSON string of the given text
+    Text(Box<dyn Fn(&S) -> String + 'f>),
+    /// As the given JSON, verbatim
+    Json(Box<dyn Fn(&S) -> String + 'f>),
+}
+
+/// Writes a (part of a) search tree as JSON. Every node is an object with its `depth`, `player`, `visits`, `value`
+/// and, if asked for, `state`, along with a `children` array of the actions explored from it:
+///
+/// ```json
+/// { "depth": 0, "player": 0, "visits": 100, "value": 0.5, "state": "...", "children": [
+///     { "action": "Left", "visits": 60, "value": 0.6, "outcomes": [{ "reward": 0.0, "node": { ... } }] }
+/// ] }
+/// ```
+///
+/// Actions are written with their `Debug` representation. An action has several outcomes only with chance nodes (see [`crate::mcts::MCTS::with_chance_nodes`]),
+/// and values that aren't finite numbers are written as `null`
+///
+/// ```
+/// use mct_rs::{export::JsonSnapshot, mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+///
+/// let mdp = FnMdp::new(
+///     || 0u32,
+///     |_state: &u32| vec![1usize, 2],
+///     |state, step, _rng| (state + *step as u32, *step as f64, state + *step as u32 >= 10),
+///     |state| *state >= 10,
+/// );
+/// let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+/// mcts.run_iterations(100);
+///
+/// let json = JsonSnapshot::new()
+///     .with_max_depth(2)
+///     .with_min_visits(5)
+///     .with_state(|state: &u32| state.to_string())
+///     .to_json(&mcts.root());
+/// assert!(json.starts_with(r#"{"depth":0,"player":0,"visits":100,"#));
+/// ```
+pub struct JsonSnapshot<'f, S> {
+    max_depth: Option<usize>,
+    min_visits: usize,
+    state: Option<StateFormat<'f, S>>,
+}
+
+impl<S> Default for JsonSnapshot<'_, S> {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            min_visits: 0,
+            state: None,
+        }
+    }
+}
+
+impl<'f, S> JsonSnapshot<'f, S> {
+    /// Writes the whole tree, without the states
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves out the children of the nodes this many actions below the node the snapshot starts from
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Leaves out the actions taken less than this many times, and everything below them
+    pub fn with_min_visits(mut self, visits: usize) -> Self {
+        self.min_visits = visits;
+        self
+    }
+
+    /// Writes the states as JSON strings of the text returned by `display`
+    pub fn with_state<F: Fn(&S) -> String + 'f>(mut self, display: F) -> Self {
+        self.state = Some(StateFormat::Text(Box::new(display)));
+        self
+    }
+
+    /// Writes the states as the JSON returned by `serialize`, e.g. `|state| serde_json::to_string(state).unwrap()`.
+    /// The JSON isn't checked, so invalid JSON makes the whole snapshot invalid
+    pub fn with_state_json<F: Fn(&S) -> String + 'f>(mut self, serialize: F) -> Self {
+        self.state = Some(StateFormat::Json(Box::new(serialize)));
+        self
+    }
+
+    /// Writes the subtree rooted at `node`
+    pub fn to_json<A>(&self, node: &TreeNode<S, A>) -> String
+    where
+        S: Eq,
+        A: Action,
+    {
+        let mut json = String::new();
+        self.write_node(&mut json, node, 0);
+        json
+    }
+
+    fn write_node<A>(&self, json: &mut String, node: &TreeNode<S, A>, depth: usize)
+    where
+        S: Eq,
+        A: Action,
+    {
+        json.push_str(&format!(
+            r#"{{"depth":{},"player":{},"visits":{},"value":{}"#,
+            node.depth(),
+            node.player(),
+            node.visits(),
+            number(node.value())
+        ));
+
+        match &self.state {
+            Some(StateFormat::Text(display)) => {
+                json.push_str(r#","state":"#);
+                push_string(json, &display(node.state()));
+            }
+            Some(StateFormat::Json(serialize)) => {
+                json.push_str(r#","state":"#);
+                json.push_str(&serialize(node.state()));
+            }
+            None => {}
+        }
+
+        if self.max_depth.is_none_or(|max| depth < max) {
+            json.push_str(r#","children":["#);
+            let edges = node.edges();
+            let edges = edges
+                .iter()
+                .filter(|edge| edge.stats().visits >= self.min_visits);
+            for (i, edge) in edges.enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                json.push_str(r#"{"action":"#);
+                push_string(json, &format!("{:?}", edge.action()));
+                json.push_str(&format!(
+                    r#","visits":{},"value":{},"outcomes":["#,
+                    edge.stats().visits,
+                    number(edge.stats().value)
+                ));
+                for (j, (child, reward)) in edge.outcomes().iter().enumerate() {
+                    if j > 0 {
+                        json.push(',');
+                    }
+                    json.push_str(&format!(r#"{{"reward":{},"node":"#, number(*reward)));
+                    self.write_node(json, child, depth + 1);
+                    json.push('}');
+                }
+                json.push_str("]}");
+            }
+            json.push(']');
+        }
+
+        json.push('}');
+    }
+}
+
+/// Writes a (part of a) search tree as CSV, with a row per node and the columns
+/// `id,parent,action,visits,value,depth`:
+/// - `id` numbers the nodes from 0 for the node the export starts from, parents before their children
+/// - `parent` and `action` are the node's parent and the action leading from it to the node, both empty for the first node.
+///   Nodes shared through transpositions are listed under the first parent found
+/// - `visits` and `value` are the node's statistics, the value being from the perspective of the player who moved into the node
+/// - `depth` is the number of actions between the root of the search and the node
+///
+/// Actions are written with their `Debug` representation, quoted when needed
+pub struct CsvExport {
+    delimiter: char,
+}
+
+impl Default for CsvExport {
+    fn default() -> Self {
+        Self { delimiter: ',' }
+    }
+}
+
+impl CsvExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Separates the columns with `delimiter` rather than commas, e.g. `'\t'` for TSV
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Writes the subtree rooted at `node`, header included
+    pub fn to_csv<S, A>(&self, node: &TreeNode<S, A>) -> String
+    where
+        S: Eq,
+        A: Action,
+    {
+        let d = self.delimiter;
+        let mut csv = format!("id{d}parent{d}action{d}visits{d}value{d}depth\n");
+
+        preorder(node, |id, parent, action, node| {
+            let parent = parent.map_or(String::new(), |parent| parent.to_string());
+            let action = action.map_or(String::new(), |action| self.field(&format!("{action:?}")));
+            let _ = writeln!(
+                csv,
+                "{id}{d}{parent}{d}{action}{d}{}{d}{}{d}{}",
+                node.visits(),
+                node.value(),
+                node.depth()
+            );
+        });
+
+        csv
+    }
+
+    /// Quotes the field if it holds a delimiter, quote or line break
+    fn field(&self, text: &str) -> String {
+        if text.contains([self.delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Visits every node of the subtree rooted at `node` once, parents before their children and children in order,
+/// with the node's number in that order, its parent's, and the action leading from the parent to it
+fn preorder<S, A, F>(node: &TreeNode<S, A>, mut visit: F)
+where
+    S: Eq,
+    A: Action,
+    F: FnMut(usize, Option<usize>, Option<&A>, &TreeNode<S, A>),
+{
+    let mut seen = HashSet::new();
+    let mut ids = 0;
+    // (node, parent id, action) - iterative, so that very deep trees don't overflow the stack
+    let mut stack = vec![(node.clone(), None, None)];
+    while let Some((node, parent, action)) = stack.pop() {
+        if !seen.insert(node.address()) {
+            continue;
+        }
+        let id = ids;
+        ids += 1;
+        visit(id, parent, action.as_ref(), &node);
+
+        // reversed, so that the first child is visited first
+        let start = stack.len();
+        for edge in node.edges() {
+            for (child, _) in edge.outcomes() {
+                stack.push((child.clone(), Some(id), Some(edge.action().clone())));
+            }
+        }
+        stack[start..].reverse();
+    }
+}
+
+/// An owned copy of a (part of a) search tree: a node per entry, numbered and ordered like the rows of [`CsvExport`].
+/// Serializable (behind the `serde` feature) when the states and actions are
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeSnapshot<S, A> {
+    pub nodes: Vec<SnapshotNode<S, A>>,
+}
+
+/// A node of a [`TreeSnapshot`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotNode<S, A> {
+    /// The index of the node in the snapshot
+    pub id: usize,
+    /// The index of its parent, None for the first node
+    pub parent: Option<usize>,
+    /// The action leading from the parent to the node, None for the first node
+    pub action: Option<A>,
+    pub state: S,
+    pub visits: usize,
+    /// The mean return of the visits, from the perspective of the player who moved into the node
+    pub value: f64,
+    /// The number of actions between the root of the search and the node
+    pub depth: usize,
+}
+
+impl<S, A> TreeSnapshot<S, A>
+where
+    S: Clone + Eq,
+    A: Action,
+{
+    /// Copies the subtree rooted at `node`
+    pub fn capture(node: &TreeNode<S, A>) -> Self {
+        let mut nodes = vec![];
+        preorder(node, |id, parent, action, node| {
+            nodes.push(SnapshotNode {
+                id,
+                parent,
+                action: action.cloned(),
+                state: node.state().clone(),
+                visits: node.visits(),
+                value: node.value(),
+                depth: node.depth(),
+            });
+        });
+        Self { nodes }
+    }
+}
+
+/// A JSON number, or `null` for the values JSON can't represent
+fn number(value: f64) -> impl Display {
+    struct Number(f64);
+
+    impl Display for Number {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            if self.0.is_finite() {
+                write!(f, "{}", self.0)
+            } else {
+                f.write_str("null")
+            }
+        }
+    }
+
+    Number(value)
+}
+
+/// Appends `text` as a JSON string, escaping what needs to be
+fn push_string(json: &mut String, text: &str) {
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{budget::Budget, mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Step {
+        One,
+        Named(&'static str),
+    }
+
+    impl Action for Step {}
+
+    #[test]
+    fn test_csv_has_a_row_per_node() {
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_state: &u32| vec![Step::One, Step::Named("two, or \"2\"")],
+            |state, step, _rng| {
+                let next = state + if *step == Step::One { 1 } else { 2 };
+                (next, 1.0, next >= 6)
+            },
+            |state| *state >= 6,
+        );
+        let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+        mcts.run_iterations(100);
+        let root = mcts.root();
+
+        let csv = CsvExport::new().to_csv(&root);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("id,parent,action,visits,value,depth"));
+        assert_eq!(
+            lines.next(),
+            Some(format!("0,,,100,{},0", root.value()).as_str())
+        );
+        assert_eq!(lines.count(), mcts.tree_stats().nodes - 1);
+        assert!(csv.contains(r#",0,"Named(""two, or \""2\"""")","#));
+
+        let tsv = CsvExport::new().with_delimiter('\t').to_csv(&root);
+        assert!(tsv.starts_with("id\tparent\taction\tvisits\tvalue\tdepth\n"));
+        assert!(tsv.contains("\t0\tOne\t"));
+    }
+
+    #[test]
+    fn test_json_snapshot_respects_the_limits() {
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_state: &u32| vec![1usize, 2],
+            |state, step, _rng| (state + *step as u32, 1.0, state + *step as u32 >= 6),
+            |state| *state >= 6,
+        );
+        let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+        mcts.run_iterations(200);
+        let root = mcts.root();
+
+        let shallow = JsonSnapshot::new().with_max_depth(1).to_json(&root);
+        // the root and its children, which have no children listed
+        assert_eq!(
+            shallow.matches(r#""depth":"#).count(),
+            1 + root.edges().len()
+        );
+        assert_eq!(shallow.matches(r#""children":"#).count(), 1);
+
+        let mut nodes = 0;
+        root.walk(|_| {
+            nodes += 1;
+            true
+        });
+        let full = JsonSnapshot::new()
+            .with_state(|state: &u32| format!("\"{state}\""))
+            .to_json(&root);
+        assert_eq!(full.matches(r#""depth":"#).count(), nodes);
+        assert!(full.contains(r#""state":"\"0\"""#));
+
+        let popular = JsonSnapshot::new()
+            .with_min_visits(usize::MAX)
+            .with_state_json(|state: &u32| state.to_string())
+            .to_json(&root);
+        let value = number(root.value());
+        assert_eq!(
+            popular,
+            format!(
+                r#"{{"depth":0,"player":0,"visits":200,"value":{value},"state":0,"children":[]}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_snapshots_follow_the_csv_rows() {
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_state: &u32| vec![1usize, 2],
+            |state, step, _rng| (state + *step as u32, 1.0, state + *step as u32 >= 6),
+            |state| *state >= 6,
+        );
+        let mut mcts = MCTS::new(mdp, RandomRollout::new())
+            .with_seed(1)
+            .with_budget(Budget::Iterations(100));
+        #[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+        let result = mcts.search().unwrap();
+        let root = mcts.root();
+
+        let snapshot = TreeSnapshot::capture(&root);
+        assert_eq!(snapshot.nodes.len(), mcts.tree_stats().nodes);
+        let csv = CsvExport::new().to_csv(&root);
+        for (node, row) in snapshot.nodes.iter().zip(csv.lines().skip(1)) {
+            let parent = node
+                .parent
+                .map_or(String::new(), |parent| parent.to_string());
+            let action = node
+                .action
+                .map_or(String::new(), |action| action.to_string());
+            let expected = format!(
+                "{},{parent},{action},{},{},{}",
+                node.id, node.visits, node.value, node.depth
+            );
+            assert_eq!(row, expected);
+        }
+        assert_eq!(snapshot.nodes[0].state, 0);
+        assert!(snapshot.nodes[1..].iter().all(|node| {
+            let parent = &snapshot.nodes[node.parent.unwrap()];
+            node.state == parent.state + node.action.unwrap() as u32
+        }));
+
+        #[cfg(feature = "serde")]
+        {
+            use crate::{stats::SearchResult, strategy::Strategy};
+
+            let json = serde_json::to_string(&snapshot).unwrap();
+            assert_eq!(
+                serde_json::from_str::<TreeSnapshot<u32, usize>>(&json).unwrap(),
+                snapshot
+            );
+            let json = serde_json::to_string(&result).unwrap();
+            assert_eq!(
+                serde_json::from_str::<SearchResult<usize>>(&json).unwrap(),
+                result
+            );
+
+            assert_eq!(
+                serde_json::to_string(&Strategy::HighestQValue).unwrap(),
+                r#""highest_q_value""#
+            );
+            assert_eq!(
+                serde_json::from_str::<Budget>(r#"{"iterations":100}"#).unwrap(),
+                Budget::Iterations(100)
+            );
+        }
+    }
+}
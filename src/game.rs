@@ -0,0 +1,229 @@
+//! Turn-based board games, described by their positions rather than as an MDP.
+//!
+//! A [`Game`] only says which moves are legal, what a move does, whose turn it is and who won;
+//! [`GameMdp`] turns that into the [`GenerativeModel`] the searchers need, with a reward of 1 for the move that wins,
+//! -1 for a move that makes another player win, and 0 otherwise (draws included).
+//! It also tells draws apart from wins (see [`GenerativeModel::outcome`]), to score them with [`crate::mcts::MCTS::with_draw_score`]
+
+use crate::{
+    action::Action,
+    mdp::{GenerativeModel, Outcome, PlayerId},
+    rand::Rng,
+};
+
+/// A position of a deterministic turn-based game, with the player to move
+pub trait Game: Clone + Eq {
+    type Move: Action;
+
+    /// The moves the player to move can make. The game is over once there are none, or someone won
+    fn legal_moves(&self) -> Vec<Self::Move>;
+
+    /// Plays a legal move, and hands the turn to the next player
+    fn make_move(&mut self, mv: &Self::Move);
+
+    /// The player who won, if the game is over and not a draw
+    fn winner(&self) -> Option<PlayerId>;
+
+    /// The player to move, numbered from 0
+    fn current_player(&self) -> PlayerId;
+
+    /// The number of players, 2 by default
+    fn num_players(&self) -> usize {
+        2
+    }
+
+    /// A hash of the position, to share the statistics of positions reached through different orders of moves,
+    /// see [`GenerativeModel::state_key`]
+    fn key(&self) -> Option<u64> {
+        None
+    }
+
+    /// The canonical form of the position among its symmetric ones (e.g. its mirror image), if it isn't that already,
+    /// to share the statistics of symmetric positions, see [`GenerativeModel::canonicalize`]
+    fn canonical(&self) -> Option<Self> {
+        None
+    }
+}
+
+/// A [`Game`] as an MDP, played from a starting position, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{game::{Game, GameMdp}, mcts::MCTS, mdp::PlayerId, policy::RandomRollout};
+///
+/// // players take turns adding 1 or 2 to a total, and whoever reaches 10 wins
+/// #[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// struct Race {
+///     total: usize,
+///     player: PlayerId,
+/// }
+///
+/// impl Game for Race {
+///     type Move = usize;
+///
+///     fn legal_moves(&self) -> Vec<usize> {
+///         match self.winner() {
+///             Some(_) => vec![],
+///             None => vec![1, 2],
+///         }
+///     }
+///
+///     fn make_move(&mut self, step: &usize) {
+///         self.total += step;
+///         self.player = 1 - self.player;
+///     }
+///
+///     fn winner(&self) -> Option<PlayerId> {
+///         // the player who just moved
+///         (self.total >= 10).then_some(1 - self.player)
+///     }
+///
+///     fn current_player(&self) -> PlayerId {
+///         self.player
+///     }
+/// }
+///
+/// let mut mcts = MCTS::new(GameMdp::new(Race::default()), RandomRollout::new()).with_seed(1);
+/// let result = mcts.search().unwrap();
+/// // leaving a multiple of 3 to go wins
+/// assert_eq!(result.action, 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GameMdp<G> {
+    start: G,
+}
+
+impl<G: Game> GameMdp<G> {
+    /// The searches start from `start`, e.g. `G::default()` for the initial position
+    pub fn new(start: G) -> Self {
+        Self { start }
+    }
+
+    /// The reward of a move that ended the game with this outcome, for the player who made it
+    fn reward(mover: PlayerId, winner: Option<PlayerId>) -> f64 {
+        match winner {
+            Some(winner) if winner == mover => 1.0,
+            Some(_) => -1.0,
+            None => 0.0,
+        }
+    }
+}
+
+impl<G: Game> GenerativeModel<G, G::Move> for GameMdp<G> {
+    fn get_actions(&self, state: &G) -> Vec<G::Move> {
+        state.legal_moves()
+    }
+
+    fn execute(&self, state: &G, action: &G::Move, _rng: &mut dyn Rng) -> (G, f64, bool) {
+        let mut next = state.clone();
+        next.make_move(action);
+        let done = self.is_terminal(&next);
+        let reward = match done {
+            true => Self::reward(state.current_player(), next.winner()),
+            false => 0.0,
+        };
+        (next, reward, done)
+    }
+
+    fn is_terminal(&self, state: &G) -> bool {
+        state.winner().is_some() || state.legal_moves().is_empty()
+    }
+
+    fn get_initial_state(&self) -> G {
+        self.start.clone()
+    }
+
+    fn player_to_move(&self, state: &G) -> PlayerId {
+        state.current_player()
+    }
+
+    fn num_players(&self) -> usize {
+        self.start.num_players()
+    }
+
+    fn state_key(&self, state: &G) -> Option<u64> {
+        state.key()
+    }
+
+    fn canonicalize(&self, state: &G) -> Option<G> {
+        state.canonical()
+    }
+
+    fn outcome(&self, state: &G) -> Option<Outcome> {
+        match state.winner() {
+            Some(winner) => Some(Outcome::Win(winner)),
+            None if self.is_terminal(state) => Some(Outcome::Draw),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, policy::RandomRollout, rand::SplitMix64};
+
+    use super::*;
+
+    /// Three in a row on a line of 5 cells, which two players fill in turn
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    struct Line {
+        cells: [Option<PlayerId>; 5],
+        player: PlayerId,
+    }
+
+    impl Game for Line {
+        type Move = usize;
+
+        fn legal_moves(&self) -> Vec<usize> {
+            match self.winner() {
+                Some(_) => vec![],
+                None => (0..5).filter(|&cell| self.cells[cell].is_none()).collect(),
+            }
+        }
+
+        fn make_move(&mut self, cell: &usize) {
+            self.cells[*cell] = Some(self.player);
+            self.player = 1 - self.player;
+        }
+
+        fn winner(&self) -> Option<PlayerId> {
+            self.cells.windows(3).find_map(|cells| match cells {
+                [Some(a), Some(b), Some(c)] if a == b && b == c => Some(*a),
+                _ => None,
+            })
+        }
+
+        fn current_player(&self) -> PlayerId {
+            self.player
+        }
+    }
+
+    #[test]
+    fn test_games_are_searched_as_mdps() {
+        // player 0 to move, and one cell away from three in a row
+        let start = Line {
+            cells: [None, Some(0), Some(0), Some(1), None],
+            player: 0,
+        };
+        let mdp = GameMdp::new(start.clone());
+        let mut rng = SplitMix64::seed(1);
+
+        let (won, reward, done) = mdp.execute(&start, &0, &mut rng);
+        assert_eq!((reward, done), (1.0, true));
+        assert!(mdp.get_actions(&won).is_empty());
+        assert_eq!(mdp.outcome(&won), Some(Outcome::Win(0)));
+
+        // missing it leaves player 1 the last cell, and a draw
+        let (missed, reward, done) = mdp.execute(&start, &4, &mut rng);
+        assert_eq!((reward, done), (0.0, false));
+        assert_eq!(mdp.player_to_move(&missed), 1);
+        assert_eq!(mdp.outcome(&missed), None);
+        let (drawn, reward, done) = mdp.execute(&missed, &0, &mut rng);
+        assert_eq!((reward, done), (0.0, true));
+        assert_eq!(mdp.outcome(&drawn), Some(Outcome::Draw));
+
+        let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+        let result = mcts.search().unwrap();
+        assert_eq!(result.action, 0);
+        assert_eq!(result.value, 1.0);
+    }
+}
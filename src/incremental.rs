@@ -0,0 +1,24 @@
+/// A state that can be advanced and rewound in place.
+///
+/// For games with large states, cloning the state for every step of every rollout dominates the runtime.
+/// States implementing this trait can be searched with [`crate::mcts::MCTS::mcts_incremental`],
+/// which keeps a single scratch copy of the root state, applies the actions along the selected path and the rollout to it,
+/// and then undoes them all to get back to the root for the next iteration.
+///
+/// The tree still keeps a state per node, so only the rollouts avoid cloning.
+///
+/// `apply` is expected to agree with the MDP's `execute` for the same action, and must be deterministic:
+/// stochastic MDPs should keep using [`crate::mcts::MCTS::mcts`]
+pub trait IncrementalState<A> {
+    /// Whatever is needed to restore the state as it was before an action was applied (e.g. the captured piece)
+    type Undo;
+
+    /// Applies the action to this state in place
+    fn apply(&mut self, action: &A) -> Self::Undo;
+
+    /// Reverts the action that produced `undo`. Actions are always undone in the reverse order they were applied in
+    fn undo(&mut self, undo: Self::Undo);
+
+    /// The reward of the transition that was just applied, and described by `undo`
+    fn reward(&self, undo: &Self::Undo) -> f64;
+}
@@ -0,0 +1,23 @@
+//! Games of imperfect information, where players don't see the whole state (e.g. the cards in their opponents' hands).
+//!
+//! See [`crate::ismcts::ISMCTS`] for how to search them
+
+use crate::{
+    mdp::{GenerativeModel, PlayerId},
+    rand::Rng,
+};
+
+/// A game whose players only see part of the state: all the states a player can't tell apart form one of their information sets.
+///
+/// The state passed to the [`GenerativeModel`] methods is the true state, hidden information included,
+/// and the actions available to a player must be the same in all the states of their information set
+pub trait InformationSetMDP<S, A, I>: GenerativeModel<S, A> {
+    /// Returns what `player` knows of the state.
+    /// Two states return the same information set if and only if the player can't tell them apart
+    fn information_set(&self, state: &S, player: PlayerId) -> I;
+
+    /// Samples a determinization of the state for `player`: a state from their information set,
+    /// i.e. the state with everything hidden from the player guessed anew (e.g. by dealing the unseen cards at random).
+    /// Any randomness must be drawn from `rng`, so that seeded searches are reproducible
+    fn determinize(&self, state: &S, player: PlayerId, rng: &mut dyn Rng) -> S;
+}
@@ -0,0 +1,405 @@
+//! Single-observer Information Set MCTS (Cowling, Powley & Whitehouse, 2012), for games of imperfect information.
+//!
+//! Searching the true state would let the search cheat by looking at hidden information, e.g. the opponents' cards.
+//! Instead, every iteration samples a determinization of the state (see [`InformationSetMDP::determinize`]), and descends the tree
+//! with the actions available in it. The nodes of the tree are the searching player's information sets,
+//! so their statistics are shared by all the determinizations the player can't tell apart.
+//!
+//! As actions are only available in some determinizations, UCB1 counts how many times an action was available
+//! rather than how many times its parent was visited
+
+use crate::{
+    action::Action,
+    budget::{Budget, Tracker},
+    error::{McstError, MdpError},
+    information_set::InformationSetMDP,
+    mdp::PlayerId,
+    policy::RolloutPolicy,
+    rand::Rng,
+    returns::Returns,
+    setup::{Configurable, SearchSetup},
+    ucb1::UCB1,
+};
+
+/// A node of the search tree: one of the searching player's information sets
+#[derive(Debug)]
+struct InfoSetNode<A, I> {
+    info: I,
+    /// N(v) - Number of iterations that went through this information set
+    visits: usize,
+    edges: Vec<InfoSetEdge<A, I>>,
+}
+
+/// An action taken from an information set, and the information sets it led to
+#[derive(Debug)]
+struct InfoSetEdge<A, I> {
+    action: A,
+    /// N(a) - Number of times the action was taken
+    visits: usize,
+    /// N'(a) - Number of times the action was available when its information set was visited
+    availability: usize,
+    /// Q(a) - Total return of the action, from the perspective of the player taking it
+    score: f64,
+    /// Taking the same action can reveal different information (e.g. the card that was drawn), hence as many nodes as information sets reached
+    outcomes: Vec<InfoSetNode<A, I>>,
+}
+
+impl<A, I> InfoSetNode<A, I>
+where
+    A: Action,
+    I: PartialEq,
+{
+    fn new(info: I) -> Self {
+        Self {
+            info,
+            visits: 0,
+            edges: vec![],
+        }
+    }
+
+    /// Runs one iteration from `state`, a determinization of this information set, and returns the returns of the players.
+    /// Descends the tree until the first action never taken from an information set, which is expanded and followed by a rollout
+    #[allow(clippy::too_many_arguments)]
+    fn iterate<M, S, P>(
+        &mut self,
+        mdp: &M,
+        policy: &P,
+        state: S,
+        observer: PlayerId,
+        tracker: &Tracker<'_>,
+        rng: &mut dyn Rng,
+    ) -> Result<Returns, MdpError>
+    where
+        M: InformationSetMDP<S, A, I>,
+        S: Clone,
+        P: RolloutPolicy<M, S, A>,
+    {
+        self.visits += 1;
+        if mdp.is_terminal(&state) {
+            return Ok(Returns::new(mdp.num_players()));
+        }
+
+        let actions = mdp.get_actions(&state);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions);
+        }
+
+        self.edges
+            .iter_mut()
+            .filter(|edge| actions.contains(&edge.action))
+            .for_each(|edge| edge.availability += 1);
+
+        let untried = actions
+            .iter()
+            .filter(|a| self.edges.iter().all(|edge| edge.action != **a))
+            .cloned()
+            .collect::<Vec<_>>();
+        let expanding = !untried.is_empty();
+
+        let index = if expanding {
+            self.edges.push(InfoSetEdge {
+                action: policy.pick(&state, &untried, rng),
+                visits: 0,
+                availability: 1,
+                score: 0.0,
+                outcomes: vec![],
+            });
+            self.edges.len() - 1
+        } else {
+            self.select(&actions, rng)
+        };
+
+        let mover = mdp.player_to_move(&state);
+        let edge = &mut self.edges[index];
+        let (next_state, reward, _) = mdp.try_execute(&state, &edge.action, rng)?;
+
+        let info = mdp.information_set(&next_state, observer);
+        let child = match edge.outcomes.iter().position(|node| node.info == info) {
+            Some(child) => child,
+            None => {
+                edge.outcomes.push(InfoSetNode::new(info));
+                edge.outcomes.len() - 1
+            }
+        };
+        let child = &mut edge.outcomes[child];
+
+        let mut returns = if expanding {
+            child.visits += 1;
+            rollout(mdp, policy, next_state, tracker, rng)?
+        } else {
+            child.iterate(mdp, policy, next_state, observer, tracker, rng)?
+        };
+        returns.credit(mover, reward);
+
+        edge.visits += 1;
+        edge.score += returns.of(mover);
+        Ok(returns)
+    }
+
+    /// Returns the index of the edge with the highest UCB1 score among those whose action is available
+    fn select(&self, actions: &[A], rng: &mut dyn Rng) -> usize {
+        let scores = self
+            .edges
+            .iter()
+            .map(|edge| {
+                if !actions.contains(&edge.action) {
+                    return f64::NEG_INFINITY;
+                }
+
+                let visits = edge.visits.max(1) as f64;
+                let exploration = (edge.availability.max(1) as f64).ln() / visits;
+                edge.score / visits + UCB1::C * exploration.sqrt()
+            })
+            .collect::<Vec<_>>();
+        let best = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        //  if there are multiple actions with the highest value choose one randomly
+        let best_edges = (0..scores.len())
+            .filter(|i| scores[*i] == best)
+            .collect::<Vec<_>>();
+        best_edges[rng.gen_range(0, best_edges.len())]
+    }
+}
+
+/// Plays the rollout policy from `state` until the end of the game, and returns the cumulative rewards of the players
+fn rollout<M, S, A, I, P>(
+    mdp: &M,
+    policy: &P,
+    mut state: S,
+    tracker: &Tracker<'_>,
+    rng: &mut dyn Rng,
+) -> Result<Returns, MdpError>
+where
+    M: InformationSetMDP<S, A, I>,
+    A: Action,
+    P: RolloutPolicy<M, S, A>,
+{
+    let mut returns = Returns::new(mdp.num_players());
+
+    while !mdp.is_terminal(&state) && !tracker.out_of_time() {
+        let actions = mdp.get_actions(&state);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions);
+        }
+
+        let action = policy.pick(&state, &actions, rng);
+        let mover = mdp.player_to_move(&state);
+        let (next_state, reward, _) = mdp.try_execute(&state, &action, rng)?;
+        returns.credit(mover, reward);
+        state = next_state;
+    }
+
+    Ok(returns)
+}
+
+/// Searches an [`InformationSetMDP`] for the best action of the player to move in its initial state,
+/// using only what that player knows of the state
+pub struct ISMCTS<M, S, A, I, P>
+where
+    M: InformationSetMDP<S, A, I>,
+    A: Action,
+    S: Clone,
+    I: PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    mdp: M,
+    policy: P,
+    state: S,
+    /// The player searching, whose information sets make up the tree
+    observer: PlayerId,
+    root: InfoSetNode<A, I>,
+    setup: SearchSetup,
+}
+
+impl<M, S, A, I, P> Configurable for ISMCTS<M, S, A, I, P>
+where
+    M: InformationSetMDP<S, A, I>,
+    A: Action,
+    S: Clone,
+    I: PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    fn setup(&mut self) -> &mut SearchSetup {
+        &mut self.setup
+    }
+}
+
+impl<M, S, A, I, P> ISMCTS<M, S, A, I, P>
+where
+    M: InformationSetMDP<S, A, I>,
+    A: Action,
+    S: Clone,
+    I: PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    pub fn new(mdp: M, policy: P) -> Self {
+        let state = mdp.get_initial_state();
+        let observer = mdp.player_to_move(&state);
+        let info = mdp.information_set(&state, observer);
+
+        Self {
+            mdp,
+            policy,
+            state,
+            observer,
+            root: InfoSetNode::new(info),
+            setup: SearchSetup::default(),
+        }
+    }
+
+    /// Runs iterations, each on a new determinization of the state, until the budget is used up
+    ///
+    /// Panics if the game fails, see [`ISMCTS::try_search`]
+    pub fn search(&mut self, budget: Budget) {
+        if let Err(error) = self.try_search(budget) {
+            panic!("search failed: {error}");
+        }
+    }
+
+    /// Same as [`ISMCTS::search`], but stops at the first failure of the game and returns it
+    pub fn try_search(&mut self, budget: Budget) -> Result<(), McstError> {
+        self.setup.run(budget, |tracker, rng| {
+            let determinization = self.mdp.determinize(&self.state, self.observer, rng);
+            self.root.iterate(
+                &self.mdp,
+                &self.policy,
+                determinization,
+                self.observer,
+                tracker,
+                rng,
+            )?;
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Returns the most visited action of the searching player, if any was tried
+    pub fn best_action(&self) -> Option<A> {
+        self.root
+            .edges
+            .iter()
+            .max_by_key(|edge| edge.visits)
+            .map(|edge| edge.action.clone())
+    }
+
+    /// Returns the mean return of each action tried by the searching player, with the number of times it was taken
+    pub fn action_values(&self) -> Vec<(A, f64, usize)> {
+        self.root
+            .edges
+            .iter()
+            .map(|edge| {
+                let value = edge.score / edge.visits.max(1) as f64;
+                (edge.action.clone(), value, edge.visits)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mdp::GenerativeModel, policy::RandomRollout};
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Move {
+        Fold,
+        Bet,
+        Call,
+    }
+
+    impl Action for Move {}
+
+    /// Each player holds one card out of 1, 2 and 3, and only sees their own.
+    /// Player 0 folds (losing 1) or bets, after which player 1 folds (losing 1) or calls, and the higher card wins 2
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Hand {
+        cards: [u8; 2],
+        moves: Vec<Move>,
+    }
+
+    struct Showdown([u8; 2]);
+
+    impl GenerativeModel<Hand, Move> for Showdown {
+        fn get_actions(&self, state: &Hand) -> Vec<Move> {
+            match state.moves.as_slice() {
+                [] => vec![Move::Fold, Move::Bet],
+                [Move::Bet] => vec![Move::Fold, Move::Call],
+                _ => vec![],
+            }
+        }
+
+        fn execute(&self, state: &Hand, action: &Move, _rng: &mut dyn Rng) -> (Hand, f64, bool) {
+            let mut next = state.clone();
+            next.moves.push(*action);
+
+            let mover = self.player_to_move(state);
+            let reward = match action {
+                Move::Fold => -1.0,
+                Move::Bet => 0.0,
+                Move::Call if state.cards[mover] > state.cards[1 - mover] => 2.0,
+                Move::Call => -2.0,
+            };
+            (next, reward, *action != Move::Bet)
+        }
+
+        fn is_terminal(&self, state: &Hand) -> bool {
+            self.get_actions(state).is_empty()
+        }
+
+        fn get_initial_state(&self) -> Hand {
+            Hand {
+                cards: self.0,
+                moves: vec![],
+            }
+        }
+
+        fn player_to_move(&self, state: &Hand) -> PlayerId {
+            state.moves.len() % 2
+        }
+
+        fn num_players(&self) -> usize {
+            2
+        }
+    }
+
+    impl InformationSetMDP<Hand, Move, (u8, Vec<Move>)> for Showdown {
+        fn information_set(&self, state: &Hand, player: PlayerId) -> (u8, Vec<Move>) {
+            (state.cards[player], state.moves.clone())
+        }
+
+        fn determinize(&self, state: &Hand, player: PlayerId, rng: &mut dyn Rng) -> Hand {
+            let own = state.cards[player];
+            let unseen = (1..=3).filter(|c| *c != own).collect::<Vec<_>>();
+
+            let mut cards = state.cards;
+            cards[1 - player] = unseen[rng.gen_range(0, unseen.len())];
+            Hand {
+                cards,
+                moves: state.moves.clone(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ismcts_only_uses_what_the_player_sees() {
+        // the opponent holds the middle card both times, but only the searching player's own card decides what they should do
+        let mut low = ISMCTS::new(Showdown([1, 2]), RandomRollout::new()).with_seed(1);
+        low.search(Budget::Iterations(2000));
+        assert_eq!(low.best_action(), Some(Move::Fold));
+
+        let mut high = ISMCTS::new(Showdown([3, 2]), RandomRollout::new()).with_seed(1);
+        high.search(Budget::Iterations(2000));
+        assert_eq!(high.best_action(), Some(Move::Bet));
+
+        // both of the opponent's cards were dealt across the determinizations, and the opponent folds against a sure loss
+        let values = high.action_values();
+        let (_, value, _) = values.iter().find(|(a, ..)| *a == Move::Bet).unwrap();
+        assert!(
+            *value > 0.5,
+            "betting the best card should win, value={value}"
+        );
+    }
+}
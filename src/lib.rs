@@ -2,10 +2,65 @@
 uniffi::setup_scaffolding!();
 
 pub mod action;
+pub mod bench;
+pub mod book;
+pub mod budget;
+pub mod builder;
+pub mod clock;
+pub mod compare;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod constrained;
+pub mod continuous;
+pub mod diff;
+pub mod envs;
+pub mod error;
+#[cfg(feature = "explorer")]
+pub mod explorer;
+pub mod export;
+pub mod game;
+pub mod incremental;
+pub mod information_set;
+pub mod ismcts;
+pub mod macro_action;
 pub mod mcts;
 pub mod mdp;
+pub mod neural;
 mod node;
+pub mod node_stats;
+pub mod observer;
+pub mod pareto;
+#[cfg(feature = "serde")]
+pub mod persist;
 pub mod policy;
+pub mod pomcp;
+pub mod pomdp;
 pub mod rand;
+pub mod record;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod replay;
+mod returns;
+pub mod rtdp;
+pub mod selfplay;
+pub mod setup;
+pub mod simulator;
+pub mod solvers;
+pub mod stats;
 pub mod strategy;
+pub mod tabular;
+#[cfg(feature = "metrics")]
+pub mod telemetry;
+pub mod test_utils;
+pub mod tournament;
+#[cfg(feature = "tracing")]
+mod trace;
+pub mod training;
+mod transposition;
+pub mod tree;
 mod ucb1;
+pub mod value;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wrappers;
+pub mod zobrist;
@@ -0,0 +1,255 @@
+//! Temporally extended actions (options, in the sense of Sutton, Precup & Singh, 1999) for long-horizon planning.
+//!
+//! A macro-action follows its own policy for as many primitive steps as it takes to reach its goal.
+//! Wrapping a model in [`WithMacroActions`] lets the search pick macro-actions as single edges of the tree,
+//! which keeps the tree shallow when the primitive actions alone would make it too deep to search
+
+use crate::{
+    action::Action,
+    error::MdpError,
+    mdp::{GenerativeModel, Outcome, PlayerId},
+    rand::Rng,
+};
+
+/// An option: where it can be started (its initiation set), what it does (its policy) and when it's done (its termination condition)
+pub trait MacroAction<S, A> {
+    /// Returns true if the macro-action can be started from this state
+    fn can_start(&self, state: &S) -> bool;
+
+    /// Returns the primitive action to take in this state, which must be one of the state's actions
+    fn act(&self, state: &S, rng: &mut dyn Rng) -> A;
+
+    /// Returns true if the macro-action is done once in this state, after `steps` primitive actions.
+    /// It also stops in terminal states, and after [`WithMacroActions::with_max_steps`] steps
+    fn should_stop(&self, state: &S, steps: usize, rng: &mut dyn Rng) -> bool;
+}
+
+/// What [`WithMacroActions`] offers the search in a state: one of the primitive actions, or one of the macro-actions, by index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice<A> {
+    Primitive(A),
+    Macro(usize),
+}
+
+impl<A: Action> Action for Choice<A> {}
+
+/// Adds macro-actions to a model, on top of (or instead of, see [`WithMacroActions::without_primitives`]) its primitive actions.
+///
+/// Executing a macro-action runs its policy until it stops, and returns the discounted sum of the rewards along the way,
+/// as a single transition. The model's discount factor still applies once per transition of the tree, whatever the number of steps it took
+pub struct WithMacroActions<M, O> {
+    inner: M,
+    macros: Vec<O>,
+    primitives: bool,
+    max_steps: usize,
+}
+
+impl<M, O> WithMacroActions<M, O> {
+    /// Macro-actions are offered by their index in `macros`, see [`Choice::Macro`].
+    /// Each of them runs for at most 1000 steps
+    pub fn new(inner: M, macros: Vec<O>) -> Self {
+        Self {
+            inner,
+            macros,
+            primitives: true,
+            max_steps: 1000,
+        }
+    }
+
+    /// Only offers the macro-actions, hiding the primitive actions from the search
+    pub fn without_primitives(mut self) -> Self {
+        self.primitives = false;
+        self
+    }
+
+    /// Stops any macro-action after this many steps, whether it considers itself done or not
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+}
+
+impl<M, O, S, A> GenerativeModel<S, Choice<A>> for WithMacroActions<M, O>
+where
+    M: GenerativeModel<S, A>,
+    O: MacroAction<S, A>,
+{
+    fn get_actions(&self, state: &S) -> Vec<Choice<A>> {
+        let primitives = match self.primitives {
+            true => self.inner.get_actions(state),
+            false => vec![],
+        };
+
+        primitives
+            .into_iter()
+            .map(Choice::Primitive)
+            .chain(
+                (0..self.macros.len())
+                    .filter(|i| self.macros[*i].can_start(state))
+                    .map(Choice::Macro),
+            )
+            .collect()
+    }
+
+    fn execute(&self, state: &S, action: &Choice<A>, rng: &mut dyn Rng) -> (S, f64, bool) {
+        self.try_execute(state, action, rng)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    fn try_execute(
+        &self,
+        state: &S,
+        action: &Choice<A>,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, f64, bool), MdpError> {
+        let option = match action {
+            Choice::Primitive(action) => return self.inner.try_execute(state, action, rng),
+            Choice::Macro(index) => &self.macros[*index],
+        };
+
+        let discount = self.inner.get_discount_factor();
+        let action = option.act(state, rng);
+        let (mut state, mut total, mut done) = self.inner.try_execute(state, &action, rng)?;
+        let mut weight = discount;
+        let mut steps = 1;
+
+        while !done && steps < self.max_steps && !option.should_stop(&state, steps, rng) {
+            let action = option.act(&state, rng);
+            let (next_state, reward, next_done) = self.inner.try_execute(&state, &action, rng)?;
+
+            total += weight * reward;
+            weight *= discount;
+            steps += 1;
+            (state, done) = (next_state, next_done);
+        }
+
+        Ok((state, total, done))
+    }
+
+    fn is_terminal(&self, state: &S) -> bool {
+        self.inner.is_terminal(state)
+    }
+
+    fn get_initial_state(&self) -> S {
+        self.inner.get_initial_state()
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.inner.get_discount_factor()
+    }
+
+    fn player_to_move(&self, state: &S) -> PlayerId {
+        self.inner.player_to_move(state)
+    }
+
+    fn num_players(&self) -> usize {
+        self.inner.num_players()
+    }
+
+    fn state_key(&self, state: &S) -> Option<u64> {
+        self.inner.state_key(state)
+    }
+
+    fn canonicalize(&self, state: &S) -> Option<S> {
+        self.inner.canonicalize(state)
+    }
+
+    fn transition_probability(&self, state: &S, action: &Choice<A>, next_state: &S) -> Option<f64> {
+        match action {
+            Choice::Primitive(action) => {
+                self.inner.transition_probability(state, action, next_state)
+            }
+            Choice::Macro(_) => None,
+        }
+    }
+
+    /// Only primitive actions have a cost, a macro-action's depends on the steps it will take
+    fn action_cost(&self, state: &S, action: &Choice<A>) -> f64 {
+        match action {
+            Choice::Primitive(action) => self.inner.action_cost(state, action),
+            Choice::Macro(_) => 0.0,
+        }
+    }
+
+    fn outcome(&self, state: &S) -> Option<Outcome> {
+        self.inner.outcome(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mcts::MCTS, mdp::FnMdp, policy::RandomRollout, rand::SplitMix64, strategy::Strategy,
+    };
+
+    use super::*;
+
+    const LEFT: usize = 0;
+    const RIGHT: usize = 1;
+
+    /// Keeps stepping right until the end of the corridor
+    struct Dash;
+
+    impl MacroAction<i32, usize> for Dash {
+        fn can_start(&self, state: &i32) -> bool {
+            *state < 20
+        }
+
+        fn act(&self, _state: &i32, _rng: &mut dyn Rng) -> usize {
+            RIGHT
+        }
+
+        fn should_stop(&self, state: &i32, _steps: usize, _rng: &mut dyn Rng) -> bool {
+            *state >= 20
+        }
+    }
+
+    #[test]
+    fn test_macro_actions_run_until_they_stop() {
+        // a corridor from -3 to 20, only its right end pays
+        let corridor = FnMdp::new(
+            || 0,
+            |_state: &i32| vec![LEFT, RIGHT],
+            |state, step: &usize, _rng| {
+                let next = if *step == RIGHT { state + 1 } else { state - 1 };
+                (
+                    next,
+                    if next == 20 { 1.0 } else { 0.0 },
+                    next <= -3 || next >= 20,
+                )
+            },
+            |state| *state <= -3 || *state >= 20,
+        );
+        let mdp = WithMacroActions::new(corridor, vec![Dash]);
+
+        let mut rng = SplitMix64::seed(1);
+        assert_eq!(
+            mdp.get_actions(&0),
+            vec![
+                Choice::Primitive(LEFT),
+                Choice::Primitive(RIGHT),
+                Choice::Macro(0)
+            ]
+        );
+        assert_eq!(
+            mdp.execute(&0, &Choice::Macro(0), &mut rng),
+            (20, 1.0, true)
+        );
+        assert_eq!(
+            mdp.execute(&5, &Choice::Primitive(LEFT), &mut rng),
+            (4, 0.0, false)
+        );
+
+        // a random walk of primitive steps mostly falls off the left end first, the macro-action always gets to the right one
+        let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(2);
+        mcts.run_iterations(200);
+        assert_eq!(
+            mcts.best_action(Strategy::HighestQValue),
+            Some(Choice::Macro(0))
+        );
+    }
+}
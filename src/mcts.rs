@@ -1,17 +1,55 @@
 use core::f64;
 use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    pin::Pin,
     rc::{Rc, Weak},
-    time::Instant,
+    task::{Context, Poll},
+    time::Duration,
 };
 
 use crate::{
-    action::Action, mdp::MDP, node::Node, policy::RolloutPolicy, rand::genrand, strategy::Strategy,
+    action::Action,
+    book::{Book, BookEntry},
+    budget::{Budget, Tracker},
+    builder::MctsBuilder,
+    clock::{Clock, SystemClock},
+    continuous::{ContinuousActions, ProgressiveWidening},
+    error::{McstError, MdpError},
+    incremental::IncrementalState,
+    mdp::{GenerativeModel, Outcome, PlayerId, checked_execute},
+    node::{Child, Node, Pruning},
+    node_stats::SelectionPolicy,
+    observer::{BackpropagationEvent, ExpansionEvent, IterationEvent, Observer},
+    policy::RolloutPolicy,
+    rand::{Rng, SplitMix64},
+    returns::Returns,
+    stats::{
+        ActionStats, OutcomeCounts, PhaseTimings, SearchCounters, SearchResult, SearchStats,
+        TreeStats, Verdict,
+    },
+    strategy::{FinalSelector, Strategy},
+    transposition::TranspositionTable,
+    tree::TreeNode,
     ucb1::UCB1,
+    value::{BatchEvaluator, ValueFunction},
 };
 
+/// Creates the payload of a node, see [`MCTS::with_node_metadata`]
+type MetadataHook<S, A> = dyn Fn(&TreeNode<S, A>) -> Box<dyn Any>;
+
+/// Estimates the return after a state, see [`MCTS::with_leaf_evaluator`]
+type LeafEvaluator<S> = dyn Fn(&S) -> f64;
+
+/// Shifts the return of an episode ending in a state, see [`MCTS::with_terminal_offset`]
+type TerminalOffset<S> = dyn Fn(&S) -> f64;
+
+/// Knows the exact return after some states, see [`MCTS::with_endgame`]
+type Endgame<S> = dyn Fn(&S) -> Option<f64>;
+
 pub struct MCTS<M, S, A, P>
 where
-    M: MDP<S, A>,
+    M: GenerativeModel<S, A>,
     A: Action,
     S: Clone,
     P: RolloutPolicy<M, S, A>,
@@ -20,164 +58,3216 @@ where
     root: Rc<Node<S, A>>,
     bandit: UCB1,
     policy: P,
+    /// The budget and final-move strategy of [`MCTS::search`]
+    budget: Budget,
+    strategy: Strategy,
+    /// Replaces `strategy` when set, see [`MCTS::with_final_selector`]
+    final_selector: Option<Rc<dyn FinalSelector<A>>>,
+    /// Rollouts stop after this many steps, see [`MCTS::with_max_rollout_depth`]
+    max_rollout_depth: Option<usize>,
+    /// Values the states rollouts stop in before the end of the episode, see [`MCTS::with_leaf_evaluator`]
+    leaf_evaluator: Option<Rc<LeafEvaluator<S>>>,
+    /// Values new nodes start with, and the visits they count for, see [`MCTS::with_warm_start`]
+    warm_start: Option<(Rc<LeafEvaluator<S>>, usize)>,
+    /// How many of the next best actions [`MCTS::search`] reports
+    runner_ups: usize,
+    /// The player values are reported for, the player to move at the root if None, see [`MCTS::for_player`]
+    perspective: Option<PlayerId>,
+    /// What a draw is worth to the reported player, see [`MCTS::with_draw_score`]
+    draw_score: Option<f64>,
+    terminal_offset: Option<Rc<TerminalOffset<S>>>,
+    /// The root values to resign below and to claim the win above, with the iterations in a row they must hold for,
+    /// see [`MCTS::with_resignation`] and [`MCTS::with_win_claim`]
+    resignation: Option<(f64, usize)>,
+    win_claim: Option<(f64, usize)>,
+    /// For how many iterations in a row the root value has been below the resignation threshold, and above the win claim one
+    streaks: Cell<(usize, usize)>,
+    /// How the episode of the current iteration ended, if it reached the end of a game
+    last_outcome: Cell<Option<Outcome>>,
+    /// How the games through the root's actions ended, see [`MCTS::outcome_counts`]
+    outcome_counts: RefCell<Vec<(A, OutcomeCounts)>>,
+    /// Consulted before every search, see [`MCTS::with_book`]
+    book: Option<Rc<dyn Book<S, A>>>,
+    endgame: Option<Rc<Endgame<S>>>,
+    /// How many more iterations [`MCTS::search`] may run for [`Strategy::RobustChild`] to find an action,
+    /// and the strategy it falls back on otherwise, see [`MCTS::with_robust_child`]
+    robust_extension: usize,
+    robust_fallback: Strategy,
+    table: Option<TranspositionTable<S, A>>,
+    chance_nodes: bool,
+    /// Rollouts stop after this many steps, and values are rewards per step, see [`MCTS::with_average_reward`]
+    horizon: Option<usize>,
+    /// Prefer the cheaper of equally good actions when picking the best one, see [`MCTS::with_cost_tie_breaks`]
+    cost_tie_breaks: bool,
+    profiling: bool,
+    #[cfg(feature = "tracing")]
+    trace_interval: usize,
+    pub(crate) observers: RefCell<Vec<Box<dyn Observer>>>,
+    /// Creates the payload of every node, see [`MCTS::with_node_metadata`]
+    metadata: Option<Rc<MetadataHook<S, A>>>,
+    /// Rules actions out of the search, see [`MCTS::with_pruning`]
+    pruning: Option<Rc<Pruning<S, A>>>,
+    clock: Rc<dyn Clock>,
+    rng: RefCell<Box<dyn Rng>>,
+    /// Total number of iterations run across all searches
+    iterations: usize,
 }
 
 impl<M, S, A, P> MCTS<M, S, A, P>
 where
-    M: MDP<S, A>,
+    M: GenerativeModel<S, A>,
     A: Action,
     S: Clone + Eq + PartialEq,
     P: RolloutPolicy<M, S, A>,
 {
+    /// The searcher owns the MDP it's given, which can also be a reference or an `Rc`/`Arc` to share one model between several searchers.
+    ///
+    /// Panics if the operating system's entropy source fails, see [`MCTS::try_new`]
     pub fn new(mdp: M, policy: P) -> Self {
+        Self::with_generator(mdp, policy, Box::new(SplitMix64::from_entropy()))
+    }
+
+    /// Same as [`MCTS::new`], but reports a failure of the entropy source rather than panicking.
+    /// Seeded searchers don't need the entropy source at all, see [`MctsBuilder::try_build`]
+    pub fn try_new(mdp: M, policy: P) -> Result<Self, McstError> {
+        let rng = SplitMix64::try_from_entropy()?;
+        Ok(Self::with_generator(mdp, policy, Box::new(rng)))
+    }
+
+    pub(crate) fn with_generator(mdp: M, policy: P, rng: Box<dyn Rng>) -> Self {
         let state = mdp.get_initial_state();
+        let player = mdp.player_to_move(&state);
         Self {
-            root: Rc::new(Node::new(state, None, None, Weak::new())),
+            root: Rc::new(Node::new(state, player, None, Weak::new())),
             mdp,
             bandit: UCB1::default(),
             policy,
+            budget: Budget::Iterations(1000),
+            strategy: Strategy::default(),
+            final_selector: None,
+            max_rollout_depth: None,
+            leaf_evaluator: None,
+            warm_start: None,
+            runner_ups: 0,
+            perspective: None,
+            draw_score: None,
+            terminal_offset: None,
+            resignation: None,
+            win_claim: None,
+            streaks: Cell::new((0, 0)),
+            last_outcome: Cell::new(None),
+            outcome_counts: RefCell::new(vec![]),
+            book: None,
+            endgame: None,
+            robust_extension: 0,
+            robust_fallback: Strategy::MostVisited,
+            table: None,
+            chance_nodes: false,
+            horizon: None,
+            cost_tie_breaks: false,
+            profiling: false,
+            #[cfg(feature = "tracing")]
+            trace_interval: 1000,
+            observers: RefCell::new(vec![]),
+            metadata: None,
+            pruning: None,
+            clock: Rc::new(SystemClock),
+            rng: RefCell::new(rng),
+            iterations: 0,
         }
     }
 
-    /// Execute the MCTS algorithm from the initial state given, with timeout in seconds
-    /// After how many milliseconds, the mcts should timeout
-    /// TODO: Move this to be more dynamic, and support max-depth timeout
-    pub fn mcts(&mut self, timeout: u128) {
-        let start_time = Instant::now();
+    /// Configures a searcher step by step, see [`MctsBuilder`]
+    pub fn builder(mdp: M, policy: P) -> MctsBuilder<M, P> {
+        MctsBuilder::new(mdp, policy)
+    }
+
+    /// Weight of the exploration term of UCB1, √2 by default.
+    /// That default suits returns in `[0, 1]`, so MDPs with larger (or smaller) returns want a proportionally larger (or smaller) constant
+    pub fn with_exploration_constant(mut self, c: f64) -> Self {
+        self.bandit.set_exploration_constant(c);
+        self
+    }
+
+    /// Selects the actions in the tree with the given policy rather than UCB1, e.g. [`crate::node_stats::Ucb1Tuned`].
+    /// Every node then also keeps the statistics the policy needs (see [`crate::node_stats`]), readable through [`TreeNode::stats`]
+    pub fn with_selection<T: SelectionPolicy + 'static>(mut self, policy: T) -> Self {
+        self.bandit.selection = Some(Rc::new(policy));
+        self
+    }
+
+    /// The budget of [`MCTS::search`], 1000 iterations by default
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// How [`MCTS::search`] picks the action it returns, [`Strategy::MostVisited`] by default
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Has [`MCTS::search`] pick its action with the given selector rather than a [`Strategy`], see [`MCTS::best_action_by`]
+    pub fn with_final_selector<F: FinalSelector<A> + 'static>(mut self, selector: F) -> Self {
+        self.final_selector = Some(Rc::new(selector));
+        self
+    }
+
+    /// Cuts rollouts short after this many steps, for long or endless episodes.
+    /// By default, rollouts go on until a terminal state (or the end of the time budget)
+    pub fn with_max_rollout_depth(mut self, depth: usize) -> Self {
+        self.max_rollout_depth = Some(depth);
+        self
+    }
+
+    /// Values the states rollouts are cut short in (see [`MCTS::with_max_rollout_depth`]) with `evaluate`,
+    /// e.g. a value function learned offline, rather than as worth nothing.
+    /// The value is the expected return after the state, from the perspective of the first player
+    pub fn with_leaf_evaluator<F: Fn(&S) -> f64 + 'static>(mut self, evaluate: F) -> Self {
+        self.leaf_evaluator = Some(Rc::new(evaluate));
+        self
+    }
+
+    /// Same as [`MCTS::with_leaf_evaluator`], with a [`ValueFunction`] such as the values a solver of [`crate::solvers`] found
+    pub fn with_value_function<V: ValueFunction<S> + 'static>(self, values: V) -> Self {
+        self.with_leaf_evaluator(move |state| values.value(state))
+    }
+
+    /// Starts every new node as if `pseudo_visits` rollouts from its state had returned its value according to `values`,
+    /// rather than with no visits at all, so that the search trusts the estimate until actual rollouts outweigh it.
+    /// Cuts the budget needed where an approximate value function exists, e.g. one learned by a solver of [`crate::solvers`].
+    /// The values are from the perspective of the first player, like [`MCTS::with_leaf_evaluator`]'s.
+    ///
+    /// The pseudo-visits count like any other, towards [`Strategy::MostVisited`] too
+    pub fn with_warm_start<V: ValueFunction<S> + 'static>(
+        mut self,
+        values: V,
+        pseudo_visits: usize,
+    ) -> Self {
+        self.warm_start = Some((Rc::new(move |state: &S| values.value(state)), pseudo_visits));
+        self
+    }
+
+    /// Makes [`MCTS::search`] report up to this many of the next best actions alongside the one it picked, none by default
+    pub fn with_runner_ups(mut self, count: usize) -> Self {
+        self.runner_ups = count;
+        self
+    }
+
+    /// Reports values from the perspective of `player`, whoever is to move at the root: the values of [`MCTS::search`]'s result,
+    /// of [`MCTS::root_action_stats`] and [`MCTS::action_stats`], and the returns observers see.
+    /// Without it, they're from the perspective of the player to move at the root, which changes as the root advances in a game.
+    ///
+    /// The search still picks the best action for the player to move, so with two players, the values of an opponent's
+    /// turn are the negated ones of its best action
+    pub fn for_player(mut self, player: PlayerId) -> Self {
+        self.perspective = Some(player);
+        self
+    }
+
+    /// Counts every game that ends in a draw (see [`GenerativeModel::outcome`]) as worth `score` more to [`MCTS::reported_player`],
+    /// and, in two-player games, as much less to their opponent: a contempt for draws when negative,
+    /// so that an engine facing a weaker opponent plays on, and a liking for them when positive, to hold a stronger one.
+    /// The score is added to whatever the model pays for the draw, usually nothing
+    pub fn with_draw_score(mut self, score: f64) -> Self {
+        self.draw_score = Some(score);
+        self
+    }
+
+    /// Adds `offset(state)` to the return of every episode ending in the terminal `state`, from the perspective of the first player
+    /// like [`MCTS::with_leaf_evaluator`]'s values: komi, or a handicap to give an engine odds in a calibration match,
+    /// without touching the rewards of the model. Only the search sees the offset, the games it plays end as they would
+    ///
+    /// ```
+    /// use mct_rs::{envs::nim::{Nim, Take}, game::GameMdp, mcts::MCTS, policy::RandomRollout};
+    ///
+    /// // taking the last stone wins, but with a komi of 1.5 for the second player, the first still comes out behind
+    /// let mut mcts = MCTS::new(GameMdp::new(Nim::new([1])), RandomRollout::new())
+    ///     .with_seed(1)
+    ///     .with_terminal_offset(|_: &Nim| -1.5);
+    /// let result = mcts.search().unwrap();
+    /// assert_eq!(result.action, Take { heap: 0, count: 1 });
+    /// assert_eq!(result.value, -0.5);
+    /// ```
+    pub fn with_terminal_offset<F: Fn(&S) -> f64 + 'static>(mut self, offset: F) -> Self {
+        self.terminal_offset = Some(Rc::new(offset));
+        self
+    }
+
+    /// Has [`MCTS::verdict`] advise resigning once the value of the root, for the player to move, has stayed below `threshold`
+    /// for `iterations` iterations in a row, e.g. -0.9 for 200 iterations with rewards of ±1 for a win or a loss
+    pub fn with_resignation(mut self, threshold: f64, iterations: usize) -> Self {
+        self.resignation = Some((threshold, iterations));
+        self
+    }
+
+    /// Has [`MCTS::verdict`] advise claiming the win once the value of the root, for the player to move, has stayed above `threshold`
+    /// for `iterations` iterations in a row
+    pub fn with_win_claim(mut self, threshold: f64, iterations: usize) -> Self {
+        self.win_claim = Some((threshold, iterations));
+        self
+    }
+
+    /// Looks the root up in the opening book before every search of [`MCTS::search`], [`MCTS::search_from`] and [`MCTS::search_async`]:
+    /// a book move is returned without searching, with 0 iterations and whatever the tree knew of it,
+    /// and book statistics seed the actions of a root that was never searched. Moves that aren't legal are left out
+    pub fn with_book<B: Book<S, A> + 'static>(mut self, book: B) -> Self {
+        self.book = Some(Rc::new(book));
+        self
+    }
+
+    /// Asks `solve` for the exact value of every state the search adds to the tree below the root, e.g. from an endgame tablebase
+    /// or an exhaustive search of small positions: the return after the state, from the perspective of the first player
+    /// like [`MCTS::with_leaf_evaluator`]'s values, or `None` if it doesn't know it.
+    /// Solved nodes are neither expanded nor rolled out from, every visit backs the exact value up instead (see [`TreeNode::exact_value`])
+    pub fn with_endgame<F: Fn(&S) -> Option<f64> + 'static>(mut self, solve: F) -> Self {
+        self.endgame = Some(Rc::new(solve));
+        self
+    }
+
+    /// When the most visited and the highest valued actions differ, [`Strategy::RobustChild`] has [`MCTS::search`]
+    /// run up to `extension` more iterations until they agree, and picks the action by the `fallback` strategy if they still don't.
+    /// By default, there's no extension and the fallback is [`Strategy::MostVisited`]
+    pub fn with_robust_child(mut self, extension: usize, fallback: Strategy) -> Self {
+        assert_ne!(
+            fallback,
+            Strategy::RobustChild,
+            "the robust child can't be its own fallback"
+        );
+        self.robust_extension = extension;
+        self.robust_fallback = fallback;
+        self
+    }
+
+    /// Draws all the randomness of the search (tie-breaks, rollouts, transition sampling) from the given generator.
+    /// Rollout-heavy searches spend a noticeable share of their time drawing numbers,
+    /// the `xoshiro` and `wyrand` features provide faster alternatives to the default [`SplitMix64`]
+    pub fn with_rng<R: Rng + 'static>(mut self, rng: R) -> Self {
+        self.rng = RefCell::new(Box::new(rng));
+        self
+    }
+
+    /// Makes the search deterministic: the same seed, MDP and budget always produce the same tree
+    /// (as long as the search is bounded by something other than wall-clock time)
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(SplitMix64::seed(seed))
+    }
+
+    /// Shares the nodes of states reached through different orders of actions, turning the tree into a DAG.
+    /// This needs the MDP to provide [`GenerativeModel::state_key`], see [`crate::transposition`] for how statistics are combined across paths
+    pub fn with_transpositions(mut self) -> Self {
+        self.table = Some(self.fresh_table());
+        self
+    }
+
+    /// A transposition table holding only the root
+    fn fresh_table(&self) -> TranspositionTable<S, A> {
+        let table = TranspositionTable::default();
+        if let Some(key) = self.mdp.state_key(&self.root.state) {
+            table.insert(key, &self.root);
+        }
+        table
+    }
+
+    /// Keeps every distinct outcome of an action as a separate child, for stochastic MDPs.
+    /// By default an action leads to whichever state it reached first, and later samples of the action are credited to that state, whatever they actually led to.
+    /// With chance nodes, the action is sampled again on every visit and its value is the expectation over its outcomes,
+    /// weighted by their probabilities when the model gives them (see [`GenerativeModel::transition_probability`]), or by how often they were sampled otherwise
+    pub fn with_chance_nodes(mut self) -> Self {
+        self.chance_nodes = true;
+        self
+    }
+
+    /// Evaluates actions by the average reward per step they lead to (gain), rather than by the sum of the rewards,
+    /// for continuing tasks whose returns would grow without bound. Rollouts stop after `horizon` steps,
+    /// and every node's value is the mean reward per step from it onward, over the path below it and the rollout
+    pub fn with_average_reward(mut self, horizon: usize) -> Self {
+        self.horizon = Some(horizon);
+        self
+    }
+
+    /// Breaks ties between equally good actions (by visits or Q-value, depending on the [`Strategy`]) in favour of the cheapest one,
+    /// according to [`GenerativeModel::action_cost`]. Costs don't change the search itself, see [`crate::wrappers::CostPenalty`] for that
+    pub fn with_cost_tie_breaks(mut self) -> Self {
+        self.cost_tie_breaks = true;
+        self
+    }
+
+    /// Measures the time spent in each phase (select/expand/simulate/backpropagate) of every search.
+    /// This adds a few clock reads to every iteration, so it is off by default
+    pub fn with_profiling(mut self) -> Self {
+        self.profiling = true;
+        self
+    }
+
+    /// Reports the search's progress to `tracing` every this many iterations (1000 by default, 0 to never report it).
+    ///
+    /// With the `tracing` feature, every search runs in an `mcts_search` span, which also gets an event whenever
+    /// the most visited action at the root changes (both at `DEBUG` level), and one when the budget is used up (`INFO`)
+    #[cfg(feature = "tracing")]
+    pub fn with_trace_interval(mut self, iterations: usize) -> Self {
+        self.trace_interval = iterations;
+        self
+    }
+
+    /// Calls the observer back as the search runs, see [`Observer`]. Several observers can be registered, and are called in that order
+    pub fn with_observer<O: Observer + 'static>(self, observer: O) -> Self {
+        self.observers.borrow_mut().push(Box::new(observer));
+        self
+    }
+
+    /// Calls every observer back
+    fn notify(&self, mut callback: impl FnMut(&mut dyn Observer)) {
+        for observer in self.observers.borrow_mut().iter_mut() {
+            callback(&mut **observer);
+        }
+    }
 
-        while start_time.elapsed().as_millis() < timeout {
-            // Find a state node to expand
-            let selected_node = self.root.select(&self.mdp, &self.bandit);
-            // let xx = !self.mdp.is_terminal(&selected_node.state);
-            if !self.mdp.is_terminal(&selected_node.state) {
-                let child = selected_node.expand(&self.mdp, &self.policy);
-                let reward = self.simulate(&child, start_time, timeout);
-                child.back_propagate(reward, &mut self.bandit);
+    fn notify_iteration(&self, iteration: usize) {
+        let event = IterationEvent {
+            iteration,
+            root_visits: *self.root.visits.borrow(),
+        };
+        self.notify(|o| o.on_iteration(&event));
+    }
+
+    /// Attaches the payload returned by `create` to every node of the tree, e.g. cached evaluations, solver bounds or debug tags,
+    /// to read and update through [`TreeNode::metadata`] and [`TreeNode::metadata_mut`].
+    /// The payload is created when the node is added to the tree, and when the search starts for the root
+    pub fn with_node_metadata<T, F>(mut self, create: F) -> Self
+    where
+        T: 'static,
+        F: Fn(&TreeNode<S, A>) -> T + 'static,
+    {
+        self.metadata = Some(Rc::new(move |node| Box::new(create(node))));
+        self
+    }
+
+    /// Hard-prunes branches: the search neither expands nor selects the actions `prune` returns true for,
+    /// e.g. moves that are legal but never worth playing, or dominated schedules, so that the budget goes to the others.
+    /// The predicate is given the state, the action, and the statistics of the action so far (all 0 while it's unexplored),
+    /// so it can also drop actions that turned out badly after enough visits.
+    ///
+    /// When the predicate rules out every action of a state, all of them are kept. Actions pruned after they were explored
+    /// keep their statistics, and the final move is still picked among every explored action of the root
+    pub fn with_pruning<F>(mut self, prune: F) -> Self
+    where
+        F: Fn(&S, &A, &ActionStats) -> bool + 'static,
+    {
+        self.pruning = Some(Rc::new(prune));
+        self
+    }
+
+    /// Gives the node what the searcher keeps for every node on top of its statistics,
+    /// i.e. the payload of the metadata hook and the statistics of the selection policy, if it doesn't have them yet,
+    /// solves it with the endgame hook (see [`MCTS::with_endgame`]),
+    /// and warm-starts it if it was never visited (see [`MCTS::with_warm_start`])
+    fn attach(&self, node: &Rc<Node<S, A>>) {
+        if let Some(solve) = &self.endgame
+            && node.exact.get().is_none()
+            && !Rc::ptr_eq(node, &self.root)
+            && !self.mdp.is_terminal(&node.state)
+        {
+            node.exact.set(solve(&node.state));
+        }
+        if let Some(selection) = &self.bandit.selection {
+            node.stats
+                .borrow_mut()
+                .get_or_insert_with(|| selection.new_stats());
+        }
+        if let Some(create) = &self.metadata
+            && node.metadata.borrow().is_none()
+        {
+            let metadata = create(&TreeNode::new(Rc::clone(node)));
+            *node.metadata.borrow_mut() = Some(metadata);
+        }
+        if let Some((values, count)) = &self.warm_start {
+            let mut returns = Returns::new(self.mdp.num_players());
+            if !self.mdp.is_terminal(&node.state) {
+                returns.credit(0, values(&node.state));
             }
+            node.warm_start(returns, *count, self.mdp.get_discount_factor());
         }
     }
 
-    /// TODO: This would eventually be moved to a trait that must be implemented on state!, this MCTS or whatever!
-    pub(crate) fn heuristic_eval(&self, _state: &S) -> f64 {
-        0.0
+    /// Reads the time from the given clock rather than the standard library's, for time budgets and profiling.
+    /// Required for time-bounded searches on targets without `std::time::Instant`, see [`crate::clock`]
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Rc::new(clock);
+        self
     }
 
-    /// Simulate until a terminal state
-    pub(crate) fn simulate(
-        &self,
-        node: &Rc<Node<S, A>>,
-        start_time: Instant,
-        timeout: u128,
-    ) -> f64 {
-        let mut state = node.state.clone();
-        let mut cumulative_reward = 0.0;
-        // let mut depth = 0;
+    /// The clock this searcher reads the time from
+    pub(crate) fn clock(&self) -> &dyn Clock {
+        &*self.clock
+    }
 
-        while !self.mdp.is_terminal(&state) && start_time.elapsed().as_millis() < timeout {
-            let actions = self.mdp.get_actions(&state);
+    /// The lookups of the transposition table so far, and how many found a node, see [`crate::telemetry`]
+    #[cfg(feature = "metrics")]
+    fn table_lookups(&self) -> (usize, usize) {
+        self.table
+            .as_ref()
+            .map_or((0, 0), TranspositionTable::lookups)
+    }
 
-            // Choose an action to execute
-            let action = self.policy.pick(&state, &actions);
+    /// Runs a search with the configured budget (see [`MCTS::with_budget`]),
+    /// and returns the best action according to the configured strategy (see [`MCTS::with_strategy`]) with the statistics backing it.
+    /// Returns `None` right away if the root is terminal or has no actions, and if no action was explored, e.g. with a budget of 0 iterations:
+    /// [`MCTS::try_search`] tells them apart
+    ///
+    /// Panics if the MDP fails, see [`MCTS::try_search`]
+    pub fn search(&mut self) -> Option<SearchResult<A>> {
+        match self.try_search() {
+            Ok(result) => result,
+            Err(McstError::TerminalRoot { .. } | McstError::NoLegalActions) => None,
+            Err(error) => panic!("search failed: {error}"),
+        }
+    }
 
-            // Execute the action
-            let (next_state, reward, ..) = self.mdp.execute(&state, &action);
+    /// Same as [`MCTS::search`], but stops at the first failure of the MDP and returns it,
+    /// and fails with [`McstError::TerminalRoot`] or [`McstError::NoLegalActions`] rather than returning `None`
+    /// when the root is terminal or has no actions.
+    /// `None` then only means that no action was explored, e.g. with a budget of 0 iterations
+    pub fn try_search(&mut self) -> Result<Option<SearchResult<A>>, McstError> {
+        self.try_search_for(self.budget)
+    }
 
-            // Discount the reward
-            // cumulative_reward += f64::powi(self.mdp.get_discount_factor(), depth) * reward;
-            cumulative_reward += reward;
-            // depth += 1;
+    /// Points the searcher at another state, e.g. a position of a game other than the MDP's initial one.
+    /// This discards the tree built so far, but keeps the searcher's configuration
+    pub fn set_root_state(&mut self, state: S) {
+        let player = self.mdp.player_to_move(&state);
+        self.root = Rc::new(Node::new(state, player, None, Weak::new()));
+        self.streaks.set((0, 0));
+        self.outcome_counts.borrow_mut().clear();
+        if self.table.is_some() {
+            self.table = Some(self.fresh_table());
+        }
+    }
 
-            state = next_state;
+    /// Copies the searcher, tree and configuration included, e.g. to explore a hypothetical line of play without disturbing this search.
+    ///
+    /// The fork shares this searcher's clock, final selector and node metadata hook, but has none of its observers,
+    /// its nodes get new payloads from the hook (see [`MCTS::with_node_metadata`]),
+    /// and it draws from its own generator seeded from the operating system (reseed it with [`MCTS::with_seed`] for a reproducible fork)
+    pub fn fork(&self) -> Self
+    where
+        M: Clone,
+        P: Clone,
+    {
+        let (root, copies) = self.root.deep_clone(self.bandit.selection.as_deref());
+
+        let fork = Self {
+            mdp: self.mdp.clone(),
+            root,
+            bandit: self.bandit.clone(),
+            policy: self.policy.clone(),
+            budget: self.budget,
+            strategy: self.strategy,
+            final_selector: self.final_selector.clone(),
+            max_rollout_depth: self.max_rollout_depth,
+            leaf_evaluator: self.leaf_evaluator.clone(),
+            warm_start: self.warm_start.clone(),
+            runner_ups: self.runner_ups,
+            perspective: self.perspective,
+            draw_score: self.draw_score,
+            terminal_offset: self.terminal_offset.clone(),
+            resignation: self.resignation,
+            win_claim: self.win_claim,
+            streaks: self.streaks.clone(),
+            last_outcome: Cell::new(None),
+            outcome_counts: self.outcome_counts.clone(),
+            book: self.book.clone(),
+            endgame: self.endgame.clone(),
+            robust_extension: self.robust_extension,
+            robust_fallback: self.robust_fallback,
+            table: self.table.as_ref().map(|table| table.remapped(&copies)),
+            chance_nodes: self.chance_nodes,
+            horizon: self.horizon,
+            cost_tie_breaks: self.cost_tie_breaks,
+            profiling: self.profiling,
+            #[cfg(feature = "tracing")]
+            trace_interval: self.trace_interval,
+            observers: RefCell::new(vec![]),
+            metadata: self.metadata.clone(),
+            pruning: self.pruning.clone(),
+            clock: Rc::clone(&self.clock),
+            rng: RefCell::new(Box::new(SplitMix64::from_entropy())),
+            iterations: self.iterations,
+        };
+        copies.values().for_each(|copy| fork.attach(copy));
+        fork
+    }
+
+    /// Runs a search from the given state for the given budget, see [`MCTS::search`].
+    /// The tree built so far is kept if the state is already the root's, and discarded otherwise (see [`MCTS::set_root_state`])
+    ///
+    /// Panics if the MDP fails, see [`MCTS::try_search_from`]
+    pub fn search_from(&mut self, state: &S, budget: Budget) -> Option<SearchResult<A>> {
+        match self.try_search_from(state, budget) {
+            Ok(result) => result,
+            Err(McstError::TerminalRoot { .. } | McstError::NoLegalActions) => None,
+            Err(error) => panic!("search failed: {error}"),
         }
+    }
 
-        if !self.mdp.is_terminal(&state) {
-            // todo! this needs to be a trait
-            cumulative_reward += self.heuristic_eval(&state);
+    /// Same as [`MCTS::search_from`], but stops at the first failure of the MDP and returns it, see [`MCTS::try_search`]
+    pub fn try_search_from(
+        &mut self,
+        state: &S,
+        budget: Budget,
+    ) -> Result<Option<SearchResult<A>>, McstError> {
+        if self.root.state != *state {
+            self.set_root_state(state.clone());
         }
+        self.try_search_for(budget)
+    }
 
-        return cumulative_reward;
+    fn try_search_for(&mut self, budget: Budget) -> Result<Option<SearchResult<A>>, McstError> {
+        if let Some(result) = self.consult_book()? {
+            return Ok(Some(result));
+        }
+        let start = self.start_clock(budget);
+        let iterations = self.run(budget, Self::simulate)?.iterations;
+        self.conclude(iterations, start)
     }
 
-    pub fn best_action(&self, strategy: Strategy) -> Option<A> {
-        let root = &self.root;
-        let children = root.children.borrow();
+    /// Runs a search with the configured budget like [`MCTS::try_search`], but as a future that hands control back
+    /// to the executor every `batch` iterations, so that a long search doesn't hold up the other tasks of its thread.
+    ///
+    /// Dropping the future cancels the search between two batches. The tree keeps every iteration run so far,
+    /// so a later search picks up from there. Time budgets are only checked between batches,
+    /// so a search can overrun its budget by the time a batch takes.
+    /// The searcher isn't `Send`, and neither is the future: with tokio, run it with `tokio::task::spawn_local`
+    pub async fn search_async(
+        &mut self,
+        batch: usize,
+    ) -> Result<Option<SearchResult<A>>, McstError> {
+        self.check_root()?;
+        if let Some(result) = self.consult_book()? {
+            return Ok(Some(result));
+        }
 
-        if children.is_empty() {
-            return None;
+        let budget = self.budget;
+        let batch = batch.max(1);
+        let start = self.start_clock(budget);
+        let mut iterations = 0;
+        loop {
+            let batch = match budget {
+                Budget::Iterations(max) => batch.min(max - iterations),
+                Budget::Time(timeout) => {
+                    let elapsed = start.map_or(Duration::ZERO, |start| {
+                        self.clock.now().saturating_sub(start)
+                    });
+                    match elapsed.as_millis() >= timeout {
+                        true => 0,
+                        false => batch,
+                    }
+                }
+            };
+            if batch == 0 {
+                break;
+            }
+            iterations += self
+                .run(Budget::Iterations(batch), Self::simulate)?
+                .iterations;
+            YieldNow::default().await;
         }
+        self.conclude(iterations, start)
+    }
 
-        match strategy {
-            Strategy::MostVisited => children
-                .iter()
-                .max_by_key(|c| *c.visits.borrow())
-                .and_then(|c| c.action),
+    /// Same as [`MCTS::run_iterations`] and [`MCTS::mcts`], for either budget, but the leaves are valued by `evaluator`
+    /// rather than rolled out: they are queued as the search expands them, and valued `batch` at a time,
+    /// e.g. in a single request to an inference server (see `RemoteEvaluator`, behind the `remote` feature).
+    /// A batch the evaluator fails to value is rolled out with the rollout policy instead.
+    ///
+    /// The queued leaves are only backpropagated once their batch is valued, so the iterations in between select
+    /// without them, and larger batches search a little more broadly than one leaf at a time.
+    ///
+    /// Panics if the MDP fails, see [`MCTS::try_mcts_batched`]
+    pub fn mcts_batched<E: BatchEvaluator<S>>(
+        &mut self,
+        budget: Budget,
+        evaluator: &E,
+        batch: usize,
+    ) -> SearchStats {
+        match self.try_mcts_batched(budget, evaluator, batch) {
+            Ok(stats) => stats,
+            Err(McstError::TerminalRoot { .. } | McstError::NoLegalActions) => {
+                SearchStats::default()
+            }
+            Err(error) => panic!("search failed: {error}"),
+        }
+    }
 
-            Strategy::HighestQValue => children
-                .iter()
-                .max_by(|a, b| {
-                    a.q_value()
-                        .partial_cmp(&b.q_value())
-                        .unwrap_or(std::cmp::Ordering::Equal)
-                })
-                .and_then(|c| c.action),
+    /// Same as [`MCTS::mcts_batched`], but stops at the first failure of the MDP and returns it, see [`MCTS::try_mcts`].
+    /// The leaves still queued when the MDP fails are left unvisited
+    pub fn try_mcts_batched<E: BatchEvaluator<S>>(
+        &mut self,
+        budget: Budget,
+        evaluator: &E,
+        batch: usize,
+    ) -> Result<SearchStats, McstError> {
+        self.check_root()?;
 
-            Strategy::Probabilistic => {
-                // Softmax over Q-values
-                let qvalues = children.iter().map(|c| c.q_value()).collect::<Vec<_>>();
+        self.attach(&self.root);
+        let start = self.start_clock(budget);
+        let batch = batch.max(1);
+        let mut guard = self.rng.borrow_mut();
+        let rng: &mut dyn Rng = &mut **guard;
+        let tracker = Tracker::start(budget, self.clock());
+        let mut counters = SearchCounters::default();
+        let mut queue = Vec::with_capacity(batch);
 
-                let maxq = qvalues.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mut search = || -> Result<(), McstError> {
+            while !tracker.exhausted(counters.iterations) {
+                counters.iterations += 1;
 
-                // subtract maxq for numerical stability
-                let expq: Vec<f64> = qvalues.iter().map(|q| (q - maxq).exp()).collect();
-                let sum = expq.iter().sum::<f64>().max(f64::MIN_POSITIVE);
+                let selected_node = self.root.select(
+                    &self.mdp,
+                    &self.bandit,
+                    self.pruning.as_deref(),
+                    self.table.as_ref(),
+                    self.chance_nodes,
+                    rng,
+                )?;
+                // a queued leaf isn't visited yet either, but it isn't new
+                if is_new(&selected_node)
+                    && !Rc::ptr_eq(&selected_node, &self.root)
+                    && !queue.iter().any(|leaf| Rc::ptr_eq(leaf, &selected_node))
+                {
+                    counters.nodes += 1;
+                    self.attach(&selected_node);
+                }
 
-                let probs = expq.iter().map(|x| x / sum).collect::<Vec<_>>();
+                let leaf = match self.mdp.is_terminal(&selected_node.state)
+                    || selected_node.exact.get().is_some()
+                {
+                    true => selected_node,
+                    false => {
+                        let child = selected_node.expand(
+                            &self.mdp,
+                            &self.policy,
+                            self.pruning.as_deref(),
+                            self.table.as_ref(),
+                            self.chance_nodes,
+                            rng,
+                        )?;
+                        if is_new(&child) {
+                            counters.nodes += 1;
+                            self.attach(&child);
+                        }
+                        self.notify(|o| {
+                            o.on_expansion(&ExpansionEvent {
+                                depth: child.depth,
+                                player: child.player,
+                            })
+                        });
+                        counters.expansions += 1;
+                        child
+                    }
+                };
 
-                // sample based on probabilities
-                let mut r = genrand(0, 10_000) as f64 / 10_000.0;
-                for (i, p) in probs.iter().enumerate() {
-                    r -= p;
-                    if r <= 0.0 {
-                        return children[i].action;
+                // only the leaves left to simulate wait for the evaluator
+                match (self.mdp.is_terminal(&leaf.state), leaf.exact.get()) {
+                    (_, Some(value)) => self.back_up(&leaf, self.exact_returns(value), 0),
+                    (true, None) => {
+                        let mut returns = Returns::new(self.mdp.num_players());
+                        self.credit_outcome(&leaf.state, &mut returns, 1.0);
+                        self.back_up(&leaf, returns, 0);
                     }
+                    (false, None) => queue.push(leaf),
+                }
+                if queue.len() == batch {
+                    self.flush(&mut queue, evaluator, rng, &tracker, &mut counters)?;
                 }
 
-                // fallback
-                children[0].action
+                self.notify_iteration(counters.iterations);
+                self.track_verdict();
             }
-            Strategy::HeuristicWin => {
-                // prioritize terminal winning moves
-                let mut winning_mvs = vec![];
-                let mut bestq = f64::NEG_INFINITY;
-                let mut best_mvs = vec![];
 
-                for child in children.iter() {
-                    let q = child.q_value();
+            self.flush(&mut queue, evaluator, rng, &tracker, &mut counters)
+        };
+        let outcome = search();
 
-                    // if child is terminal with positive reward (win)
-                    // if let Some(reward) = child.score.borrow() {}
+        drop(guard);
+        self.iterations += counters.iterations;
+        let elapsed = start.map(|start| self.clock.now().saturating_sub(start));
+        outcome.map(|()| counters.stats(elapsed))
+    }
 
-                    if *child.score.borrow() > 0.0 {
-                        winning_mvs.push(child);
+    /// Values the queued leaves in one batch, or rolls them out if the evaluator fails, and backpropagates them
+    fn flush<E: BatchEvaluator<S>>(
+        &self,
+        queue: &mut Vec<Rc<Node<S, A>>>,
+        evaluator: &E,
+        rng: &mut dyn Rng,
+        tracker: &Tracker<'_>,
+        counters: &mut SearchCounters,
+    ) -> Result<(), McstError> {
+        if queue.is_empty() {
+            return Ok(());
+        }
+        let states = queue
+            .iter()
+            .map(|leaf| leaf.state.clone())
+            .collect::<Vec<_>>();
+        let values = evaluator
+            .try_value_batch(&states)
+            .ok()
+            .filter(|values| values.len() == states.len());
+
+        for (i, leaf) in queue.drain(..).enumerate() {
+            let (returns, steps) = match &values {
+                Some(values) => (self.exact_returns(values[i]), 0),
+                None => {
+                    let (returns, steps) = self.simulate(&leaf, rng, tracker)?;
+                    counters.rollouts += 1;
+                    counters.rollout_steps += steps;
+                    counters.max_rollout_steps = counters.max_rollout_steps.max(steps);
+                    (returns, steps)
+                }
+            };
+            self.back_up(&leaf, returns, steps);
+        }
+        Ok(())
+    }
+
+    /// Backpropagates the returns from the leaf of an iteration, and tells the observers
+    fn back_up(&self, leaf: &Rc<Node<S, A>>, returns: Returns, steps: usize) {
+        let backpropagation = BackpropagationEvent {
+            depth: leaf.depth,
+            rollout_steps: steps,
+            rollout_return: returns.of(self.reported_player()),
+        };
+        leaf.back_propagate(
+            returns,
+            self.horizon.map(|_| steps),
+            self.mdp.get_discount_factor(),
+        );
+        self.notify(|o| o.on_backpropagation(&backpropagation));
+        self.count_outcome(leaf);
+    }
+
+    /// Looks the root up in the book (see [`MCTS::with_book`]): returns the result of a book move,
+    /// or seeds the root's actions with the book's statistics if the root was never searched
+    fn consult_book(&self) -> Result<Option<SearchResult<A>>, McstError> {
+        let Some(entry) = self
+            .book
+            .as_ref()
+            .and_then(|book| book.lookup(&self.root.state))
+        else {
+            return Ok(None);
+        };
+        let legal = self.mdp.get_actions(&self.root.state);
+
+        match entry {
+            BookEntry::Move(action) if legal.contains(&action) => {
+                let stats = self.action_stats(&action).unwrap_or_default();
+                let std_error = self
+                    .root
+                    .find_child_index(&action)
+                    .map_or(f64::INFINITY, |index| {
+                        self.root.children.borrow()[index].std_error()
+                    });
+                Ok(Some(SearchResult {
+                    outcomes: self.outcome_counts(&action),
+                    action,
+                    value: stats.value,
+                    visits: stats.visits,
+                    std_error,
+                    iterations: 0,
+                    elapsed: None,
+                    runner_ups: vec![],
+                }))
+            }
+            BookEntry::Move(_) => Ok(None),
+            BookEntry::Stats(moves) => {
+                if *self.root.visits.borrow() > 0 {
+                    return Ok(None);
+                }
+                self.attach(&self.root);
+                let mut guard = self.rng.borrow_mut();
+                for (action, stats) in moves {
+                    if stats.visits == 0 || !legal.contains(&action) {
                         continue;
                     }
+                    let child = self.root.get_outcome_child(
+                        &self.mdp,
+                        &action,
+                        self.table.as_ref(),
+                        self.chance_nodes,
+                        &mut **guard,
+                    )?;
+                    self.attach(&child);
+                    child.seed(stats.value, stats.visits);
+                    *self.root.visits.borrow_mut() += stats.visits;
+                }
+                Ok(None)
+            }
+        }
+    }
 
-                    if q > bestq {
-                        bestq = q;
-                        best_mvs = vec![child];
-                    } else if (q - bestq).abs() < 1e-9 {
-                        best_mvs.push(child);
-                    }
+    /// Ends a search of `iterations` iterations started at `start`: runs the extension of [`Strategy::RobustChild`],
+    /// then picks the final action
+    fn conclude(
+        &mut self,
+        mut iterations: usize,
+        start: Option<Duration>,
+    ) -> Result<Option<SearchResult<A>>, McstError> {
+        if self.strategy == Strategy::RobustChild {
+            let mut extension = 0;
+            while extension < self.robust_extension && self.robust_child().is_none() {
+                iterations += self.run(Budget::Iterations(1), Self::simulate)?.iterations;
+                extension += 1;
+            }
+        }
+        let elapsed = start.map(|start| self.clock.now().saturating_sub(start));
+
+        let best = match &self.final_selector {
+            Some(selector) => self.best_action_by(&**selector),
+            None => self.best_action(self.strategy),
+        };
+        let Some(action) = best else {
+            return Ok(None);
+        };
+
+        let mut others = self
+            .root
+            .children
+            .borrow()
+            .iter()
+            .map(|child| {
+                (
+                    child.action.clone(),
+                    self.reported_value(child),
+                    child.visits(),
+                )
+            })
+            .collect::<Vec<_>>();
+        others.sort_by_key(|(.., visits)| std::cmp::Reverse(*visits));
+
+        let chosen = others
+            .iter()
+            .position(|(a, ..)| *a == action)
+            .expect("the best action is one of the root's");
+        let (action, value, visits) = others.remove(chosen);
+        others.truncate(self.runner_ups);
+        let std_error = self
+            .root
+            .find_child_index(&action)
+            .map_or(f64::INFINITY, |index| {
+                self.root.children.borrow()[index].std_error()
+            });
+        let outcomes = self.outcome_counts(&action);
+
+        Ok(Some(SearchResult {
+            action,
+            value,
+            visits,
+            std_error,
+            iterations,
+            elapsed,
+            runner_ups: others,
+            outcomes,
+        }))
+    }
+
+    /// Execute the MCTS algorithm from the initial state given, with timeout in seconds
+    /// After how many milliseconds, the mcts should timeout
+    /// TODO: Move this to be more dynamic, and support max-depth timeout
+    ///
+    /// Panics if the MDP fails, see [`MCTS::try_mcts`]
+    pub fn mcts(&mut self, timeout: u128) -> SearchStats {
+        match self.try_mcts(timeout) {
+            Ok(stats) => stats,
+            Err(McstError::TerminalRoot { .. } | McstError::NoLegalActions) => {
+                SearchStats::default()
+            }
+            Err(error) => panic!("search failed: {error}"),
+        }
+    }
+
+    /// Same as [`MCTS::mcts`], but stops at the first failure of the MDP (see [`GenerativeModel::try_execute`]) and returns it.
+    /// The tree keeps everything searched before the failure.
+    /// Fails with [`McstError::TerminalRoot`] without searching if the root is terminal,
+    /// and with [`McstError::NoLegalActions`] if it has no actions
+    pub fn try_mcts(&mut self, timeout: u128) -> Result<SearchStats, McstError> {
+        self.run_measured(Budget::Time(timeout), Self::simulate)
+    }
+
+    /// Execute the MCTS algorithm for a fixed number of iterations, regardless of how long that takes
+    ///
+    /// Panics if the MDP fails, see [`MCTS::try_run_iterations`]
+    pub fn run_iterations(&mut self, iterations: usize) -> SearchStats {
+        match self.try_run_iterations(iterations) {
+            Ok(stats) => stats,
+            Err(McstError::TerminalRoot { .. } | McstError::NoLegalActions) => {
+                SearchStats::default()
+            }
+            Err(error) => panic!("search failed: {error}"),
+        }
+    }
+
+    /// Same as [`MCTS::run_iterations`], but stops at the first failure of the MDP and returns it,
+    /// see [`MCTS::try_mcts`]
+    pub fn try_run_iterations(&mut self, iterations: usize) -> Result<SearchStats, McstError> {
+        self.run_measured(Budget::Iterations(iterations), Self::simulate)
+    }
+
+    /// Reads the clock at the start of a search, if the search needs it.
+    /// Iteration-bounded searches don't otherwise need a clock, which some targets lack
+    fn start_clock(&self, budget: Budget) -> Option<Duration> {
+        let timed = matches!(budget, Budget::Time(_)) || self.profiling;
+        timed.then(|| self.clock.now())
+    }
+
+    /// Same as [`MCTS::run`], timing the search when the clock is read anyway
+    fn run_measured<F>(&mut self, budget: Budget, simulate: F) -> Result<SearchStats, McstError>
+    where
+        F: FnMut(
+            &Self,
+            &Rc<Node<S, A>>,
+            &mut dyn Rng,
+            &Tracker<'_>,
+        ) -> Result<(Returns, usize), McstError>,
+    {
+        let start = self.start_clock(budget);
+        let counters = self.run(budget, simulate)?;
+        let elapsed = start.map(|start| self.clock.now().saturating_sub(start));
+        Ok(counters.stats(elapsed))
+    }
+
+    /// Fails with [`McstError::TerminalRoot`] if the root is terminal, and with [`McstError::NoLegalActions`] if it has no actions,
+    /// rather than searching in vain
+    fn check_root(&self) -> Result<(), McstError> {
+        self.check_terminal_root()?;
+        if self.root.children.borrow().is_empty()
+            && self.mdp.get_actions(&self.root.state).is_empty()
+        {
+            return Err(McstError::NoLegalActions);
+        }
+        Ok(())
+    }
+
+    /// The part of [`MCTS::check_root`] that applies to MDPs whose actions aren't listed, see [`MCTS::try_mcts_continuous`]
+    fn check_terminal_root(&self) -> Result<(), McstError> {
+        match self.terminal_value() {
+            Some(value) => Err(McstError::TerminalRoot { value }),
+            None => Ok(()),
+        }
+    }
+
+    /// What a terminal root is worth to [`MCTS::reported_player`]: nothing, as the rewards that led there are behind it,
+    /// but for the draw score and terminal offset (see [`MCTS::with_draw_score`] and [`MCTS::with_terminal_offset`]).
+    /// `None` if the root isn't terminal. How the game ended is up to the model, see [`GenerativeModel::outcome`]
+    pub fn terminal_value(&self) -> Option<f64> {
+        if !self.mdp.is_terminal(&self.root.state) {
+            return None;
+        }
+        let mut returns = Returns::new(self.mdp.num_players());
+        self.credit_ending(&self.root.state, &mut returns, 1.0);
+        Some(returns.of(self.reported_player()))
+    }
+
+    /// Runs the select/expand/simulate/backpropagate loop until the budget is used up, or the MDP fails
+    pub(crate) fn run<F>(
+        &mut self,
+        budget: Budget,
+        mut simulate: F,
+    ) -> Result<SearchCounters, McstError>
+    where
+        F: FnMut(
+            &Self,
+            &Rc<Node<S, A>>,
+            &mut dyn Rng,
+            &Tracker<'_>,
+        ) -> Result<(Returns, usize), McstError>,
+    {
+        self.check_root()?;
+
+        self.attach(&self.root);
+        let mut guard = self.rng.borrow_mut();
+        let rng: &mut dyn Rng = &mut **guard;
+        let tracker = Tracker::start(budget, self.clock());
+        let mut counters = SearchCounters::default();
+        let mut phases = PhaseTimings::default();
+        let profiling = self.profiling.then(|| self.clock());
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("mcts_search", ?budget).entered();
+        #[cfg(feature = "tracing")]
+        let mut trace = crate::trace::SearchTrace::new(self.trace_interval);
+        #[cfg(feature = "metrics")]
+        let mut metrics =
+            crate::telemetry::SearchMetrics::start(self.clock(), self.table_lookups());
+
+        let mut search = || -> Result<(), McstError> {
+            while !tracker.exhausted(counters.iterations) {
+                counters.iterations += 1;
+
+                // Find a state node to expand
+                let selected_node = timed(profiling, &mut phases.select, || {
+                    self.root.select(
+                        &self.mdp,
+                        &self.bandit,
+                        self.pruning.as_deref(),
+                        self.table.as_ref(),
+                        self.chance_nodes,
+                        rng,
+                    )
+                })?;
+                // a new node at the end of the selection is an outcome first sampled there, with chance nodes
+                if is_new(&selected_node) && !Rc::ptr_eq(&selected_node, &self.root) {
+                    counters.nodes += 1;
+                    self.attach(&selected_node);
                 }
 
-                let chosen = if !winning_mvs.is_empty() {
-                    &winning_mvs[genrand(0, winning_mvs.len())]
+                if !self.mdp.is_terminal(&selected_node.state)
+                    && selected_node.exact.get().is_none()
+                {
+                    let child = timed(profiling, &mut phases.expand, || {
+                        selected_node.expand(
+                            &self.mdp,
+                            &self.policy,
+                            self.pruning.as_deref(),
+                            self.table.as_ref(),
+                            self.chance_nodes,
+                            rng,
+                        )
+                    })?;
+                    // rather than a transposition, or an outcome sampled before
+                    if is_new(&child) {
+                        counters.nodes += 1;
+                        self.attach(&child);
+                    }
+                    self.notify(|o| {
+                        o.on_expansion(&ExpansionEvent {
+                            depth: child.depth,
+                            player: child.player,
+                        })
+                    });
+
+                    // a solved state has its exact value rather than a rollout
+                    let exact = child.exact.get();
+                    let (returns, steps) = match exact {
+                        Some(value) => (self.exact_returns(value), 0),
+                        None => timed(profiling, &mut phases.simulate, || {
+                            simulate(self, &child, rng, &tracker)
+                        })?,
+                    };
+                    let backpropagation = BackpropagationEvent {
+                        depth: child.depth,
+                        rollout_steps: steps,
+                        rollout_return: returns.of(self.reported_player()),
+                    };
+                    timed(profiling, &mut phases.backpropagate, || {
+                        child.back_propagate(
+                            returns,
+                            self.horizon.map(|_| steps),
+                            self.mdp.get_discount_factor(),
+                        )
+                    });
+                    self.notify(|o| o.on_backpropagation(&backpropagation));
+                    self.count_outcome(&child);
+
+                    counters.expansions += 1;
+                    if exact.is_none() {
+                        counters.rollouts += 1;
+                        counters.rollout_steps += steps;
+                        counters.max_rollout_steps = counters.max_rollout_steps.max(steps);
+                    }
                 } else {
-                    &best_mvs[genrand(0, best_mvs.len())]
-                };
+                    // Nothing left to simulate, but the way there still earned the rewards along it
+                    let returns = match selected_node.exact.get() {
+                        Some(value) => self.exact_returns(value),
+                        None => {
+                            let mut returns = Returns::new(self.mdp.num_players());
+                            self.credit_outcome(&selected_node.state, &mut returns, 1.0);
+                            returns
+                        }
+                    };
+                    let backpropagation = BackpropagationEvent {
+                        depth: selected_node.depth,
+                        rollout_steps: 0,
+                        rollout_return: returns.of(self.reported_player()),
+                    };
+                    timed(profiling, &mut phases.backpropagate, || {
+                        selected_node.back_propagate(
+                            returns,
+                            self.horizon.map(|_| 0),
+                            self.mdp.get_discount_factor(),
+                        )
+                    });
+                    self.notify(|o| o.on_backpropagation(&backpropagation));
+                    self.count_outcome(&selected_node);
+                }
+
+                self.notify_iteration(counters.iterations);
+                self.track_verdict();
+                #[cfg(feature = "tracing")]
+                trace.iteration(counters.iterations, &self.root);
+                #[cfg(feature = "metrics")]
+                metrics.iteration(&self.root);
+            }
+
+            Ok(())
+        };
+        let outcome = search();
+        #[cfg(feature = "tracing")]
+        trace.finished(counters.iterations, &outcome);
+        #[cfg(feature = "metrics")]
+        metrics.finished(self.clock(), &counters, self.table_lookups(), &outcome);
+
+        drop(guard);
+        self.iterations += counters.iterations;
+
+        counters.phases = self.profiling.then_some(phases);
+        outcome.map(|()| counters)
+    }
+
+    /// Total number of iterations run by this searcher so far, across all its searches
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// The model the searcher plans in
+    pub fn mdp(&self) -> &M {
+        &self.mdp
+    }
+
+    /// The value of a state a rollout stopped in before the end of the episode, see [`MCTS::with_leaf_evaluator`]
+    pub(crate) fn heuristic_eval(&self, state: &S) -> f64 {
+        self.leaf_evaluator
+            .as_ref()
+            .map_or(0.0, |evaluate| evaluate(state))
+    }
+
+    /// The returns of a state solved with the exact `value` (see [`MCTS::with_endgame`]), or valued at `value` by an evaluator
+    fn exact_returns(&self, value: f64) -> Returns {
+        let mut returns = Returns::new(self.mdp.num_players());
+        returns.credit(0, value);
+        returns
+    }
+
+    /// Credits what the search makes of the end of an episode in the terminal `state`, on top of the rewards of the model,
+    /// `weight` being the discount of the steps it took to get there
+    fn credit_outcome(&self, state: &S, returns: &mut Returns, weight: f64) {
+        self.last_outcome.set(self.mdp.outcome(state));
+        self.credit_ending(state, returns, weight);
+    }
+
+    /// Same as [`MCTS::credit_outcome`], without keeping the outcome for [`MCTS::outcome_counts`]
+    fn credit_ending(&self, state: &S, returns: &mut Returns, weight: f64) {
+        if let Some(score) = self.draw_score
+            && self.mdp.outcome(state) == Some(Outcome::Draw)
+        {
+            returns.credit(self.reported_player(), weight * score);
+        }
+        if let Some(offset) = &self.terminal_offset {
+            returns.credit(0, weight * offset(state));
+        }
+    }
+
+    /// Returns true once a rollout of this many steps has to stop, in average-reward mode or past the maximum rollout depth
+    fn past_horizon(&self, steps: usize) -> bool {
+        self.horizon
+            .into_iter()
+            .chain(self.max_rollout_depth)
+            .any(|limit| steps >= limit)
+    }
+
+    /// Simulate until a terminal state, returning the discounted cumulative rewards of the players and the number of steps taken
+    pub(crate) fn simulate(
+        &self,
+        node: &Rc<Node<S, A>>,
+        rng: &mut dyn Rng,
+        tracker: &Tracker<'_>,
+    ) -> Result<(Returns, usize), McstError> {
+        let mut state = node.state.clone();
+        let mut cumulative_reward = Returns::new(self.mdp.num_players());
+        let discount = self.mdp.get_discount_factor();
+        let mut weight = 1.0;
+        let mut steps = 0;
 
-                chosen.action
+        while !self.mdp.is_terminal(&state) && !tracker.out_of_time() && !self.past_horizon(steps) {
+            let actions = self.mdp.get_actions(&state);
+            if actions.is_empty() {
+                return Err(MdpError::NoActions.into());
             }
+
+            // Choose an action to execute
+            let action = self.policy.pick(&state, &actions, rng);
+            let mover = self.mdp.player_to_move(&state);
+
+            // Execute the action
+            let (next_state, reward, ..) = checked_execute(&self.mdp, &state, &action, rng)?;
+
+            // Discount the reward
+            cumulative_reward.credit(mover, weight * reward);
+            weight *= discount;
+            steps += 1;
+
+            state = next_state;
+        }
+
+        match self.mdp.is_terminal(&state) {
+            true => self.credit_outcome(&state, &mut cumulative_reward, weight),
+            false => cumulative_reward.credit(0, weight * self.heuristic_eval(&state)),
         }
+
+        Ok((cumulative_reward, steps))
+    }
+
+    /// A read-only view of the root of the search tree, to walk the tree from
+    pub fn root(&self) -> TreeNode<S, A> {
+        TreeNode::new(Rc::clone(&self.root))
+    }
+
+    /// Reports the size and shape of the current search tree,
+    /// e.g. to tune search budgets, or to verify that pruning actually shrinks the tree
+    pub fn tree_stats(&self) -> TreeStats {
+        self.root.tree_stats()
+    }
+
+    /// Returns the mean return of every player (indexed by [`crate::mdp::PlayerId`]) for each of the root's explored actions.
+    /// [`MCTS::best_action`] only looks at the values of the player to move at the root
+    pub fn player_values(&self) -> Vec<(A, Vec<f64>)> {
+        let players = self.mdp.num_players();
+        let mover = self.root.player;
+
+        self.root
+            .children
+            .borrow()
+            .iter()
+            .map(|child| {
+                let visits = child.visits().max(1) as f64;
+                let values = match players {
+                    0 | 1 => vec![child.q_value()],
+                    // zero-sum: what one player gains the other loses
+                    2 => {
+                        let own = child.q_value();
+                        if mover == 0 {
+                            vec![own, -own]
+                        } else {
+                            vec![-own, own]
+                        }
+                    }
+                    _ => {
+                        let scores = child.scores();
+                        (0..players)
+                            .map(|player| scores.get(player).map_or(0.0, |s| s / visits))
+                            .collect()
+                    }
+                };
+                (child.action.clone(), values)
+            })
+            .collect()
+    }
+
+    /// The share of the root's visits every explored action got, the target of policy networks
+    /// (see [`crate::training::TrainingExample`]). Empty until the root was searched
+    pub fn visit_distribution(&self) -> Vec<(A, f64)> {
+        let children = self.root.children.borrow();
+        let total = children.iter().map(|child| child.visits()).sum::<usize>();
+        if total == 0 {
+            return vec![];
+        }
+        children
+            .iter()
+            .map(|child| (child.action.clone(), child.visits() as f64 / total as f64))
+            .collect()
+    }
+
+    /// Returns the statistics of every explored action of the root, with values from the perspective of [`MCTS::for_player`]'s player
+    pub fn root_action_stats(&self) -> Vec<(A, ActionStats)> {
+        self.root
+            .children
+            .borrow()
+            .iter()
+            .map(|child| (child.action.clone(), self.reported_stats(child)))
+            .collect()
+    }
+
+    /// Returns the statistics of one of the root's actions, e.g. to tell how bad the search thinks a move is,
+    /// or `None` if the search never tried it
+    pub fn action_stats(&self, action: &A) -> Option<ActionStats> {
+        let index = self.root.find_child_index(action)?;
+        Some(self.reported_stats(&self.root.children.borrow()[index]))
+    }
+
+    /// The player values are reported for, see [`MCTS::for_player`]
+    pub fn reported_player(&self) -> PlayerId {
+        self.perspective.unwrap_or(self.root.player)
+    }
+
+    /// The mean return of an action of the root for [`MCTS::reported_player`]
+    fn reported_value(&self, child: &Child<S, A>) -> f64 {
+        let player = self.reported_player();
+        if player == self.root.player {
+            return child.q_value();
+        }
+        match self.mdp.num_players() {
+            // zero-sum, whether or not the MDP counts its two players
+            0..=2 => -child.q_value(),
+            _ => {
+                let visits = child.visits().max(1) as f64;
+                child.scores().get(player).map_or(0.0, |s| s / visits)
+            }
+        }
+    }
+
+    fn reported_stats(&self, child: &Child<S, A>) -> ActionStats {
+        ActionStats {
+            value: self.reported_value(child),
+            ..child.stats()
+        }
+    }
+
+    /// Same as [`MCTS::best_action`], with a user-defined selection rule.
+    /// The selector gets the values of the player to move, whoever [`MCTS::for_player`] reports them for
+    ///
+    /// ```
+    /// use mct_rs::{mcts::MCTS, mdp::FnMdp, policy::RandomRollout, stats::ActionStats};
+    ///
+    /// let mdp = FnMdp::new(
+    ///     || 0u32,
+    ///     |_state: &u32| vec![1usize, 2],
+    ///     |state, step, _rng| (state + *step as u32, *step as f64, state + *step as u32 >= 10),
+    ///     |state| *state >= 10,
+    /// );
+    /// let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+    /// mcts.run_iterations(200);
+    ///
+    /// // the highest lower bound of the value, so that barely explored actions don't win on luck
+    /// let cautious = |actions: &[(usize, ActionStats)]| {
+    ///     let bound = |s: &ActionStats| s.value - 1.0 / (s.visits as f64).sqrt();
+    ///     actions
+    ///         .iter()
+    ///         .max_by(|(_, a), (_, b)| bound(a).total_cmp(&bound(b)))
+    ///         .map(|(action, _)| *action)
+    /// };
+    /// assert!(mcts.best_action_by(&cautious).is_some());
+    /// ```
+    pub fn best_action_by<F: FinalSelector<A> + ?Sized>(&self, selector: &F) -> Option<A> {
+        // the selector picks for the player to move, whoever the values are reported for
+        let stats = self
+            .root
+            .children
+            .borrow()
+            .iter()
+            .map(|child| (child.action.clone(), child.stats()))
+            .collect::<Vec<_>>();
+        selector.select(&stats)
+    }
+
+    /// The value of the root for the player to move: the value of its most visited action, `None` until one was explored
+    pub fn root_value(&self) -> Option<f64> {
+        self.root
+            .children
+            .borrow()
+            .iter()
+            .max_by_key(|child| child.visits())
+            .map(Child::q_value)
+    }
+
+    /// How the games through one of the root's actions ended for [`MCTS::reported_player`], across the searches from this root,
+    /// or `None` if none did with an outcome (see [`GenerativeModel::outcome`]). Rollouts cut short don't count
+    pub fn outcome_counts(&self, action: &A) -> Option<OutcomeCounts> {
+        self.outcome_counts
+            .borrow()
+            .iter()
+            .find(|(a, _)| a == action)
+            .map(|(_, counts)| *counts)
+    }
+
+    /// Counts how the episode of the iteration that reached `leaf` ended, for the root's action it went through
+    fn count_outcome(&self, leaf: &Rc<Node<S, A>>) {
+        let Some(outcome) = self.last_outcome.take() else {
+            return;
+        };
+        let Some(step) = leaf.first_step(&self.root) else {
+            return;
+        };
+        let children = self.root.children.borrow();
+        let Some(child) = children
+            .iter()
+            .find(|child| child.outcomes().any(|o| Rc::ptr_eq(&o.node, &step)))
+        else {
+            return;
+        };
+
+        let mut counts = self.outcome_counts.borrow_mut();
+        let index = match counts
+            .iter()
+            .position(|(action, _)| *action == child.action)
+        {
+            Some(index) => index,
+            None => {
+                counts.push((child.action.clone(), OutcomeCounts::default()));
+                counts.len() - 1
+            }
+        };
+        let counts = &mut counts[index].1;
+        match outcome {
+            Outcome::Draw => counts.draws += 1,
+            Outcome::Win(winner) if winner == self.reported_player() => counts.wins += 1,
+            Outcome::Win(_) => counts.losses += 1,
+        }
+    }
+
+    /// Whether the player to move should resign or claim the win, as configured by [`MCTS::with_resignation`] and [`MCTS::with_win_claim`],
+    /// going by the iterations run from this root so far, across searches. [`Verdict::PlayOn`] without either threshold
+    ///
+    /// ```
+    /// use mct_rs::{envs::nim::Nim, game::GameMdp, mcts::MCTS, policy::RandomRollout, stats::Verdict};
+    ///
+    /// // two heaps of 3: whatever the first player takes, the second evens the heaps out and wins
+    /// let mut mcts = MCTS::new(GameMdp::new(Nim::new([3, 3])), RandomRollout::new())
+    ///     .with_seed(1)
+    ///     .with_resignation(-0.8, 500);
+    /// mcts.run_iterations(3000);
+    /// assert_eq!(mcts.verdict(), Verdict::Resign);
+    /// ```
+    pub fn verdict(&self) -> Verdict {
+        let (losing, winning) = self.streaks.get();
+        match (self.resignation, self.win_claim) {
+            (Some((_, iterations)), _) if losing >= iterations => Verdict::Resign,
+            (_, Some((_, iterations))) if winning >= iterations => Verdict::ClaimWin,
+            _ => Verdict::PlayOn,
+        }
+    }
+
+    /// Extends or breaks the streaks of iterations the root value has stayed past the thresholds for, see [`MCTS::verdict`]
+    fn track_verdict(&self) {
+        if self.resignation.is_none() && self.win_claim.is_none() {
+            return;
+        }
+        let Some(value) = self.root_value() else {
+            return;
+        };
+        let (losing, winning) = self.streaks.get();
+        let past =
+            |threshold: Option<(f64, usize)>, beyond: fn(f64, f64) -> bool, streak: usize| {
+                match threshold {
+                    Some((threshold, _)) if beyond(value, threshold) => streak + 1,
+                    _ => 0,
+                }
+            };
+        self.streaks.set((
+            past(
+                self.resignation,
+                |value, threshold| value < threshold,
+                losing,
+            ),
+            past(
+                self.win_claim,
+                |value, threshold| value > threshold,
+                winning,
+            ),
+        ));
+    }
+
+    /// Returns the most visited action of the root if it's also the highest valued one
+    fn robust_child(&self) -> Option<A> {
+        let children = self.root.children.borrow();
+        let most_visits = children.iter().map(Child::visits).max()?;
+        let best_q = children
+            .iter()
+            .map(Child::q_value)
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        children
+            .iter()
+            .find(|c| c.visits() == most_visits && c.q_value() >= best_q - 1e-9)
+            .map(|c| c.action.clone())
+    }
+
+    /// Returns true if the action leads to a terminal state the player to move wins, whatever its outcome:
+    /// by the model's outcome of the state if it has one (so that a draw the model pays for isn't taken for a win),
+    /// by a positive reward otherwise
+    fn wins(&self, child: &Child<S, A>) -> bool {
+        child.outcomes().all(|o| {
+            self.mdp.is_terminal(&o.node.state)
+                && match self.mdp.outcome(&o.node.state) {
+                    Some(outcome) => outcome == Outcome::Win(self.root.player),
+                    None => o.reward > 0.0,
+                }
+        })
+    }
+
+    /// Same as [`MCTS::best_action`], but fails with [`McstError::TerminalRoot`] if the root is terminal
+    /// (see [`MCTS::terminal_value`] for what it's worth) and with [`McstError::NoLegalActions`] if it has no actions,
+    /// so that `None` only means that no action was explored yet
+    pub fn try_best_action(&self, strategy: Strategy) -> Result<Option<A>, McstError> {
+        self.check_root()?;
+        Ok(self.best_action(strategy))
+    }
+
+    /// Returns every explored action of the root, best first according to the strategy, with the score they're ranked by:
+    /// - [`Strategy::MostVisited`] and [`Strategy::RobustChild`]: the number of visits
+    /// - [`Strategy::HighestQValue`]: the Q-value
+    /// - [`Strategy::Probabilistic`]: the probability [`MCTS::best_action`] picks the action with
+    /// - [`Strategy::HeuristicWin`]: the Q-value, with the winning actions ranked first
+    ///
+    /// Unlike [`MCTS::best_action`], nothing is left to chance, so callers can look at several lines, or skip actions they rule out for reasons the MDP doesn't know about
+    pub fn ranked_actions(&self, strategy: Strategy) -> Vec<(A, f64)> {
+        let root = &self.root;
+        let children = root.children.borrow();
+
+        let cost = |child: &Child<S, A>| match self.cost_tie_breaks {
+            true => self.mdp.action_cost(&root.state, &child.action),
+            false => 0.0,
+        };
+        let wins = |child: &Child<S, A>| strategy == Strategy::HeuristicWin && self.wins(child);
+
+        let mut scores = children
+            .iter()
+            .map(|child| match strategy {
+                Strategy::MostVisited | Strategy::RobustChild => child.visits() as f64,
+                _ => child.q_value(),
+            })
+            .collect::<Vec<_>>();
+        if strategy == Strategy::Probabilistic {
+            scores = softmax(&scores);
+        }
+
+        let mut ranked = children.iter().zip(scores).collect::<Vec<_>>();
+        ranked.sort_by(|(a, score_a), (b, score_b)| {
+            wins(b)
+                .cmp(&wins(a))
+                .then_with(|| score_b.total_cmp(score_a))
+                .then_with(|| cost(a).total_cmp(&cost(b)))
+        });
+
+        ranked
+            .into_iter()
+            .map(|(child, score)| (child.action.clone(), score))
+            .collect()
+    }
+
+    /// Picks the action to take from the root by `strategy`, `None` if there's none to pick:
+    /// when the root is terminal or has no actions, or when no action was explored yet. [`MCTS::try_best_action`] tells those apart
+    pub fn best_action(&self, strategy: Strategy) -> Option<A> {
+        let root = &self.root;
+        let children = root.children.borrow();
+        let mut rng = self.rng.borrow_mut();
+
+        if children.is_empty() {
+            return None;
+        }
+
+        let cost = |action: &A| match self.cost_tie_breaks {
+            true => self.mdp.action_cost(&root.state, action),
+            false => 0.0,
+        };
+
+        match strategy {
+            Strategy::MostVisited => children
+                .iter()
+                .max_by(|a, b| {
+                    a.visits()
+                        .cmp(&b.visits())
+                        .then_with(|| cost(&b.action).total_cmp(&cost(&a.action)))
+                })
+                .map(|c| c.action.clone()),
+
+            Strategy::HighestQValue => children
+                .iter()
+                .max_by(|a, b| {
+                    let (qa, qb) = (a.q_value(), b.q_value());
+                    if (qa - qb).abs() < 1e-9 {
+                        return cost(&b.action).total_cmp(&cost(&a.action));
+                    }
+                    qa.partial_cmp(&qb).unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .map(|c| c.action.clone()),
+
+            Strategy::RobustChild => {
+                drop(rng);
+                self.robust_child()
+                    .or_else(|| self.best_action(self.robust_fallback))
+            }
+
+            Strategy::Probabilistic => {
+                let qvalues = children.iter().map(|c| c.q_value()).collect::<Vec<_>>();
+                let probs = softmax(&qvalues);
+
+                // sample based on probabilities
+                let mut r = rng.gen_f64();
+                for (i, p) in probs.iter().enumerate() {
+                    r -= p;
+                    if r <= 0.0 {
+                        return Some(children[i].action.clone());
+                    }
+                }
+
+                // fallback
+                Some(children[0].action.clone())
+            }
+            Strategy::HeuristicWin => {
+                // prioritize terminal winning moves
+                let mut winning_mvs = vec![];
+                let mut bestq = f64::NEG_INFINITY;
+                let mut best_mvs = vec![];
+
+                for child in children.iter() {
+                    let q = child.q_value();
+
+                    if self.wins(child) {
+                        winning_mvs.push(child);
+                        continue;
+                    }
+
+                    if q > bestq {
+                        bestq = q;
+                        best_mvs = vec![child];
+                    } else if (q - bestq).abs() < 1e-9 {
+                        best_mvs.push(child);
+                    }
+                }
+
+                let mut candidates = if !winning_mvs.is_empty() {
+                    winning_mvs
+                } else {
+                    best_mvs
+                };
+                if self.cost_tie_breaks {
+                    let cheapest = candidates
+                        .iter()
+                        .map(|c| cost(&c.action))
+                        .fold(f64::INFINITY, f64::min);
+                    candidates.retain(|c| cost(&c.action) <= cheapest);
+                }
+                let chosen = &candidates[rng.gen_range(0, candidates.len())];
+
+                Some(chosen.action.clone())
+            }
+        }
+    }
+}
+
+/// Runs `f`, adding the time it took to `total` when profiling (i.e. given a clock)
+fn timed<T>(profiling: Option<&dyn Clock>, total: &mut Duration, f: impl FnOnce() -> T) -> T {
+    let Some(clock) = profiling else {
+        return f();
+    };
+
+    let start = clock.now();
+    let result = f();
+    *total += clock.now().saturating_sub(start);
+    result
+}
+
+/// Returns true if the search never went through the node, i.e. it was just added to the tree
+fn is_new<S, A>(node: &Node<S, A>) -> bool {
+    *node.visits.borrow() == 0
+}
+
+/// A future that is pending once, asking to be polled again right away, see [`MCTS::search_async`]
+#[derive(Default)]
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl<M, S, A, P> MCTS<M, S, A, P>
+where
+    M: GenerativeModel<S, A>,
+    A: Action,
+    S: Clone + Eq + PartialEq + IncrementalState<A>,
+    P: RolloutPolicy<M, S, A>,
+{
+    /// Same as [`MCTS::mcts`] and [`MCTS::run_iterations`], for either budget, but rather than cloning a state for every rollout,
+    /// a single scratch copy of the root state is advanced and rewound in place (see [`IncrementalState`]).
+    /// Only the rollouts run on the scratch state: the tree keeps a state per node,
+    /// so selection and expansion still execute the model as [`MCTS::mcts`] does.
+    ///
+    /// The actions along the tree are replayed on the scratch state, which has to reach the state of the node the rollout starts from.
+    /// It doesn't with stochastic models (the tree holds the outcomes they sampled, not the ones `apply` makes),
+    /// nor with models that canonicalize states (see [`GenerativeModel::canonicalize`]): the search then fails with
+    /// [`MdpError::ContractViolation`].
+    ///
+    /// Panics if the MDP fails, see [`MCTS::try_mcts_incremental`]
+    pub fn mcts_incremental(&mut self, budget: Budget) -> SearchStats {
+        match self.try_mcts_incremental(budget) {
+            Ok(stats) => stats,
+            Err(McstError::TerminalRoot { .. } | McstError::NoLegalActions) => {
+                SearchStats::default()
+            }
+            Err(error) => panic!("search failed: {error}"),
+        }
+    }
+
+    /// Same as [`MCTS::mcts_incremental`], but stops at the first failure of the MDP and returns it, see [`MCTS::try_mcts`]
+    pub fn try_mcts_incremental(&mut self, budget: Budget) -> Result<SearchStats, McstError> {
+        let mut scratch = self.root.state.clone();
+
+        self.run_measured(budget, |mcts, node, rng, tracker| {
+            mcts.simulate_incremental(&mut scratch, node, rng, tracker)
+        })
+    }
+
+    /// Simulate until a terminal state, starting with `scratch` at the root state and leaving it there once done.
+    /// Returns the discounted cumulative reward and the number of steps taken
+    pub(crate) fn simulate_incremental(
+        &self,
+        scratch: &mut S,
+        node: &Rc<Node<S, A>>,
+        rng: &mut dyn Rng,
+        tracker: &Tracker<'_>,
+    ) -> Result<(Returns, usize), McstError> {
+        // Walk the scratch state down to the node the rollout starts from
+        let mut undos = node
+            .path_actions()
+            .iter()
+            .map(|action| scratch.apply(action))
+            .collect::<Vec<_>>();
+        if *scratch != node.state {
+            while let Some(undo) = undos.pop() {
+                scratch.undo(undo);
+            }
+            return Err(MdpError::ContractViolation(
+                "applying the actions from the root didn't reach the state of the tree: \
+                 apply has to agree with execute, and the model be deterministic"
+                    .to_string(),
+            )
+            .into());
+        }
+
+        let path_length = undos.len();
+        let mut cumulative_reward = Returns::new(self.mdp.num_players());
+        let discount = self.mdp.get_discount_factor();
+        let mut weight = 1.0;
+        // the scratch state has to be rewound even when the rollout fails
+        let mut failure = None;
+
+        while !self.mdp.is_terminal(scratch)
+            && !tracker.out_of_time()
+            && !self.past_horizon(undos.len() - path_length)
+        {
+            let actions = self.mdp.get_actions(scratch);
+            if actions.is_empty() {
+                failure = Some(MdpError::NoActions.into());
+                break;
+            }
+            let action = self.policy.pick(scratch, &actions, rng);
+            let mover = self.mdp.player_to_move(scratch);
+
+            let undo = scratch.apply(&action);
+            let reward = scratch.reward(&undo);
+            undos.push(undo);
+            if reward.is_nan() {
+                failure = Some(McstError::NotANumber("a reward of the model"));
+                break;
+            }
+            cumulative_reward.credit(mover, weight * reward);
+            weight *= discount;
+        }
+
+        match self.mdp.is_terminal(scratch) {
+            true => self.credit_outcome(scratch, &mut cumulative_reward, weight),
+            false => cumulative_reward.credit(0, weight * self.heuristic_eval(scratch)),
+        }
+
+        let steps = undos.len() - path_length;
+
+        // and back to the root for the next iteration
+        while let Some(undo) = undos.pop() {
+            scratch.undo(undo);
+        }
+
+        match failure {
+            Some(error) => Err(error),
+            None => Ok((cumulative_reward, steps)),
+        }
+    }
+}
+
+impl<M, S, A, P> MCTS<M, S, A, P>
+where
+    M: ContinuousActions<S, A>,
+    A: Action,
+    S: Clone + Eq + PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    /// Execute the MCTS algorithm over actions sampled from the MDP rather than enumerated (see [`ContinuousActions`]),
+    /// e.g. real-valued controls. A node visited N times tries at most as many actions as the widening allows,
+    /// and the rollouts play sampled actions rather than the rollout policy's
+    ///
+    /// Panics if the MDP fails, see [`MCTS::try_mcts_continuous`]
+    pub fn mcts_continuous(
+        &mut self,
+        budget: Budget,
+        widening: ProgressiveWidening,
+    ) -> SearchStats {
+        match self.try_mcts_continuous(budget, widening) {
+            Ok(stats) => stats,
+            Err(McstError::TerminalRoot { .. }) => SearchStats::default(),
+            Err(error) => panic!("search failed: {error}"),
+        }
+    }
+
+    /// Same as [`MCTS::mcts_continuous`], but stops at the first failure of the MDP and returns it, see [`MCTS::try_mcts`].
+    /// The root's actions are sampled, so it never fails with [`McstError::NoLegalActions`]
+    pub fn try_mcts_continuous(
+        &mut self,
+        budget: Budget,
+        widening: ProgressiveWidening,
+    ) -> Result<SearchStats, McstError> {
+        self.check_terminal_root()?;
+
+        self.attach(&self.root);
+        let start = self.start_clock(budget);
+        let mut guard = self.rng.borrow_mut();
+        let rng: &mut dyn Rng = &mut **guard;
+        let tracker = Tracker::start(budget, self.clock());
+        let mut counters = SearchCounters::default();
+
+        let mut search = || -> Result<(), McstError> {
+            while !tracker.exhausted(counters.iterations) {
+                counters.iterations += 1;
+
+                let leaf = self.root.select_widening(
+                    &self.mdp,
+                    &self.bandit,
+                    &widening,
+                    self.table.as_ref(),
+                    self.chance_nodes,
+                    rng,
+                )?;
+                if is_new(&leaf) && !Rc::ptr_eq(&leaf, &self.root) {
+                    counters.nodes += 1;
+                }
+                self.attach(&leaf);
+                let (returns, steps) = self.simulate_continuous(&leaf, rng, &tracker)?;
+                counters.rollouts += 1;
+                counters.rollout_steps += steps;
+                counters.max_rollout_steps = counters.max_rollout_steps.max(steps);
+                let backpropagation = BackpropagationEvent {
+                    depth: leaf.depth,
+                    rollout_steps: steps,
+                    rollout_return: returns.of(self.reported_player()),
+                };
+                leaf.back_propagate(
+                    returns,
+                    self.horizon.map(|_| steps),
+                    self.mdp.get_discount_factor(),
+                );
+                self.notify(|o| o.on_backpropagation(&backpropagation));
+                self.count_outcome(&leaf);
+                self.notify_iteration(counters.iterations);
+                self.track_verdict();
+            }
+
+            Ok(())
+        };
+        let outcome = search();
+
+        drop(guard);
+        self.iterations += counters.iterations;
+        let elapsed = start.map(|start| self.clock.now().saturating_sub(start));
+        outcome.map(|()| counters.stats(elapsed))
+    }
+
+    /// Simulate until a terminal state with sampled actions, returning the discounted cumulative rewards of the players and the number of steps taken
+    fn simulate_continuous(
+        &self,
+        node: &Rc<Node<S, A>>,
+        rng: &mut dyn Rng,
+        tracker: &Tracker<'_>,
+    ) -> Result<(Returns, usize), McstError> {
+        let mut state = node.state.clone();
+        let mut cumulative_reward = Returns::new(self.mdp.num_players());
+        let discount = self.mdp.get_discount_factor();
+        let mut weight = 1.0;
+        let mut steps = 0;
+
+        while !self.mdp.is_terminal(&state) && !tracker.out_of_time() && !self.past_horizon(steps) {
+            let action = self.mdp.sample_action(&state, rng);
+            let mover = self.mdp.player_to_move(&state);
+
+            let (next_state, reward, ..) = checked_execute(&self.mdp, &state, &action, rng)?;
+            cumulative_reward.credit(mover, weight * reward);
+            weight *= discount;
+            steps += 1;
+            state = next_state;
+        }
+
+        match self.mdp.is_terminal(&state) {
+            true => self.credit_outcome(&state, &mut cumulative_reward, weight),
+            false => cumulative_reward.credit(0, weight * self.heuristic_eval(&state)),
+        }
+
+        Ok((cumulative_reward, steps))
+    }
+}
+
+/// Softmax of the values: the higher the value, the higher the probability
+fn softmax(values: &[f64]) -> Vec<f64> {
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    // subtract max for numerical stability
+    let exps = values.iter().map(|v| (v - max).exp()).collect::<Vec<_>>();
+    let sum = exps.iter().sum::<f64>().max(f64::MIN_POSITIVE);
+
+    exps.iter().map(|x| x / sum).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mdp::{ExplicitMDP, FnMdp, PlayerId},
+        policy::RandomRollout,
+        replay::{Recorder, Replayer, RngLog},
+        solvers::value_iteration::ValueIteration,
+    };
+
+    use std::{pin::pin, task::Waker};
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Step {
+        Left,
+        Right,
+    }
+
+    impl Action for Step {}
+
+    /// A random walk between 0 and 10, where every step has a chance of slipping the other way
+    struct Walk;
+
+    impl GenerativeModel<i32, Step> for Walk {
+        fn get_actions(&self, _state: &i32) -> Vec<Step> {
+            vec![Step::Left, Step::Right]
+        }
+
+        fn execute(&self, state: &i32, action: &Step, rng: &mut dyn Rng) -> (i32, f64, bool) {
+            self.sample_transition(state, action, rng)
+        }
+
+        fn is_terminal(&self, state: &i32) -> bool {
+            *state <= 0 || *state >= 10
+        }
+
+        fn get_initial_state(&self) -> i32 {
+            5
+        }
+    }
+
+    impl ExplicitMDP<i32, Step> for Walk {
+        fn get_states(&self) -> Vec<i32> {
+            (0..=10).collect()
+        }
+
+        fn get_transitions(&self, state: &i32, action: &Step) -> Vec<(i32, f64)> {
+            let direction = match action {
+                Step::Left => -1,
+                Step::Right => 1,
+            };
+            vec![(state + direction, 0.8), (state - direction, 0.2)]
+        }
+
+        fn get_reward(&self, _state: &i32, _action: &Step, next_state: &i32) -> f64 {
+            if *next_state == 10 { 1.0 } else { 0.0 }
+        }
+
+        fn get_goal_states(&self) -> Vec<i32> {
+            vec![10]
+        }
+    }
+
+    fn seeded_search(seed: u64) -> (TreeStats, Option<Step>) {
+        let mut mcts = MCTS::new(Walk, RandomRollout::new()).with_seed(seed);
+        mcts.run_iterations(300);
+        (mcts.tree_stats(), mcts.best_action(Strategy::Probabilistic))
+    }
+
+    #[test]
+    fn test_seeded_searches_are_reproducible() {
+        for seed in 0..5 {
+            assert_eq!(seeded_search(seed), seeded_search(seed));
+
+            // the builder's generator replaces its seed
+            let mut built = MCTS::builder(Walk, RandomRollout::new())
+                .with_seed(seed + 1)
+                .with_rng(SplitMix64::seed(seed))
+                .build();
+            built.run_iterations(300);
+            let built = (
+                built.tree_stats(),
+                built.best_action(Strategy::Probabilistic),
+            );
+            assert_eq!(built, seeded_search(seed));
+        }
+    }
+
+    #[test]
+    fn test_replay_reproduces_recorded_search() {
+        let recorder = Recorder::new(SplitMix64::seed(3));
+        let log = recorder.log();
+
+        let mut recorded = MCTS::new(Walk, RandomRollout::new()).with_rng(recorder);
+        recorded.run_iterations(300);
+
+        let bytes = log.borrow().to_bytes();
+        let mut replayed = MCTS::new(Walk, RandomRollout::new())
+            .with_rng(Replayer::new(RngLog::from_bytes(&bytes).unwrap()));
+        replayed.run_iterations(300);
+
+        // the same draws grow the same tree, down to the statistics of every root action
+        assert_eq!(replayed.iterations(), 300);
+        assert_eq!(replayed.tree_stats(), recorded.tree_stats());
+        assert_eq!(replayed.root_action_stats(), recorded.root_action_stats());
+        assert_eq!(recorded.root_action_stats().len(), 2);
+        assert_eq!(
+            replayed.best_action(Strategy::MostVisited),
+            recorded.best_action(Strategy::MostVisited)
+        );
+    }
+
+    #[test]
+    fn test_time_budget_reads_injected_clock() {
+        // every reading moves the clock a millisecond forward
+        let ticks = std::cell::Cell::new(0);
+        let clock = move || {
+            ticks.set(ticks.get() + 1);
+            Duration::from_millis(ticks.get())
+        };
+
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_clock(clock);
+        mcts.mcts(50);
+
+        assert!(mcts.iterations() > 0);
+        assert!(mcts.iterations() < 50);
+    }
+
+    /// Steps the walk in place, paying 1 on reaching 10
+    impl IncrementalState<Step> for i32 {
+        type Undo = Step;
+
+        fn apply(&mut self, step: &Step) -> Step {
+            *self += match step {
+                Step::Left => -1,
+                Step::Right => 1,
+            };
+            *step
+        }
+
+        fn undo(&mut self, step: Step) {
+            *self -= match step {
+                Step::Left => -1,
+                Step::Right => 1,
+            };
+        }
+
+        fn reward(&self, _step: &Step) -> f64 {
+            f64::from(*self == 10)
+        }
+    }
+
+    /// The walk without slipping, from 5, taking the steps `actions` allows
+    fn steady_walk(actions: impl Fn(&i32) -> Vec<Step>) -> impl GenerativeModel<i32, Step> {
+        FnMdp::new(
+            || 5,
+            actions,
+            |state: &i32, step: &Step, _rng| {
+                let mut next = *state;
+                let undo = next.apply(step);
+                (next, next.reward(&undo), false)
+            },
+            |state| *state <= 0 || *state >= 10,
+        )
+    }
+
+    /// Values the walk by how close it is to 10, counting the batches it was asked for, or fails to
+    struct Distance {
+        batches: Cell<usize>,
+        fails: bool,
+    }
+
+    impl BatchEvaluator<i32> for Distance {
+        type Error = ();
+
+        fn try_value_batch(&self, states: &[i32]) -> Result<Vec<f64>, ()> {
+            self.batches.set(self.batches.get() + 1);
+            match self.fails {
+                true => Err(()),
+                false => Ok(states
+                    .iter()
+                    .map(|state| f64::from(*state) / 10.0)
+                    .collect()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_batched_searches_value_leaves_a_batch_at_a_time() {
+        let both = |_state: &i32| vec![Step::Left, Step::Right];
+        let search = |fails: bool| {
+            let evaluator = Distance {
+                batches: Cell::new(0),
+                fails,
+            };
+            let mut mcts = MCTS::new(steady_walk(both), RandomRollout::new()).with_seed(1);
+            let stats = mcts.mcts_batched(Budget::Iterations(300), &evaluator, 16);
+            (
+                stats,
+                evaluator.batches.get(),
+                mcts.best_action(Strategy::MostVisited),
+            )
+        };
+
+        let (stats, batches, best) = search(false);
+        assert_eq!(stats.iterations, 300);
+        assert_eq!(stats.simulations, 0);
+        // the leaves ending the walk are valued by the model, and don't wait for a batch
+        assert!((1..=19).contains(&batches), "batches={batches}");
+        assert_eq!(best, Some(Step::Right));
+
+        // every batch the evaluator fails on is rolled out instead
+        let (stats, failed, _) = search(true);
+        assert_eq!(stats.iterations, 300);
+        assert!(stats.simulations > 0);
+        assert!(stats.simulations <= 16 * failed);
+    }
+
+    #[test]
+    fn test_incremental_searches_match_cloning_ones() {
+        let both = |_state: &i32| vec![Step::Left, Step::Right];
+        let search = |incremental: bool| {
+            let mut mcts = MCTS::new(steady_walk(both), RandomRollout::new()).with_seed(1);
+            let stats = match incremental {
+                true => mcts.mcts_incremental(Budget::Iterations(300)),
+                false => mcts.run_iterations(300),
+            };
+            (
+                stats.iterations,
+                mcts.tree_stats(),
+                mcts.root_action_stats(),
+            )
+        };
+        let cloning = search(false);
+        assert_eq!(cloning.0, 300);
+        assert_eq!(search(true), cloning);
+
+        // slips take the walk where replaying the steps doesn't
+        let mut mcts = MCTS::new(Walk, RandomRollout::new()).with_seed(1);
+        assert!(matches!(
+            mcts.try_mcts_incremental(Budget::Iterations(300)),
+            Err(McstError::Mdp(MdpError::ContractViolation(_)))
+        ));
+
+        // a rollout that fails still leaves the scratch state at the root, going left from 5 to a dead end at 3
+        let dead_end = |state: &i32| match *state == 3 {
+            true => vec![],
+            false => vec![Step::Left],
+        };
+        let mcts = MCTS::new(steady_walk(dead_end), RandomRollout::new());
+        let mut scratch = 5;
+        let tracker = Tracker::start(Budget::Iterations(1), &*mcts.clock);
+        let rollout =
+            mcts.simulate_incremental(&mut scratch, &mcts.root, &mut SplitMix64::seed(1), &tracker);
+        assert_eq!(rollout, Err(McstError::Mdp(MdpError::NoActions)));
+        assert_eq!(scratch, 5);
+    }
+
+    #[test]
+    fn test_mdp_failures_stop_the_search() {
+        // counts up by one, with no way out of 3 even though it isn't terminal
+        let dead_end = crate::mdp::FnMdp::new(
+            || 0,
+            |state: &i32| {
+                if *state == 3 {
+                    vec![]
+                } else {
+                    vec![Step::Right]
+                }
+            },
+            |state, _step: &Step, _rng| (state + 1, 0.0, false),
+            |state| *state >= 5,
+        );
+        let mut mcts = MCTS::new(dead_end, RandomRollout::new()).with_seed(1);
+        assert_eq!(
+            mcts.try_run_iterations(10),
+            Err(McstError::Mdp(MdpError::NoActions))
+        );
+        assert_eq!(mcts.iterations(), 1);
+
+        // a model whose steps can fail
+        struct Fallible;
+        impl GenerativeModel<i32, Step> for Fallible {
+            fn get_actions(&self, _state: &i32) -> Vec<Step> {
+                vec![Step::Left, Step::Right]
+            }
+
+            fn execute(&self, state: &i32, action: &Step, rng: &mut dyn Rng) -> (i32, f64, bool) {
+                self.try_execute(state, action, rng).unwrap()
+            }
+
+            fn try_execute(
+                &self,
+                state: &i32,
+                action: &Step,
+                _rng: &mut dyn Rng,
+            ) -> Result<(i32, f64, bool), MdpError> {
+                match action {
+                    Step::Left => Err(MdpError::Custom(format!("can't go left from {state}"))),
+                    Step::Right => Ok((state + 1, 0.0, *state >= 9)),
+                }
+            }
+
+            fn is_terminal(&self, state: &i32) -> bool {
+                *state >= 10
+            }
+
+            fn get_initial_state(&self) -> i32 {
+                0
+            }
+        }
+
+        let mut mcts = MCTS::new(Fallible, RandomRollout::new()).with_seed(1);
+        let error = mcts.try_run_iterations(100).unwrap_err();
+        assert!(matches!(error, McstError::Mdp(MdpError::Custom(_))));
+
+        // rewards that aren't numbers
+        let poisoned = crate::mdp::FnMdp::new(
+            || 0,
+            |_state: &i32| vec![Step::Right],
+            |state, _step: &Step, _rng| (state + 1, f64::NAN, *state >= 4),
+            |state| *state >= 5,
+        );
+        let mut mcts = MCTS::new(poisoned, RandomRollout::new()).with_seed(1);
+        assert_eq!(
+            mcts.try_run_iterations(10),
+            Err(McstError::NotANumber("a reward of the model"))
+        );
+
+        // nothing to search from a terminal root, which the panicking methods take as nothing to do
+        let over = crate::mdp::FnMdp::new(
+            || 5,
+            |_state: &i32| vec![Step::Right],
+            |state, _step: &Step, _rng| (state + 1, 0.0, true),
+            |state| *state >= 5,
+        );
+        let mut mcts = MCTS::try_new(over, RandomRollout::new()).unwrap();
+        assert_eq!(
+            mcts.try_search(),
+            Err(McstError::TerminalRoot { value: 0.0 })
+        );
+        assert_eq!(mcts.search(), None);
+        mcts.run_iterations(10);
+        assert_eq!(mcts.iterations(), 0);
+    }
+
+    /// Players take turns removing 1 or 2 of the stones, whoever takes the last one wins
+    struct Nim(u32);
+
+    impl GenerativeModel<(u32, PlayerId), usize> for Nim {
+        fn get_actions(&self, (stones, _): &(u32, PlayerId)) -> Vec<usize> {
+            (1..=2).filter(|take| *take as u32 <= *stones).collect()
+        }
+
+        fn execute(
+            &self,
+            (stones, player): &(u32, PlayerId),
+            take: &usize,
+            _rng: &mut dyn Rng,
+        ) -> ((u32, PlayerId), f64, bool) {
+            let left = stones - *take as u32;
+            let reward = if left == 0 { 1.0 } else { 0.0 };
+            ((left, 1 - player), reward, left == 0)
+        }
+
+        fn is_terminal(&self, (stones, _): &(u32, PlayerId)) -> bool {
+            *stones == 0
+        }
+
+        fn get_initial_state(&self) -> (u32, PlayerId) {
+            (self.0, 0)
+        }
+
+        fn player_to_move(&self, (_, player): &(u32, PlayerId)) -> PlayerId {
+            *player
+        }
+    }
+
+    #[test]
+    fn test_two_player_search_plays_adversarially() {
+        // the winning move always leaves a multiple of 3 stones
+        for (stones, take) in [(4, 1), (5, 2), (7, 1), (8, 2)] {
+            let mut mcts = MCTS::new(Nim(stones), RandomRollout::new()).with_seed(3);
+            mcts.run_iterations(3000);
+            assert_eq!(mcts.best_action(Strategy::MostVisited), Some(take));
+        }
+    }
+
+    #[test]
+    fn test_values_are_reported_for_the_chosen_player() {
+        let search = |mcts: MCTS<Nim, _, _, _>| {
+            let mut mcts = mcts
+                .with_seed(3)
+                .with_budget(Budget::Iterations(3000))
+                .with_runner_ups(1);
+            let result = mcts.search().unwrap();
+            (result, mcts.root_action_stats(), mcts.reported_player())
+        };
+        let (mover, mover_stats, player) = search(MCTS::new(Nim(4), RandomRollout::new()));
+        assert_eq!(player, 0);
+        let (opponent, opponent_stats, player) =
+            search(MCTS::new(Nim(4), RandomRollout::new()).for_player(1));
+        assert_eq!(player, 1);
+
+        // the same search, for the player to move, with the values seen from the other side
+        assert_eq!(opponent.action, 1);
+        assert_eq!(opponent.action, mover.action);
+        assert!(mover.value > 0.0);
+        assert_eq!(opponent.value, -mover.value);
+        assert_eq!(opponent.runner_ups[0].1, -mover.runner_ups[0].1);
+        for ((action, stats), (same, negated)) in mover_stats.iter().zip(&opponent_stats) {
+            assert_eq!(action, same);
+            assert_eq!(
+                (negated.visits, negated.value),
+                (stats.visits, -stats.value)
+            );
+        }
+    }
+
+    #[test]
+    fn test_solved_states_back_their_exact_values_up() {
+        // the player to move wins unless a multiple of 3 is left, solved up to 5 stones
+        let solve = |(stones, player): &(u32, PlayerId)| {
+            let wins = stones % 3 != 0;
+            (*stones <= 5).then_some(if wins == (*player == 0) { 1.0 } else { -1.0 })
+        };
+        let mut mcts = MCTS::new(Nim(7), RandomRollout::new())
+            .with_seed(3)
+            .with_endgame(solve);
+        let stats = mcts.run_iterations(200);
+        // only the 6 stones left by taking 1 were rolled out from, everything below is solved
+        assert_eq!(stats.simulations, 1);
+        assert_eq!(mcts.best_action(Strategy::MostVisited), Some(1));
+
+        let edges = mcts.root().edges();
+        let (solved, unsolved) = match edges[0].action() {
+            1 => (&edges[1], &edges[0]),
+            _ => (&edges[0], &edges[1]),
+        };
+        assert_eq!(solved.child().exact_value(), Some(-1.0));
+        assert!(solved.child().is_leaf());
+        assert_eq!(solved.stats().value, -1.0);
+        assert_eq!(unsolved.child().exact_value(), None);
+        assert_eq!(mcts.root().exact_value(), None);
+    }
+
+    #[test]
+    fn test_verdicts_need_the_value_to_stay_past_the_thresholds() {
+        let mut mcts = MCTS::new(Nim(4), RandomRollout::new())
+            .with_seed(3)
+            .with_resignation(-0.8, 200)
+            .with_win_claim(0.8, 200);
+        mcts.run_iterations(3000);
+        assert!(mcts.root_value().unwrap() > 0.8);
+        assert_eq!(mcts.verdict(), Verdict::ClaimWin);
+
+        // leaving a multiple of 3 loses, but it takes a while to tell
+        mcts.set_root_state((3, 0));
+        assert_eq!(mcts.verdict(), Verdict::PlayOn);
+        mcts.run_iterations(10);
+        assert_eq!(mcts.verdict(), Verdict::PlayOn);
+        mcts.run_iterations(3000);
+        assert_eq!(mcts.verdict(), Verdict::Resign);
+
+        let mut unconfigured = MCTS::new(Nim(3), RandomRollout::new()).with_seed(3);
+        unconfigured.run_iterations(3000);
+        assert_eq!(unconfigured.verdict(), Verdict::PlayOn);
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Ending {
+        Draw,
+        /// Tosses a coin for who wins
+        Toss,
+    }
+
+    impl Action for Ending {}
+
+    /// Player 0 settles for a draw or gambles on a win, the state being how the game ended
+    struct DrawOrToss;
+
+    impl GenerativeModel<Option<Outcome>, Ending> for DrawOrToss {
+        fn get_actions(&self, state: &Option<Outcome>) -> Vec<Ending> {
+            match state {
+                Some(_) => vec![],
+                None => vec![Ending::Draw, Ending::Toss],
+            }
+        }
+
+        fn execute(
+            &self,
+            _state: &Option<Outcome>,
+            ending: &Ending,
+            rng: &mut dyn Rng,
+        ) -> (Option<Outcome>, f64, bool) {
+            match ending {
+                Ending::Draw => (Some(Outcome::Draw), 0.0, true),
+                Ending::Toss => {
+                    let winner = rng.gen_range(0, 2);
+                    let reward = if winner == 0 { 1.0 } else { -1.0 };
+                    (Some(Outcome::Win(winner)), reward, true)
+                }
+            }
+        }
+
+        fn is_terminal(&self, state: &Option<Outcome>) -> bool {
+            state.is_some()
+        }
+
+        fn get_initial_state(&self) -> Option<Outcome> {
+            None
+        }
+
+        fn outcome(&self, state: &Option<Outcome>) -> Option<Outcome> {
+            *state
+        }
+    }
+
+    #[test]
+    fn test_draw_scores_make_draws_worth_more_or_less() {
+        let search = |mcts: MCTS<DrawOrToss, _, _, _>| {
+            let mut mcts = mcts.with_seed(1).with_chance_nodes();
+            mcts.run_iterations(2000);
+            let draw = mcts.action_stats(&Ending::Draw).unwrap();
+            (mcts.best_action(Strategy::HighestQValue), draw.value)
+        };
+        let searcher = || MCTS::new(DrawOrToss, RandomRollout::new());
+        assert_eq!(
+            search(searcher().with_draw_score(0.5)),
+            (Some(Ending::Draw), 0.5)
+        );
+        assert_eq!(
+            search(searcher().with_draw_score(-0.5)),
+            (Some(Ending::Toss), -0.5)
+        );
+        // a draw the opponent likes is one the player to move doesn't
+        assert_eq!(
+            search(searcher().for_player(1).with_draw_score(0.5)),
+            (Some(Ending::Toss), 0.5)
+        );
+    }
+
+    #[test]
+    fn test_roots_without_moves_are_told_apart() {
+        let mut mcts = MCTS::new(DrawOrToss, RandomRollout::new())
+            .with_seed(1)
+            .with_draw_score(0.5);
+        assert_eq!(mcts.try_best_action(Strategy::MostVisited), Ok(None));
+        assert_eq!(mcts.terminal_value(), None);
+
+        mcts.set_root_state(Some(Outcome::Draw));
+        let draw = McstError::TerminalRoot { value: 0.5 };
+        assert_eq!(mcts.try_search(), Err(draw.clone()));
+        assert_eq!(mcts.iterations(), 0);
+        assert_eq!(mcts.try_best_action(Strategy::MostVisited), Err(draw));
+        // valuing the root doesn't count as an outcome of a search
+        mcts.last_outcome.set(Some(Outcome::Win(1)));
+        assert_eq!(mcts.terminal_value(), Some(0.5));
+        assert_eq!(mcts.last_outcome.take(), Some(Outcome::Win(1)));
+
+        // stuck, yet not terminal, which the searches that don't fail take as a terminal root
+        let stuck = steady_walk(|_state: &i32| vec![]);
+        let mut mcts = MCTS::new(stuck, RandomRollout::new()).with_seed(1);
+        assert_eq!(mcts.try_run_iterations(10), Err(McstError::NoLegalActions));
+        assert_eq!(mcts.search(), None);
+        assert_eq!(mcts.search_from(&5, Budget::Iterations(10)), None);
+        assert_eq!(mcts.mcts(10), SearchStats::default());
+        assert_eq!(mcts.run_iterations(10), SearchStats::default());
+        assert_eq!(
+            mcts.mcts_incremental(Budget::Iterations(10)),
+            SearchStats::default()
+        );
+        assert_eq!(
+            crate::compare::Planner::plan(&mut mcts, &5, Budget::Iterations(10)),
+            Ok(None)
+        );
+        assert_eq!(mcts.iterations(), 0);
+        assert_eq!(
+            mcts.try_best_action(Strategy::MostVisited),
+            Err(McstError::NoLegalActions)
+        );
+        assert_eq!(mcts.terminal_value(), None);
+    }
+
+    #[test]
+    fn test_win_probabilities_count_how_games_ended() {
+        let mut mcts = MCTS::new(DrawOrToss, RandomRollout::new())
+            .with_seed(1)
+            .with_chance_nodes()
+            .with_draw_score(-0.5);
+        let result = mcts.search().unwrap();
+        assert_eq!(result.action, Ending::Toss);
+        let counts = result.outcomes.unwrap();
+        assert_eq!(counts.total(), result.visits);
+        assert!((result.win_probability() - 0.5).abs() < 0.05);
+
+        let draw = mcts.action_stats(&Ending::Draw).unwrap();
+        let expected = OutcomeCounts {
+            draws: draw.visits,
+            ..Default::default()
+        };
+        assert_eq!(mcts.outcome_counts(&Ending::Draw), Some(expected));
+
+        // the opponent wins the tosses the player to move loses
+        let counts = |player: PlayerId| {
+            let mut mcts = MCTS::new(DrawOrToss, RandomRollout::new())
+                .with_seed(1)
+                .with_chance_nodes()
+                .for_player(player);
+            mcts.run_iterations(1000);
+            mcts.outcome_counts(&Ending::Toss).unwrap()
+        };
+        let (mover, opponent) = (counts(0), counts(1));
+        assert!(mover.wins > 0 && mover.losses > 0);
+        assert_eq!((opponent.wins, opponent.losses), (mover.losses, mover.wins));
+
+        // without outcomes, the value is all there is to go by
+        let mut nim = MCTS::new(Nim(4), RandomRollout::new()).with_seed(3);
+        let result = nim.search().unwrap();
+        assert_eq!(result.outcomes, None);
+        assert_eq!(result.win_probability(), (result.value + 1.0) / 2.0);
+    }
+
+    #[test]
+    fn test_terminal_offsets_shift_the_returns_of_the_first_player() {
+        let search = |offset: fn(&Option<Outcome>) -> f64, player: PlayerId| {
+            let mut mcts = MCTS::new(DrawOrToss, RandomRollout::new())
+                .with_seed(1)
+                .with_chance_nodes()
+                .with_terminal_offset(offset)
+                .for_player(player);
+            mcts.run_iterations(2000);
+            let draw = mcts.action_stats(&Ending::Draw).unwrap();
+            (mcts.best_action(Strategy::HighestQValue), draw.value)
+        };
+        // a komi for the first player, whoever the values are reported for
+        assert_eq!(search(|_| 0.5, 0).1, 0.5);
+        assert_eq!(search(|_| 0.5, 1).1, -0.5);
+        // winning by too little to cover the handicap loses
+        let handicap = |state: &Option<Outcome>| match state {
+            Some(Outcome::Win(0)) => -1.5,
+            _ => 0.0,
+        };
+        assert_eq!(search(handicap, 0), (Some(Ending::Draw), 0.0));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Share {
+        /// 1 for the player taking it
+        Grab,
+        /// nothing for anyone
+        Give,
+    }
+
+    impl Action for Share {}
+
+    /// Three players take a turn each, starting with player 1
+    struct Sharing;
+
+    impl GenerativeModel<(u8, PlayerId), Share> for Sharing {
+        fn get_actions(&self, _state: &(u8, PlayerId)) -> Vec<Share> {
+            vec![Share::Grab, Share::Give]
+        }
+
+        fn execute(
+            &self,
+            (turn, player): &(u8, PlayerId),
+            share: &Share,
+            _rng: &mut dyn Rng,
+        ) -> ((u8, PlayerId), f64, bool) {
+            let reward = match share {
+                Share::Grab => 1.0,
+                Share::Give => 0.0,
+            };
+            ((turn + 1, (player + 1) % 3), reward, turn + 1 == 3)
+        }
+
+        fn is_terminal(&self, (turn, _): &(u8, PlayerId)) -> bool {
+            *turn == 3
+        }
+
+        fn get_initial_state(&self) -> (u8, PlayerId) {
+            (0, 1)
+        }
+
+        fn player_to_move(&self, (_, player): &(u8, PlayerId)) -> PlayerId {
+            *player
+        }
+
+        fn num_players(&self) -> usize {
+            3
+        }
+    }
+
+    #[test]
+    fn test_max_n_backs_up_every_players_returns() {
+        let mut mcts = MCTS::new(Sharing, RandomRollout::new()).with_seed(2);
+        mcts.run_iterations(500);
+
+        assert_eq!(mcts.best_action(Strategy::MostVisited), Some(Share::Grab));
+
+        let values = mcts.player_values();
+        let (_, grab) = values.iter().find(|(a, _)| *a == Share::Grab).unwrap();
+        let (_, give) = values.iter().find(|(a, _)| *a == Share::Give).unwrap();
+        assert_eq!(grab.len(), 3);
+        assert_eq!(grab[1], 1.0);
+        assert_eq!(give[1], 0.0);
+        // the other players grab whatever player 1 does
+        assert!(grab[2] > 0.5 && grab[0] > 0.5);
+    }
+
+    #[test]
+    fn test_chance_nodes_keep_every_outcome_apart() {
+        let outcomes = |mcts: &MCTS<Walk, i32, Step, RandomRollout>| {
+            let children = mcts.root.children.borrow();
+            let right = children.iter().find(|c| c.action == Step::Right).unwrap();
+            let mut states = right.outcomes().map(|o| o.node.state).collect::<Vec<_>>();
+            states.sort();
+            states
+        };
+
+        let mut merged = MCTS::new(Walk, RandomRollout::new()).with_seed(4);
+        merged.run_iterations(300);
+        assert_eq!(outcomes(&merged).len(), 1);
+
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(4)
+            .with_chance_nodes();
+        mcts.run_iterations(300);
+        assert_eq!(outcomes(&mcts), vec![4, 6]);
+        assert_eq!(mcts.best_action(Strategy::MostVisited), Some(Step::Right));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Control(f64);
+
+    // never NaN
+    impl Eq for Control {}
+
+    impl Action for Control {}
+
+    /// A single real-valued control in [-1, 1], paying more the closer it is to 0.3
+    struct Aim;
+
+    impl GenerativeModel<bool, Control> for Aim {
+        fn get_actions(&self, _state: &bool) -> Vec<Control> {
+            vec![]
+        }
+
+        fn execute(
+            &self,
+            _state: &bool,
+            action: &Control,
+            _rng: &mut dyn Rng,
+        ) -> (bool, f64, bool) {
+            (true, 1.0 - (action.0 - 0.3).powi(2), true)
+        }
+
+        fn is_terminal(&self, done: &bool) -> bool {
+            *done
+        }
+
+        fn get_initial_state(&self) -> bool {
+            false
+        }
+    }
+
+    impl ContinuousActions<bool, Control> for Aim {
+        fn sample_action(&self, _state: &bool, rng: &mut dyn Rng) -> Control {
+            Control(rng.gen_f64() * 2.0 - 1.0)
+        }
+
+        fn refine_action(
+            &self,
+            _state: &bool,
+            action: &Control,
+            rng: &mut dyn Rng,
+        ) -> Option<Control> {
+            let nudge = (rng.gen_f64() - 0.5) * 0.1;
+            Some(Control((action.0 + nudge).clamp(-1.0, 1.0)))
+        }
+    }
+
+    #[test]
+    fn test_continuous_search_homes_in_on_the_best_control() {
+        let mut mcts = MCTS::new(Aim, RandomRollout::new()).with_seed(6);
+        let stats = mcts.mcts_continuous(Budget::Iterations(2000), ProgressiveWidening::default());
+
+        // about sqrt(2000) actions were tried, not one per iteration
+        let tried = mcts.root.children.borrow().len();
+        assert!((40..=50).contains(&tried), "tried={tried}");
+        assert_eq!(stats.iterations, 2000);
+        assert_eq!(stats.simulations, 2000);
+        assert_eq!(stats.nodes_created, tried);
+
+        let Control(best) = mcts.best_action(Strategy::MostVisited).unwrap();
+        assert!((best - 0.3).abs() < 0.05, "best={best}");
+    }
+
+    #[test]
+    fn test_continuous_search_of_a_terminal_root_fails_up_front() {
+        let mut mcts = MCTS::new(Aim, RandomRollout::new()).with_seed(6);
+        mcts.set_root_state(true);
+
+        assert_eq!(
+            mcts.try_mcts_continuous(Budget::Iterations(10), ProgressiveWidening::default()),
+            Err(McstError::TerminalRoot { value: 0.0 })
+        );
+        assert_eq!(mcts.iterations(), 0);
+        // the panicking variant reports it as an empty search, as `mcts_incremental` does
+        assert_eq!(
+            mcts.mcts_continuous(Budget::Iterations(10), ProgressiveWidening::default()),
+            SearchStats::default()
+        );
+    }
+
+    #[test]
+    fn test_average_reward_searches_continuing_tasks() {
+        // never ends: a steady income of 1 per step, or a one-off jackpot of 5 followed by nothing
+        let mdp = FnMdp::new(
+            || 0u8,
+            |_state: &u8| vec![0usize, 1],
+            |state, action: &usize, _rng| match (state, action) {
+                (0, 1) | (2, _) => (2, if *state == 0 { 5.0 } else { 0.0 }, false),
+                _ => (1, 1.0, false),
+            },
+            |_state| false,
+        );
+
+        let mut mcts = MCTS::new(mdp, RandomRollout::new())
+            .with_seed(1)
+            .with_average_reward(20);
+        mcts.run_iterations(300);
+
+        assert_eq!(mcts.best_action(Strategy::HighestQValue), Some(0));
+        let steady = mcts.root.find_child(&0).unwrap().q_value();
+        assert!((0.9..=1.0).contains(&steady), "steady={steady}");
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Word(String);
+
+    impl Action for Word {}
+
+    #[test]
+    fn test_actions_only_need_to_be_clone() {
+        // one word to say, and only one of them pays
+        let mdp = FnMdp::new(
+            || false,
+            |_said: &bool| ["no", "maybe", "yes"].map(|w| Word(w.to_string())).to_vec(),
+            |_said, word: &Word, _rng| (true, if word.0 == "yes" { 1.0 } else { 0.0 }, true),
+            |said| *said,
+        );
+
+        let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+        mcts.run_iterations(100);
+
+        assert_eq!(
+            mcts.best_action(Strategy::MostVisited),
+            Some(Word("yes".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_cost_tie_breaks_prefer_the_cheaper_action() {
+        // both routes get there, the first one cheaper
+        struct Routes;
+
+        impl GenerativeModel<bool, usize> for Routes {
+            fn get_actions(&self, _arrived: &bool) -> Vec<usize> {
+                vec![0, 1]
+            }
+
+            fn execute(
+                &self,
+                _arrived: &bool,
+                _route: &usize,
+                _rng: &mut dyn Rng,
+            ) -> (bool, f64, bool) {
+                (true, 1.0, true)
+            }
+
+            fn is_terminal(&self, arrived: &bool) -> bool {
+                *arrived
+            }
+
+            fn get_initial_state(&self) -> bool {
+                false
+            }
+
+            fn action_cost(&self, _arrived: &bool, route: &usize) -> f64 {
+                if *route == 0 { 1.0 } else { 2.0 }
+            }
+        }
+
+        let mut mcts = MCTS::new(Routes, RandomRollout::new())
+            .with_seed(1)
+            .with_cost_tie_breaks();
+        mcts.run_iterations(50);
+        assert_eq!(mcts.best_action(Strategy::HighestQValue), Some(0));
+        assert_eq!(mcts.best_action(Strategy::HeuristicWin), Some(0));
+    }
+
+    #[test]
+    fn test_search_reports_the_chosen_action_and_runner_ups() {
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_budget(Budget::Iterations(300))
+            .with_runner_ups(5);
+
+        let result = mcts.search().unwrap();
+        assert_eq!(result.action, Step::Right);
+        assert_eq!(result.iterations, 300);
+        assert_eq!(
+            mcts.action_stats(&Step::Right),
+            Some(ActionStats {
+                visits: result.visits,
+                value: result.value
+            })
+        );
+        assert_eq!(result.elapsed, None);
+
+        // only one other action to report
+        assert_eq!(result.runner_ups.len(), 1);
+        let (other, value, visits) = result.runner_ups[0];
+        assert_eq!(other, Step::Left);
+        assert!(result.value > value && result.visits > visits);
+    }
+
+    #[test]
+    fn test_confidence_interval_narrows_with_the_budget() {
+        let search = |iterations| {
+            MCTS::new(Walk, RandomRollout::new())
+                .with_seed(1)
+                .with_budget(Budget::Iterations(iterations))
+                .search()
+                .unwrap()
+        };
+
+        let short = search(300);
+        let (low, high) = short.confidence_interval(1.96);
+        assert!(low < short.value && short.value < high);
+        let long = search(3000);
+        assert!(0.0 < long.std_error && long.std_error < short.std_error);
+
+        // every return is the same, so there's nothing to be unsure about
+        let certain = FnMdp::new(
+            || 0,
+            |_state: &i32| vec![Step::Left, Step::Right],
+            |_state, _step, _rng| (1, 1.0, true),
+            |state| *state == 1,
+        );
+        let result = MCTS::new(certain, RandomRollout::new())
+            .with_seed(1)
+            .with_budget(Budget::Iterations(10))
+            .search()
+            .unwrap();
+        assert_eq!(result.confidence_interval(1.96), (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_search_from_another_state() {
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_transpositions();
+
+        // one step from losing
+        let result = mcts.search_from(&1, Budget::Iterations(200)).unwrap();
+        assert_eq!(result.action, Step::Right);
+        assert_eq!(*mcts.root().state(), 1);
+
+        // the same state keeps the tree, another one starts over
+        mcts.search_from(&1, Budget::Iterations(100));
+        assert_eq!(mcts.root().visits(), 300);
+        mcts.search_from(&9, Budget::Iterations(100));
+        assert_eq!(mcts.root().visits(), 100);
+        assert_eq!(mcts.search_from(&10, Budget::Iterations(100)), None);
+    }
+
+    #[test]
+    fn test_async_search_yields_between_batches() {
+        let searcher = || {
+            MCTS::new(Walk, RandomRollout::new())
+                .with_seed(3)
+                .with_budget(Budget::Iterations(250))
+        };
+        let expected = searcher().search().unwrap();
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut mcts = searcher();
+        let mut search = pin!(mcts.search_async(100));
+        let mut polls = 1;
+        let result = loop {
+            match search.as_mut().poll(&mut cx) {
+                Poll::Ready(result) => break result,
+                Poll::Pending => polls += 1,
+            }
+        };
+        // the same search, in batches of 100, 100 and 50
+        assert_eq!(result.unwrap(), Some(expected));
+        assert_eq!(polls, 4);
+
+        // dropped after its first batch, the search leaves the tree with it
+        let mut mcts = searcher();
+        {
+            let mut search = pin!(mcts.search_async(100));
+            assert!(search.as_mut().poll(&mut cx).is_pending());
+        }
+        assert_eq!(mcts.iterations(), 100);
+    }
+
+    #[test]
+    fn test_pruned_actions_are_never_searched() {
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_pruning(|state, step, _stats| *state > 2 && *step == Step::Left);
+        mcts.run_iterations(300);
+
+        mcts.root().walk(|node| {
+            let edges = node.edges();
+            if *node.state() > 2 {
+                assert!(edges.iter().all(|edge| *edge.action() == Step::Right));
+            }
+            true
+        });
+        assert_eq!(mcts.root_action_stats().len(), 1);
+
+        // pruning every action prunes none
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_pruning(|_state, _step, _stats| true);
+        mcts.run_iterations(100);
+        assert_eq!(mcts.root_action_stats().len(), 2);
+    }
+
+    #[test]
+    fn test_warm_start_seeds_new_nodes() {
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_warm_start(|_state: &i32| 0.5, 30);
+        mcts.run_iterations(1);
+        let stats = mcts.root_action_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].1.visits, 31);
+        // the root itself only counts the rollout
+        assert_eq!(mcts.root().visits(), 1);
+
+        let solution = ValueIteration::new().solve(&Walk);
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_warm_start(solution.clone(), 50);
+        mcts.run_iterations(20);
+        assert!(mcts.root_action_stats().iter().all(|(_, s)| s.visits >= 50));
+        assert_eq!(
+            mcts.best_action(Strategy::HighestQValue).as_ref(),
+            solution.action(&5)
+        );
+    }
+
+    #[test]
+    fn test_fork_copies_the_tree() {
+        /// Counts up to 10 in steps of 1 or 2, so that different orders of steps reach the same sums
+        #[derive(Clone)]
+        struct Steps;
+
+        impl GenerativeModel<u32, usize> for Steps {
+            fn get_actions(&self, _state: &u32) -> Vec<usize> {
+                vec![1, 2]
+            }
+
+            fn execute(&self, state: &u32, step: &usize, _rng: &mut dyn Rng) -> (u32, f64, bool) {
+                let next = state + *step as u32;
+                (next, *step as f64, next >= 10)
+            }
+
+            fn is_terminal(&self, state: &u32) -> bool {
+                *state >= 10
+            }
+
+            fn get_initial_state(&self) -> u32 {
+                0
+            }
+
+            fn state_key(&self, state: &u32) -> Option<u64> {
+                Some(u64::from(*state))
+            }
+        }
+
+        let mut mcts = MCTS::new(Steps, RandomRollout::new())
+            .with_seed(1)
+            .with_transpositions();
+        mcts.run_iterations(300);
+        let stats = mcts.tree_stats();
+
+        let mut fork = mcts.fork().with_seed(2);
+        // the nodes shared through transpositions are still shared
+        assert_eq!(fork.tree_stats().nodes, stats.nodes);
+        assert_eq!(fork.tree_stats().max_depth, stats.max_depth);
+        assert_eq!(fork.root_action_stats(), mcts.root_action_stats());
+
+        fork.run_iterations(100);
+        assert_eq!(fork.root().visits(), 400);
+        assert!(fork.tree_stats().nodes >= stats.nodes);
+        assert_eq!(mcts.root().visits(), 300);
+        assert_eq!(mcts.tree_stats(), stats);
+    }
+
+    #[test]
+    fn test_ranked_actions_follow_the_strategy() {
+        let mut mcts = MCTS::new(Walk, RandomRollout::new()).with_seed(1);
+        mcts.run_iterations(300);
+
+        let by_visits = mcts.ranked_actions(Strategy::MostVisited);
+        assert_eq!(by_visits.len(), 2);
+        assert_eq!(by_visits[0].0, Step::Right);
+        assert!(by_visits[0].1 >= by_visits[1].1);
+
+        let by_probability = mcts.ranked_actions(Strategy::Probabilistic);
+        assert!((by_probability.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+
+        let by_value = mcts.ranked_actions(Strategy::HighestQValue);
+        assert_eq!(
+            Some(by_value[0].0),
+            mcts.best_action(Strategy::HighestQValue)
+        );
+    }
+
+    #[test]
+    fn test_robust_child_searches_until_visits_and_values_agree() {
+        let robust = |extension| {
+            MCTS::new(Walk, RandomRollout::new())
+                .with_seed(2)
+                .with_budget(Budget::Iterations(3))
+                .with_strategy(Strategy::RobustChild)
+                .with_robust_child(extension, Strategy::HighestQValue)
+        };
+
+        // after 3 iterations, the most visited action isn't the highest valued one
+        let mut mcts = robust(0);
+        let result = mcts.search().unwrap();
+        assert_eq!(result.iterations, 3);
+        assert_eq!(mcts.robust_child(), None);
+        assert_eq!(
+            Some(result.action),
+            mcts.best_action(Strategy::HighestQValue)
+        );
+
+        let mut mcts = robust(1000);
+        let result = mcts.search().unwrap();
+        assert!(result.iterations > 3);
+        assert_eq!(Some(result.action), mcts.robust_child());
+        assert_eq!(Some(result.action), mcts.best_action(Strategy::MostVisited));
+    }
+
+    #[test]
+    fn test_final_selector_replaces_the_strategy() {
+        // the least visited action, which no strategy would pick
+        let contrarian = |actions: &[(Step, ActionStats)]| {
+            actions
+                .iter()
+                .min_by_key(|(_, stats)| stats.visits)
+                .map(|(action, _)| *action)
+        };
+
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_budget(Budget::Iterations(300))
+            .with_final_selector(contrarian);
+
+        let result = mcts.search().unwrap();
+        assert_eq!(result.action, Step::Left);
+        assert_eq!(mcts.best_action_by(&contrarian), Some(Step::Left));
+    }
+    #[test]
+    fn test_run_iterations_reports_search_stats() {
+        let mut mcts = MCTS::new(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_max_rollout_depth(20);
+
+        let stats = mcts.run_iterations(200);
+        assert_eq!(stats.iterations, 200);
+        assert!(stats.simulations > 0 && stats.simulations <= 200);
+        assert!(stats.max_rollout_depth <= 20);
+        assert!(stats.avg_rollout_depth <= stats.max_rollout_depth as f64);
+        // everything but the root was created by this search
+        assert_eq!(stats.nodes_created, mcts.tree_stats().nodes - 1);
+        assert_eq!(stats.elapsed, None);
+
+        let nodes = mcts.tree_stats().nodes;
+        let mut mcts = mcts.with_profiling();
+        let stats = mcts.run_iterations(100);
+        assert_eq!(stats.nodes_created, mcts.tree_stats().nodes - nodes);
+        assert!(stats.elapsed.is_some() && stats.phases.is_some());
+    }
+
+    #[test]
+    fn test_observers_see_every_iteration() {
+        #[derive(Default)]
+        struct Counts {
+            expansions: usize,
+            backpropagations: usize,
+            iterations: Vec<IterationEvent>,
+        }
+
+        struct Counter(Rc<RefCell<Counts>>);
+
+        impl Observer for Counter {
+            fn on_expansion(&mut self, event: &ExpansionEvent) {
+                assert!(event.depth >= 1);
+                self.0.borrow_mut().expansions += 1;
+            }
+
+            fn on_backpropagation(&mut self, _event: &BackpropagationEvent) {
+                self.0.borrow_mut().backpropagations += 1;
+            }
+
+            fn on_iteration(&mut self, event: &IterationEvent) {
+                self.0.borrow_mut().iterations.push(*event);
+            }
+        }
+
+        let counts = Rc::new(RefCell::new(Counts::default()));
+        let mut mcts = MCTS::builder(Walk, RandomRollout::new())
+            .with_seed(1)
+            .with_budget(Budget::Iterations(100))
+            .with_observer(Counter(Rc::clone(&counts)))
+            .build();
+        mcts.search();
+
+        let counts = counts.borrow();
+        // every iteration backs up once, from a new node or from a terminal leaf
+        assert_eq!(counts.backpropagations, 100);
+        assert!(counts.expansions > 0 && counts.expansions <= 100);
+        assert_eq!(counts.iterations.len(), 100);
+        assert_eq!(
+            counts.iterations.last(),
+            Some(&IterationEvent {
+                iteration: 100,
+                root_visits: 100
+            })
+        );
     }
 }
@@ -1,56 +1,347 @@
-use crate::rand::genrand;
+use std::{rc::Rc, sync::Arc};
 
-/// currently rethinking MDP to be implemented by State, i.e. making MDP itself state
-/// Markov Decision Processes
-pub trait MDP<S, A> {
-    /// Returns all states of this MDP
-    fn get_states(&self) -> Vec<S>;
+use crate::{
+    error::{McstError, MdpError},
+    rand::Rng,
+};
+
+/// Identifies the players of a game, numbered from 0
+pub type PlayerId = usize;
 
+/// How a game ended, see [`GenerativeModel::outcome`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    Win(PlayerId),
+    Draw,
+}
+
+/// A simulator of an environment: all that MCTS needs is to sample what happens when an action is taken.
+/// Exact solvers need the full model, see [`ExplicitMDP`]
+pub trait GenerativeModel<S, A> {
     /// Returns all actions with non-zero probability from this state
     fn get_actions(&self, state: &S) -> Vec<A>;
 
-    /// Returns all non-zero probability transitions for this action from state,
-    /// as a list of (state, probability) pairs
-    fn get_transitions(&self, state: &S, action: &A) -> Vec<(S, f64)>;
+    /// Returns the new state after the application of the provided action on it, and the reward/outcome of such move(application)
+    /// In games, the reward is from the perspective of the player who took the action (see [`GenerativeModel::player_to_move`]).
+    /// Any randomness must be drawn from `rng`, so that seeded searches are reproducible.
+    /// An [`ExplicitMDP`] can simply sample its transitions with [`ExplicitMDP::sample_transition`]
+    fn execute(&self, state: &S, action: &A, rng: &mut dyn Rng) -> (S, f64, bool);
 
-    /// Returns the reward for transitioning from state to nextState via action
-    fn get_reward(&self, state: &S, action: &A, next_state: &S) -> f64;
+    /// Same as [`GenerativeModel::execute`], but reports failures as errors rather than panicking.
+    /// This is what the search calls, so models that can fail should override it (and have `execute` unwrap it)
+    fn try_execute(
+        &self,
+        state: &S,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, f64, bool), MdpError> {
+        Ok(self.execute(state, action, rng))
+    }
 
     /// Returns true if and only if state is a terminal state of this MDP
     fn is_terminal(&self, state: &S) -> bool;
 
-    /// Returns the discount dactor for this MDP
-    fn get_discount_factor(&self) -> f64;
-
     /// Returns the initial state of this MDP
     fn get_initial_state(&self) -> S;
 
+    /// Returns the discount factor for this MDP
+    fn get_discount_factor(&self) -> f64 {
+        1.0
+    }
+
+    /// Returns the player whose turn it is in this state, i.e. who picks the action taken from it.
+    /// Single-agent MDPs can leave this as is: there is only player 0.
+    /// Two-player zero-sum games number their players 0 and 1, and the search backs rewards up negamax-style,
+    /// so that each player picks the moves that are best for them rather than for their opponent
+    fn player_to_move(&self, _state: &S) -> PlayerId {
+        0
+    }
+
+    /// Returns the number of players, only needed for games with more than two.
+    /// Those are searched with max^n backups: the rewards of every player are backed up separately,
+    /// and every player picks the moves that are best for themselves, with a reward only counting for the player who took the action
+    fn num_players(&self) -> usize {
+        1
+    }
+
+    /// Returns a hash of the state, used to share the nodes of states reached through different orders of actions
+    /// when the transposition table is enabled (see [`crate::mcts::MCTS::with_transpositions`])
+    fn state_key(&self, _state: &S) -> Option<u64> {
+        None
+    }
+
+    /// Returns the canonical form of the state, if it differs from the state itself, e.g. the first of all its reflections/rotations in some fixed order.
+    /// Newly reached states are stored in their canonical form before being hashed (see [`GenerativeModel::state_key`]) or matched against their siblings,
+    /// so that symmetric states share statistics. The root state is never canonicalized, so the actions returned by the search always apply to it
+    fn canonicalize(&self, _state: &S) -> Option<S> {
+        None
+    }
+
+    /// Returns the probability of reaching `next_state` by taking the action in state, if known.
+    /// With chance nodes (see [`crate::mcts::MCTS::with_chance_nodes`]), the outcomes of an action are weighted by these probabilities
+    /// rather than by how often they were sampled, as long as they're known for all of them
+    fn transition_probability(&self, _state: &S, _action: &A, _next_state: &S) -> Option<f64> {
+        None
+    }
+
+    /// Returns the cost of taking the action in this state (e.g. fuel, money or time), kept apart from the reward.
+    /// The search ignores it unless told otherwise: [`crate::wrappers::CostPenalty`] subtracts it from the rewards,
+    /// and [`crate::mcts::MCTS::with_cost_tie_breaks`] prefers the cheaper of equally good actions
+    fn action_cost(&self, _state: &S, _action: &A) -> f64 {
+        0.0
+    }
+
+    /// Returns how the game ended in a terminal state, if it's a game with winners and draws at all.
+    /// The rewards already say how good the end is; the outcome lets the search tell draws from wins and losses,
+    /// e.g. to score draws differently (see [`crate::mcts::MCTS::with_draw_score`])
+    fn outcome(&self, _state: &S) -> Option<Outcome> {
+        None
+    }
+}
+
+/// Implements [`GenerativeModel`] for pointers to models, so that a searcher can be given `&M`, `Rc<M>`, `Arc<M>` or `Box<M>`
+/// rather than the model itself, and several searchers can share one expensive model (big lookup tables, loaded networks)
+macro_rules! shared_model {
+    ($($pointer:ty),*) => {$(
+        impl<M, S, A> GenerativeModel<S, A> for $pointer
+        where
+            M: GenerativeModel<S, A> + ?Sized,
+        {
+            fn get_actions(&self, state: &S) -> Vec<A> {
+                (**self).get_actions(state)
+            }
+
+            fn execute(&self, state: &S, action: &A, rng: &mut dyn Rng) -> (S, f64, bool) {
+                (**self).execute(state, action, rng)
+            }
+
+            fn try_execute(
+                &self,
+                state: &S,
+                action: &A,
+                rng: &mut dyn Rng,
+            ) -> Result<(S, f64, bool), MdpError> {
+                (**self).try_execute(state, action, rng)
+            }
+
+            fn is_terminal(&self, state: &S) -> bool {
+                (**self).is_terminal(state)
+            }
+
+            fn get_initial_state(&self) -> S {
+                (**self).get_initial_state()
+            }
+
+            fn get_discount_factor(&self) -> f64 {
+                (**self).get_discount_factor()
+            }
+
+            fn player_to_move(&self, state: &S) -> PlayerId {
+                (**self).player_to_move(state)
+            }
+
+            fn num_players(&self) -> usize {
+                (**self).num_players()
+            }
+
+            fn state_key(&self, state: &S) -> Option<u64> {
+                (**self).state_key(state)
+            }
+
+            fn canonicalize(&self, state: &S) -> Option<S> {
+                (**self).canonicalize(state)
+            }
+
+            fn transition_probability(&self, state: &S, action: &A, next_state: &S) -> Option<f64> {
+                (**self).transition_probability(state, action, next_state)
+            }
+
+            fn action_cost(&self, state: &S, action: &A) -> f64 {
+                (**self).action_cost(state, action)
+            }
+
+            fn outcome(&self, state: &S) -> Option<Outcome> {
+                (**self).outcome(state)
+            }
+        }
+    )*};
+}
+
+shared_model!(&M, Rc<M>, Arc<M>, Box<M>);
+
+/// Takes a step of the model for the search, rejecting rewards that aren't numbers
+pub(crate) fn checked_execute<M, S, A>(
+    mdp: &M,
+    state: &S,
+    action: &A,
+    rng: &mut dyn Rng,
+) -> Result<(S, f64, bool), McstError>
+where
+    M: GenerativeModel<S, A> + ?Sized,
+{
+    let (next_state, reward, done) = mdp.try_execute(state, action, rng)?;
+    if reward.is_nan() {
+        return Err(McstError::NotANumber("a reward of the model"));
+    }
+    Ok((next_state, reward, done))
+}
+
+/// Markov Decision Processes whose states, transition probabilities and rewards can all be enumerated,
+/// as needed by exact solvers
+pub trait ExplicitMDP<S, A>: GenerativeModel<S, A> {
+    /// Returns all states of this MDP
+    fn get_states(&self) -> Vec<S>;
+
+    /// Returns all non-zero probability transitions for this action from state,
+    /// as a list of (state, probability) pairs
+    fn get_transitions(&self, state: &S, action: &A) -> Vec<(S, f64)>;
+
+    /// Returns the reward for transitioning from state to nextState via action
+    fn get_reward(&self, state: &S, action: &A, next_state: &S) -> f64;
+
     /// Returns all goal states of this MDP
     fn get_goal_states(&self) -> Vec<S>;
 
-    /// Returns the new state after the application of the provided action on it, and the reward/outcome of such move(application)
-    fn execute(&self, state: &S, action: &A) -> (S, f64, bool) {
-        let mut transitions = self.get_transitions(state, &action);
-        assert!(!transitions.is_empty(), "No transitions for this action");
+    /// Samples the next state from [`ExplicitMDP::get_transitions`], for use as [`GenerativeModel::execute`].
+    /// Panics if there are no transitions for the action
+    fn sample_transition(&self, state: &S, action: &A, rng: &mut dyn Rng) -> (S, f64, bool) {
+        self.try_sample_transition(state, action, rng)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Same as [`ExplicitMDP::sample_transition`], for use as [`GenerativeModel::try_execute`]
+    fn try_sample_transition(
+        &self,
+        state: &S,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, f64, bool), MdpError> {
+        let mut transitions = self.get_transitions(state, action);
+        if transitions.is_empty() {
+            return Err(MdpError::NoTransitions);
+        }
 
         // Sample from probabilities
-        let r = (genrand(0, 1000) as f64) / 1000.0; // uniform (0, 1)
+        let r = rng.gen_f64(); // uniform [0, 1)
         let mut cumulative = 0.0;
-        // let mut chosen_state = transitions[0].0;
 
         let chosen_index = transitions
             .iter()
             .position(|(_, p)| {
                 cumulative += p;
-                return cumulative >= r;
+                cumulative >= r
             })
             .unwrap_or(0);
 
         let (chosen_state, _) = transitions.swap_remove(chosen_index);
 
-        let reward = self.get_reward(state, &action, &chosen_state);
+        let reward = self.get_reward(state, action, &chosen_state);
         let done = self.is_terminal(&chosen_state);
 
-        return (chosen_state, reward, done);
+        Ok((chosen_state, reward, done))
+    }
+}
+
+/// A [`GenerativeModel`] built from closures, for prototyping without a dedicated struct and impl.
+///
+/// ```
+/// use mct_rs::{action::Action, mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// struct Step(u32);
+/// impl Action for Step {}
+///
+/// // count up from 0 by 1 or 2, the episode ends on reaching 5 and every step costs 1
+/// let mdp = FnMdp::new(
+///     || 0,
+///     |_state| vec![Step(1), Step(2)],
+///     |state, step: &Step, _rng| (state + step.0, -1.0, state + step.0 >= 5),
+///     |state| *state >= 5,
+/// );
+///
+/// let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+/// mcts.run_iterations(100);
+/// ```
+pub struct FnMdp<I, Ac, St, T> {
+    initial: I,
+    actions: Ac,
+    step: St,
+    is_terminal: T,
+}
+
+impl<I, Ac, St, T> FnMdp<I, Ac, St, T> {
+    /// `step` plays the role of [`GenerativeModel::execute`]
+    pub fn new<S, A>(initial: I, actions: Ac, step: St, is_terminal: T) -> Self
+    where
+        I: Fn() -> S,
+        Ac: Fn(&S) -> Vec<A>,
+        St: Fn(&S, &A, &mut dyn Rng) -> (S, f64, bool),
+        T: Fn(&S) -> bool,
+    {
+        Self {
+            initial,
+            actions,
+            step,
+            is_terminal,
+        }
+    }
+}
+
+impl<S, A, I, Ac, St, T> GenerativeModel<S, A> for FnMdp<I, Ac, St, T>
+where
+    I: Fn() -> S,
+    Ac: Fn(&S) -> Vec<A>,
+    St: Fn(&S, &A, &mut dyn Rng) -> (S, f64, bool),
+    T: Fn(&S) -> bool,
+{
+    fn get_actions(&self, state: &S) -> Vec<A> {
+        (self.actions)(state)
+    }
+
+    fn execute(&self, state: &S, action: &A, rng: &mut dyn Rng) -> (S, f64, bool) {
+        (self.step)(state, action, rng)
+    }
+
+    fn is_terminal(&self, state: &S) -> bool {
+        (self.is_terminal)(state)
+    }
+
+    fn get_initial_state(&self) -> S {
+        (self.initial)()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, policy::RandomRollout};
+
+    use super::*;
+
+    #[test]
+    fn test_searchers_share_a_model() {
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_state: &u32| vec![1usize, 2],
+            |state, step, _rng| {
+                (
+                    state + *step as u32,
+                    *step as f64,
+                    state + *step as u32 >= 10,
+                )
+            },
+            |state| *state >= 10,
+        );
+
+        let mut borrowed = MCTS::new(&mdp, RandomRollout::new()).with_seed(1);
+        let best = borrowed.search().map(|result| result.action);
+        drop(borrowed);
+
+        let shared = Arc::new(mdp);
+        for _ in 0..2 {
+            let mut mcts = MCTS::new(Arc::clone(&shared), RandomRollout::new()).with_seed(1);
+            assert_eq!(mcts.search().map(|result| result.action), best);
+        }
+        assert_eq!(Arc::strong_count(&shared), 1);
     }
 }
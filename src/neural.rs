@@ -0,0 +1,294 @@
+//! Values of states from a neural network run in-process, whatever runs it.
+//!
+//! A [`StateEncoder`] turns states into the flat `f32` features of a tensor, and a [`Network`] maps a batch of them
+//! to one value per state. [`NeuralEvaluator`] puts the two together as a [`ValueFunction`], to hand to
+//! [`crate::mcts::MCTS::with_value_function`] for AlphaZero-style searches that value leaves rather than rolling out.
+//!
+//! Inference engines are wired in by implementing [`Network`], e.g. for an ONNX Runtime session
+//! by building an input tensor of `batch.shape` from `batch.features`, running the session and reading the value head out.
+//! Rust-native networks work the same way: with candle, `Tensor::from_slice(batch.features, batch.shape, &device)`
+//! is the input of the model's `forward`, on whichever device the network was loaded on.
+//! The crate doesn't depend on any of them itself.
+//!
+//! Networks are much faster on batches than on single states, so [`NeuralEvaluator::prefetch`] values states
+//! ahead of the search (e.g. the successors of the root), in batches of [`NeuralEvaluator::with_batch_size`]
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    error::Error,
+    hash::Hash,
+};
+
+use crate::value::ValueFunction;
+
+/// Why a network couldn't value a batch of states
+pub type NetworkError = Box<dyn Error + Send + Sync>;
+
+/// Turns states into the input tensor of a [`Network`]
+pub trait StateEncoder<S> {
+    /// The shape of the tensor of a single state, e.g. `[planes, rows, columns]` for a board
+    fn shape(&self) -> Vec<usize>;
+
+    /// Appends the features of the state to `features`, in row-major order, as many as the shape holds
+    fn encode(&self, state: &S, features: &mut Vec<f32>);
+}
+
+/// The encoded states of a batch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Batch<'a> {
+    /// The features of every state, one after the other
+    pub features: &'a [f32],
+    /// The shape of the whole batch: the number of states, then the shape of a single state
+    pub shape: &'a [usize],
+}
+
+impl Batch<'_> {
+    /// The number of states in the batch
+    pub fn len(&self) -> usize {
+        self.shape.first().copied().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A model valuing batches of encoded states
+pub trait Network {
+    /// One value per state of the batch, in order, from the perspective of the player to move in it
+    fn predict(&self, batch: Batch<'_>) -> Result<Vec<f32>, NetworkError>;
+}
+
+/// Any closure from a batch to its values is a network, e.g. one calling into an inference engine
+impl<F> Network for F
+where
+    F: Fn(Batch<'_>) -> Result<Vec<f32>, NetworkError>,
+{
+    fn predict(&self, batch: Batch<'_>) -> Result<Vec<f32>, NetworkError> {
+        self(batch)
+    }
+}
+
+/// Values states with a network, caching the values of the states it has seen, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{
+///     mcts::MCTS,
+///     mdp::FnMdp,
+///     neural::{Batch, NetworkError, NeuralEvaluator, StateEncoder},
+///     policy::RandomRollout,
+/// };
+///
+/// /// A state as a single feature, its distance to 10
+/// struct Distance;
+///
+/// impl StateEncoder<u32> for Distance {
+///     fn shape(&self) -> Vec<usize> {
+///         vec![1]
+///     }
+///
+///     fn encode(&self, state: &u32, features: &mut Vec<f32>) {
+///         features.push(10.0 - *state as f32);
+///     }
+/// }
+///
+/// // a stand-in for a trained model: the closer to 10, the better
+/// let network = |batch: Batch<'_>| -> Result<Vec<f32>, NetworkError> {
+///     Ok(batch.features.iter().map(|distance| -distance).collect())
+/// };
+///
+/// let mdp = FnMdp::new(|| 0u32, |_: &u32| vec![1usize, 2], |s, a, _| (s + *a as u32, 0.0, s + *a as u32 >= 10), |s| *s >= 10);
+/// let mut mcts = MCTS::new(mdp, RandomRollout::new())
+///     .with_max_rollout_depth(0)
+///     .with_value_function(NeuralEvaluator::new(Distance, network));
+/// mcts.run_iterations(100);
+/// ```
+pub struct NeuralEvaluator<S, E, N> {
+    encoder: E,
+    network: N,
+    batch_size: usize,
+    cache: RefCell<HashMap<S, f64>>,
+    failures: Cell<usize>,
+}
+
+impl<S, E, N> NeuralEvaluator<S, E, N>
+where
+    S: Clone + Eq + Hash,
+    E: StateEncoder<S>,
+    N: Network,
+{
+    /// Prefetches states in batches of 64
+    pub fn new(encoder: E, network: N) -> Self {
+        Self {
+            encoder,
+            network,
+            batch_size: 64,
+            cache: RefCell::new(HashMap::new()),
+            failures: Cell::new(0),
+        }
+    }
+
+    /// Values the states in a single batch, without going through the cache
+    pub fn try_evaluate_batch(&self, states: &[S]) -> Result<Vec<f64>, NetworkError> {
+        let mut features = vec![];
+        for state in states {
+            self.encoder.encode(state, &mut features);
+        }
+        let mut shape = vec![states.len()];
+        shape.extend(self.encoder.shape());
+        let expected = shape.iter().product::<usize>();
+        if features.len() != expected {
+            return Err(format!(
+                "the encoder wrote {} features for a batch of shape {shape:?}",
+                features.len()
+            )
+            .into());
+        }
+
+        let values = self.network.predict(Batch {
+            features: &features,
+            shape: &shape,
+        })?;
+        match values.len() == states.len() {
+            true => Ok(values.into_iter().map(f64::from).collect()),
+            false => Err(format!("{} values for {} states", values.len(), states.len()).into()),
+        }
+    }
+
+    /// Passes at most this many states to the network at once when prefetching
+    pub fn with_batch_size(mut self, states: usize) -> Self {
+        self.batch_size = states.max(1);
+        self
+    }
+
+    /// Values the states that aren't cached yet, in batches, and caches them.
+    /// Returns the number of states the network couldn't value
+    pub fn prefetch(&self, states: &[S]) -> usize {
+        let missing = {
+            let cache = self.cache.borrow();
+            let mut seen = HashSet::new();
+            states
+                .iter()
+                .filter(|state| !cache.contains_key(state) && seen.insert(*state))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        let mut failed = 0;
+        for batch in missing.chunks(self.batch_size) {
+            match self.try_evaluate_batch(batch) {
+                Ok(values) => self
+                    .cache
+                    .borrow_mut()
+                    .extend(batch.iter().cloned().zip(values)),
+                Err(_) => failed += batch.len(),
+            }
+        }
+        self.failures.set(self.failures.get() + failed);
+        failed
+    }
+
+    /// The number of states the network failed to value so far, while searching or prefetching
+    pub fn failures(&self) -> usize {
+        self.failures.get()
+    }
+
+    /// Forgets the cached values, e.g. after the network's weights were updated
+    pub fn clear_cache(&self) {
+        self.cache.borrow_mut().clear();
+    }
+}
+
+impl<S, E, N> ValueFunction<S> for NeuralEvaluator<S, E, N>
+where
+    S: Clone + Eq + Hash,
+    E: StateEncoder<S>,
+    N: Network,
+{
+    /// The cached value of the state, or the network's (then cached), or 0 if the network failed
+    fn value(&self, state: &S) -> f64 {
+        if let Some(value) = self.cache.borrow().get(state) {
+            return *value;
+        }
+        match self.try_evaluate_batch(std::slice::from_ref(state)) {
+            Ok(values) => {
+                self.cache.borrow_mut().insert(state.clone(), values[0]);
+                values[0]
+            }
+            Err(_) => {
+                self.failures.set(self.failures.get() + 1);
+                0.0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board of 2 cells, as 2 planes (one per player) of 2 cells
+    struct Planes;
+
+    impl StateEncoder<[Option<usize>; 2]> for Planes {
+        fn shape(&self) -> Vec<usize> {
+            vec![2, 2]
+        }
+
+        fn encode(&self, state: &[Option<usize>; 2], features: &mut Vec<f32>) {
+            for player in 0..2 {
+                features.extend(state.iter().map(|cell| f32::from(*cell == Some(player))));
+            }
+        }
+    }
+
+    #[test]
+    fn test_networks_value_encoded_states() {
+        let calls = Cell::new(0);
+        // player 0's pieces are worth 1, player 1's -1
+        let network = |batch: Batch<'_>| -> Result<Vec<f32>, NetworkError> {
+            calls.set(calls.get() + 1);
+            assert_eq!(&batch.shape[1..], &[2, 2]);
+            Ok(batch
+                .features
+                .chunks(4)
+                .map(|planes| planes[0] + planes[1] - planes[2] - planes[3])
+                .collect())
+        };
+        let evaluator = NeuralEvaluator::new(Planes, network);
+
+        let states = [
+            [None, None],
+            [Some(0), None],
+            [Some(0), Some(1)],
+            [Some(0), Some(0)],
+        ];
+        assert_eq!(
+            evaluator.try_evaluate_batch(&states).unwrap(),
+            vec![0.0, 1.0, 0.0, 2.0]
+        );
+        assert_eq!(calls.get(), 1);
+
+        assert_eq!(evaluator.value(&[Some(1), None]), -1.0);
+        assert_eq!(evaluator.value(&[Some(1), None]), -1.0);
+        assert_eq!(calls.get(), 2);
+
+        // 4 new states, one of them twice, and one cached already
+        let evaluator = evaluator.with_batch_size(2);
+        let mut prefetched = states.to_vec();
+        prefetched.extend([states[0], [Some(1), None]]);
+        assert_eq!(evaluator.prefetch(&prefetched), 0);
+        assert_eq!(calls.get(), 4);
+        assert_eq!(evaluator.value(&states[3]), 2.0);
+        assert_eq!(calls.get(), 4);
+
+        let broken =
+            NeuralEvaluator::new(Planes, |_: Batch<'_>| -> Result<Vec<f32>, NetworkError> {
+                Ok(vec![])
+            });
+        assert!(broken.try_evaluate_batch(&states).is_err());
+        assert_eq!(broken.value(&states[1]), 0.0);
+        assert_eq!(broken.failures(), 1);
+    }
+}
@@ -1,32 +1,224 @@
 use std::{
-    cell::RefCell,
+    any::Any,
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    hash::{BuildHasherDefault, Hasher},
     rc::{Rc, Weak},
 };
 
-use crate::{action::Action, mdp::MDP, policy::RolloutPolicy, ucb1::UCB1};
+use crate::{
+    action::Action,
+    continuous::{ContinuousActions, ProgressiveWidening},
+    error::{McstError, MdpError},
+    mdp::{GenerativeModel, PlayerId, checked_execute},
+    node_stats::{ErasedSelection, NodeStats},
+    policy::RolloutPolicy,
+    rand::Rng,
+    returns::Returns,
+    stats::{ActionStats, TreeStats},
+    transposition::TranspositionTable,
+    ucb1::UCB1,
+};
+
+/// [`Action::key`]s are already hashes, so the index over them doesn't need to hash them again
+#[derive(Debug, Default)]
+pub(crate) struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 << 8) | u64::from(*byte);
+        }
+    }
+
+    fn write_u64(&mut self, key: u64) {
+        self.0 = key;
+    }
+}
+
+/// One outcome of an action: the node it led to, the reward of getting there and the probability of it, if known
+#[derive(Debug)]
+pub(crate) struct Outcome<S, A> {
+    pub(crate) node: Rc<Node<S, A>>,
+    /// The immediate reward of the transition, from the perspective of the player who took the action
+    pub(crate) reward: f64,
+    probability: Option<f64>,
+}
+
+/// An edge of the tree, i.e. an action taken from a node and the node(s) it resulted in.
+/// The action lives on the edge rather than on the child, since with transpositions the same node can be reached through different actions.
+/// So does the reward of the transition, which only counts towards the returns of the paths going through this edge
+///
+/// With chance nodes enabled (see [`crate::mcts::MCTS::with_chance_nodes`]), the edge is a chance node:
+/// it holds one child per distinct outcome of the action sampled so far, and its statistics are the expectation over them
+#[derive(Debug)]
+pub(crate) struct Child<S, A> {
+    pub(crate) action: A,
+    /// The first outcome of the action, and the only one unless chance nodes are enabled
+    pub(crate) first: Outcome<S, A>,
+    /// The other outcomes of the action sampled so far
+    others: Vec<Outcome<S, A>>,
+}
+
+impl<S, A> Child<S, A>
+where
+    S: Eq,
+    A: Action,
+{
+    fn new(action: A, first: Outcome<S, A>) -> Self {
+        Self {
+            action,
+            first,
+            others: vec![],
+        }
+    }
+
+    /// Every outcome of the action sampled so far
+    pub(crate) fn outcomes(&self) -> impl Iterator<Item = &Outcome<S, A>> {
+        std::iter::once(&self.first).chain(&self.others)
+    }
+
+    /// N(a) - The number of times this action was taken
+    pub(crate) fn visits(&self) -> usize {
+        self.outcomes().map(|o| *o.node.visits.borrow()).sum()
+    }
+
+    /// Q(a) - The expected return of the action, from the perspective of the player taking it.
+    /// Outcomes are weighted by their probabilities when all of them are known, or by how often they were sampled otherwise
+    pub(crate) fn q_value(&self) -> f64 {
+        if self.others.is_empty() {
+            return self.first.node.q_value();
+        }
+
+        let visited = || self.outcomes().filter(|o| *o.node.visits.borrow() > 0);
+        let probabilities = visited().map(|o| o.probability).collect::<Option<Vec<_>>>();
+
+        match probabilities {
+            Some(probabilities) if probabilities.iter().sum::<f64>() > 0.0 => {
+                let weighted = visited()
+                    .zip(&probabilities)
+                    .map(|(o, p)| p * o.node.q_value())
+                    .sum::<f64>();
+                weighted / probabilities.iter().sum::<f64>()
+            }
+            _ => {
+                let score = self.outcomes().map(|o| *o.node.score.borrow()).sum::<f64>();
+                score / self.visits().max(1) as f64
+            }
+        }
+    }
+
+    /// The standard error of the mean of the returns backed up through the action, i.e. how far off Q(a) might be.
+    /// Infinite until the action was taken twice
+    pub(crate) fn std_error(&self) -> f64 {
+        let visits = self.visits();
+        if visits < 2 {
+            return f64::INFINITY;
+        }
+
+        let n = visits as f64;
+        let score = self.outcomes().map(|o| *o.node.score.borrow()).sum::<f64>();
+        let squares = self
+            .outcomes()
+            .map(|o| *o.node.squared_score.borrow())
+            .sum::<f64>();
+        let mean = score / n;
+        // the sample variance, floored at 0 against rounding errors
+        let variance = ((squares - n * mean * mean) / (n - 1.0)).max(0.0);
+        (variance / n).sqrt()
+    }
+
+    /// The visits and Q-value of the action
+    pub(crate) fn stats(&self) -> ActionStats {
+        ActionStats {
+            visits: self.visits(),
+            value: self.q_value(),
+        }
+    }
+
+    /// Same as [`Node::ucb1_terms`], for the action
+    pub(crate) fn ucb1_terms(&self) -> (f64, f64) {
+        if self.others.is_empty() {
+            return self.first.node.ucb1_terms();
+        }
+
+        let visits = self.visits().max(1) as f64;
+        (self.q_value(), visits.sqrt().recip())
+    }
+
+    /// Total return of every player over all the outcomes, see [`Node::scores`]
+    pub(crate) fn scores(&self) -> Vec<f64> {
+        let mut totals = self.first.node.scores.borrow().clone();
+        for outcome in &self.others {
+            let scores = outcome.node.scores.borrow();
+            totals.resize(totals.len().max(scores.len()), 0.0);
+            totals
+                .iter_mut()
+                .zip(scores.iter())
+                .for_each(|(t, s)| *t += s);
+        }
+        totals
+    }
+}
+
+/// The copies of the nodes of a tree, by the address of their original, see [`Node::deep_clone`]
+pub(crate) type Copies<S, A> = HashMap<*const Node<S, A>, Rc<Node<S, A>>>;
+
+/// Returns true for the actions of a state the search shouldn't take, see [`crate::mcts::MCTS::with_pruning`]
+pub(crate) type Pruning<S, A> = dyn Fn(&S, &A, &ActionStats) -> bool;
 
 #[derive(Debug)]
 pub struct Node<S, A> {
     pub state: S,
-    /// The action that resulted in this Node(State)
-    // pub(crate) action: Option<A>,
-    pub(crate) action: Option<A>,
     // pub reward: Option<f64>,
-    parent: Weak<Node<S, A>>,
+    /// The node this one was most recently reached from.
+    /// With transpositions, a node can have several parents, and this always points at the one on the current path
+    parent: RefCell<Weak<Node<S, A>>>,
+    /// The number of actions between the root and this node
+    pub(crate) depth: usize,
+    /// The player to move in this node's state, i.e. the one choosing among its children
+    pub(crate) player: PlayerId,
     /// rather than storing stats(time visited for the bandit) in UCB1, we only store children and times visited here
     /// In UCB1 where we need to explore all the actions first before we start exploiting
     /// All we just do is compare total actions on this state with the total children (explored children of this node)
     /// If they're the same, we've explored everything, else we haven't, and we just add the missing action(child)
-    /// Since each node has `action` we can easily use this to know which action/child has been checked or not
+    /// Since each child edge has an `action` we can easily use this to know which action/child has been checked or not
     /// IF ALL CHILDREN NODES OF THIS NODE ARE VISITED, THIS NODE IS CONSIDERED FULLY EXPANDED, otherwise it's not full expanded
     // pub(crate) children: RefCell<Vec<Rc<Node<S, A>>>>,
-    pub(crate) children: RefCell<Vec<Rc<Node<S, A>>>>,
+    pub(crate) children: RefCell<Vec<Child<S, A>>>,
+    /// Maps the [`Action::key`] of each child's action to its index in `children`.
+    /// Only populated for actions that provide a key
+    child_index: RefCell<HashMap<u64, usize, BuildHasherDefault<KeyHasher>>>,
     /// Records the number of times this node has been on the backpropagation path
     /// N(v) - A node is considered visited if it has been evaluated at least once.
     pub(crate) visits: RefCell<usize>,
-    /// Q(v) - Total simulation reward
+    /// The reward of the edge this node was most recently reached through (see [`Outcome::reward`]),
+    /// kept alongside `parent` so that backing up doesn't have to look for the edge
+    reward: Cell<f64>,
+    /// Q(v) - Total return over all the visits, from the perspective of the player who moved into this node:
+    /// the reward of the edge into it, plus the discounted return after it
+    /// (so that Q(v)/N(v) is the expected return of taking the edge, i.e. r + γ·V)
     // pub(crate) score: RefCell<f64>,
     pub(crate) score: RefCell<f64>,
+    /// Total of the squares of the returns making up `score`, for the spread of the returns around Q(v)/N(v)
+    squared_score: RefCell<f64>,
+    /// Total return of every player over all the visits, only kept for games with more than two players
+    /// (in two-player games, the other player's total is just `-score`)
+    pub(crate) scores: RefCell<Vec<f64>>,
+    /// Cached (Q(v)/N(v), 1/sqrt(N(v))) i.e. the parts of this node's UCB1 score that depend only on its own statistics.
+    /// Cleared whenever this node's statistics change
+    ucb1_terms: Cell<Option<(f64, f64)>>,
+    /// The user's payload, see [`crate::mcts::MCTS::with_node_metadata`]
+    pub(crate) metadata: RefCell<Option<Box<dyn Any>>>,
+    /// The statistics the selection policy needs, if it isn't UCB1, see [`crate::mcts::MCTS::with_selection`]
+    pub(crate) stats: RefCell<Option<Box<dyn NodeStats>>>,
+    /// The exact return after this node's state, from the perspective of the first player, if the search knows it.
+    /// Solved nodes aren't expanded, see [`crate::mcts::MCTS::with_endgame`]
+    pub(crate) exact: Cell<Option<f64>>,
 }
 
 impl<S, A: Action> Node<S, A>
@@ -35,18 +227,117 @@ where
 {
     pub(crate) fn new(
         state: S,
-        action: Option<A>,
-        score: Option<f64>,
+        player: PlayerId,
+        reward: Option<f64>,
         parent: Weak<Node<S, A>>,
     ) -> Self {
         Self {
             visits: RefCell::new(0),
             state,
-            action,
-            score: RefCell::new(score.unwrap_or(0.0)),
-            parent,
+            reward: Cell::new(reward.unwrap_or(0.0)),
+            score: RefCell::new(0.0),
+            squared_score: RefCell::new(0.0),
+            scores: RefCell::new(vec![]),
+            depth: parent.upgrade().map_or(0, |p| p.depth + 1),
+            player,
+            parent: RefCell::new(parent),
             children: RefCell::new(vec![]),
+            child_index: RefCell::new(HashMap::default()),
             // score: RefCell::new(0f64),
+            ucb1_terms: Cell::new(None),
+            metadata: RefCell::new(None),
+            stats: RefCell::new(None),
+            exact: Cell::new(None),
+        }
+    }
+
+    /// Copies the subtree rooted at this node, nodes shared through transpositions staying shared in the copy.
+    /// Returns the copy of the node, and the copies of every node of the subtree by the address of their original.
+    /// The `selection` the tree was searched with copies the statistics it needs
+    pub(crate) fn deep_clone(
+        self: &Rc<Self>,
+        selection: Option<&dyn ErasedSelection>,
+    ) -> (Rc<Self>, Copies<S, A>)
+    where
+        S: Clone,
+    {
+        let mut copies = Copies::new();
+        let mut originals = vec![];
+
+        // the nodes and their statistics first (iterative, like `TreeNode::walk`)...
+        let mut stack = vec![Rc::clone(self)];
+        while let Some(node) = stack.pop() {
+            if copies.contains_key(&Rc::as_ptr(&node)) {
+                continue;
+            }
+            copies.insert(Rc::as_ptr(&node), Rc::new(node.copy_statistics(selection)));
+            let children = node.children.borrow();
+            stack.extend(
+                children
+                    .iter()
+                    .flat_map(Child::outcomes)
+                    .map(|o| Rc::clone(&o.node)),
+            );
+            drop(children);
+            originals.push(node);
+        }
+
+        // ...then the edges between them
+        let copy_of = |node: &Rc<Self>| Rc::clone(&copies[&Rc::as_ptr(node)]);
+        for node in &originals {
+            let copy = copy_of(node);
+            if let Some(parent) = node.parent.borrow().upgrade()
+                && let Some(parent) = copies.get(&Rc::as_ptr(&parent))
+            {
+                *copy.parent.borrow_mut() = Rc::downgrade(parent);
+            }
+
+            let copy_outcome = |o: &Outcome<S, A>| Outcome {
+                node: copy_of(&o.node),
+                reward: o.reward,
+                probability: o.probability,
+            };
+            *copy.children.borrow_mut() = node
+                .children
+                .borrow()
+                .iter()
+                .map(|child| Child {
+                    action: child.action.clone(),
+                    first: copy_outcome(&child.first),
+                    others: child.others.iter().map(copy_outcome).collect(),
+                })
+                .collect();
+            *copy.child_index.borrow_mut() = node.child_index.borrow().clone();
+        }
+
+        (copy_of(self), copies)
+    }
+
+    /// A node with the same state and statistics, but no parent or children yet
+    fn copy_statistics(&self, selection: Option<&dyn ErasedSelection>) -> Self
+    where
+        S: Clone,
+    {
+        Self {
+            state: self.state.clone(),
+            parent: RefCell::new(Weak::new()),
+            depth: self.depth,
+            player: self.player,
+            children: RefCell::new(vec![]),
+            child_index: RefCell::new(HashMap::default()),
+            visits: RefCell::new(*self.visits.borrow()),
+            reward: Cell::new(self.reward.get()),
+            score: RefCell::new(*self.score.borrow()),
+            squared_score: RefCell::new(*self.squared_score.borrow()),
+            scores: RefCell::new(self.scores.borrow().clone()),
+            ucb1_terms: Cell::new(None),
+            metadata: RefCell::new(None),
+            stats: RefCell::new(
+                selection
+                    .zip(self.stats.borrow().as_deref())
+                    .map(|(selection, stats)| selection.clone_stats(stats)),
+            ),
+            exact: Cell::new(self.exact.get()),
         }
     }
 
@@ -59,138 +350,491 @@ where
         }
     }
 
+    /// Records that this node was just reached from `parent`, through an edge with this reward
+    fn reached_from(&self, parent: &Rc<Self>, reward: f64) {
+        *self.parent.borrow_mut() = Rc::downgrade(parent);
+        self.reward.set(reward);
+    }
+
+    /// Returns the index of the edge for this action, if it has already been explored
+    pub(crate) fn find_child_index(&self, action: &A) -> Option<usize> {
+        let children = self.children.borrow();
+
+        if let Some(key) = action.key() {
+            let index = self.child_index.borrow().get(&key).copied();
+            match index {
+                Some(index) if children[index].action == *action => return Some(index),
+                // The key isn't shared with any other child's action, so there's no child for this action
+                None => return None,
+                // Two different actions hashed to the same key, the slow path below disambiguates
+                Some(_) => {}
+            }
+        }
+
+        children.iter().position(|child| child.action == *action)
+    }
+
+    /// Returns the (first) child reached through this action, if it has already been explored
+    pub(crate) fn find_child(&self, action: &A) -> Option<Rc<Node<S, A>>> {
+        self.find_child_index(action)
+            .map(|index| Rc::clone(&self.children.borrow()[index].first.node))
+    }
+
+    /// Returns true if a child has already been created for this action
+    pub(crate) fn has_child(&self, action: &A) -> bool {
+        self.find_child(action).is_some()
+    }
+
     // /// Simulate the outcome of an action, and return the child node
-    pub(crate) fn get_outcome_child<M>(self: &Rc<Self>, mdp: &M, action: &A) -> Rc<Node<S, A>>
+    /// With `chance_nodes`, the action is sampled again every time, and a new child is added for every new outcome
+    pub(crate) fn get_outcome_child<M>(
+        self: &Rc<Self>,
+        mdp: &M,
+        action: &A,
+        table: Option<&TranspositionTable<S, A>>,
+        chance_nodes: bool,
+        rng: &mut dyn Rng,
+    ) -> Result<Rc<Node<S, A>>, McstError>
     where
-        M: MDP<S, A>,
+        M: GenerativeModel<S, A>,
     {
-        // Chose one outcome based on transition probabilities
-        let (next_state, reward, _) = mdp.execute(&self.state, action);
-
         // If a child already exists for this *resulting state* and action, return it.
         // We do that here by checking if any of the children(node) was a product of the action A
-        for child in self.children.borrow().iter() {
-            if let Some(child_action) = child.action {
-                if child_action == *action {
-                    return Rc::clone(child);
-                }
+        let index = self.find_child_index(action);
+        if let Some(index) = index
+            && !chance_nodes
+        {
+            let children = self.children.borrow();
+            let first = &children[index].first;
+            first.node.reached_from(self, first.reward);
+            return Ok(Rc::clone(&first.node));
+        }
+
+        // Chose one outcome based on transition probabilities
+        let (next_state, reward, _) = checked_execute(mdp, &self.state, action, rng)?;
+        let probability = mdp.transition_probability(&self.state, action, &next_state);
+
+        // Symmetric states are stored in their canonical form, so a sibling might already hold the same state
+        let (next_state, sibling) = match mdp.canonicalize(&next_state) {
+            Some(canonical) => {
+                let sibling = self
+                    .children
+                    .borrow()
+                    .iter()
+                    .flat_map(Child::outcomes)
+                    .find(|o| o.node.state == canonical)
+                    .map(|o| Rc::clone(&o.node));
+                (canonical, sibling)
+            }
+            None => (next_state, None),
+        };
+
+        // An outcome of this action that was already sampled
+        if let Some(index) = index {
+            let children = self.children.borrow();
+            let outcome = children[index]
+                .outcomes()
+                .find(|o| o.node.state == next_state);
+            if let Some(outcome) = outcome {
+                outcome.node.reached_from(self, outcome.reward);
+                return Ok(Rc::clone(&outcome.node));
             }
         }
 
-        // for child in self.children.borrow().iter() {
-        //     if next_state == child.state {
-        //         return Rc::clone(child);
-        //     }
-        // }
+        let key = table.and(mdp.state_key(&next_state));
 
-        // This outcome has not occured from this state-action pair previously
-        let new_child = Rc::new(Node::new(
-            next_state,
-            Some(*action),
-            Some(reward),
-            Rc::downgrade(self),
-        ));
+        // The resulting state might have already been reached through a different order of actions
+        let transposition = sibling.or_else(|| {
+            table
+                .zip(key)
+                .and_then(|(table, key)| table.get(key, self.depth + 1, &next_state))
+        });
 
-        self.children.borrow_mut().push(Rc::clone(&new_child));
+        let new_child = match transposition {
+            Some(node) => {
+                node.reached_from(self, reward);
+                node
+            }
+            None => {
+                // This outcome has not occured from this state-action pair previously
+                let player = mdp.player_to_move(&next_state);
+                let node = Rc::new(Node::new(
+                    next_state,
+                    player,
+                    Some(reward),
+                    Rc::downgrade(self),
+                ));
+                if let (Some(table), Some(key)) = (table, key) {
+                    table.insert(key, &node);
+                }
+                node
+            }
+        };
+
+        let outcome = Outcome {
+            node: Rc::clone(&new_child),
+            reward,
+            probability,
+        };
+        let mut children = self.children.borrow_mut();
+        match index {
+            Some(index) => children[index].others.push(outcome),
+            None => {
+                if let Some(key) = action.key() {
+                    // on a collision, the first child keeps the key and the others are found by scanning
+                    self.child_index
+                        .borrow_mut()
+                        .entry(key)
+                        .or_insert(children.len());
+                }
+                children.push(Child::new(action.clone(), outcome));
+            }
+        }
 
-        return new_child;
+        Ok(new_child)
     }
 
-    /// TODO:  This should be considered as a trait, but a default value just incase the user wants to provide something custom here
-    pub fn ucb1(self: &Rc<Self>, exploration_constant: f64) -> f64 {
-        let parent_visits = if let Some(parent) = self.parent.upgrade() {
-            *(parent.visits.borrow()) as f64
-        } else {
-            1.0
+    /// Returns (Q(v)/N(v), 1/sqrt(N(v))), so that the UCB1 score of this node is
+    /// `q + c * sqrt(ln(N(parent))) * inv_sqrt_visits` without recomputing a logarithm and square root for every child
+    pub(crate) fn ucb1_terms(&self) -> (f64, f64) {
+        if let Some(terms) = self.ucb1_terms.get() {
+            return terms;
         }
-        .max(1f64);
-
-        // self.q_value()
-        //     + exploration_constant
-        //         * (parent_visits.ln() / (*self.visits.borrow() as f64 + 1e-6)).sqrt()
 
         let child_visits = (*self.visits.borrow()).max(1) as f64;
-        self.q_value() + (exploration_constant * (parent_visits.ln() / child_visits).sqrt())
-        // self.q_value() + (exploration_constant * (parent_visits.ln() / child_visits))
-        // self.q_value() + f64::sqrt((2f64 * parent_visits.ln()) / child_visits)
+        let terms = (self.q_value(), child_visits.sqrt().recip());
+        self.ucb1_terms.set(Some(terms));
+        terms
+    }
+
+    /// The actions of this node's state the search may take: those the pruning predicate keeps, or all of them if it keeps none
+    fn candidate_actions<M>(&self, mdp: &M, prune: Option<&Pruning<S, A>>) -> Vec<A>
+    where
+        M: GenerativeModel<S, A>,
+    {
+        let actions = mdp.get_actions(&self.state);
+        let Some(prune) = prune else {
+            return actions;
+        };
+
+        let children = self.children.borrow();
+        let kept = actions
+            .iter()
+            .filter(|action| {
+                let stats = self
+                    .find_child_index(action)
+                    .map(|index| children[index].stats())
+                    .unwrap_or_default();
+                !prune(&self.state, action, &stats)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if kept.is_empty() { actions } else { kept }
     }
 
     /// Select a node that is not fully expanded
-    pub(crate) fn select<M>(self: &Rc<Self>, mdp: &M, bandit: &UCB1) -> Rc<Self>
+    pub(crate) fn select<M>(
+        self: &Rc<Self>,
+        mdp: &M,
+        bandit: &UCB1,
+        prune: Option<&Pruning<S, A>>,
+        table: Option<&TranspositionTable<S, A>>,
+        chance_nodes: bool,
+        rng: &mut dyn Rng,
+    ) -> Result<Rc<Self>, McstError>
     where
-        M: MDP<S, A>,
+        M: GenerativeModel<S, A>,
     {
-        if !self.is_full_expanded(mdp) || mdp.is_terminal(&self.state) {
-            return Rc::clone(&self);
+        if mdp.is_terminal(&self.state) {
+            return Ok(Rc::clone(self));
+        }
+
+        let full_expanded = match prune {
+            None => self.is_full_expanded(mdp),
+            Some(_) => self
+                .candidate_actions(mdp, prune)
+                .iter()
+                .all(|action| self.has_child(action)),
+        };
+        if !full_expanded {
+            return Ok(Rc::clone(self));
         }
 
         // Assuming this node is already fully expanded
         // (i.e. all it's children have been explored),
         // we need to make an informed decision about which of it's
         // children to select to become the next node under scope
-        let actions = mdp.get_actions(&self.state);
-        let action = bandit.select(&self, actions);
-        return self.get_outcome_child(mdp, &action).select(mdp, bandit);
+        let actions = self.candidate_actions(mdp, prune);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions.into());
+        }
+
+        let action = bandit.select(self, actions, rng);
+        self.get_outcome_child(mdp, &action, table, chance_nodes, rng)?
+            .select(mdp, bandit, prune, table, chance_nodes, rng)
     }
 
-    pub(crate) fn expand<M, P>(self: &Rc<Self>, mdp: &M, policy: &P) -> Rc<Self>
+    pub(crate) fn expand<M, P>(
+        self: &Rc<Self>,
+        mdp: &M,
+        policy: &P,
+        prune: Option<&Pruning<S, A>>,
+        table: Option<&TranspositionTable<S, A>>,
+        chance_nodes: bool,
+        rng: &mut dyn Rng,
+    ) -> Result<Rc<Self>, McstError>
     where
-        M: MDP<S, A>,
+        M: GenerativeModel<S, A>,
         P: RolloutPolicy<M, S, A>,
     {
         if mdp.is_terminal(&self.state) {
-            return Rc::clone(&self);
+            return Ok(Rc::clone(self));
         }
 
-        let explored = self
-            .children
-            .borrow()
-            .iter()
-            .flat_map(|x| x.action)
-            .collect::<Vec<_>>();
-
-        // let children = self.children.borrow();
         // Randomly select an unexpected action to expand
-        let actions = mdp.get_actions(&self.state);
+        let actions = self.candidate_actions(mdp, prune);
         let expandable_actions = actions
             .into_iter()
-            .filter(|a| !explored.contains(a))
+            .filter(|a| !self.has_child(a))
             .collect::<Vec<_>>();
+        if expandable_actions.is_empty() {
+            return Err(MdpError::NoActions.into());
+        }
 
         // let index = genrand(0, expandable_actions.len());
-        let action = policy.pick(&self.state, &expandable_actions);
+        let action = policy.pick(&self.state, &expandable_actions, rng);
         // let action = expandable_actions[index];
 
-        return self.get_outcome_child(mdp, &action);
+        self.get_outcome_child(mdp, &action, table, chance_nodes, rng)
+    }
+
+    /// Same as [`Node::select`] followed by [`Node::expand`], for actions that are sampled rather than enumerated.
+    /// A node tries a new action while it has fewer children than the widening allows, and picks among the ones it tried otherwise
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn select_widening<M>(
+        self: &Rc<Self>,
+        mdp: &M,
+        bandit: &UCB1,
+        widening: &ProgressiveWidening,
+        table: Option<&TranspositionTable<S, A>>,
+        chance_nodes: bool,
+        rng: &mut dyn Rng,
+    ) -> Result<Rc<Self>, McstError>
+    where
+        M: ContinuousActions<S, A>,
+    {
+        if mdp.is_terminal(&self.state) {
+            return Ok(Rc::clone(self));
+        }
+
+        let tried = self
+            .children
+            .borrow()
+            .iter()
+            .map(|child| (child.action.clone(), child.q_value()))
+            .collect::<Vec<_>>();
+
+        if tried.len() < widening.limit(*self.visits.borrow()) {
+            // half of the new actions are looked for around the best one so far, if the MDP can do that
+            let best = tried
+                .iter()
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+                .filter(|_| rng.gen_f64() < 0.5);
+            let action = best
+                .and_then(|(action, _)| mdp.refine_action(&self.state, action, rng))
+                .unwrap_or_else(|| mdp.sample_action(&self.state, rng));
+
+            return self.get_outcome_child(mdp, &action, table, chance_nodes, rng);
+        }
+
+        let actions = tried.into_iter().map(|(action, _)| action).collect();
+        let action = bandit.select(self, actions, rng);
+        self.get_outcome_child(mdp, &action, table, chance_nodes, rng)?
+            .select_widening(mdp, bandit, widening, table, chance_nodes, rng)
     }
 
-    /// BackPropagate the reward back to the parent node
-    pub(crate) fn back_propagate(self: &Rc<Self>, reward: f64, q_function: &mut UCB1) {
+    /// The node on the current path from `root` to this one that `root`'s action led to, None for `root` itself
+    pub(crate) fn first_step(self: &Rc<Self>, root: &Rc<Self>) -> Option<Rc<Self>> {
+        let mut node = Rc::clone(self);
+        loop {
+            let parent = node.parent.borrow().upgrade()?;
+            if Rc::ptr_eq(&parent, root) {
+                return Some(node);
+            }
+            node = parent;
+        }
+    }
+
+    /// BackPropagate the returns from this node's state (e.g. of a rollout) back to the parent node.
+    /// Every edge on the way turns the returns G after it into r + γ·G, with r its reward and γ the `discount` factor,
+    /// and the node below it records them for the player who moved into it, i.e. the one who chose it.
+    ///
+    /// In average-reward mode (see [`crate::mcts::MCTS::with_average_reward`]), `steps` is the number of steps the returns were accumulated over,
+    /// and every node records the reward per step from its parent onward rather than the return
+    pub(crate) fn back_propagate(
+        self: &Rc<Self>,
+        mut returns: Returns,
+        mut steps: Option<usize>,
+        discount: f64,
+    ) {
+        let parent = self.parent.borrow().upgrade();
+        let mover = parent.as_ref().map_or(self.player, |p| p.player);
+
+        if parent.is_some() {
+            returns.discount(discount);
+            returns.credit(mover, self.reward.get());
+            steps = steps.map(|steps| steps + 1);
+        }
+        let scale = steps.map_or(1.0, |steps| (steps.max(1) as f64).recip());
+
         *self.visits.borrow_mut() += 1;
-        *self.score.borrow_mut() += reward;
+        let value = returns.of(mover) * scale;
+        *self.score.borrow_mut() += value;
+        *self.squared_score.borrow_mut() += value * value;
+        if let Some(stats) = self.stats.borrow_mut().as_mut() {
+            stats.record(value);
+        }
+        if let Returns::MaxN(values) = &returns {
+            let mut scores = self.scores.borrow_mut();
+            scores.resize(values.len(), 0.0);
+            scores
+                .iter_mut()
+                .zip(values)
+                .for_each(|(total, v)| *total += v * scale);
+        }
+        self.ucb1_terms.set(None);
 
-        if let Some(parent) = self.parent.upgrade() {
-            parent.back_propagate(reward, q_function);
+        if let Some(parent) = parent {
+            parent.back_propagate(returns, steps, discount);
         }
     }
 
+    /// Starts a node that was never visited with `count` visits, as if that many rollouts from its state had returned `returns`
+    /// (e.g. a value function's estimate). The edge into it still earns its reward, so the node is worth r + γ·returns.
+    /// Leaves the root and visited nodes alone
+    pub(crate) fn warm_start(&self, mut returns: Returns, count: usize, discount: f64) {
+        let Some(parent) = self.parent.borrow().upgrade() else {
+            return;
+        };
+        if count == 0 || *self.visits.borrow() > 0 {
+            return;
+        }
+
+        returns.discount(discount);
+        returns.credit(parent.player, self.reward.get());
+        let value = returns.of(parent.player);
+
+        *self.visits.borrow_mut() = count;
+        *self.score.borrow_mut() = value * count as f64;
+        *self.squared_score.borrow_mut() = value * value * count as f64;
+        if let Some(stats) = self.stats.borrow_mut().as_mut() {
+            (0..count).for_each(|_| stats.record(value));
+        }
+        if let Returns::MaxN(values) = &returns {
+            *self.scores.borrow_mut() = values.iter().map(|v| v * count as f64).collect();
+        }
+        self.ucb1_terms.set(None);
+    }
+
+    /// Adds `count` visits worth `value` each to the node, for the player who moved into it, e.g. what an opening book knows of a move.
+    /// Unlike [`Node::warm_start`], the value already includes the reward of the edge into the node
+    pub(crate) fn seed(&self, value: f64, count: usize) {
+        *self.visits.borrow_mut() += count;
+        *self.score.borrow_mut() += value * count as f64;
+        *self.squared_score.borrow_mut() += value * value * count as f64;
+        if let Some(stats) = self.stats.borrow_mut().as_mut() {
+            (0..count).for_each(|_| stats.record(value));
+        }
+        self.ucb1_terms.set(None);
+    }
+
     /// Returns true if and only if all child actions have been expanded
-    fn is_full_expanded<M: MDP<S, A>>(&self, mdp: &M) -> bool {
+    fn is_full_expanded<M: GenerativeModel<S, A>>(&self, mdp: &M) -> bool {
         let actions = mdp.get_actions(&self.state);
-        let explored = self
-            .children
-            .borrow()
-            .iter()
-            .flat_map(|c| c.action)
-            .collect::<Vec<_>>();
+        actions.len() == self.children.borrow().len()
+    }
+
+    /// Returns the actions leading from the root of the tree to this node, in the order they were taken
+    pub(crate) fn path_actions(self: &Rc<Self>) -> Vec<A> {
+        let mut actions = vec![];
+        let mut node = Rc::clone(self);
 
-        return actions.len() == explored.len();
+        loop {
+            let Some(parent) = node.parent.borrow().upgrade() else {
+                break;
+            };
+
+            let action = parent
+                .children
+                .borrow()
+                .iter()
+                .find(|c| c.outcomes().any(|o| Rc::ptr_eq(&o.node, &node)))
+                .map(|c| c.action.clone())
+                .expect("a node is always one of its parent's children");
+
+            actions.push(action);
+            node = parent;
+        }
+
+        actions.reverse();
+        actions
+    }
+
+    /// Walks the subtree rooted at this node and reports its size and shape
+    pub(crate) fn tree_stats(self: &Rc<Self>) -> TreeStats {
+        // Each node lives in an Rc allocation, which carries the strong and weak counts alongside the node
+        let node_size = std::mem::size_of::<Node<S, A>>() + 2 * std::mem::size_of::<usize>();
+        let child_size = std::mem::size_of::<Child<S, A>>();
+        let outcome_size = std::mem::size_of::<Outcome<S, A>>();
+
+        let mut stats = TreeStats::default();
+        let mut parents = 0;
+        let mut edges = 0;
+
+        // nodes shared through transpositions are only counted once
+        let mut seen = HashSet::new();
+
+        // iterative, so that very deep trees don't overflow the stack
+        let mut stack = vec![(Rc::clone(self), 0)];
+        while let Some((node, depth)) = stack.pop() {
+            if !seen.insert(Rc::as_ptr(&node)) {
+                continue;
+            }
+
+            let children = node.children.borrow();
+
+            stats.nodes += 1;
+            stats.approx_bytes += node_size + children.capacity() * child_size;
+            stats.approx_bytes += children
+                .iter()
+                .map(|c| c.others.capacity() * outcome_size)
+                .sum::<usize>();
+            stats.max_depth = stats.max_depth.max(depth);
+
+            if !children.is_empty() {
+                parents += 1;
+                edges += children.len();
+            }
+
+            stack.extend(
+                children
+                    .iter()
+                    .flat_map(Child::outcomes)
+                    .map(|o| (Rc::clone(&o.node), depth + 1)),
+            );
+        }
+
+        if parents > 0 {
+            stats.avg_branching_factor = edges as f64 / parents as f64;
+        }
+
+        stats
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::policy::RandomRollout;
+    use crate::{policy::RandomRollout, rand::SplitMix64};
 
     use super::*;
 
@@ -203,8 +847,13 @@ mod tests {
     impl Action for TestAction {}
     struct DummyMDP;
 
-    impl MDP<u32, TestAction> for DummyMDP {
-        fn execute(&self, state: &u32, action: &TestAction) -> (u32, f64, bool) {
+    impl GenerativeModel<u32, TestAction> for DummyMDP {
+        fn execute(
+            &self,
+            state: &u32,
+            action: &TestAction,
+            _rng: &mut dyn Rng,
+        ) -> (u32, f64, bool) {
             let next_state = match action {
                 TestAction::A => *state + 1,
                 TestAction::B => *state + 2,
@@ -221,160 +870,407 @@ mod tests {
             *state >= 10
         }
 
-        fn get_states(&self) -> Vec<u32> {
-            todo!()
-        }
-
-        fn get_transitions(&self, state: &u32, action: &TestAction) -> Vec<(u32, f64)> {
-            todo!()
-        }
-
-        fn get_reward(&self, state: &u32, action: &TestAction, next_state: &u32) -> f64 {
-            todo!()
-        }
-
-        fn get_discount_factor(&self) -> f64 {
-            todo!()
-        }
-
         fn get_initial_state(&self) -> u32 {
-            todo!()
+            0
         }
 
-        fn get_goal_states(&self) -> Vec<u32> {
-            todo!()
+        fn state_key(&self, state: &u32) -> Option<u64> {
+            Some(*state as u64)
         }
     }
 
-    struct DummyUCB;
-
     #[test]
     fn test_node_new() {
-        let node: Node<u32, TestAction> = Node::new(0, None, None, Weak::new());
+        let node: Node<u32, TestAction> = Node::new(0, 0, None, Weak::new());
         assert_eq!(*node.visits.borrow(), 0);
         assert_eq!(node.state, 0);
-        assert!(node.action.is_none());
+        assert!(node.parent.borrow().upgrade().is_none());
         assert!(node.children.borrow().is_empty());
     }
 
     #[test]
     fn test_get_outcome_child_adds_new_child() {
-        let root = Rc::new(Node::new(0, None, None, Weak::new()));
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
         let mdp = DummyMDP;
 
-        let child = root.get_outcome_child(&mdp, &TestAction::A);
+        let child = root
+            .get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
 
         assert_eq!(root.children.borrow().len(), 1);
         assert_eq!(child.state, 1); // 0 + 1
-        assert!(Rc::ptr_eq(&child.parent.upgrade().unwrap(), &root));
+        assert!(Rc::ptr_eq(&child.parent.borrow().upgrade().unwrap(), &root));
     }
 
     #[test]
     fn test_get_outcome_child_returns_existing_child() {
-        let root = Rc::new(Node::new(0, None, None, Weak::new()));
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
         let mdp = DummyMDP;
 
-        let child1 = root.get_outcome_child(&mdp, &TestAction::A);
-        let child2 = root.get_outcome_child(&mdp, &TestAction::A);
+        let child1 = root
+            .get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
+        let child2 = root
+            .get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
 
         assert!(Rc::ptr_eq(&child1, &child2));
         assert_eq!(root.children.borrow().len(), 1);
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct KeyedAction(u32);
+
+    impl Action for KeyedAction {
+        fn key(&self) -> Option<u64> {
+            // deliberately collides for every pair of actions, to exercise the fallback
+            Some(crate::action::hash_action(&(self.0 / 2)))
+        }
+    }
+
+    struct KeyedMDP;
+
+    impl GenerativeModel<u32, KeyedAction> for KeyedMDP {
+        fn execute(
+            &self,
+            state: &u32,
+            action: &KeyedAction,
+            _rng: &mut dyn Rng,
+        ) -> (u32, f64, bool) {
+            (state + action.0, 0.0, false)
+        }
+
+        fn get_actions(&self, _state: &u32) -> Vec<KeyedAction> {
+            (0..6).map(KeyedAction).collect()
+        }
+
+        fn is_terminal(&self, _state: &u32) -> bool {
+            false
+        }
+
+        fn get_initial_state(&self) -> u32 {
+            0
+        }
+    }
+
+    #[test]
+    fn test_find_child_with_keyed_actions() {
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
+        let mdp = KeyedMDP;
+
+        assert!(root.find_child(&KeyedAction(3)).is_none());
+
+        let children = (0..6)
+            .map(|i| {
+                root.get_outcome_child(&mdp, &KeyedAction(i), None, false, &mut rng)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        for (i, child) in children.iter().enumerate() {
+            let found = root.find_child(&KeyedAction(i as u32)).unwrap();
+            assert!(Rc::ptr_eq(&found, child));
+            assert!(Rc::ptr_eq(
+                &root
+                    .get_outcome_child(&mdp, &KeyedAction(i as u32), None, false, &mut rng)
+                    .unwrap(),
+                child
+            ));
+        }
+
+        assert_eq!(root.children.borrow().len(), 6);
+        assert!(!root.has_child(&KeyedAction(6)));
+    }
+
     #[test]
     fn test_is_full_expanded() {
-        let node = Rc::new(Node::new(0, None, None, Weak::new()));
+        let mut rng = SplitMix64::seed(7);
+        let node = Rc::new(Node::new(0, 0, None, Weak::new()));
         let mdp = DummyMDP;
 
         assert!(!node.is_full_expanded(&mdp));
 
         // Expand all actions
-        node.get_outcome_child(&mdp, &TestAction::A);
-        node.get_outcome_child(&mdp, &TestAction::B);
+        node.get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
+        node.get_outcome_child(&mdp, &TestAction::B, None, false, &mut rng)
+            .unwrap();
 
         assert!(node.is_full_expanded(&mdp));
     }
 
     #[test]
     fn test_expand_adds_one_child() {
-        let node = Rc::new(Node::new(0, None, None, Weak::new()));
+        let mut rng = SplitMix64::seed(7);
+        let node = Rc::new(Node::new(0, 0, None, Weak::new()));
         let mdp = DummyMDP;
         let policy = RandomRollout::new();
 
         assert_eq!(node.children.borrow().len(), 0);
 
-        let child = node.expand(&mdp, &policy);
+        let child = node
+            .expand(&mdp, &policy, None, None, false, &mut rng)
+            .unwrap();
 
         assert_eq!(node.children.borrow().len(), 1);
         assert_eq!(
-            *node.children.borrow()[0].score.borrow(),
+            *node.children.borrow()[0].first.node.score.borrow(),
             *child.score.borrow()
         );
-        assert_eq!(node.children.borrow()[0].state, child.state);
-        assert_eq!(node.children.borrow()[0].visits, child.visits);
+        assert_eq!(node.children.borrow()[0].first.node.state, child.state);
+        assert_eq!(node.children.borrow()[0].first.node.visits, child.visits);
     }
 
     #[test]
     fn test_expand_terminal_returns_self() {
-        let node = Rc::new(Node::new(10, None, None, Weak::new())); // terminal state
+        let mut rng = SplitMix64::seed(7);
+        let node = Rc::new(Node::new(10, 0, None, Weak::new())); // terminal state
         let mdp = DummyMDP;
         let policy = RandomRollout::new();
 
-        let child = node.expand(&mdp, &policy);
+        let child = node
+            .expand(&mdp, &policy, None, None, false, &mut rng)
+            .unwrap();
 
         assert!(Rc::ptr_eq(&node, &child));
     }
 
     #[test]
     fn test_back_propagate_increments_visits() {
-        let root = Rc::new(Node::new(0, None, None, Weak::new()));
-        let child = Rc::new(Node::new(
-            1,
-            Some(TestAction::A),
-            Some(1.0),
-            Rc::downgrade(&root),
-        ));
-        root.children.borrow_mut().push(Rc::clone(&child));
-
-        let mut q = UCB1::default();
-        child.back_propagate(10.0, &mut q);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
+        let child = Rc::new(Node::new(1, 0, Some(1.0), Rc::downgrade(&root)));
+        let outcome = Outcome {
+            node: Rc::clone(&child),
+            reward: 1.0,
+            probability: None,
+        };
+        root.children
+            .borrow_mut()
+            .push(Child::new(TestAction::A, outcome));
+
+        child.back_propagate(10.0.into(), None, 0.5);
 
         assert_eq!(*child.visits.borrow(), 1);
         assert_eq!(*root.visits.borrow(), 1);
+
+        // the edge's reward, plus the discounted return after it
+        assert_eq!(*child.score.borrow(), 6.0);
+        assert_eq!(*root.score.borrow(), 6.0);
+    }
+
+    #[test]
+    fn test_ucb1_terms_match_ucb1() {
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
+        let mdp = DummyMDP;
+
+        let a = root
+            .get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
+        let b = root
+            .get_outcome_child(&mdp, &TestAction::B, None, false, &mut rng)
+            .unwrap();
+        a.back_propagate(1.0.into(), None, 1.0);
+        a.back_propagate(0.0.into(), None, 1.0);
+        b.back_propagate(1.0.into(), None, 1.0);
+
+        let parent_visits = *root.visits.borrow() as f64;
+        for child in [&a, &b] {
+            let (q, inv_sqrt_visits) = child.ucb1_terms();
+            let cached = q + UCB1::C * parent_visits.ln().sqrt() * inv_sqrt_visits;
+
+            let child_visits = *child.visits.borrow() as f64;
+            let expected = child.q_value() + UCB1::C * (parent_visits.ln() / child_visits).sqrt();
+            assert!((cached - expected).abs() < 1e-12);
+        }
+
+        // the cached terms are invalidated once the statistics change
+        a.back_propagate(1.0.into(), None, 1.0);
+        assert_eq!(a.ucb1_terms().0, a.q_value());
+    }
+
+    #[test]
+    fn test_path_actions() {
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
+        let mdp = DummyMDP;
+
+        assert!(root.path_actions().is_empty());
+
+        let leaf = root
+            .get_outcome_child(&mdp, &TestAction::B, None, false, &mut rng)
+            .unwrap()
+            .get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
+        assert_eq!(leaf.path_actions(), vec![TestAction::B, TestAction::A]);
+    }
+
+    #[test]
+    fn test_transpositions_share_nodes() {
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
+        let mdp = DummyMDP;
+        let table = TranspositionTable::default();
+        table.insert(0, &root);
+
+        // A then B and B then A both end up in state 3
+        let ab = root
+            .get_outcome_child(&mdp, &TestAction::A, Some(&table), false, &mut rng)
+            .unwrap()
+            .get_outcome_child(&mdp, &TestAction::B, Some(&table), false, &mut rng)
+            .unwrap();
+        let b = root
+            .get_outcome_child(&mdp, &TestAction::B, Some(&table), false, &mut rng)
+            .unwrap();
+        let ba = b
+            .get_outcome_child(&mdp, &TestAction::A, Some(&table), false, &mut rng)
+            .unwrap();
+
+        assert!(Rc::ptr_eq(&ab, &ba));
+        // the shared node now points at the parent it was most recently reached from
+        assert!(Rc::ptr_eq(&ba.parent.borrow().upgrade().unwrap(), &b));
+        assert_eq!(ba.path_actions(), vec![TestAction::B, TestAction::A]);
+
+        // states at different depths are never shared, which keeps the graph acyclic
+        let bb = b
+            .get_outcome_child(&mdp, &TestAction::B, Some(&table), false, &mut rng)
+            .unwrap();
+        let aaa = root
+            .get_outcome_child(&mdp, &TestAction::A, Some(&table), false, &mut rng)
+            .unwrap()
+            .get_outcome_child(&mdp, &TestAction::A, Some(&table), false, &mut rng)
+            .unwrap()
+            .get_outcome_child(&mdp, &TestAction::A, Some(&table), false, &mut rng)
+            .unwrap();
+        assert_eq!(bb.state, 4);
+        assert_eq!(aaa.state, 3);
+        assert!(!Rc::ptr_eq(&aaa, &ab));
+
+        assert_eq!(root.tree_stats().nodes, 7);
+    }
+
+    /// Actions A and B mirror each other, so both lead to the same canonical state
+    struct SymmetricMDP;
+
+    impl GenerativeModel<i32, TestAction> for SymmetricMDP {
+        fn execute(
+            &self,
+            state: &i32,
+            action: &TestAction,
+            _rng: &mut dyn Rng,
+        ) -> (i32, f64, bool) {
+            let next_state = match action {
+                TestAction::A => *state + 1,
+                TestAction::B => *state - 1,
+            };
+            (next_state, 0.0, false)
+        }
+
+        fn get_actions(&self, _state: &i32) -> Vec<TestAction> {
+            vec![TestAction::A, TestAction::B]
+        }
+
+        fn is_terminal(&self, _state: &i32) -> bool {
+            false
+        }
+
+        fn canonicalize(&self, state: &i32) -> Option<i32> {
+            Some(state.abs())
+        }
+
+        fn get_initial_state(&self) -> i32 {
+            0
+        }
+    }
+
+    #[test]
+    fn test_symmetric_siblings_share_a_node() {
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
+        let mdp = SymmetricMDP;
+
+        let a = root
+            .get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
+        let b = root
+            .get_outcome_child(&mdp, &TestAction::B, None, false, &mut rng)
+            .unwrap();
+
+        assert_eq!(b.state, 1);
+        assert!(Rc::ptr_eq(&a, &b));
+        // each action still has its own edge, so the root counts as fully expanded
+        assert_eq!(root.children.borrow().len(), 2);
+        assert!(root.is_full_expanded(&mdp));
+        assert_eq!(root.tree_stats().nodes, 2);
+    }
+
+    #[test]
+    fn test_tree_stats() {
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
+        let mdp = DummyMDP;
+
+        let stats = root.tree_stats();
+        assert_eq!(stats.nodes, 1);
+        assert_eq!(stats.max_depth, 0);
+        assert_eq!(stats.avg_branching_factor, 0.0);
+
+        let child = root
+            .get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
+        root.get_outcome_child(&mdp, &TestAction::B, None, false, &mut rng)
+            .unwrap();
+        child
+            .get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
+
+        let stats = root.tree_stats();
+        assert_eq!(stats.nodes, 4);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.avg_branching_factor, 1.5);
+        assert!(stats.approx_bytes >= 4 * std::mem::size_of::<Node<u32, TestAction>>());
     }
 
     #[test]
     fn test_select_returns_terminal_node() {
-        let root = Rc::new(Node::new(10, None, None, Weak::new())); // terminal state
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(10, 0, None, Weak::new())); // terminal state
         let mdp = DummyMDP;
         let bandit = UCB1::default();
 
-        let selected = root.select(&mdp, &bandit);
+        let selected = root
+            .select(&mdp, &bandit, None, None, false, &mut rng)
+            .unwrap();
         assert!(Rc::ptr_eq(&selected, &root));
     }
 
     #[test]
     fn test_select_traverses_fully_expanded() {
-        let root = Rc::new(Node::new(0, None, None, Weak::new()));
+        let mut rng = SplitMix64::seed(7);
+        let root = Rc::new(Node::new(0, 0, None, Weak::new()));
         let mdp = DummyMDP;
         let bandit = UCB1::default();
 
         // Expand both actions
-        root.get_outcome_child(&mdp, &TestAction::A);
-        root.get_outcome_child(&mdp, &TestAction::B);
+        root.get_outcome_child(&mdp, &TestAction::A, None, false, &mut rng)
+            .unwrap();
+        root.get_outcome_child(&mdp, &TestAction::B, None, false, &mut rng)
+            .unwrap();
 
-        let selected = root.select(&mdp, &bandit);
+        let selected = root
+            .select(&mdp, &bandit, None, None, false, &mut rng)
+            .unwrap();
 
         // Should return one of the children
-        // assert!(root.children.borrow().contains(&selected));
-
         assert_eq!(root.children.borrow().len(), 2);
-
-        assert_eq!(
-            *root.children.borrow()[0].score.borrow(),
-            *selected.score.borrow()
+        assert!(
+            root.children
+                .borrow()
+                .iter()
+                .any(|c| Rc::ptr_eq(&c.first.node, &selected))
         );
-        assert_eq!(root.children.borrow()[0].state, selected.state);
-        assert_eq!(root.children.borrow()[0].visits, selected.visits);
     }
 }
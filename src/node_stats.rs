@@ -0,0 +1,183 @@
+//! Statistics kept per node on top of the visits and mean return every node has, for selection rules that need more,
+//! see [`crate::mcts::MCTS::with_selection`].
+//!
+//! A [`SelectionPolicy`] declares the [`NodeStats`] it scores the actions by, and only searchers using it keep them,
+//! so the nodes of every other searcher stay as small as they are
+
+use std::{any::Any, fmt::Debug, rc::Rc};
+
+/// Statistics of the returns backed up through a node
+pub trait NodeStats: Any + Debug {
+    /// Records a return backed up through the node, from the perspective of the player who moved into it
+    fn record(&mut self, value: f64);
+}
+
+/// The mean and variance of the returns, kept with Welford's algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Variance {
+    count: usize,
+    mean: f64,
+    /// The sum of the squared differences from the mean
+    m2: f64,
+}
+
+impl Variance {
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// The population variance of the returns, 0 until there are two of them
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / self.count as f64
+        }
+    }
+}
+
+impl NodeStats for Variance {
+    fn record(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+}
+
+/// The lowest and highest returns, e.g. for risk-averse or optimistic selection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinMax {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for MinMax {
+    /// No returns yet: an empty range
+    fn default() -> Self {
+        Self {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl NodeStats for MinMax {
+    fn record(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+}
+
+/// An in-tree selection rule, replacing UCB1: once every action of a node was tried, the search takes the highest scored one,
+/// ties broken at random
+pub trait SelectionPolicy {
+    /// The statistics the policy needs, kept for every node of the tree
+    type Stats: NodeStats + Default + Clone;
+
+    /// Scores the action leading to a node with these statistics, taken `visits` times out of the `parent_visits` of the node it's taken in
+    fn score(&self, stats: &Self::Stats, visits: usize, parent_visits: usize) -> f64;
+}
+
+/// UCB1-Tuned (Auer et al., 2002), which scales the exploration of every action by the variance of its returns.
+/// Assumes returns between 0 and 1, like the bound it's derived from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ucb1Tuned;
+
+impl SelectionPolicy for Ucb1Tuned {
+    type Stats = Variance;
+
+    fn score(&self, stats: &Variance, visits: usize, parent_visits: usize) -> f64 {
+        let n = visits.max(1) as f64;
+        let log = (parent_visits.max(1) as f64).ln();
+        let bound = stats.variance() + (2.0 * log / n).sqrt();
+        stats.mean() + (log / n * bound.min(0.25)).sqrt()
+    }
+}
+
+/// A [`SelectionPolicy`] behind a pointer, its statistics type erased
+pub(crate) trait ErasedSelection {
+    fn new_stats(&self) -> Box<dyn NodeStats>;
+
+    /// Copies statistics made by [`ErasedSelection::new_stats`]
+    fn clone_stats(&self, stats: &dyn NodeStats) -> Box<dyn NodeStats>;
+
+    /// Scores an action, as a node without statistics if `stats` is None
+    fn score(&self, stats: Option<&dyn NodeStats>, visits: usize, parent_visits: usize) -> f64;
+}
+
+impl<P: SelectionPolicy> ErasedSelection for P {
+    fn new_stats(&self) -> Box<dyn NodeStats> {
+        Box::new(P::Stats::default())
+    }
+
+    fn clone_stats(&self, stats: &dyn NodeStats) -> Box<dyn NodeStats> {
+        match (stats as &dyn Any).downcast_ref::<P::Stats>() {
+            Some(stats) => Box::new(stats.clone()),
+            None => self.new_stats(),
+        }
+    }
+
+    fn score(&self, stats: Option<&dyn NodeStats>, visits: usize, parent_visits: usize) -> f64 {
+        let stats = stats.and_then(|stats| (stats as &dyn Any).downcast_ref::<P::Stats>());
+        match stats {
+            Some(stats) => SelectionPolicy::score(self, stats, visits, parent_visits),
+            None => SelectionPolicy::score(self, &P::Stats::default(), visits, parent_visits),
+        }
+    }
+}
+
+pub(crate) type Selection = Rc<dyn ErasedSelection>;
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+
+    use super::*;
+
+    #[test]
+    fn test_selection_policy_keeps_its_stats() {
+        let mut variance = Variance::default();
+        [1.0, 2.0, 3.0, 4.0]
+            .into_iter()
+            .for_each(|v| variance.record(v));
+        assert_eq!(variance.mean(), 2.5);
+        assert_eq!(variance.variance(), 1.25);
+
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_state: &u32| vec![1usize, 2],
+            |state, step, _rng| {
+                (
+                    state + *step as u32,
+                    0.1 * *step as f64,
+                    state + *step as u32 >= 6,
+                )
+            },
+            |state| *state >= 6,
+        );
+        // shared, so that the searcher can be forked
+        let mut mcts = MCTS::new(&mdp, RandomRollout::new())
+            .with_seed(1)
+            .with_selection(Ucb1Tuned);
+        mcts.run_iterations(200);
+
+        let root = mcts.root();
+        let stats = *root.stats::<Variance>().unwrap();
+        assert_eq!(stats.count(), 200);
+        assert!(root.stats::<MinMax>().is_none());
+        root.walk(|node| {
+            let stats = node.stats::<Variance>().unwrap();
+            assert_eq!(stats.count(), node.visits());
+            assert!((stats.mean() - node.value()).abs() < 1e-9);
+            true
+        });
+
+        let fork = mcts.fork();
+        assert_eq!(*fork.root().stats::<Variance>().unwrap(), stats);
+    }
+}
@@ -0,0 +1,45 @@
+//! Hooks into a running search, for live dashboards, logging or research instrumentation, see [`crate::mcts::MCTS::with_observer`]
+
+use crate::mdp::PlayerId;
+
+/// Called back by the search as it runs. Every callback does nothing by default, so observers only implement the ones they need.
+///
+/// The callbacks run inside the search loop, and are given plain statistics rather than access to the tree:
+/// anything slow they do slows the search down by as much
+pub trait Observer {
+    /// An untried action was expanded, adding its outcome to the tree.
+    /// Not called by [`crate::mcts::MCTS::mcts_continuous`], which expands actions while selecting them
+    fn on_expansion(&mut self, _event: &ExpansionEvent) {}
+
+    /// The returns of an iteration were backed up from a leaf to the root
+    fn on_backpropagation(&mut self, _event: &BackpropagationEvent) {}
+
+    /// An iteration of the search is done
+    fn on_iteration(&mut self, _event: &IterationEvent) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpansionEvent {
+    /// The number of actions between the root and the new node
+    pub depth: usize,
+    /// The player to move in the new node's state
+    pub player: PlayerId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackpropagationEvent {
+    /// The depth of the leaf the returns were backed up from
+    pub depth: usize,
+    /// The number of steps of the rollout from the leaf, 0 for a terminal leaf
+    pub rollout_steps: usize,
+    /// The return of the rollout, from the perspective of [`crate::mcts::MCTS::reported_player`]
+    pub rollout_return: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IterationEvent {
+    /// The number of iterations of the current search so far, this one included
+    pub iteration: usize,
+    /// The number of visits of the root, over all the searches from it
+    pub root_visits: usize,
+}
@@ -0,0 +1,364 @@
+//! Multi-objective MCTS, for MDPs whose rewards are vectors trading objectives off against each other (e.g. time vs. cost).
+//!
+//! There is no single best action anymore, only actions that aren't beaten on every objective at once by another action,
+//! i.e. the Pareto front. The search uses Pareto-UCB (Wang & Sebag, 2012): every child gets a vector of UCB1 scores,
+//! one per objective, and the next child is picked at random among the children whose scores no other child dominates
+
+use crate::{
+    action::Action,
+    budget::{Budget, Tracker},
+    error::{McstError, MdpError},
+    mdp::GenerativeModel,
+    policy::RolloutPolicy,
+    rand::Rng,
+    setup::{Configurable, SearchSetup},
+};
+
+/// A single-agent MDP with one reward per objective, all of them to be maximized
+pub trait MultiObjectiveMDP<S, A>: GenerativeModel<S, A> {
+    /// Number of objectives, i.e. the length of every reward vector
+    fn objectives(&self) -> usize;
+
+    /// Same as [`GenerativeModel::execute`], but with the reward of every objective.
+    /// `execute` can return any scalarization of them (e.g. their sum), which the multi-objective search ignores
+    fn execute_objectives(
+        &self,
+        state: &S,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, Vec<f64>, bool), MdpError>;
+}
+
+/// Returns true if `a` is at least as good as `b` on every objective, and better on at least one
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a >= b) && a.iter().zip(b).any(|(a, b)| a > b)
+}
+
+/// Returns the indices of the vectors no other vector dominates
+fn non_dominated(vectors: &[Vec<f64>]) -> Vec<usize> {
+    (0..vectors.len())
+        .filter(|i| !vectors.iter().any(|other| dominates(other, &vectors[*i])))
+        .collect()
+}
+
+#[derive(Debug)]
+struct ParetoNode<S, A> {
+    state: S,
+    visits: usize,
+    edges: Vec<ParetoEdge<S, A>>,
+}
+
+#[derive(Debug)]
+struct ParetoEdge<S, A> {
+    action: A,
+    /// The rewards of the transition to the child
+    rewards: Vec<f64>,
+    visits: usize,
+    /// Total return of every objective, transition rewards included
+    totals: Vec<f64>,
+    node: ParetoNode<S, A>,
+}
+
+impl<S, A> ParetoEdge<S, A> {
+    /// The mean return of every objective
+    fn means(&self) -> Vec<f64> {
+        let visits = self.visits.max(1) as f64;
+        self.totals.iter().map(|total| total / visits).collect()
+    }
+}
+
+impl<S, A> ParetoNode<S, A>
+where
+    S: Clone,
+    A: Action,
+{
+    fn new(state: S) -> Self {
+        Self {
+            state,
+            visits: 0,
+            edges: vec![],
+        }
+    }
+
+    /// Runs one iteration below this node and returns the return of every objective.
+    /// Descends the tree until the first untried action, which is expanded and followed by a rollout
+    fn iterate<M, P>(
+        &mut self,
+        mdp: &M,
+        policy: &P,
+        tracker: &Tracker<'_>,
+        rng: &mut dyn Rng,
+    ) -> Result<Vec<f64>, MdpError>
+    where
+        M: MultiObjectiveMDP<S, A>,
+        P: RolloutPolicy<M, S, A>,
+    {
+        self.visits += 1;
+        if mdp.is_terminal(&self.state) {
+            return Ok(vec![0.0; mdp.objectives()]);
+        }
+
+        let actions = mdp.get_actions(&self.state);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions);
+        }
+
+        let untried = actions
+            .into_iter()
+            .filter(|a| self.edges.iter().all(|edge| edge.action != *a))
+            .collect::<Vec<_>>();
+
+        let (index, mut returns) = if untried.is_empty() {
+            let index = self.select(mdp.objectives(), rng);
+            let edge = &mut self.edges[index];
+            (index, edge.node.iterate(mdp, policy, tracker, rng)?)
+        } else {
+            let action = policy.pick(&self.state, &untried, rng);
+            let (next_state, rewards, _) = mdp.execute_objectives(&self.state, &action, rng)?;
+            let returns = rollout(mdp, policy, next_state.clone(), tracker, rng)?;
+
+            self.edges.push(ParetoEdge {
+                action,
+                rewards,
+                visits: 0,
+                totals: vec![0.0; mdp.objectives()],
+                node: ParetoNode::new(next_state),
+            });
+            self.edges.last_mut().unwrap().node.visits += 1;
+            (self.edges.len() - 1, returns)
+        };
+
+        let edge = &mut self.edges[index];
+        returns
+            .iter_mut()
+            .zip(&edge.rewards)
+            .for_each(|(r, reward)| *r += reward);
+        edge.visits += 1;
+        edge.totals
+            .iter_mut()
+            .zip(&returns)
+            .for_each(|(total, r)| *total += r);
+        Ok(returns)
+    }
+
+    /// Pareto-UCB: picks at random among the children whose vector of UCB1 scores isn't dominated
+    fn select(&self, objectives: usize, rng: &mut dyn Rng) -> usize {
+        let log_term = 4.0 * (self.visits.max(1) as f64).ln() + (objectives as f64).ln();
+        let scores = self
+            .edges
+            .iter()
+            .map(|edge| {
+                let exploration = (log_term / (2.0 * edge.visits.max(1) as f64)).sqrt();
+                edge.means()
+                    .into_iter()
+                    .map(|mean| mean + exploration)
+                    .collect()
+            })
+            .collect::<Vec<_>>();
+
+        let front = non_dominated(&scores);
+        front[rng.gen_range(0, front.len())]
+    }
+}
+
+/// Plays the rollout policy from `state` until the end of the episode, and returns the return of every objective
+fn rollout<M, S, A, P>(
+    mdp: &M,
+    policy: &P,
+    mut state: S,
+    tracker: &Tracker<'_>,
+    rng: &mut dyn Rng,
+) -> Result<Vec<f64>, MdpError>
+where
+    M: MultiObjectiveMDP<S, A>,
+    A: Action,
+    P: RolloutPolicy<M, S, A>,
+{
+    let mut returns = vec![0.0; mdp.objectives()];
+
+    while !mdp.is_terminal(&state) && !tracker.out_of_time() {
+        let actions = mdp.get_actions(&state);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions);
+        }
+
+        let action = policy.pick(&state, &actions, rng);
+        let (next_state, rewards, _) = mdp.execute_objectives(&state, &action, rng)?;
+        returns
+            .iter_mut()
+            .zip(&rewards)
+            .for_each(|(r, reward)| *r += reward);
+        state = next_state;
+    }
+
+    Ok(returns)
+}
+
+/// Searches a [`MultiObjectiveMDP`] for its Pareto-optimal actions from the initial state
+pub struct ParetoMCTS<M, S, A, P>
+where
+    M: MultiObjectiveMDP<S, A>,
+    A: Action,
+    S: Clone,
+    P: RolloutPolicy<M, S, A>,
+{
+    mdp: M,
+    policy: P,
+    root: ParetoNode<S, A>,
+    setup: SearchSetup,
+}
+
+impl<M, S, A, P> Configurable for ParetoMCTS<M, S, A, P>
+where
+    M: MultiObjectiveMDP<S, A>,
+    A: Action,
+    S: Clone,
+    P: RolloutPolicy<M, S, A>,
+{
+    fn setup(&mut self) -> &mut SearchSetup {
+        &mut self.setup
+    }
+}
+
+impl<M, S, A, P> ParetoMCTS<M, S, A, P>
+where
+    M: MultiObjectiveMDP<S, A>,
+    A: Action,
+    S: Clone,
+    P: RolloutPolicy<M, S, A>,
+{
+    pub fn new(mdp: M, policy: P) -> Self {
+        let state = mdp.get_initial_state();
+        Self {
+            mdp,
+            policy,
+            root: ParetoNode::new(state),
+            setup: SearchSetup::default(),
+        }
+    }
+
+    /// Runs iterations until the budget is used up
+    ///
+    /// Panics if the MDP fails, see [`ParetoMCTS::try_search`]
+    pub fn search(&mut self, budget: Budget) {
+        if let Err(error) = self.try_search(budget) {
+            panic!("search failed: {error}");
+        }
+    }
+
+    /// Same as [`ParetoMCTS::search`], but stops at the first failure of the MDP and returns it
+    pub fn try_search(&mut self, budget: Budget) -> Result<(), McstError> {
+        self.setup.run(budget, |tracker, rng| {
+            self.root.iterate(&self.mdp, &self.policy, tracker, rng)?;
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Returns the mean return of every objective for each of the root's explored actions, with the number of times it was taken
+    pub fn action_values(&self) -> Vec<(A, Vec<f64>, usize)> {
+        self.root
+            .edges
+            .iter()
+            .map(|edge| (edge.action.clone(), edge.means(), edge.visits))
+            .collect()
+    }
+
+    /// Returns the root's actions whose mean returns no other action dominates, with those returns
+    pub fn pareto_front(&self) -> Vec<(A, Vec<f64>)> {
+        let means = self
+            .root
+            .edges
+            .iter()
+            .map(ParetoEdge::means)
+            .collect::<Vec<_>>();
+
+        non_dominated(&means)
+            .into_iter()
+            .map(|i| (self.root.edges[i].action.clone(), means[i].clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::policy::RandomRollout;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Route {
+        Highway,
+        Backroad,
+        Detour,
+    }
+
+    impl Action for Route {}
+
+    /// Two legs to drive, each by one of three routes trading time against cost (both negated, to be maximized).
+    /// The detour is both slower and dearer than the backroad
+    struct Trip;
+
+    impl Trip {
+        fn rewards(route: &Route) -> Vec<f64> {
+            match route {
+                Route::Highway => vec![-1.0, -5.0],
+                Route::Backroad => vec![-5.0, -1.0],
+                Route::Detour => vec![-6.0, -2.0],
+            }
+        }
+    }
+
+    impl GenerativeModel<u8, Route> for Trip {
+        fn get_actions(&self, _state: &u8) -> Vec<Route> {
+            vec![Route::Highway, Route::Backroad, Route::Detour]
+        }
+
+        fn execute(&self, state: &u8, action: &Route, _rng: &mut dyn Rng) -> (u8, f64, bool) {
+            let reward = Self::rewards(action).iter().sum();
+            (state + 1, reward, *state + 1 >= 2)
+        }
+
+        fn is_terminal(&self, state: &u8) -> bool {
+            *state >= 2
+        }
+
+        fn get_initial_state(&self) -> u8 {
+            0
+        }
+    }
+
+    impl MultiObjectiveMDP<u8, Route> for Trip {
+        fn objectives(&self) -> usize {
+            2
+        }
+
+        fn execute_objectives(
+            &self,
+            state: &u8,
+            action: &Route,
+            _rng: &mut dyn Rng,
+        ) -> Result<(u8, Vec<f64>, bool), MdpError> {
+            Ok((state + 1, Self::rewards(action), *state + 1 >= 2))
+        }
+    }
+
+    #[test]
+    fn test_pareto_front_keeps_every_trade_off() {
+        assert!(dominates(&[1.0, 2.0], &[1.0, 1.0]));
+        assert!(!dominates(&[1.0, 2.0], &[2.0, 1.0]));
+        assert!(!dominates(&[1.0, 1.0], &[1.0, 1.0]));
+
+        let mut search = ParetoMCTS::new(Trip, RandomRollout::new()).with_seed(1);
+        search.search(Budget::Iterations(1000));
+
+        let mut front = search
+            .pareto_front()
+            .into_iter()
+            .map(|(route, _)| route)
+            .collect::<Vec<_>>();
+        front.sort_by_key(|route| *route as u8);
+        assert_eq!(front, vec![Route::Highway, Route::Backroad]);
+    }
+}
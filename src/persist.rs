@@ -0,0 +1,122 @@
+//! Saving and loading learned models (behind the `serde` feature), so that tables trained offline
+//! can ship with an engine and guide its searches at runtime.
+//!
+//! Covers [`QTable`](crate::solvers::q_table::QTable), [`TabularValues`](crate::value::TabularValues)
+//! and [`TabularPolicy`](crate::policy::TabularPolicy), for states and actions that are themselves serializable.
+//! They're written as JSON here, but implement serde's traits, so any serde format (e.g. bincode) works on them as well
+//!
+//! ```
+//! use mct_rs::{persist, solvers::q_table::QTable};
+//!
+//! let mut table = QTable::new();
+//! table.set((0u8, 1u8), 2usize, 0.5);
+//!
+//! let json = persist::to_json(&table).unwrap();
+//! let loaded: QTable<(u8, u8), usize> = persist::from_json(&json).unwrap();
+//! assert_eq!(loaded.get(&(0, 1), &2), 0.5);
+//! ```
+
+use std::{error::Error, fmt::Display, path::Path};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Why a model couldn't be saved or loaded
+#[derive(Debug)]
+pub enum PersistError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for PersistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "couldn't access the model: {error}"),
+            Self::Json(error) => write!(f, "invalid JSON model: {error}"),
+        }
+    }
+}
+
+impl Error for PersistError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Json(error) => Some(error),
+        }
+    }
+}
+
+pub fn to_json<T: Serialize>(model: &T) -> Result<String, PersistError> {
+    serde_json::to_string(model).map_err(PersistError::Json)
+}
+
+pub fn from_json<T: DeserializeOwned>(text: &str) -> Result<T, PersistError> {
+    serde_json::from_str(text).map_err(PersistError::Json)
+}
+
+/// Writes the model to a JSON file, replacing it if it exists
+pub fn save<T: Serialize>(model: &T, path: impl AsRef<Path>) -> Result<(), PersistError> {
+    std::fs::write(path, to_json(model)?).map_err(PersistError::Io)
+}
+
+/// Reads a model written by [`save`]
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, PersistError> {
+    from_json(&std::fs::read_to_string(path).map_err(PersistError::Io)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        policy::TabularPolicy,
+        rand::SplitMix64,
+        solvers::{q_learning::QLearning, q_table::QTable},
+        tabular::TabularMDP,
+        value::TabularValues,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_models_survive_a_round_trip() {
+        let mdp = TabularMDP::new(
+            vec![
+                vec![vec![0.0, 1.0], vec![0.5, 0.5]],
+                vec![vec![0.0, 1.0], vec![0.0, 0.0]],
+            ],
+            vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+        )
+        .with_terminal_states([1]);
+        let table = QLearning::new()
+            .with_initial_value(0.5)
+            .train(&mdp, &mut SplitMix64::seed(1));
+
+        let path = std::env::temp_dir().join(format!("mct-rs-q-table-{}.json", std::process::id()));
+        save(&table, &path).unwrap();
+        let loaded: QTable<usize, usize> = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), table.len());
+        assert_eq!(loaded.get(&0, &1), table.get(&0, &1));
+        assert_eq!(loaded.get(&5, &0), 0.5);
+
+        // states that JSON objects couldn't have as keys
+        let values = [((0u8, 'a'), 1.5)]
+            .into_iter()
+            .collect::<TabularValues<_>>();
+        let loaded: TabularValues<(u8, char)> = from_json(&to_json(&values).unwrap()).unwrap();
+        assert_eq!(loaded.get(&(0, 'a')), Some(1.5));
+
+        let policy = [(vec![1, 2], 3usize)]
+            .into_iter()
+            .collect::<TabularPolicy<_, _>>();
+        let loaded: TabularPolicy<Vec<u8>, usize> = from_json(&to_json(&policy).unwrap()).unwrap();
+        assert_eq!(loaded.get(&vec![1, 2]), Some(&3));
+
+        assert!(matches!(
+            from_json::<QTable<usize, usize>>("{"),
+            Err(PersistError::Json(_))
+        ));
+        assert!(matches!(
+            load::<QTable<usize, usize>>(std::env::temp_dir().join("mct-rs-missing.json")),
+            Err(PersistError::Io(_))
+        ));
+    }
+}
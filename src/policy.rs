@@ -1,9 +1,14 @@
-use crate::{action::Action, mdp::MDP, rand::genrand};
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{action::Action, mdp::GenerativeModel, rand::Rng};
 
 pub trait RolloutPolicy<M, S, A> {
-    fn pick(&self, state: &S, actions: &Vec<A>) -> A;
+    // a `&Vec` rather than a slice, as implementors have been written against it
+    #[allow(clippy::ptr_arg)]
+    fn pick(&self, state: &S, actions: &Vec<A>, rng: &mut dyn Rng) -> A;
 }
 
+#[derive(Debug, Default, Clone, Copy)]
 pub struct RandomRollout;
 
 impl RandomRollout {
@@ -14,15 +19,141 @@ impl RandomRollout {
 
 impl<M, S, A> RolloutPolicy<M, S, A> for RandomRollout
 where
-    M: MDP<S, A>,
+    M: GenerativeModel<S, A>,
     A: Action,
 {
-    fn pick(&self, _state: &S, actions: &Vec<A>) -> A {
+    fn pick(&self, _state: &S, actions: &Vec<A>, rng: &mut dyn Rng) -> A {
         if actions.len() == 1 {
-            return actions[0];
+            return actions[0].clone();
+        }
+
+        let index = rng.gen_range(0, actions.len());
+        actions[index].clone()
+    }
+}
+
+/// A decision rule mapping states to actions, as solvers compute them.
+///
+/// Implemented for closures, for the tables of [`TabularPolicy`], and for what the solvers of [`crate::solvers`] return.
+/// The search can play it in its rollouts through [`PolicyRollout`]
+pub trait Policy<S, A> {
+    /// The action to take in the state, or None if the policy has none for it
+    fn action(&self, state: &S) -> Option<A>;
+}
+
+impl<S, A, F> Policy<S, A> for F
+where
+    F: Fn(&S) -> Option<A>,
+{
+    fn action(&self, state: &S) -> Option<A> {
+        self(state)
+    }
+}
+
+/// The actions to take in finitely many states
+#[derive(Debug, Clone)]
+pub struct TabularPolicy<S, A> {
+    actions: HashMap<S, A>,
+}
+
+impl<S, A> Default for TabularPolicy<S, A> {
+    fn default() -> Self {
+        Self {
+            actions: HashMap::new(),
         }
+    }
+}
+
+impl<S, A> TabularPolicy<S, A>
+where
+    S: Eq + Hash,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, state: &S) -> Option<&A> {
+        self.actions.get(state)
+    }
+
+    pub fn insert(&mut self, state: S, action: A) {
+        self.actions.insert(state, action);
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
 
-        let index = genrand(0, actions.len());
-        return actions[index];
+    pub fn iter(&self) -> impl Iterator<Item = (&S, &A)> {
+        self.actions.iter()
+    }
+}
+
+impl<S: Eq + Hash, A> FromIterator<(S, A)> for TabularPolicy<S, A> {
+    fn from_iter<T: IntoIterator<Item = (S, A)>>(iter: T) -> Self {
+        Self {
+            actions: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<S, A> From<HashMap<S, A>> for TabularPolicy<S, A> {
+    fn from(actions: HashMap<S, A>) -> Self {
+        Self { actions }
+    }
+}
+
+/// As a list of (state, action) pairs, whatever the type of the states
+#[cfg(feature = "serde")]
+impl<S: serde::Serialize, A: serde::Serialize> serde::Serialize for TabularPolicy<S, A> {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(&self.actions)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, S, A> serde::Deserialize<'de> for TabularPolicy<S, A>
+where
+    S: serde::Deserialize<'de> + Eq + Hash,
+    A: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let actions = <Vec<(S, A)> as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(actions.into_iter().collect())
+    }
+}
+
+impl<S: Eq + Hash, A: Clone> Policy<S, A> for TabularPolicy<S, A> {
+    fn action(&self, state: &S) -> Option<A> {
+        self.get(state).cloned()
+    }
+}
+
+/// Rolls out with a [`Policy`], playing a random action in the states it has none for
+/// (or only one the MDP doesn't allow there)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PolicyRollout<P>(pub P);
+
+impl<P> PolicyRollout<P> {
+    pub fn new(policy: P) -> Self {
+        Self(policy)
+    }
+}
+
+impl<M, S, A, P> RolloutPolicy<M, S, A> for PolicyRollout<P>
+where
+    M: GenerativeModel<S, A>,
+    A: Action,
+    P: Policy<S, A>,
+{
+    fn pick(&self, state: &S, actions: &Vec<A>, rng: &mut dyn Rng) -> A {
+        match self.0.action(state) {
+            Some(action) if actions.contains(&action) => action,
+            _ => RolloutPolicy::<M, S, A>::pick(&RandomRollout, state, actions, rng),
+        }
     }
 }
@@ -0,0 +1,509 @@
+//! Partially Observable Monte-Carlo Planning (Silver & Veness, 2010): MCTS over histories of actions and observations rather than states.
+//!
+//! Every node of the tree stands for a history, and holds a particle approximation of the belief at that history,
+//! i.e. a bag of the states the POMDP was in when the simulations went through it.
+//! Each iteration samples a state from the root's belief and simulates from it, adding the states it goes through to the beliefs of the nodes it visits.
+//! After acting, [`POMCP::update`] moves the root down to the history that was actually observed, keeping its subtree and belief
+
+use crate::{
+    action::Action,
+    budget::Budget,
+    error::{McstError, MdpError},
+    policy::RolloutPolicy,
+    pomdp::POMDP,
+    rand::Rng,
+    setup::{Configurable, SearchSetup},
+    ucb1::UCB1,
+};
+
+/// How many states [`POMCP::update`] may simulate, per missing particle, to refill the belief
+const ATTEMPTS_PER_PARTICLE: usize = 10;
+
+/// A node of the search tree, standing for the history of actions and observations leading to it
+#[derive(Debug)]
+struct BeliefNode<S, A, O> {
+    /// N(h) - Number of simulations that went through this history
+    visits: usize,
+    /// B(h) - The states the POMDP was in at this history, during the simulations that went through it
+    particles: Vec<S>,
+    /// One edge per action available at this history, empty until the node is expanded
+    actions: Vec<ActionNode<S, A, O>>,
+}
+
+/// An action taken at a history, and the histories extending it with each observation received after the action
+#[derive(Debug)]
+struct ActionNode<S, A, O> {
+    action: A,
+    /// N(ha) - Number of times the action was taken at this history
+    visits: usize,
+    /// V(ha) - Mean discounted return of the action at this history
+    value: f64,
+    children: Vec<(O, BeliefNode<S, A, O>)>,
+}
+
+impl<S, A, O> BeliefNode<S, A, O>
+where
+    S: Clone,
+    A: Action,
+    O: PartialEq,
+{
+    fn new() -> Self {
+        Self {
+            visits: 0,
+            particles: vec![],
+            actions: vec![],
+        }
+    }
+
+    /// Simulates one episode from `state`, at this history and `depth` actions below the root, and returns its discounted return.
+    /// The first visit of a node expands it and finishes the episode with a rollout
+    #[allow(clippy::too_many_arguments)]
+    fn simulate<M, P>(
+        &mut self,
+        pomdp: &M,
+        policy: &P,
+        state: S,
+        depth: usize,
+        max_depth: usize,
+        exploration: f64,
+        rng: &mut dyn Rng,
+    ) -> Result<f64, MdpError>
+    where
+        M: POMDP<S, A, O>,
+        P: RolloutPolicy<M, S, A>,
+    {
+        if depth >= max_depth || pomdp.is_terminal(&state) {
+            return Ok(0.0);
+        }
+
+        if self.actions.is_empty() {
+            let actions = pomdp.get_actions(&state);
+            if actions.is_empty() {
+                return Err(MdpError::NoActions);
+            }
+
+            self.actions = actions
+                .into_iter()
+                .map(|action| ActionNode {
+                    action,
+                    visits: 0,
+                    value: 0.0,
+                    children: vec![],
+                })
+                .collect();
+            return rollout(pomdp, policy, state, depth, max_depth, rng);
+        }
+
+        let index = self.select(exploration, rng);
+        let edge = &mut self.actions[index];
+        let (next_state, reward, _) = pomdp.try_execute(&state, &edge.action, rng)?;
+        let observation = pomdp.observe(&edge.action, &next_state, rng);
+
+        let child = match edge.children.iter().position(|(o, _)| *o == observation) {
+            Some(child) => child,
+            None => {
+                edge.children.push((observation, BeliefNode::new()));
+                edge.children.len() - 1
+            }
+        };
+        let child = &mut edge.children[child].1;
+        child.particles.push(next_state.clone());
+
+        let future = child.simulate(
+            pomdp,
+            policy,
+            next_state,
+            depth + 1,
+            max_depth,
+            exploration,
+            rng,
+        )?;
+        let value = reward + pomdp.get_discount_factor() * future;
+
+        self.visits += 1;
+        edge.visits += 1;
+        edge.value += (value - edge.value) / edge.visits as f64;
+        Ok(value)
+    }
+
+    /// Returns the index of the action to take next at this history: an untried one if any, or else the one with the highest UCB1 score
+    fn select(&self, exploration: f64, rng: &mut dyn Rng) -> usize {
+        let untried = (0..self.actions.len())
+            .filter(|i| self.actions[*i].visits == 0)
+            .collect::<Vec<_>>();
+        if !untried.is_empty() {
+            return untried[rng.gen_range(0, untried.len())];
+        }
+
+        let log_visits = (self.visits.max(1) as f64).ln();
+        let scores = self
+            .actions
+            .iter()
+            .map(|edge| edge.value + exploration * (log_visits / edge.visits as f64).sqrt())
+            .collect::<Vec<_>>();
+        let best = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        //  if there are multiple actions with the highest value choose one randomly
+        let best_actions = (0..scores.len())
+            .filter(|i| scores[*i] == best)
+            .collect::<Vec<_>>();
+        best_actions[rng.gen_range(0, best_actions.len())]
+    }
+}
+
+/// Plays the rollout policy from `state` until the episode ends or the search horizon is reached, and returns the discounted return
+fn rollout<M, S, A, O, P>(
+    pomdp: &M,
+    policy: &P,
+    mut state: S,
+    depth: usize,
+    max_depth: usize,
+    rng: &mut dyn Rng,
+) -> Result<f64, MdpError>
+where
+    M: POMDP<S, A, O>,
+    P: RolloutPolicy<M, S, A>,
+{
+    let discount = pomdp.get_discount_factor();
+    let mut value = 0.0;
+    let mut weight = 1.0;
+
+    for _ in depth..max_depth {
+        if pomdp.is_terminal(&state) {
+            break;
+        }
+
+        let actions = pomdp.get_actions(&state);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions);
+        }
+
+        let action = policy.pick(&state, &actions, rng);
+        let (next_state, reward, _) = pomdp.try_execute(&state, &action, rng)?;
+        value += weight * reward;
+        weight *= discount;
+        state = next_state;
+    }
+
+    Ok(value)
+}
+
+/// Searches a [`POMDP`] for the best action to take after the history observed so far
+pub struct POMCP<M, S, A, O, P>
+where
+    M: POMDP<S, A, O>,
+    A: Action,
+    S: Clone,
+    O: PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    pomdp: M,
+    policy: P,
+    root: BeliefNode<S, A, O>,
+    /// Number of states the belief is refilled to after every update
+    particles: usize,
+    max_depth: usize,
+    exploration: f64,
+    setup: SearchSetup,
+}
+
+impl<M, S, A, O, P> Configurable for POMCP<M, S, A, O, P>
+where
+    M: POMDP<S, A, O>,
+    A: Action,
+    S: Clone,
+    O: PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    fn setup(&mut self) -> &mut SearchSetup {
+        &mut self.setup
+    }
+}
+
+impl<M, S, A, O, P> POMCP<M, S, A, O, P>
+where
+    M: POMDP<S, A, O>,
+    A: Action,
+    S: Clone,
+    O: PartialEq,
+    P: RolloutPolicy<M, S, A>,
+{
+    /// Starts from the initial belief (see [`POMDP::sample_initial_state`]),
+    /// with a belief of 1000 particles, a horizon of 100 actions, and the same exploration constant as [`crate::mcts::MCTS`]
+    pub fn new(pomdp: M, policy: P) -> Self {
+        Self {
+            pomdp,
+            policy,
+            root: BeliefNode::new(),
+            particles: 1000,
+            max_depth: 100,
+            exploration: UCB1::C,
+            setup: SearchSetup::default(),
+        }
+    }
+
+    /// Number of states [`POMCP::update`] tops the new belief up to, when the search didn't leave enough of them at the observed history
+    pub fn with_particles(mut self, particles: usize) -> Self {
+        self.particles = particles;
+        self
+    }
+
+    /// Maximum number of actions simulated past the current history, in the tree and the rollouts combined.
+    /// Undiscounted POMDPs that can go on forever need it to end their simulations
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Weight of the exploration term of UCB1.
+    /// UCB1 assumes returns in `[0, 1]`, so this should be scaled to the range of the POMDP's returns
+    pub fn with_exploration_constant(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Runs simulations from the current belief until the budget is used up
+    ///
+    /// Panics if the POMDP fails, see [`POMCP::try_search`]
+    pub fn search(&mut self, budget: Budget) {
+        if let Err(error) = self.try_search(budget) {
+            panic!("search failed: {error}");
+        }
+    }
+
+    /// Same as [`POMCP::search`], but stops at the first failure of the POMDP and returns it
+    pub fn try_search(&mut self, budget: Budget) -> Result<(), McstError> {
+        self.setup.run(budget, |_, rng| {
+            let state = match self.root.particles.len() {
+                0 => self.pomdp.sample_initial_state(rng),
+                n => self.root.particles[rng.gen_range(0, n)].clone(),
+            };
+            self.root.simulate(
+                &self.pomdp,
+                &self.policy,
+                state,
+                0,
+                self.max_depth,
+                self.exploration,
+                rng,
+            )?;
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Returns the most tried action at the current history, if the search got that far
+    pub fn best_action(&self) -> Option<A> {
+        self.root
+            .actions
+            .iter()
+            .filter(|edge| edge.visits > 0)
+            .max_by_key(|edge| edge.visits)
+            .map(|edge| edge.action.clone())
+    }
+
+    /// Returns the estimated value of each action tried at the current history, with the number of times it was tried
+    pub fn action_values(&self) -> Vec<(A, f64, usize)> {
+        self.root
+            .actions
+            .iter()
+            .map(|edge| (edge.action.clone(), edge.value, edge.visits))
+            .collect()
+    }
+
+    /// The states the POMDP may be in after the history observed so far, as a bag of samples.
+    /// Empty until the first [`POMCP::update`], before which the belief is the initial one
+    pub fn belief(&self) -> &[S] {
+        &self.root.particles
+    }
+
+    /// Moves on to the history extended by the action actually taken and the observation actually received,
+    /// keeping the part of the tree searched below it.
+    ///
+    /// If the search left fewer than [`POMCP::with_particles`] states at the new history, the belief is topped up
+    /// by simulating the action from the current belief and keeping the states consistent with the observation
+    /// (or weighting them by [`POMDP::observation_probability`] when the POMDP provides it).
+    /// Fails with [`MdpError::EmptyBelief`] if not a single state could be found, in which case the searcher is left untouched
+    pub fn update(&mut self, action: &A, observation: &O) -> Result<(), McstError> {
+        let rng = self.setup.rng();
+        let edge = self.root.actions.iter().position(|e| e.action == *action);
+        let child = edge.and_then(|e| {
+            self.root.actions[e]
+                .children
+                .iter()
+                .position(|(o, _)| o == observation)
+                .map(|c| (e, c))
+        });
+        let searched = child.map_or(0, |(e, c)| {
+            self.root.actions[e].children[c].1.particles.len()
+        });
+
+        let missing = self.particles.saturating_sub(searched);
+        let mut particles = vec![];
+        for _ in 0..missing * ATTEMPTS_PER_PARTICLE {
+            if particles.len() == missing {
+                break;
+            }
+
+            let state = match self.root.particles.len() {
+                0 => self.pomdp.sample_initial_state(rng),
+                n => self.root.particles[rng.gen_range(0, n)].clone(),
+            };
+            let (next_state, ..) = self.pomdp.try_execute(&state, action, rng)?;
+
+            let consistent =
+                match self
+                    .pomdp
+                    .observation_probability(action, &next_state, observation)
+                {
+                    Some(probability) => rng.gen_f64() < probability,
+                    None => self.pomdp.observe(action, &next_state, rng) == *observation,
+                };
+            if consistent {
+                particles.push(next_state);
+            }
+        }
+
+        if searched + particles.len() == 0 {
+            return Err(MdpError::EmptyBelief.into());
+        }
+
+        let mut root = match child {
+            Some((e, c)) => self.root.actions[e].children.swap_remove(c).1,
+            None => BeliefNode::new(),
+        };
+        root.particles.extend(particles);
+        self.root = root;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mdp::GenerativeModel, policy::RandomRollout};
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Door {
+        Left,
+        Right,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum TigerAction {
+        Listen,
+        Open(Door),
+    }
+
+    impl Action for TigerAction {}
+
+    /// The tiger problem (Kaelbling et al., 1998): a tiger hides behind one of two doors, and a treasure behind the other.
+    /// Listening costs 1 and hears the tiger behind the right door 85% of the time, opening the treasure's door pays 10 and the tiger's costs 100.
+    /// The state is where the tiger is, or `None` once a door was opened
+    struct Tiger;
+
+    impl GenerativeModel<Option<Door>, TigerAction> for Tiger {
+        fn get_actions(&self, _state: &Option<Door>) -> Vec<TigerAction> {
+            vec![
+                TigerAction::Listen,
+                TigerAction::Open(Door::Left),
+                TigerAction::Open(Door::Right),
+            ]
+        }
+
+        fn execute(
+            &self,
+            state: &Option<Door>,
+            action: &TigerAction,
+            _rng: &mut dyn Rng,
+        ) -> (Option<Door>, f64, bool) {
+            match action {
+                TigerAction::Listen => (*state, -1.0, false),
+                TigerAction::Open(door) if Some(*door) == *state => (None, -100.0, true),
+                TigerAction::Open(_) => (None, 10.0, true),
+            }
+        }
+
+        fn is_terminal(&self, state: &Option<Door>) -> bool {
+            state.is_none()
+        }
+
+        fn get_initial_state(&self) -> Option<Door> {
+            Some(Door::Left)
+        }
+
+        fn get_discount_factor(&self) -> f64 {
+            0.95
+        }
+    }
+
+    impl POMDP<Option<Door>, TigerAction, Option<Door>> for Tiger {
+        fn observe(
+            &self,
+            _action: &TigerAction,
+            next_state: &Option<Door>,
+            rng: &mut dyn Rng,
+        ) -> Option<Door> {
+            let heard = match next_state {
+                Some(Door::Left) => Door::Left,
+                Some(Door::Right) => Door::Right,
+                None => return None,
+            };
+
+            if rng.gen_f64() < 0.85 {
+                Some(heard)
+            } else if heard == Door::Left {
+                Some(Door::Right)
+            } else {
+                Some(Door::Left)
+            }
+        }
+
+        fn sample_initial_state(&self, rng: &mut dyn Rng) -> Option<Door> {
+            if rng.gen_f64() < 0.5 {
+                Some(Door::Left)
+            } else {
+                Some(Door::Right)
+            }
+        }
+    }
+
+    #[test]
+    fn test_pomcp_listens_until_sure_of_the_tiger() {
+        let mut pomcp = POMCP::new(Tiger, RandomRollout::new())
+            .with_seed(1)
+            .with_exploration_constant(110.0)
+            .with_max_depth(20);
+
+        pomcp.search(Budget::Iterations(5000));
+        assert_eq!(pomcp.best_action(), Some(TigerAction::Listen));
+
+        for _ in 0..2 {
+            pomcp
+                .update(&TigerAction::Listen, &Some(Door::Left))
+                .unwrap();
+            pomcp.search(Budget::Iterations(5000));
+        }
+
+        let tiger_left = pomcp
+            .belief()
+            .iter()
+            .filter(|s| **s == Some(Door::Left))
+            .count();
+        assert!(tiger_left as f64 > 0.9 * pomcp.belief().len() as f64);
+        assert_eq!(pomcp.best_action(), Some(TigerAction::Open(Door::Right)));
+
+        // the game is over once a door was opened, there's nothing to observe after that
+        pomcp
+            .update(&TigerAction::Open(Door::Right), &None)
+            .unwrap();
+        assert!(
+            pomcp
+                .update(&TigerAction::Listen, &Some(Door::Left))
+                .is_err()
+        );
+    }
+}
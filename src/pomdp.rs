@@ -0,0 +1,33 @@
+//! Partially observable MDPs, where the agent never sees the state itself, only observations emitted along the way.
+//!
+//! See [`crate::pomcp::POMCP`] for how to plan in them
+
+use crate::{mdp::GenerativeModel, rand::Rng};
+
+/// A generative model of a POMDP: on top of sampling transitions (see [`GenerativeModel`]),
+/// it can sample the observation received after each of them, and a state from the initial belief.
+///
+/// The state passed to the [`GenerativeModel`] methods is the true, hidden state,
+/// and the actions available in a state must only depend on what the agent has observed so far
+pub trait POMDP<S, A, O>: GenerativeModel<S, A> {
+    /// Returns the observation received after taking the action and ending up in `next_state`.
+    /// Any randomness must be drawn from `rng`, just like in [`GenerativeModel::execute`]
+    fn observe(&self, action: &A, next_state: &S, rng: &mut dyn Rng) -> O;
+
+    /// Returns the probability of receiving the observation after taking the action and ending up in `next_state`, if known.
+    /// Beliefs are then updated by weighting their states with it rather than by matching sampled observations, which needs far fewer samples
+    fn observation_probability(
+        &self,
+        _action: &A,
+        _next_state: &S,
+        _observation: &O,
+    ) -> Option<f64> {
+        None
+    }
+
+    /// Samples a state from the initial belief, i.e. what the agent knows about the state before observing anything.
+    /// Defaults to [`GenerativeModel::get_initial_state`], for POMDPs whose initial state is known
+    fn sample_initial_state(&self, _rng: &mut dyn Rng) -> S {
+        self.get_initial_state()
+    }
+}
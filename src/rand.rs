@@ -1,20 +1,218 @@
+use std::cell::RefCell;
+
 use getrandom::getrandom;
 
+use crate::error::McstError;
+
+/// A source of randomness for the search.
+/// Tie-breaks, rollout choices and transition sampling all draw from the searcher's `Rng`,
+/// so a seeded generator makes a whole search reproducible
+pub trait Rng {
+    /// Returns the next 64 random bits
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a uniformly distributed number in `min..max`
+    fn gen_range(&mut self, min: usize, max: usize) -> usize {
+        assert!(
+            min < max,
+            "min must be less than max. min={min} -> max={max}"
+        );
+        let range = (max - min) as u64;
+        let max_usable = u64::MAX - u64::MAX % range;
+
+        loop {
+            let value = self.next_u64();
+            if value < max_usable {
+                return min + (value % range) as usize;
+            }
+            // else: retry
+        }
+    }
+
+    /// Returns a uniformly distributed number in `0.0..1.0`
+    fn gen_f64(&mut self) -> f64 {
+        // the top 53 bits fill the mantissa exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Draws every number from the operating system's entropy source.
+/// That's a syscall per number, which is far too slow for the rollout hot loop, so this is best used to seed other generators.
+/// Not reproducible
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsRng;
+
+impl OsRng {
+    /// Same as [`Rng::next_u64`], but reports a failure of the entropy source rather than panicking
+    pub fn try_next_u64(&mut self) -> Result<u64, McstError> {
+        let mut buf = [0u8; std::mem::size_of::<u64>()];
+        getrandom(&mut buf).map_err(McstError::Rng)?;
+        Ok(u64::from_ne_bytes(buf))
+    }
+}
+
+impl Rng for OsRng {
+    fn next_u64(&mut self) -> u64 {
+        self.try_next_u64()
+            .unwrap_or_else(|error| panic!("random failed: {error}"))
+    }
+}
+
+/// SplitMix64, a small and fast seedable generator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// The same seed always produces the same sequence of numbers
+    pub fn seed(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seeds the generator from the operating system's entropy source,
+    /// which is then only used this once: all the numbers are generated in userspace
+    pub fn from_entropy() -> Self {
+        Self::seed(OsRng.next_u64())
+    }
+
+    /// Same as [`Self::from_entropy`], but reports a failure of the entropy source rather than panicking
+    pub fn try_from_entropy() -> Result<Self, McstError> {
+        OsRng.try_next_u64().map(Self::seed)
+    }
+}
+
+impl Rng for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// xoshiro256++, a fast generator with a 256-bit state, i.e. a much longer period than [`SplitMix64`]
+#[cfg(feature = "xoshiro")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xoshiro256PlusPlus {
+    state: [u64; 4],
+}
+
+#[cfg(feature = "xoshiro")]
+impl Xoshiro256PlusPlus {
+    /// The same seed always produces the same sequence of numbers.
+    /// The 256-bit state is filled from the seed with [`SplitMix64`], as recommended by the authors
+    pub fn seed(seed: u64) -> Self {
+        let mut seeder = SplitMix64::seed(seed);
+        Self {
+            state: std::array::from_fn(|_| seeder.next_u64()),
+        }
+    }
+
+    /// Seeds the generator once from the operating system's entropy source
+    pub fn from_entropy() -> Self {
+        Self::seed(OsRng.next_u64())
+    }
+
+    /// Same as [`Self::from_entropy`], but reports a failure of the entropy source rather than panicking
+    pub fn try_from_entropy() -> Result<Self, McstError> {
+        OsRng.try_next_u64().map(Self::seed)
+    }
+}
+
+#[cfg(feature = "xoshiro")]
+impl Rng for Xoshiro256PlusPlus {
+    fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s0.wrapping_add(*s3).rotate_left(23).wrapping_add(*s0);
+
+        let t = *s1 << 17;
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+
+        result
+    }
+}
+
+/// wyrand, the smallest and usually the fastest of the generators here: one add and one 128-bit multiply per draw
+#[cfg(feature = "wyrand")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WyRand {
+    state: u64,
+}
+
+#[cfg(feature = "wyrand")]
+impl WyRand {
+    /// The same seed always produces the same sequence of numbers
+    pub fn seed(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seeds the generator once from the operating system's entropy source
+    pub fn from_entropy() -> Self {
+        Self::seed(OsRng.next_u64())
+    }
+
+    /// Same as [`Self::from_entropy`], but reports a failure of the entropy source rather than panicking
+    pub fn try_from_entropy() -> Result<Self, McstError> {
+        OsRng.try_next_u64().map(Self::seed)
+    }
+}
+
+#[cfg(feature = "wyrand")]
+impl Rng for WyRand {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0xA076_1D64_78BD_642F);
+        let t = u128::from(self.state) * u128::from(self.state ^ 0xE703_7ED1_A0B4_28DB);
+        (t >> 64) as u64 ^ t as u64
+    }
+}
+
+thread_local! {
+    static THREAD_RNG: RefCell<SplitMix64> = RefCell::new(SplitMix64::from_entropy());
+}
+
+/// Returns a uniformly distributed number in `min..max`, from a generator local to the current thread (seeded from the OS once)
 pub fn genrand(min: usize, max: usize) -> usize {
-    assert!(
-        min < max,
-        "min must be less than max. min={min} -> max={max}"
-    );
-    let range = max - min;
-    let mut buf = [0u8; std::mem::size_of::<usize>()];
-
-    loop {
-        getrandom(&mut buf).expect("random failed");
-        let value = usize::from_ne_bytes(buf);
-        let max_usable = usize::MAX - usize::MAX % range;
-        if value < max_usable {
-            return min + (value % range);
+    THREAD_RNG.with_borrow_mut(|rng| rng.gen_range(min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "xoshiro")]
+    #[test]
+    fn test_xoshiro_matches_reference_outputs() {
+        let mut rng = Xoshiro256PlusPlus {
+            state: [1, 2, 3, 4],
+        };
+        let outputs = [rng.next_u64(), rng.next_u64(), rng.next_u64()];
+        assert_eq!(outputs, [41943041, 58720359, 3588806011781223]);
+    }
+
+    #[cfg(feature = "wyrand")]
+    #[test]
+    fn test_wyrand_is_reproducible() {
+        let draws = |seed| {
+            let mut rng = WyRand::seed(seed);
+            (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>()
+        };
+        assert_eq!(draws(5), draws(5));
+        assert_ne!(draws(5), draws(6));
+    }
+
+    #[test]
+    fn test_gen_range_stays_in_range() {
+        let mut rng = SplitMix64::seed(11);
+        for max in 1..100 {
+            let value = rng.gen_range(max / 2, max + max / 2 + 1);
+            assert!((max / 2..max + max / 2 + 1).contains(&value));
         }
-        // else: retry
     }
 }
@@ -0,0 +1,200 @@
+//! Records of played games: the starting position, every move with what the search found about it, and the result,
+//! as the self-play driver produces them (see [`crate::selfplay::PlayedGame::record`]).
+//!
+//! Records replay into the positions of the game (see [`GameRecord::positions`]), to search them again or browse their trees.
+//! With the `serde` feature, [`RecordWriter`] stores them one JSON object per line and [`RecordReader`] reads them back,
+//! so that a file of records grows game by game, and any serde format works on them as well
+
+#[cfg(feature = "serde")]
+use std::io;
+
+#[cfg(feature = "serde")]
+use crate::persist::PersistError;
+use crate::{game::Game, mdp::PlayerId, stats::ActionStats};
+
+/// A move of a recorded game
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedMove<M> {
+    pub action: M,
+    /// The player who made it
+    pub player: PlayerId,
+    /// What the search found about the moves it weighed before making it, see [`crate::mcts::MCTS::root_action_stats`].
+    /// Empty for agents that don't search
+    pub stats: Vec<(M, ActionStats)>,
+}
+
+/// A played game, see the [module documentation](self)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord<G, M> {
+    pub start: G,
+    pub moves: Vec<RecordedMove<M>>,
+    /// The names of the agents who played, by player
+    pub players: Vec<String>,
+    /// The player who won, None for a draw or a game that was stopped
+    pub winner: Option<PlayerId>,
+}
+
+impl<G: Game> GameRecord<G, G::Move> {
+    /// Every position of the game, from the start to the final one
+    pub fn positions(&self) -> Vec<G> {
+        let mut positions = vec![self.start.clone()];
+        for mv in &self.moves {
+            let mut next = positions[positions.len() - 1].clone();
+            next.make_move(&mv.action);
+            positions.push(next);
+        }
+        positions
+    }
+
+    pub fn final_position(&self) -> G {
+        let mut position = self.start.clone();
+        for mv in &self.moves {
+            position.make_move(&mv.action);
+        }
+        position
+    }
+}
+
+/// Writes records as JSON lines, e.g. to a buffered file opened for appending
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct RecordWriter<W> {
+    writer: W,
+    written: usize,
+}
+
+#[cfg(feature = "serde")]
+impl<W: io::Write> RecordWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, written: 0 }
+    }
+
+    pub fn write<G, M>(&mut self, record: &GameRecord<G, M>) -> io::Result<()>
+    where
+        G: serde::Serialize,
+        M: serde::Serialize,
+    {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// The number of records written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads records written by [`RecordWriter`], one per line, skipping blank lines
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct RecordReader<R, G, M> {
+    lines: io::Lines<R>,
+    records: std::marker::PhantomData<(G, M)>,
+}
+
+#[cfg(feature = "serde")]
+impl<R: io::BufRead, G, M> RecordReader<R, G, M> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            records: std::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<R, G, M> Iterator for RecordReader<R, G, M>
+where
+    R: io::BufRead,
+    G: serde::de::DeserializeOwned,
+    M: serde::de::DeserializeOwned,
+{
+    type Item = Result<GameRecord<G, M>, PersistError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(error) => return Some(Err(PersistError::Io(error))),
+            };
+            if !line.trim().is_empty() {
+                return Some(serde_json::from_str(&line).map_err(PersistError::Json));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        budget::Budget,
+        envs::nim::Nim,
+        mcts::MCTS,
+        policy::RandomRollout,
+        selfplay::{Match, PolicyAgent},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_matches_are_recorded_move_by_move() {
+        let result = Match::new(Nim::new([2, 3]))
+            .with_games(2)
+            .with_agent("uct", Budget::Iterations(200), |mdp| {
+                MCTS::new(mdp, RandomRollout::new()).with_seed(1)
+            })
+            .with_agent("random", Budget::Iterations(1), |_| {
+                PolicyAgent::new(RandomRollout).with_seed(1)
+            })
+            .run();
+        let records = result.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].players, vec!["random", "uct"]);
+
+        for (record, game) in records.iter().zip(&result.games) {
+            assert_eq!(record.winner, game.winner);
+            let positions = record.positions();
+            assert_eq!(positions.len(), record.moves.len() + 1);
+            assert_eq!(positions.last(), Some(&record.final_position()));
+            assert_eq!(record.final_position(), game.final_position());
+            for (mv, position) in record.moves.iter().zip(&positions) {
+                assert_eq!(mv.player, position.current_player());
+                // only the search weighed its moves
+                let searched = record.players[mv.player] == "uct";
+                assert_eq!(!mv.stats.is_empty(), searched);
+                if searched {
+                    assert!(mv.stats.iter().any(|(action, _)| *action == mv.action));
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            let mut writer = RecordWriter::new(vec![]);
+            for record in &records {
+                writer.write(record).unwrap();
+            }
+            assert_eq!(writer.written(), 2);
+            let mut text = writer.into_inner();
+            text.extend_from_slice(b"\n");
+            let read = RecordReader::new(text.as_slice())
+                .collect::<Result<Vec<GameRecord<Nim, _>>, _>>()
+                .unwrap();
+            assert_eq!(read, records);
+            let broken = RecordReader::<_, Nim, crate::envs::nim::Take>::new(&b"{"[..]).next();
+            assert!(matches!(broken, Some(Err(PersistError::Json(_)))));
+        }
+    }
+}
@@ -0,0 +1,468 @@
+//! Values of states computed by an inference server rather than in-process (behind the `remote` feature),
+//! so that a lightweight client can search while a heavy model runs elsewhere.
+//!
+//! [`RemoteEvaluator`] posts the states as JSON to an HTTP endpoint, `{"states": [...]}`, and reads their values back,
+//! `{"values": [...]}`, in the same order. It is a [`BatchEvaluator`]: [`crate::mcts::MCTS::mcts_batched`] queues
+//! the leaves of the search and values them a batch at a time, in a single request, and rolls a batch out
+//! with the rollout policy when its request fails or times out. [`RemoteEvaluator::prefetch`] values states ahead
+//! of the search (e.g. every successor of the root), and every value the server sends is cached.
+//!
+//! Only plain HTTP/1.1 is spoken, without TLS: run the server next to the client, or behind a local proxy
+//!
+//! ```no_run
+//! use mct_rs::{budget::Budget, mcts::MCTS, mdp::FnMdp, policy::RandomRollout, remote::RemoteEvaluator};
+//!
+//! let mdp = FnMdp::new(|| 0u32, |_: &u32| vec![1usize, 2], |s, a, _| (s + *a as u32, 0.0, s + *a as u32 >= 10), |s| *s >= 10);
+//! let evaluator = RemoteEvaluator::new("http://[::1]:8000/values").unwrap();
+//! evaluator.prefetch(&[1, 2]);
+//!
+//! let mut mcts = MCTS::new(mdp, RandomRollout::new());
+//! mcts.mcts_batched(Budget::Iterations(100), &evaluator, 16);
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    hash::Hash,
+    io::{Read, Write},
+    net::{Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::value::BatchEvaluator;
+
+/// Why the server couldn't value a batch of states
+#[derive(Debug)]
+pub enum RemoteError {
+    /// The URL isn't an `http://host[:port]/path` one, IPv6 hosts being bracketed as in `http://[::1]:8000/`
+    Url(String),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The server answered with something other than the values of the states
+    Http(String),
+}
+
+impl Display for RemoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Url(url) => write!(f, "unsupported URL {url}"),
+            Self::Io(error) => write!(f, "couldn't reach the server: {error}"),
+            Self::Json(error) => write!(f, "invalid JSON: {error}"),
+            Self::Http(reason) => write!(f, "unexpected response: {reason}"),
+        }
+    }
+}
+
+impl Error for RemoteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Json(error) => Some(error),
+            Self::Url(_) | Self::Http(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RemoteError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a, S> {
+    states: &'a [S],
+}
+
+#[derive(Deserialize)]
+struct Response {
+    values: Vec<f64>,
+}
+
+/// The value of states as an inference server computes them, see the [module documentation](self)
+pub struct RemoteEvaluator<S> {
+    /// The name or address of the server, without the brackets of IPv6 addresses, and its port, to connect to
+    address: (String, u16),
+    /// `host[:port]` as in the URL, for the `Host` header
+    host: String,
+    path: String,
+    timeout: Duration,
+    batch_size: usize,
+    cache: RefCell<HashMap<S, f64>>,
+    requests: Cell<usize>,
+    failures: Cell<usize>,
+}
+
+impl<S> RemoteEvaluator<S>
+where
+    S: Clone + Eq + Hash + Serialize,
+{
+    /// Posts to `url`, e.g. `http://localhost:8000/values`, with batches of 64 states and a timeout of a second
+    pub fn new(url: &str) -> Result<Self, RemoteError> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| RemoteError::Url(url.to_string()))?;
+        let (host, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        let address = authority(host).ok_or_else(|| RemoteError::Url(url.to_string()))?;
+
+        Ok(Self {
+            address,
+            host: host.to_string(),
+            path: path.to_string(),
+            timeout: Duration::from_secs(1),
+            batch_size: 64,
+            cache: RefCell::new(HashMap::new()),
+            requests: Cell::new(0),
+            failures: Cell::new(0),
+        })
+    }
+
+    /// Gives up on connecting, sending or waiting for an answer after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends at most this many states per request
+    pub fn with_batch_size(mut self, states: usize) -> Self {
+        self.batch_size = states.max(1);
+        self
+    }
+
+    /// Values the states, in as many requests as there are batches, without going through the cache
+    pub fn try_evaluate_batch(&self, states: &[S]) -> Result<Vec<f64>, RemoteError> {
+        let mut values = Vec::with_capacity(states.len());
+        for batch in states.chunks(self.batch_size) {
+            values.extend(self.post(batch)?);
+        }
+        Ok(values)
+    }
+
+    /// Values the states that aren't cached yet, in batches, and caches them.
+    /// Returns the number of states the server couldn't value
+    pub fn prefetch(&self, states: &[S]) -> usize {
+        let missing = {
+            let cache = self.cache.borrow();
+            let mut seen = HashSet::new();
+            states
+                .iter()
+                .filter(|state| !cache.contains_key(state) && seen.insert(*state))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        let mut failed = 0;
+        for batch in missing.chunks(self.batch_size) {
+            match self.post(batch) {
+                Ok(values) => self
+                    .cache
+                    .borrow_mut()
+                    .extend(batch.iter().cloned().zip(values)),
+                Err(_) => {
+                    self.failures.set(self.failures.get() + 1);
+                    failed += batch.len();
+                }
+            }
+        }
+        failed
+    }
+
+    /// The number of requests sent so far, successful or not
+    pub fn requests(&self) -> usize {
+        self.requests.get()
+    }
+
+    /// The number of requests that failed so far, while searching or prefetching
+    pub fn failures(&self) -> usize {
+        self.failures.get()
+    }
+
+    /// Forgets the cached values, e.g. after the model behind the server changed
+    pub fn clear_cache(&self) {
+        self.cache.borrow_mut().clear();
+    }
+
+    /// Sends a batch of states, and reads their values back
+    fn post(&self, states: &[S]) -> Result<Vec<f64>, RemoteError> {
+        self.requests.set(self.requests.get() + 1);
+        let body = serde_json::to_vec(&Request { states }).map_err(RemoteError::Json)?;
+
+        let mut stream = self.connect()?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        let head = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.host,
+            body.len()
+        );
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&body)?;
+
+        let mut response = vec![];
+        stream.read_to_end(&mut response)?;
+        let body = http_body(&response)?;
+        let Response { values } = serde_json::from_slice(&body).map_err(RemoteError::Json)?;
+        match values.len() == states.len() {
+            true => Ok(values),
+            false => Err(RemoteError::Http(format!(
+                "{} values for {} states",
+                values.len(),
+                states.len()
+            ))),
+        }
+    }
+
+    /// Connects to the first of the server's addresses that answers in time
+    fn connect(&self) -> Result<TcpStream, RemoteError> {
+        let mut last = None;
+        let (name, port) = &self.address;
+        for address in (name.as_str(), *port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, self.timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(error) => last = Some(error),
+            }
+        }
+        Err(last.map_or_else(|| RemoteError::Url(self.host.clone()), RemoteError::Io))
+    }
+}
+
+impl<S> BatchEvaluator<S> for RemoteEvaluator<S>
+where
+    S: Clone + Eq + Hash + Serialize,
+{
+    type Error = RemoteError;
+
+    /// The cached values of the states, and the server's for the others (then cached), in batches of [`RemoteEvaluator::with_batch_size`]
+    fn try_value_batch(&self, states: &[S]) -> Result<Vec<f64>, RemoteError> {
+        let missing = {
+            let cache = self.cache.borrow();
+            let mut seen = HashSet::new();
+            states
+                .iter()
+                .filter(|state| !cache.contains_key(state) && seen.insert(*state))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        if !missing.is_empty() {
+            let values = self.try_evaluate_batch(&missing).inspect_err(|_| {
+                self.failures.set(self.failures.get() + 1);
+            })?;
+            self.cache
+                .borrow_mut()
+                .extend(missing.into_iter().zip(values));
+        }
+
+        let cache = self.cache.borrow();
+        Ok(states.iter().map(|state| cache[state]).collect())
+    }
+}
+
+/// Splits the `host[:port]` of a URL into the name or address to connect to and the port, 80 by default
+fn authority(host: &str) -> Option<(String, u16)> {
+    if let Ok(address) = host.parse::<SocketAddr>() {
+        return Some((address.ip().to_string(), address.port()));
+    }
+    if let Some(ip) = host
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        return ip.parse::<Ipv6Addr>().ok().map(|ip| (ip.to_string(), 80));
+    }
+    // names and IPv4 addresses, as unbracketed IPv6 ones are ambiguous
+    match host.split_once(':') {
+        Some((name, port)) if !name.is_empty() => Some((name.to_string(), port.parse().ok()?)),
+        Some(_) => None,
+        None => (!host.is_empty()).then(|| (host.to_string(), 80)),
+    }
+}
+
+/// The body of a successful HTTP response, whether it's sent whole or in chunks
+fn http_body(response: &[u8]) -> Result<Vec<u8>, RemoteError> {
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| RemoteError::Http("no end of headers".to_string()))?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let body = &response[split + 4..];
+
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(RemoteError::Http(status.to_string()));
+    }
+    let chunked = head.lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+    if !chunked {
+        return Ok(body.to_vec());
+    }
+
+    let mut decoded = vec![];
+    let mut rest = body;
+    loop {
+        let end = rest
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or_else(|| RemoteError::Http("truncated chunk".to_string()))?;
+        let size = String::from_utf8_lossy(&rest[..end]);
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or_default().trim(), 16)
+            .map_err(|_| RemoteError::Http(format!("invalid chunk size {size}")))?;
+        if size == 0 {
+            return Ok(decoded);
+        }
+        let chunk = rest
+            .get(end + 2..end + 2 + size)
+            .ok_or_else(|| RemoteError::Http("truncated chunk".to_string()))?;
+        decoded.extend_from_slice(chunk);
+        rest = rest.get(end + 4 + size..).unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use crate::{
+        budget::Budget,
+        mcts::MCTS,
+        mdp::{FnMdp, GenerativeModel},
+        policy::RandomRollout,
+    };
+
+    use super::*;
+
+    /// Serves `requests` requests on `address`, valuing every state at twice itself, and chunking the responses
+    fn serve(address: &str, requests: usize) -> String {
+        let listener = TcpListener::bind(address).unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut request = vec![];
+                let mut buffer = [0; 1024];
+                // the body ends with the closing brace of the JSON object
+                while !request.ends_with(b"}") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let start = request.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+                let states: serde_json::Value = serde_json::from_slice(&request[start..]).unwrap();
+                let values = states["states"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|state| state.as_f64().unwrap() * 2.0)
+                    .collect::<Vec<_>>();
+                let body = serde_json::json!({ "values": values }).to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{body}\r\n0\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{address}/values")
+    }
+
+    /// A URL nothing listens on
+    fn closed() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/values", listener.local_addr().unwrap())
+    }
+
+    /// Counts up by 1 or 2 from 0 to 30
+    fn count_up() -> impl GenerativeModel<u32, usize> {
+        FnMdp::new(
+            || 0u32,
+            |_: &u32| vec![1usize, 2],
+            |s, a, _| (s + *a as u32, 0.0, s + *a as u32 >= 30),
+            |s| *s >= 30,
+        )
+    }
+
+    #[test]
+    fn test_remote_values_are_batched_and_cached() {
+        let evaluator = RemoteEvaluator::new(&serve("127.0.0.1:0", 4))
+            .unwrap()
+            .with_batch_size(2)
+            .with_timeout(Duration::from_millis(200));
+
+        assert_eq!(evaluator.prefetch(&[1u32, 2, 3, 4, 5]), 0);
+        assert_eq!(evaluator.requests(), 3);
+        assert_eq!(evaluator.try_value_batch(&[3, 5]).unwrap(), vec![6.0, 10.0]);
+        assert_eq!(evaluator.requests(), 3);
+        assert_eq!(
+            evaluator.try_value_batch(&[1, 7, 7]).unwrap(),
+            vec![2.0, 14.0, 14.0]
+        );
+        assert_eq!(evaluator.requests(), 4);
+
+        // the server is gone after 4 requests
+        assert!(evaluator.try_value_batch(&[8]).is_err());
+        assert_eq!(evaluator.failures(), 1);
+
+        assert!(matches!(
+            RemoteEvaluator::<u32>::new("https://localhost/values"),
+            Err(RemoteError::Url(_))
+        ));
+    }
+
+    #[test]
+    fn test_remote_hosts_are_parsed_as_in_urls() {
+        assert_eq!(authority("localhost"), Some(("localhost".to_string(), 80)));
+        assert_eq!(
+            authority("localhost:8000"),
+            Some(("localhost".to_string(), 8000))
+        );
+        assert_eq!(
+            authority("10.0.0.1:8000"),
+            Some(("10.0.0.1".to_string(), 8000))
+        );
+        assert_eq!(authority("[::1]"), Some(("::1".to_string(), 80)));
+        assert_eq!(authority("[::1]:8000"), Some(("::1".to_string(), 8000)));
+        for invalid in ["", ":8000", "localhost:http", "::1", "[::1"] {
+            assert_eq!(authority(invalid), None, "{invalid}");
+        }
+
+        let evaluator = RemoteEvaluator::new(&serve("[::1]:0", 1)).unwrap();
+        assert_eq!(
+            evaluator.try_value_batch(&[1u32, 2]).unwrap(),
+            vec![2.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn test_batched_search_sends_a_request_per_batch() {
+        let evaluator = RemoteEvaluator::new(&serve("127.0.0.1:0", 5)).unwrap();
+
+        let mut mcts = MCTS::new(count_up(), RandomRollout::new()).with_seed(1);
+        let stats = mcts.mcts_batched(Budget::Iterations(40), &evaluator, 8);
+
+        assert_eq!(stats.iterations, 40);
+        assert_eq!(stats.simulations, 0);
+        assert!((1..=5).contains(&evaluator.requests()));
+        assert_eq!(evaluator.failures(), 0);
+    }
+
+    #[test]
+    fn test_batched_search_rolls_out_when_the_server_fails() {
+        let evaluator = RemoteEvaluator::new(&closed())
+            .unwrap()
+            .with_timeout(Duration::from_millis(200));
+
+        let mut mcts = MCTS::new(count_up(), RandomRollout::new()).with_seed(1);
+        let stats = mcts.mcts_batched(Budget::Iterations(40), &evaluator, 8);
+
+        assert_eq!(stats.iterations, 40);
+        assert_eq!(stats.simulations, 40);
+        assert_eq!(evaluator.failures(), 5);
+    }
+}
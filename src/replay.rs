@@ -0,0 +1,139 @@
+//! Record-and-replay of every random draw a search makes.
+//!
+//! Wrap the searcher's generator in a [`Recorder`] to capture every draw (tie-breaks, rollout choices, transition samples) into an [`RngLog`].
+//! Feeding that log to a [`Replayer`] reproduces the exact same search, which makes rare crashes or blunders reported from production debuggable.
+//! The replayed search must do the same amount of work as the recorded one, i.e. replay it with `Budget::Iterations`.
+//! For searches that were bounded by time, use one less than the number of iterations they ran for (see [`crate::mcts::MCTS::iterations`]),
+//! since the time running out might have cut the last rollout short
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::rand::Rng;
+
+/// The sequence of random draws made by a search
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RngLog {
+    draws: Vec<u64>,
+}
+
+impl RngLog {
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+
+    /// Encodes the log as 8 little-endian bytes per draw
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.draws.iter().flat_map(|d| d.to_le_bytes()).collect()
+    }
+
+    /// Decodes a log produced by [`RngLog::to_bytes`], returns None if the bytes are not a whole number of draws
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let chunks = bytes.chunks_exact(8);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+
+        let draws = chunks
+            .map(|c| u64::from_le_bytes(c.try_into().expect("chunks are 8 bytes long")))
+            .collect();
+        Some(Self { draws })
+    }
+}
+
+/// Forwards every draw from the wrapped generator, keeping a copy of each in a log
+#[derive(Debug)]
+pub struct Recorder<R> {
+    inner: R,
+    log: Rc<RefCell<RngLog>>,
+}
+
+impl<R: Rng> Recorder<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            log: Rc::new(RefCell::new(RngLog::default())),
+        }
+    }
+
+    /// A handle on the log, which stays readable after the recorder has been handed over to the searcher
+    pub fn log(&self) -> Rc<RefCell<RngLog>> {
+        Rc::clone(&self.log)
+    }
+}
+
+impl<R: Rng> Rng for Recorder<R> {
+    fn next_u64(&mut self) -> u64 {
+        let value = self.inner.next_u64();
+        self.log.borrow_mut().draws.push(value);
+        value
+    }
+}
+
+/// Plays back the draws of a recorded log, in order
+#[derive(Debug)]
+pub struct Replayer {
+    log: RngLog,
+    position: usize,
+}
+
+impl Replayer {
+    pub fn new(log: RngLog) -> Self {
+        Self { log, position: 0 }
+    }
+
+    /// Number of draws that haven't been replayed yet
+    pub fn remaining(&self) -> usize {
+        self.log.len() - self.position
+    }
+}
+
+impl Rng for Replayer {
+    fn next_u64(&mut self) -> u64 {
+        let Some(value) = self.log.draws.get(self.position) else {
+            panic!(
+                "replay log exhausted after {} draws: the replayed search diverged from the recorded one",
+                self.position
+            );
+        };
+
+        self.position += 1;
+        *value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rand::SplitMix64;
+
+    use super::*;
+
+    #[test]
+    fn test_replayer_reproduces_recorded_draws() {
+        let mut recorder = Recorder::new(SplitMix64::seed(3));
+        let log = recorder.log();
+
+        let recorded = (0..100)
+            .map(|i| recorder.gen_range(0, i + 1))
+            .collect::<Vec<_>>();
+
+        let bytes = log.borrow().to_bytes();
+        let mut replayer = Replayer::new(RngLog::from_bytes(&bytes).unwrap());
+        let replayed = (0..100)
+            .map(|i| replayer.gen_range(0, i + 1))
+            .collect::<Vec<_>>();
+
+        assert_eq!(recorded, replayed);
+        assert_eq!(replayer.remaining(), 0);
+        assert!(RngLog::from_bytes(&bytes[1..]).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "replay log exhausted")]
+    fn test_replayer_panics_when_exhausted() {
+        Replayer::new(RngLog::default()).next_u64();
+    }
+}
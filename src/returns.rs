@@ -0,0 +1,53 @@
+use crate::mdp::PlayerId;
+
+/// The returns of one iteration of the search, for every player, as they are backed up the tree
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Returns {
+    /// One- and two-player games: the return from player 0's perspective,
+    /// which any other player sees negated, i.e. the game is taken to be zero-sum (negamax)
+    ZeroSum(f64),
+    /// Games with more than two players: one return per player, each of them maximizing their own (max^n)
+    MaxN(Vec<f64>),
+}
+
+impl Returns {
+    /// No returns yet, for a game with this many players (see [`crate::mdp::GenerativeModel::num_players`])
+    pub(crate) fn new(players: usize) -> Self {
+        if players > 2 {
+            Self::MaxN(vec![0.0; players])
+        } else {
+            Self::ZeroSum(0.0)
+        }
+    }
+
+    /// Credits the reward of a transition to the player who took it
+    pub(crate) fn credit(&mut self, mover: PlayerId, reward: f64) {
+        match self {
+            Self::ZeroSum(value) => *value += if mover == 0 { reward } else { -reward },
+            Self::MaxN(values) => values[mover] += reward,
+        }
+    }
+
+    /// Discounts the returns by one step, i.e. from the state they were accumulated from to the one before it
+    pub(crate) fn discount(&mut self, factor: f64) {
+        match self {
+            Self::ZeroSum(value) => *value *= factor,
+            Self::MaxN(values) => values.iter_mut().for_each(|v| *v *= factor),
+        }
+    }
+
+    /// The return from `player`'s perspective
+    pub(crate) fn of(&self, player: PlayerId) -> f64 {
+        match self {
+            Self::ZeroSum(value) if player == 0 => *value,
+            Self::ZeroSum(value) => -*value,
+            Self::MaxN(values) => values[player],
+        }
+    }
+}
+
+impl From<f64> for Returns {
+    fn from(value: f64) -> Self {
+        Self::ZeroSum(value)
+    }
+}
@@ -0,0 +1,577 @@
+//! Real-Time Dynamic Programming (Barto, Bradtke & Singh, 1995): an anytime planner that runs greedy trials from the current state,
+//! applying the Bellman optimality update to every state it goes through.
+//!
+//! Only the states reachable by acting (near-)optimally are ever backed up, which makes it much cheaper than value iteration
+//! on goal-directed problems, and often better than UCT on stochastic shortest path ones, where every step costs something.
+//! It converges on the optimal values of the states it visits as long as the heuristic values of the states it hasn't visited
+//! are upper bounds on their actual values (e.g. 0, the default, when every reward is a cost).
+//!
+//! The backups need the outcomes of every action: [`RTDP::new`] estimates them from samples of any [`GenerativeModel`],
+//! and [`RTDP::explicit`] reads them off an [`ExplicitMDP`]. Either way, they're only queried once per state.
+//! Single-agent only: the values are those of player 0.
+//!
+//! With [`RTDP::with_labeling`], it becomes Labeled RTDP (Bonet & Geffner, 2003): states whose values, and those of every state
+//! the greedy policy can reach from them, have settled within ε are labeled solved. Trials stop at solved states,
+//! and the search ends as soon as the current state is solved, with the values of the reachable states ε-consistent
+//! (optimal on small MDPs, under an admissible heuristic)
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    rc::Rc,
+};
+
+use crate::{
+    action::Action,
+    budget::{Budget, Tracker},
+    clock::{Clock, SystemClock},
+    error::{McstError, MdpError},
+    mdp::{ExplicitMDP, GenerativeModel, checked_execute},
+    rand::{Rng, SplitMix64},
+    value::{TabularValues, ValueFunction},
+};
+
+/// What taking an action can lead to: (next state, probability, expected reward, whether the episode ends there)
+type Outcome<S> = (S, f64, f64, bool);
+
+/// The actions of a state, with their outcomes
+type Actions<S, A> = Vec<(A, Vec<Outcome<S>>)>;
+
+/// Values the states not backed up yet, see [`RTDP::with_heuristic`]
+type Heuristic<S> = dyn Fn(&S) -> f64;
+
+/// How the outcomes of an action are found, see [`RTDP::new`] and [`RTDP::explicit`]
+type OutcomesOf<M, S, A> =
+    fn(&M, &S, &A, usize, &mut dyn Rng) -> Result<Vec<Outcome<S>>, McstError>;
+
+/// Estimates the outcomes from `samples` draws of the model, averaging the rewards of every next state
+fn sampled<M, S, A>(
+    mdp: &M,
+    state: &S,
+    action: &A,
+    samples: usize,
+    rng: &mut dyn Rng,
+) -> Result<Vec<Outcome<S>>, McstError>
+where
+    M: GenerativeModel<S, A>,
+    S: Clone + Eq + Hash,
+{
+    let samples = samples.max(1);
+    let mut outcomes: Vec<Outcome<S>> = vec![];
+    let mut index = HashMap::<S, usize>::new();
+    for _ in 0..samples {
+        let (next, reward, done) = checked_execute(mdp, state, action, rng)?;
+        let done = done || mdp.is_terminal(&next);
+        match index.get(&next) {
+            // the counts and reward totals for now, turned into probabilities and means below
+            Some(&i) => {
+                let outcome = &mut outcomes[i];
+                outcome.1 += 1.0;
+                outcome.2 += reward;
+                outcome.3 |= done;
+            }
+            None => {
+                index.insert(next.clone(), outcomes.len());
+                outcomes.push((next, 1.0, reward, done));
+            }
+        }
+    }
+
+    for outcome in &mut outcomes {
+        outcome.2 /= outcome.1;
+        outcome.1 /= samples as f64;
+    }
+    Ok(outcomes)
+}
+
+/// Reads the outcomes off the transitions of the MDP
+fn enumerated<M, S, A>(
+    mdp: &M,
+    state: &S,
+    action: &A,
+    _samples: usize,
+    _rng: &mut dyn Rng,
+) -> Result<Vec<Outcome<S>>, McstError>
+where
+    M: ExplicitMDP<S, A>,
+{
+    let transitions = mdp.get_transitions(state, action);
+    if transitions.is_empty() {
+        return Err(MdpError::NoTransitions.into());
+    }
+
+    transitions
+        .into_iter()
+        .map(|(next, p)| {
+            let reward = mdp.get_reward(state, action, &next);
+            if reward.is_nan() {
+                return Err(McstError::NotANumber("a reward of the model"));
+            }
+            let done = mdp.is_terminal(&next);
+            Ok((next, p, reward, done))
+        })
+        .collect()
+}
+
+/// The work done by an [`RTDP`] planner, over all its searches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RtdpStats {
+    /// The number of trials run
+    pub trials: usize,
+    /// The number of Bellman backups applied
+    pub backups: usize,
+    /// The number of states whose outcomes are known, i.e. that trials went through
+    pub states: usize,
+    /// The number of those that are labeled solved, see [`RTDP::with_labeling`]
+    pub solved: usize,
+}
+
+impl RtdpStats {
+    /// The share of the known states that are solved, 0 before the first search
+    pub fn solved_fraction(&self) -> f64 {
+        match self.states {
+            0 => 0.0,
+            states => self.solved as f64 / states as f64,
+        }
+    }
+}
+
+/// Plans by Real-Time Dynamic Programming, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{budget::Budget, rtdp::RTDP, tabular::TabularMDP};
+///
+/// // walking from 0 to the goal 2 costs 1 a step, running gets there at once but only works half of the time
+/// let mdp = TabularMDP::new(
+///     vec![
+///         vec![vec![0.0, 1.0, 0.0], vec![0.5, 0.0, 0.5]],
+///         vec![vec![0.0, 0.0, 1.0], vec![0.0, 0.0, 1.0]],
+///         vec![vec![0.0; 3], vec![0.0; 3]],
+///     ],
+///     vec![vec![-1.0, -1.0], vec![-1.0, -1.0], vec![0.0, 0.0]],
+/// )
+/// .with_terminal_states([2]);
+///
+/// let mut rtdp = RTDP::explicit(mdp).with_seed(1);
+/// rtdp.search(Budget::Iterations(200));
+/// assert!((rtdp.value(&0) + 2.0).abs() < 1e-6);
+/// ```
+pub struct RTDP<M, S, A> {
+    mdp: M,
+    root: S,
+    outcomes_of: OutcomesOf<M, S, A>,
+    /// Draws per action of the sampled model, see [`RTDP::with_samples`]
+    samples: usize,
+    /// V(s) of the states backed up so far
+    values: HashMap<S, f64>,
+    /// The actions of every state trials went through and their outcomes, so that backups don't query the MDP again
+    model: HashMap<S, Actions<S, A>>,
+    /// The values of the states not backed up yet
+    heuristic: Option<Box<Heuristic<S>>>,
+    max_trial_length: usize,
+    /// ε of the labeling, if any, see [`RTDP::with_labeling`]
+    labeling: Option<f64>,
+    solved: HashSet<S>,
+    stats: RtdpStats,
+    clock: Rc<dyn Clock>,
+    rng: Box<dyn Rng>,
+}
+
+impl<M, S, A> RTDP<M, S, A>
+where
+    M: GenerativeModel<S, A>,
+    S: Clone + Eq + Hash,
+    A: Action,
+{
+    /// Plans from the MDP's initial state, estimating the outcomes of every action from 20 samples of it.
+    /// Trials are cut short after 1000 actions, and states not backed up yet are worth 0
+    pub fn new(mdp: M) -> Self {
+        Self::with_outcomes(mdp, sampled::<M, S, A>)
+    }
+
+    fn with_outcomes(mdp: M, outcomes_of: OutcomesOf<M, S, A>) -> Self {
+        let root = mdp.get_initial_state();
+        Self {
+            mdp,
+            root,
+            outcomes_of,
+            samples: 20,
+            values: HashMap::new(),
+            model: HashMap::new(),
+            heuristic: None,
+            max_trial_length: 1000,
+            labeling: None,
+            solved: HashSet::new(),
+            stats: RtdpStats::default(),
+            clock: Rc::new(SystemClock),
+            rng: Box::new(SplitMix64::from_entropy()),
+        }
+    }
+
+    /// Draws all the randomness of the planner from the given generator, see [`crate::mcts::MCTS::with_rng`]
+    pub fn with_rng<R: Rng + 'static>(mut self, rng: R) -> Self {
+        self.rng = Box::new(rng);
+        self
+    }
+
+    /// Makes the planner deterministic, see [`crate::mcts::MCTS::with_seed`]
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(SplitMix64::seed(seed))
+    }
+
+    /// Reads the time from the given clock, for time budgets, see [`crate::mcts::MCTS::with_clock`]
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Rc::new(clock);
+        self
+    }
+
+    /// Estimates the outcomes of every action from this many samples rather than 20.
+    /// Ignored with [`RTDP::explicit`], which knows them exactly
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Values the states not backed up yet with `heuristic` rather than 0.
+    /// Trials only converge on the optimal values if it never underestimates them
+    pub fn with_heuristic<V: ValueFunction<S> + 'static>(mut self, heuristic: V) -> Self {
+        self.heuristic = Some(Box::new(move |state: &S| heuristic.value(state)));
+        self
+    }
+
+    /// Cuts trials short after this many actions, for MDPs whose episodes are long or endless
+    pub fn with_max_trial_length(mut self, actions: usize) -> Self {
+        self.max_trial_length = actions;
+        self
+    }
+
+    /// Labels states solved once their values and those of the states the greedy policy reaches from them
+    /// change by no more than `epsilon` in a backup (LRTDP, see the [module documentation](self)).
+    /// The search then stops early once the current state is solved, see [`RTDP::is_solved`]
+    pub fn with_labeling(mut self, epsilon: f64) -> Self {
+        self.labeling = Some(epsilon);
+        self
+    }
+
+    /// Plans from another state, e.g. the one reached after acting. The values learned so far are kept
+    pub fn set_root_state(&mut self, state: S) {
+        self.root = state;
+    }
+
+    /// Runs trials from the current state until the budget is used up, each iteration of the budget being one trial,
+    /// or until the state is solved with [`RTDP::with_labeling`].
+    ///
+    /// Panics if the MDP fails, see [`RTDP::try_search`]
+    pub fn search(&mut self, budget: Budget) {
+        match self.try_search(budget) {
+            Ok(()) | Err(McstError::TerminalRoot { .. }) => {}
+            Err(error) => panic!("search failed: {error}"),
+        }
+    }
+
+    /// Same as [`RTDP::search`], but stops at the first failure of the MDP and returns it
+    pub fn try_search(&mut self, budget: Budget) -> Result<(), McstError> {
+        if self.mdp.is_terminal(&self.root) {
+            return Err(McstError::TerminalRoot {
+                value: self.value(&self.root),
+            });
+        }
+
+        let clock = Rc::clone(&self.clock);
+        let tracker = Tracker::start(budget, &*clock);
+        let mut trials = 0;
+        let mut outcome = Ok(());
+        while outcome.is_ok() && !tracker.exhausted(trials) && !self.solved.contains(&self.root) {
+            trials += 1;
+            outcome = self.trial(&tracker);
+        }
+
+        self.stats.states = self.model.len();
+        self.stats.solved = self.solved.len();
+        outcome
+    }
+
+    /// Acts greedily from the root, backing up every state on the way, until the episode ends (or a solved state is reached).
+    /// With labeling, then checks whether the states of the trial are solved, from the last one back
+    fn trial(&mut self, tracker: &Tracker<'_>) -> Result<(), McstError> {
+        self.stats.trials += 1;
+        let mut state = self.root.clone();
+        let mut visited = vec![];
+
+        for _ in 0..self.max_trial_length {
+            if self.solved.contains(&state) {
+                break;
+            }
+            visited.push(state.clone());
+            let best = self.backup(&state)?;
+            if tracker.out_of_time() {
+                break;
+            }
+
+            let outcomes = &self.model[&state][best].1;
+            let r = self.rng.gen_f64();
+            let mut cumulative = 0.0;
+            let next = outcomes
+                .iter()
+                .find(|outcome| {
+                    cumulative += outcome.1;
+                    cumulative >= r
+                })
+                .unwrap_or(&outcomes[outcomes.len() - 1]);
+            if next.3 {
+                break;
+            }
+            state = next.0.clone();
+        }
+
+        if let Some(epsilon) = self.labeling {
+            while let Some(state) = visited.pop() {
+                if tracker.out_of_time() || !self.check_solved(state, epsilon)? {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Labels the state and every state the greedy policy reaches from it solved if none of their residuals exceeds ε,
+    /// and backs them all up otherwise. Returns whether they were labeled
+    fn check_solved(&mut self, state: S, epsilon: f64) -> Result<bool, McstError> {
+        let mut consistent = true;
+        let mut open = vec![];
+        let mut closed = vec![];
+        let mut seen = HashSet::new();
+        if !self.solved.contains(&state) {
+            seen.insert(state.clone());
+            open.push(state);
+        }
+
+        while let Some(state) = open.pop() {
+            let (best, max) = self.greedy(&state)?;
+            let residual = (max - self.value(&state)).abs();
+            closed.push(state);
+            if residual > epsilon {
+                consistent = false;
+                continue;
+            }
+
+            let state = &closed[closed.len() - 1];
+            for (next, p, _, done) in &self.model[state][best].1 {
+                if *p > 0.0 && !done && !self.solved.contains(next) && seen.insert(next.clone()) {
+                    open.push(next.clone());
+                }
+            }
+        }
+
+        if consistent {
+            self.solved.extend(closed);
+        } else {
+            while let Some(state) = closed.pop() {
+                self.backup(&state)?;
+            }
+        }
+        Ok(consistent)
+    }
+
+    /// Whether the state is labeled solved, see [`RTDP::with_labeling`]
+    pub fn is_solved(&self, state: &S) -> bool {
+        self.mdp.is_terminal(state) || self.solved.contains(state)
+    }
+
+    /// Learns the outcomes of the actions of the state if it's new
+    fn explore(&mut self, state: &S) -> Result<(), McstError> {
+        if self.model.contains_key(state) {
+            return Ok(());
+        }
+
+        let actions = self.mdp.get_actions(state);
+        if actions.is_empty() {
+            return Err(MdpError::NoActions.into());
+        }
+        let actions = actions
+            .into_iter()
+            .map(|action| {
+                let outcomes =
+                    (self.outcomes_of)(&self.mdp, state, &action, self.samples, &mut *self.rng)?;
+                Ok((action, outcomes))
+            })
+            .collect::<Result<Vec<_>, McstError>>()?;
+        self.model.insert(state.clone(), actions);
+        Ok(())
+    }
+
+    /// The index of the best action of the state, ties broken at random, and its Q-value
+    fn greedy(&mut self, state: &S) -> Result<(usize, f64), McstError> {
+        self.explore(state)?;
+
+        let q_values = self.q_values(state);
+        let max = q_values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let best = (0..q_values.len())
+            .filter(|&i| q_values[i] == max)
+            .collect::<Vec<_>>();
+        let best = match best.len() {
+            0 => 0,
+            n => best[self.rng.gen_range(0, n)],
+        };
+        Ok((best, max))
+    }
+
+    /// Sets V(s) to the best of its Q-values, and returns the index of the best action
+    fn backup(&mut self, state: &S) -> Result<usize, McstError> {
+        let (best, max) = self.greedy(state)?;
+        self.values.insert(state.clone(), max);
+        self.stats.backups += 1;
+        Ok(best)
+    }
+
+    /// Q(s, a) = Σ P(s'|s, a) · (R + γ·V(s')) of every action of a state whose outcomes are known
+    fn q_values(&self, state: &S) -> Vec<f64> {
+        let discount = self.mdp.get_discount_factor();
+        self.model[state]
+            .iter()
+            .map(|(_, outcomes)| {
+                outcomes
+                    .iter()
+                    .map(|(next, p, reward, done)| {
+                        let value = if *done { 0.0 } else { self.value(next) };
+                        p * (reward + discount * value)
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// V(s) as learned so far, or its heuristic value if no trial backed it up yet
+    pub fn value(&self, state: &S) -> f64 {
+        if self.mdp.is_terminal(state) {
+            return 0.0;
+        }
+        match self.values.get(state) {
+            Some(value) => *value,
+            None => self
+                .heuristic
+                .as_ref()
+                .map_or(0.0, |heuristic| heuristic(state)),
+        }
+    }
+
+    /// The greedy action in the current state, or None before the first search from it
+    pub fn best_action(&self) -> Option<A> {
+        self.action_values()
+            .into_iter()
+            .fold(None, |best: Option<(A, f64)>, (action, q)| match best {
+                Some(best) if best.1 >= q => Some(best),
+                _ => Some((action, q)),
+            })
+            .map(|(action, _)| action)
+    }
+
+    /// Q(s, a) of every action of the current state, or nothing before the first search from it
+    pub fn action_values(&self) -> Vec<(A, f64)> {
+        match self.model.get(&self.root) {
+            Some(actions) => actions
+                .iter()
+                .map(|(action, _)| action.clone())
+                .zip(self.q_values(&self.root))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// The values of every state backed up so far, e.g. to warm-start a tree search, see [`crate::mcts::MCTS::with_warm_start`]
+    pub fn values(&self) -> TabularValues<S> {
+        self.values.iter().map(|(s, v)| (s.clone(), *v)).collect()
+    }
+
+    pub fn stats(&self) -> RtdpStats {
+        self.stats
+    }
+}
+
+impl<M, S, A> RTDP<M, S, A>
+where
+    M: ExplicitMDP<S, A>,
+    S: Clone + Eq + Hash,
+    A: Action,
+{
+    /// Same as [`RTDP::new`], with the exact outcomes of every action, as listed by [`ExplicitMDP::get_transitions`]
+    pub fn explicit(mdp: M) -> Self {
+        Self::with_outcomes(mdp, enumerated::<M, S, A>)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{solvers::value_iteration::ValueIteration, tabular::TabularMDP};
+
+    use super::*;
+
+    /// A line from 0 to the goal 4, where walking costs 1 a step
+    /// and running costs 1.5 to go two steps at once, failing a tenth of the time
+    fn line() -> TabularMDP {
+        let transitions = (0..5)
+            .map(|s| {
+                let to = |targets: &[(usize, f64)]| {
+                    let mut row = vec![0.0; 5];
+                    targets.iter().for_each(|(t, p)| row[*t] += p);
+                    row
+                };
+                match s {
+                    4 => vec![vec![0.0; 5], vec![0.0; 5]],
+                    s => vec![to(&[(s + 1, 1.0)]), to(&[((s + 2).min(4), 0.9), (s, 0.1)])],
+                }
+            })
+            .collect();
+        let rewards = (0..5).map(|_| vec![-1.0, -1.5]).collect();
+        TabularMDP::new(transitions, rewards).with_terminal_states([4])
+    }
+
+    #[test]
+    fn test_rtdp_finds_the_shortest_paths() {
+        let mdp = line();
+        let solution = ValueIteration::new().solve(&mdp);
+
+        let mut rtdp = RTDP::explicit(mdp.clone()).with_seed(1);
+        rtdp.search(Budget::Iterations(300));
+        assert!((rtdp.value(&0) - solution.value(&0).unwrap()).abs() < 1e-6);
+        assert_eq!(rtdp.best_action().as_ref(), solution.action(&0));
+        assert_eq!(rtdp.stats().trials, 300);
+        assert!(rtdp.stats().states <= 4);
+
+        let mut sampled = RTDP::new(mdp).with_seed(1).with_samples(200);
+        sampled.search(Budget::Iterations(300));
+        assert!((sampled.value(&0) - solution.value(&0).unwrap()).abs() < 0.2);
+        assert_eq!(sampled.best_action().as_ref(), solution.action(&0));
+
+        sampled.set_root_state(4);
+        assert!(matches!(
+            sampled.try_search(Budget::Iterations(1)),
+            Err(McstError::TerminalRoot { value: 0.0 })
+        ));
+    }
+
+    #[test]
+    fn test_labeled_rtdp_stops_once_solved() {
+        let mdp = line();
+        let solution = ValueIteration::new().solve(&mdp);
+
+        let mut rtdp = RTDP::explicit(mdp).with_seed(1).with_labeling(1e-9);
+        rtdp.search(Budget::Iterations(10_000));
+        assert!(rtdp.is_solved(&0));
+        assert!(rtdp.stats().trials < 10_000);
+        // states only tried on the way, off the optimal paths, aren't labeled
+        let stats = rtdp.stats();
+        assert!(stats.solved_fraction() > 0.5 && stats.solved < stats.states);
+        for state in 0..4 {
+            if rtdp.is_solved(&state) {
+                assert!((rtdp.value(&state) - solution.value(&state).unwrap()).abs() < 1e-6);
+            }
+        }
+
+        // nothing left to do
+        let trials = rtdp.stats().trials;
+        rtdp.search(Budget::Iterations(10));
+        assert_eq!(rtdp.stats().trials, trials);
+    }
+}
@@ -0,0 +1,430 @@
+//! Matches between two agents on a two-player [`Game`]: any [`Planner`], e.g. a searcher, or a fixed policy through [`PolicyAgent`].
+//!
+//! The agents take turns playing first, a fresh planner for every game, and each decides every move within its own budget.
+//! Every game is returned with its moves and what the agents found about them, so it can be replayed
+//! (see [`PlayedGame::final_position`]) or stored for later (see [`PlayedGame::record`])
+//!
+//! ```
+//! use mct_rs::{
+//!     budget::Budget,
+//!     envs::nim::Nim,
+//!     mcts::MCTS,
+//!     policy::RandomRollout,
+//!     selfplay::{Match, PolicyAgent},
+//! };
+//!
+//! let result = Match::new(Nim::new([3, 4, 5]))
+//!     .with_games(4)
+//!     .with_agent("uct", Budget::Iterations(500), |mdp| {
+//!         MCTS::new(mdp, RandomRollout::new()).with_seed(1).with_transpositions()
+//!     })
+//!     .with_agent("random", Budget::Iterations(1), |_| PolicyAgent::new(RandomRollout).with_seed(1))
+//!     .run();
+//!
+//! println!("{result}");
+//! assert!(result.score(0) > result.score(1));
+//! ```
+
+use std::fmt::Display;
+
+use crate::{
+    budget::Budget,
+    compare::Planner,
+    error::{McstError, MdpError},
+    game::{Game, GameMdp},
+    mdp::PlayerId,
+    policy::RolloutPolicy,
+    rand::{Rng, SplitMix64},
+    record::{GameRecord, RecordedMove},
+    stats::ActionStats,
+    tournament::EloEstimate,
+};
+
+/// An agent that plays the moves its policy picks, without searching, e.g. [`crate::policy::RandomRollout`]
+/// for a random player or [`crate::policy::PolicyRollout`] for a handwritten one
+pub struct PolicyAgent<P> {
+    policy: P,
+    rng: Box<dyn Rng>,
+}
+
+impl<P> PolicyAgent<P> {
+    pub fn new(policy: P) -> Self {
+        Self {
+            policy,
+            rng: Box::new(SplitMix64::from_entropy()),
+        }
+    }
+
+    /// Draws the randomness of the policy from the given generator, see [`crate::mcts::MCTS::with_rng`]
+    pub fn with_rng<R: Rng + 'static>(mut self, rng: R) -> Self {
+        self.rng = Box::new(rng);
+        self
+    }
+
+    /// Makes the agent deterministic, see [`crate::mcts::MCTS::with_seed`]
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(SplitMix64::seed(seed))
+    }
+}
+
+impl<G, P> Planner<G, G::Move> for PolicyAgent<P>
+where
+    G: Game,
+    P: RolloutPolicy<GameMdp<G>, G, G::Move>,
+{
+    fn plan(&mut self, state: &G, _budget: Budget) -> Result<Option<G::Move>, McstError> {
+        let moves = state.legal_moves();
+        if moves.is_empty() || state.winner().is_some() {
+            return Ok(None);
+        }
+        Ok(Some(self.policy.pick(state, &moves, &mut *self.rng)))
+    }
+}
+
+/// A game of the match
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayedGame<G: Game> {
+    pub start: G,
+    pub moves: Vec<G::Move>,
+    /// What the agent found about the moves it weighed, before every move (see [`Planner::root_stats`])
+    pub stats: Vec<Vec<(G::Move, ActionStats)>>,
+    /// The agents playing players 0 and 1, by the order they were added to the match
+    pub seats: [usize; 2],
+    /// The player who won, None for a draw, or for a game stopped after the match's maximum number of moves
+    pub winner: Option<PlayerId>,
+}
+
+impl<G: Game> PlayedGame<G> {
+    /// The agent who won, None for a draw
+    pub fn winning_agent(&self) -> Option<usize> {
+        self.winner.map(|player| self.seats[player])
+    }
+
+    /// The position the moves led to
+    pub fn final_position(&self) -> G {
+        let mut position = self.start.clone();
+        for mv in &self.moves {
+            position.make_move(mv);
+        }
+        position
+    }
+
+    /// The game as a record, with the names of its agents (e.g. [`MatchResult::agents`]), to store it, see [`GameRecord`]
+    pub fn record(&self, agents: &[String]) -> GameRecord<G, G::Move> {
+        let mut position = self.start.clone();
+        let moves = self
+            .moves
+            .iter()
+            .zip(&self.stats)
+            .map(|(mv, stats)| {
+                let player = position.current_player();
+                position.make_move(mv);
+                RecordedMove {
+                    action: mv.clone(),
+                    player,
+                    stats: stats.clone(),
+                }
+            })
+            .collect();
+        GameRecord {
+            start: self.start.clone(),
+            players: self.seats.map(|agent| agents[agent].clone()).to_vec(),
+            moves,
+            winner: self.winner,
+        }
+    }
+}
+
+/// The games of a match, see [`Match::run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchResult<G: Game> {
+    /// The names of the agents
+    pub agents: [String; 2],
+    pub games: Vec<PlayedGame<G>>,
+}
+
+impl<G: Game> MatchResult<G> {
+    /// The number of games the agent won
+    pub fn wins(&self, agent: usize) -> usize {
+        self.games
+            .iter()
+            .filter(|game| game.winning_agent() == Some(agent))
+            .count()
+    }
+
+    pub fn draws(&self) -> usize {
+        self.games
+            .iter()
+            .filter(|game| game.winner.is_none())
+            .count()
+    }
+
+    /// The agent's share of the points, a win counting 1 and a draw 1/2. 0.5 without games
+    pub fn score(&self, agent: usize) -> f64 {
+        if self.games.is_empty() {
+            return 0.5;
+        }
+        (self.wins(agent) as f64 + self.draws() as f64 / 2.0) / self.games.len() as f64
+    }
+
+    /// Every game as a record, see [`PlayedGame::record`]
+    pub fn records(&self) -> Vec<GameRecord<G, G::Move>> {
+        self.games
+            .iter()
+            .map(|game| game.record(&self.agents))
+            .collect()
+    }
+
+    /// How much stronger the first agent is than the second, see [`EloEstimate::from_results`]
+    pub fn elo(&self) -> EloEstimate {
+        EloEstimate::from_results(self.wins(0), self.draws(), self.wins(1))
+    }
+}
+
+impl<G: Game> Display for MatchResult<G> {
+    /// A single line of `key=value` pairs, like [`crate::bench::BenchReport`]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "agents={}/{} games={} wins={}/{} draws={} score={:.3}",
+            self.agents[0],
+            self.agents[1],
+            self.games.len(),
+            self.wins(0),
+            self.wins(1),
+            self.draws(),
+            self.score(0)
+        )
+    }
+}
+
+/// Builds a fresh planner for every game
+type AgentFactory<'a, G> = dyn Fn(GameMdp<G>) -> Box<dyn Planner<G, <G as Game>::Move> + 'a> + 'a;
+
+/// A named planner factory, with the budget of every move
+pub(crate) struct Agent<'a, G: Game> {
+    pub(crate) name: String,
+    pub(crate) budget: Budget,
+    pub(crate) create: Box<AgentFactory<'a, G>>,
+}
+
+impl<'a, G: Game + 'a> Agent<'a, G> {
+    pub(crate) fn new<P, F>(name: &str, budget: Budget, create: F) -> Self
+    where
+        P: Planner<G, G::Move> + 'a,
+        F: Fn(GameMdp<G>) -> P + 'a,
+    {
+        let create =
+            move |mdp: GameMdp<G>| Box::new(create(mdp)) as Box<dyn Planner<G, G::Move> + 'a>;
+        Self {
+            name: name.to_string(),
+            budget,
+            create: Box::new(create),
+        }
+    }
+}
+
+/// Plays a game from `start`, with the agents of `seats` playing players 0 and 1, stopped after `max_moves` moves
+pub(crate) fn play<G: Game>(
+    start: &G,
+    agents: &[Agent<'_, G>],
+    seats: [usize; 2],
+    max_moves: usize,
+) -> Result<PlayedGame<G>, McstError> {
+    let mdp = GameMdp::new(start.clone());
+    let mut planners = seats.map(|agent| (agents[agent].create)(mdp.clone()));
+    let mut position = start.clone();
+    let mut moves = vec![];
+    let mut stats = vec![];
+
+    while moves.len() < max_moves
+        && position.winner().is_none()
+        && !position.legal_moves().is_empty()
+    {
+        let player = position.current_player();
+        let agent = &agents[seats[player]];
+        let mv = planners[player]
+            .plan(&position, agent.budget)?
+            .ok_or_else(|| {
+                MdpError::ContractViolation(format!("{} passed in a game not over", agent.name))
+            })?;
+        if !position.legal_moves().contains(&mv) {
+            return Err(MdpError::ContractViolation(format!(
+                "{} played the illegal move {mv:?}",
+                agent.name
+            ))
+            .into());
+        }
+        position.make_move(&mv);
+        moves.push(mv);
+        stats.push(planners[player].root_stats());
+    }
+
+    Ok(PlayedGame {
+        start: start.clone(),
+        winner: position.winner(),
+        moves,
+        stats,
+        seats,
+    })
+}
+
+/// A match between two agents, see the [module documentation](self)
+pub struct Match<'a, G: Game> {
+    start: G,
+    agents: Vec<Agent<'a, G>>,
+    games: usize,
+    max_moves: usize,
+}
+
+impl<'a, G: Game + 'a> Match<'a, G> {
+    /// Games start from `start`. Plays 10 games, stopped as draws after 1000 moves
+    pub fn new(start: G) -> Self {
+        Self {
+            start,
+            agents: vec![],
+            games: 10,
+            max_moves: 1000,
+        }
+    }
+
+    /// Adds an agent, built by `create` for every game, which gets `budget` for every move.
+    /// The first agent added plays first in the even games, the second one in the odd ones
+    pub fn with_agent<P, F>(mut self, name: &str, budget: Budget, create: F) -> Self
+    where
+        P: Planner<G, G::Move> + 'a,
+        F: Fn(GameMdp<G>) -> P + 'a,
+    {
+        self.agents.push(Agent::new(name, budget, create));
+        self
+    }
+
+    pub fn with_games(mut self, games: usize) -> Self {
+        self.games = games;
+        self
+    }
+
+    /// Stops games after this many moves, as draws
+    pub fn with_max_moves(mut self, moves: usize) -> Self {
+        self.max_moves = moves;
+        self
+    }
+
+    /// Plays the games of the match.
+    ///
+    /// Panics unless the match has exactly two agents and the game two players, or if an agent fails, see [`Match::try_run`]
+    pub fn run(&self) -> MatchResult<G> {
+        self.try_run()
+            .unwrap_or_else(|error| panic!("match failed: {error}"))
+    }
+
+    /// Same as [`Match::run`], but stops at the first failure of an agent and returns it,
+    /// including an agent passing or playing an illegal move in a game that isn't over
+    pub fn try_run(&self) -> Result<MatchResult<G>, McstError> {
+        assert_eq!(
+            self.agents.len(),
+            2,
+            "a match needs exactly two agents. agents={}",
+            self.agents.len()
+        );
+        assert_eq!(
+            self.start.num_players(),
+            2,
+            "a match needs a two-player game"
+        );
+
+        let games = (0..self.games)
+            .map(|game| {
+                play(
+                    &self.start,
+                    &self.agents,
+                    [game % 2, 1 - game % 2],
+                    self.max_moves,
+                )
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(MatchResult {
+            agents: [self.agents[0].name.clone(), self.agents[1].name.clone()],
+            games,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        envs::nim::{Nim, Take},
+        mcts::MCTS,
+        policy::RandomRollout,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_agents_take_turns_playing_first() {
+        let result = Match::new(Nim::new([3, 4, 5]))
+            .with_games(6)
+            .with_agent("uct", Budget::Iterations(1000), |mdp| {
+                MCTS::new(mdp, RandomRollout::new())
+                    .with_seed(1)
+                    .with_transpositions()
+            })
+            .with_agent("random", Budget::Iterations(1), |_| {
+                PolicyAgent::new(RandomRollout).with_seed(2)
+            })
+            .run();
+
+        assert_eq!(result.games.len(), 6);
+        for (i, game) in result.games.iter().enumerate() {
+            assert_eq!(game.seats, [i % 2, 1 - i % 2]);
+            let end = game.final_position();
+            assert!(end.heaps().iter().all(|heap| *heap == 0));
+            assert_eq!(game.winner, end.winner());
+        }
+        // the search wins from either side against random play
+        assert_eq!(result.wins(0), 6);
+        assert_eq!((result.draws(), result.score(1)), (0, 0.0));
+        assert_eq!(result.elo().elo, f64::INFINITY);
+        assert_eq!(
+            result.to_string(),
+            "agents=uct/random games=6 wins=6/0 draws=0 score=1.000"
+        );
+    }
+
+    #[test]
+    fn test_matches_stop_long_games_and_illegal_moves() {
+        let drawn = Match::new(Nim::new([3, 4, 5]))
+            .with_games(2)
+            .with_max_moves(2)
+            .with_agent("a", Budget::Iterations(1), |_| {
+                PolicyAgent::new(RandomRollout).with_seed(1)
+            })
+            .with_agent("b", Budget::Iterations(1), |_| {
+                PolicyAgent::new(RandomRollout).with_seed(2)
+            })
+            .run();
+        assert!(drawn.games.iter().all(|game| game.moves.len() == 2));
+        assert_eq!((drawn.draws(), drawn.score(0)), (2, 0.5));
+        assert_eq!(drawn.elo().elo, 0.0);
+
+        // takes one more object than the first heap holds
+        struct Cheat;
+        impl Planner<Nim, Take> for Cheat {
+            fn plan(&mut self, state: &Nim, _budget: Budget) -> Result<Option<Take>, McstError> {
+                let count = state.heaps()[0] + 1;
+                Ok(Some(Take { heap: 0, count }))
+            }
+        }
+        let error = Match::new(Nim::new([3, 4, 5]))
+            .with_games(1)
+            .with_agent("cheat", Budget::Iterations(1), |_| Cheat)
+            .with_agent("random", Budget::Iterations(1), |_| {
+                PolicyAgent::new(RandomRollout).with_seed(2)
+            })
+            .try_run();
+        assert!(matches!(
+            error,
+            Err(McstError::Mdp(MdpError::ContractViolation(_)))
+        ));
+    }
+}
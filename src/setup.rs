@@ -0,0 +1,74 @@
+//! The randomness, time source and budget loop that the standalone searchers
+//! ([`crate::pomcp::POMCP`], [`crate::ismcts::ISMCTS`], [`crate::pareto::ParetoMCTS`] and [`crate::constrained::ConstrainedMCTS`]) share.
+//!
+//! Each of them holds a [`SearchSetup`] and implements [`Configurable`], which gives them the same
+//! `with_rng`, `with_seed` and `with_clock` builder methods as [`crate::mcts::MCTS`]
+
+use crate::{
+    budget::{Budget, Tracker},
+    clock::{Clock, SystemClock},
+    error::McstError,
+    rand::{Rng, SplitMix64},
+};
+
+/// Where a search draws its randomness and reads the time from
+pub struct SearchSetup {
+    clock: Box<dyn Clock>,
+    rng: Box<dyn Rng>,
+}
+
+impl Default for SearchSetup {
+    /// Seeded from the OS, and timed by the [`SystemClock`]
+    fn default() -> Self {
+        Self {
+            clock: Box::new(SystemClock),
+            rng: Box::new(SplitMix64::from_entropy()),
+        }
+    }
+}
+
+impl SearchSetup {
+    /// Calls `iterate` until the budget is used up, handing it the budget (to cut rollouts short) and the generator
+    pub(crate) fn run<F>(&mut self, budget: Budget, mut iterate: F) -> Result<usize, McstError>
+    where
+        F: FnMut(&Tracker<'_>, &mut dyn Rng) -> Result<(), McstError>,
+    {
+        let tracker = Tracker::start(budget, &*self.clock);
+        let rng = &mut *self.rng;
+        let mut iterations = 0;
+
+        while !tracker.exhausted(iterations) {
+            iterations += 1;
+            iterate(&tracker, rng)?;
+        }
+
+        Ok(iterations)
+    }
+
+    /// The generator the search draws from, e.g. to sample outside of [`SearchSetup::run`]
+    pub(crate) fn rng(&mut self) -> &mut dyn Rng {
+        &mut *self.rng
+    }
+}
+
+/// The builder methods of the searchers holding a [`SearchSetup`]
+pub trait Configurable: Sized {
+    fn setup(&mut self) -> &mut SearchSetup;
+
+    /// Draws all the randomness of the search from the given generator, see [`crate::mcts::MCTS::with_rng`]
+    fn with_rng<R: Rng + 'static>(mut self, rng: R) -> Self {
+        self.setup().rng = Box::new(rng);
+        self
+    }
+
+    /// Makes the search deterministic, see [`crate::mcts::MCTS::with_seed`]
+    fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(SplitMix64::seed(seed))
+    }
+
+    /// Reads the time from the given clock, for time budgets, see [`crate::mcts::MCTS::with_clock`]
+    fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.setup().clock = Box::new(clock);
+        self
+    }
+}
@@ -0,0 +1,226 @@
+//! Searching simulators that need `&mut self` to step, e.g. external engines or physics libraries behind FFI.
+//!
+//! The search only ever sees a [`GenerativeModel`], which steps from any state it's given through `&self`.
+//! [`SimulatorModel`] bridges the two: it owns the simulator, and before every call moves it to the state the search asks about,
+//! by restoring a snapshot of it (see [`Simulator::save`] and [`Simulator::restore`]).
+//! Snapshots are only restored when the simulator isn't already in that state,
+//! so a rollout, which keeps stepping from where the last step left off, restores once at its start rather than at every step
+
+use std::cell::{RefCell, RefMut};
+
+use crate::{
+    error::MdpError,
+    mdp::{GenerativeModel, PlayerId},
+    rand::Rng,
+};
+
+/// A simulator with a current state, which it steps in place.
+///
+/// `S` is a snapshot of the simulator's state: restoring a snapshot must put the simulator back exactly as it was when the snapshot was saved,
+/// since the search will step from it again and again. Two snapshots comparing equal must be of the same state
+pub trait Simulator<S, A> {
+    /// Puts the simulator in its initial state
+    fn reset(&mut self);
+
+    /// Returns a snapshot of the simulator's current state
+    fn save(&mut self) -> S;
+
+    /// Puts the simulator back in the state of the snapshot
+    fn restore(&mut self, snapshot: &S);
+
+    /// Returns the actions available in the current state
+    fn actions(&mut self) -> Vec<A>;
+
+    /// Takes the action in the current state, returning its reward and whether the simulator reached a terminal state.
+    /// Any randomness must be drawn from `rng`, see [`GenerativeModel::execute`]
+    fn step(&mut self, action: &A, rng: &mut dyn Rng) -> Result<(f64, bool), MdpError>;
+
+    /// Returns true if the current state is terminal
+    fn is_terminal(&mut self) -> bool;
+
+    /// See [`GenerativeModel::player_to_move`]
+    fn player_to_move(&mut self) -> PlayerId {
+        0
+    }
+
+    /// See [`GenerativeModel::num_players`]
+    fn num_players(&self) -> usize {
+        1
+    }
+
+    /// See [`GenerativeModel::get_discount_factor`]
+    fn discount_factor(&self) -> f64 {
+        1.0
+    }
+}
+
+struct Synced<Sim, S> {
+    simulator: Sim,
+    /// The snapshot of the simulator's current state, if known
+    current: Option<S>,
+}
+
+impl<Sim, S: Clone + PartialEq> Synced<Sim, S> {
+    /// Moves the simulator to `state`, if it isn't there already
+    fn at<A>(&mut self, state: &S) -> &mut Sim
+    where
+        Sim: Simulator<S, A>,
+    {
+        if self.current.as_ref() != Some(state) {
+            self.simulator.restore(state);
+            self.current = Some(state.clone());
+        }
+        &mut self.simulator
+    }
+}
+
+/// A [`GenerativeModel`] driving a [`Simulator`], see the [module documentation](self).
+///
+/// The simulator is borrowed mutably for the duration of every call, which the search never nests
+pub struct SimulatorModel<Sim, S> {
+    inner: RefCell<Synced<Sim, S>>,
+}
+
+impl<Sim, S> SimulatorModel<Sim, S>
+where
+    S: Clone + PartialEq,
+{
+    pub fn new(simulator: Sim) -> Self {
+        Self {
+            inner: RefCell::new(Synced {
+                simulator,
+                current: None,
+            }),
+        }
+    }
+
+    /// Gives the simulator back, in whatever state the search left it
+    pub fn into_inner(self) -> Sim {
+        self.inner.into_inner().simulator
+    }
+
+    /// Borrows the simulator, after moving it to `state`
+    fn at<A>(&self, state: &S) -> RefMut<'_, Sim>
+    where
+        Sim: Simulator<S, A>,
+    {
+        RefMut::map(self.inner.borrow_mut(), |inner| inner.at(state))
+    }
+}
+
+impl<Sim, S, A> GenerativeModel<S, A> for SimulatorModel<Sim, S>
+where
+    Sim: Simulator<S, A>,
+    S: Clone + PartialEq,
+{
+    fn get_actions(&self, state: &S) -> Vec<A> {
+        self.at(state).actions()
+    }
+
+    fn execute(&self, state: &S, action: &A, rng: &mut dyn Rng) -> (S, f64, bool) {
+        self.try_execute(state, action, rng)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    fn try_execute(
+        &self,
+        state: &S,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, f64, bool), MdpError> {
+        let mut inner = self.inner.borrow_mut();
+        inner.at(state);
+        // a failed step may have left the simulator anywhere
+        inner.current = None;
+
+        let (reward, done) = inner.simulator.step(action, rng)?;
+        let next_state = inner.simulator.save();
+        inner.current = Some(next_state.clone());
+        Ok((next_state, reward, done))
+    }
+
+    fn is_terminal(&self, state: &S) -> bool {
+        self.at(state).is_terminal()
+    }
+
+    fn get_initial_state(&self) -> S {
+        let mut inner = self.inner.borrow_mut();
+        inner.simulator.reset();
+        let state = inner.simulator.save();
+        inner.current = Some(state.clone());
+        state
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.inner.borrow().simulator.discount_factor()
+    }
+
+    fn player_to_move(&self, state: &S) -> PlayerId {
+        self.at(state).player_to_move()
+    }
+
+    fn num_players(&self) -> usize {
+        self.inner.borrow().simulator.num_players()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use crate::{mcts::MCTS, policy::RandomRollout};
+
+    use super::*;
+
+    /// A counter stepped in place, which keeps count of how often it was stepped and restored
+    #[derive(Default)]
+    struct Counter {
+        value: u32,
+        steps: Rc<Cell<usize>>,
+        restores: Rc<Cell<usize>>,
+    }
+
+    impl Simulator<u32, usize> for Counter {
+        fn reset(&mut self) {
+            self.value = 0;
+        }
+
+        fn save(&mut self) -> u32 {
+            self.value
+        }
+
+        fn restore(&mut self, snapshot: &u32) {
+            self.value = *snapshot;
+            self.restores.set(self.restores.get() + 1);
+        }
+
+        fn actions(&mut self) -> Vec<usize> {
+            vec![1, 2]
+        }
+
+        fn step(&mut self, action: &usize, _rng: &mut dyn Rng) -> Result<(f64, bool), MdpError> {
+            self.value += *action as u32;
+            self.steps.set(self.steps.get() + 1);
+            Ok((*action as f64, self.value >= 20))
+        }
+
+        fn is_terminal(&mut self) -> bool {
+            self.value >= 20
+        }
+    }
+
+    #[test]
+    fn test_rollouts_step_without_restoring() {
+        let counter = Counter::default();
+        let (steps, restores) = (Rc::clone(&counter.steps), Rc::clone(&counter.restores));
+
+        let mut mcts = MCTS::new(SimulatorModel::new(counter), RandomRollout::new()).with_seed(1);
+        let stats = mcts.run_iterations(100);
+        assert_eq!(stats.iterations, 100);
+        assert!(steps.get() > stats.simulations);
+        // the selection hops between the states of the tree, one restore per node along the path,
+        // but the expansion and rollout step on from where the last step left off
+        let path = mcts.tree_stats().max_depth + 1;
+        assert!(restores.get() <= 100 * path);
+    }
+}
@@ -0,0 +1,10 @@
+//! Exact and learning-based solvers for MDPs, as alternatives to tree search on problems small enough for them,
+//! and as ground truth to test the search against
+
+mod episodes;
+pub mod monte_carlo;
+pub mod prioritized_sweeping;
+pub mod q_learning;
+pub mod q_table;
+pub mod sarsa;
+pub mod value_iteration;
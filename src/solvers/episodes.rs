@@ -0,0 +1,183 @@
+//! The episode loop the temporal-difference learners share
+
+/// The builder methods of the settings every temporal-difference learner has, for a learner with a `settings: TdSettings` field
+macro_rules! td_settings {
+    () => {
+        /// α, the step size of the updates
+        pub fn with_learning_rate(mut self, alpha: f64) -> Self {
+            self.settings.learning_rate = alpha;
+            self
+        }
+
+        /// ε, the probability of exploring a random action rather than taking the best known one
+        pub fn with_exploration_rate(mut self, epsilon: f64) -> Self {
+            self.settings.exploration_rate = epsilon;
+            self
+        }
+
+        /// γ, the discount of future rewards, rather than the MDP's
+        pub fn with_discount_factor(mut self, gamma: f64) -> Self {
+            self.settings.discount = Some(gamma);
+            self
+        }
+
+        pub fn with_episodes(mut self, episodes: usize) -> Self {
+            self.settings.episodes = episodes;
+            self
+        }
+
+        /// Cuts episodes short after this many steps, for MDPs whose episodes are long or endless
+        pub fn with_max_episode_length(mut self, steps: usize) -> Self {
+            self.settings.max_steps = Some(steps);
+            self
+        }
+
+        /// Values the pairs the table hasn't seen yet at `value` rather than 0, see [`QTable::with_initial_value`]
+        pub fn with_initial_value(mut self, value: f64) -> Self {
+            self.settings.initial_value = value;
+            self
+        }
+    };
+}
+
+pub(crate) use td_settings;
+
+use std::hash::Hash;
+
+use crate::{
+    action::Action,
+    error::{McstError, MdpError},
+    mdp::{GenerativeModel, checked_execute},
+    rand::Rng,
+    solvers::q_table::QTable,
+};
+
+/// The settings every temporal-difference learner has
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TdSettings {
+    /// α, the step size of the updates
+    pub(crate) learning_rate: f64,
+    /// ε, the probability of a random action rather than the best known one
+    pub(crate) exploration_rate: f64,
+    pub(crate) episodes: usize,
+    /// Episodes are cut short after this many steps
+    pub(crate) max_steps: Option<usize>,
+    /// γ, the MDP's own by default
+    pub(crate) discount: Option<f64>,
+    /// The value of the pairs the table hasn't seen yet
+    pub(crate) initial_value: f64,
+}
+
+impl Default for TdSettings {
+    fn default() -> Self {
+        Self {
+            learning_rate: 0.1,
+            exploration_rate: 0.1,
+            episodes: 1000,
+            max_steps: None,
+            discount: None,
+            initial_value: 0.0,
+        }
+    }
+}
+
+impl TdSettings {
+    /// A random action with probability ε, the best known one otherwise (ties broken at random)
+    pub(crate) fn epsilon_greedy<S, A>(
+        &self,
+        table: &QTable<S, A>,
+        state: &S,
+        actions: &[A],
+        rng: &mut dyn Rng,
+    ) -> A
+    where
+        S: Eq + Hash,
+        A: Action,
+    {
+        if rng.gen_f64() < self.exploration_rate {
+            return actions[rng.gen_range(0, actions.len())].clone();
+        }
+
+        let values = actions
+            .iter()
+            .map(|action| table.get(state, action))
+            .collect::<Vec<_>>();
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let best = (0..actions.len())
+            .filter(|&i| values[i] == max)
+            .collect::<Vec<_>>();
+        match best.len() {
+            0 => actions[rng.gen_range(0, actions.len())].clone(),
+            n => actions[best[rng.gen_range(0, n)]].clone(),
+        }
+    }
+
+    /// Runs the episodes from the MDP's initial state, acting ε-greedily on the table, and after every step moves
+    /// Q(s, a) towards `r + γ·target`, where `target` estimates the value of the next state
+    /// from the table, the action the learner will take there and every action available there.
+    /// The target of a terminal state is 0, while the last state of an episode cut short is still bootstrapped from
+    pub(crate) fn run<M, S, A, T>(
+        &self,
+        mdp: &M,
+        table: &mut QTable<S, A>,
+        rng: &mut dyn Rng,
+        target: T,
+    ) -> Result<(), McstError>
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+        T: Fn(&QTable<S, A>, &S, &A, &[A]) -> f64,
+    {
+        let discount = self.discount.unwrap_or_else(|| mdp.get_discount_factor());
+        let actions_of = |state: &S| {
+            let actions = mdp.get_actions(state);
+            match actions.is_empty() {
+                true => Err(McstError::from(MdpError::NoActions)),
+                false => Ok(actions),
+            }
+        };
+
+        for _ in 0..self.episodes {
+            let mut state = mdp.get_initial_state();
+            if mdp.is_terminal(&state) {
+                continue;
+            }
+            let actions = actions_of(&state)?;
+            let mut action = self.epsilon_greedy(table, &state, &actions, rng);
+
+            let mut steps = 0;
+            loop {
+                let (next_state, reward, done) = checked_execute(mdp, &state, &action, rng)?;
+                let done = done || mdp.is_terminal(&next_state);
+                steps += 1;
+
+                let next = match done {
+                    true => None,
+                    false => {
+                        let actions = actions_of(&next_state)?;
+                        let action = self.epsilon_greedy(table, &next_state, &actions, rng);
+                        Some((action, actions))
+                    }
+                };
+                let bootstrap = next.as_ref().map_or(0.0, |(action, actions)| {
+                    target(table, &next_state, action, actions)
+                });
+
+                let old = table.get(&state, &action);
+                let new = old + self.learning_rate * (reward + discount * bootstrap - old);
+                table.set(state, action, new);
+
+                match next {
+                    Some((next_action, _)) if self.max_steps.is_none_or(|max| steps < max) => {
+                        state = next_state;
+                        action = next_action;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+//! Monte Carlo evaluation of a [`RolloutPolicy`]: plays episodes with it against a [`GenerativeModel`]
+//! and estimates the value of every state reached as the mean of the discounted returns that followed it.
+//!
+//! First-visit evaluation only counts the return after the first visit of a state in every episode, and is unbiased.
+//! Every-visit evaluation counts them all, which gives more samples per episode at the cost of a bias that fades with their number
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    action::Action,
+    error::{McstError, MdpError},
+    mdp::{GenerativeModel, checked_execute},
+    policy::RolloutPolicy,
+    rand::Rng,
+    value::ValueFunction,
+};
+
+/// Which visits of a state in an episode count towards its estimate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visits {
+    #[default]
+    First,
+    Every,
+}
+
+/// Estimates the values of states under a policy, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{policy::RandomRollout, rand::SplitMix64, solvers::monte_carlo::MonteCarlo, tabular::TabularMDP};
+///
+/// // action 0 ends the episode with 1, action 1 pays 2 but only ends it half of the time:
+/// // playing at random, V(0) = 0.5 * 1 + 0.5 * (2 + 0.5 * V(0)) = 2
+/// let mdp = TabularMDP::new(
+///     vec![vec![vec![0.0, 1.0], vec![0.5, 0.5]], vec![vec![0.0, 1.0], vec![0.0, 0.0]]],
+///     vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+/// )
+/// .with_terminal_states([1]);
+///
+/// let estimates = MonteCarlo::new()
+///     .with_episodes(5000)
+///     .evaluate(&mdp, &RandomRollout::new(), &mut SplitMix64::seed(1));
+/// assert!((estimates.value(&0).unwrap() - 2.0).abs() < 0.2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonteCarlo {
+    visits: Visits,
+    episodes: usize,
+    max_steps: Option<usize>,
+    discount: Option<f64>,
+}
+
+impl Default for MonteCarlo {
+    fn default() -> Self {
+        Self {
+            visits: Visits::First,
+            episodes: 1000,
+            max_steps: None,
+            discount: None,
+        }
+    }
+}
+
+impl MonteCarlo {
+    /// First-visit evaluation over 1000 episodes, with the MDP's discount factor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_visits(mut self, visits: Visits) -> Self {
+        self.visits = visits;
+        self
+    }
+
+    pub fn with_episodes(mut self, episodes: usize) -> Self {
+        self.episodes = episodes;
+        self
+    }
+
+    /// Cuts episodes short after this many steps, for MDPs whose episodes are long or endless.
+    /// The returns of an episode cut short only count the rewards up to there
+    pub fn with_max_episode_length(mut self, steps: usize) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    /// γ, the discount of future rewards, rather than the MDP's
+    pub fn with_discount_factor(mut self, gamma: f64) -> Self {
+        self.discount = Some(gamma);
+        self
+    }
+
+    /// Plays the episodes with the policy from the MDP's initial state.
+    ///
+    /// Panics if the MDP fails, see [`MonteCarlo::try_evaluate`]
+    pub fn evaluate<M, S, A, P>(&self, mdp: &M, policy: &P, rng: &mut dyn Rng) -> Estimates<S>
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+        P: RolloutPolicy<M, S, A>,
+    {
+        self.try_evaluate(mdp, policy, rng)
+            .unwrap_or_else(|error| panic!("evaluation failed: {error}"))
+    }
+
+    /// Same as [`MonteCarlo::evaluate`], but stops at the first failure of the MDP and returns it
+    pub fn try_evaluate<M, S, A, P>(
+        &self,
+        mdp: &M,
+        policy: &P,
+        rng: &mut dyn Rng,
+    ) -> Result<Estimates<S>, McstError>
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+        P: RolloutPolicy<M, S, A>,
+    {
+        let discount = self.discount.unwrap_or_else(|| mdp.get_discount_factor());
+        let mut estimates = Estimates::default();
+
+        for _ in 0..self.episodes {
+            // (state, reward of the step taken in it)
+            let mut episode = Vec::new();
+            let mut state = mdp.get_initial_state();
+            while !mdp.is_terminal(&state) && self.max_steps.is_none_or(|max| episode.len() < max) {
+                let actions = mdp.get_actions(&state);
+                if actions.is_empty() {
+                    return Err(MdpError::NoActions.into());
+                }
+                let action = policy.pick(&state, &actions, rng);
+                let (next_state, reward, done) = checked_execute(mdp, &state, &action, rng)?;
+                episode.push((state, reward));
+                state = next_state;
+                if done {
+                    break;
+                }
+            }
+
+            let mut first = HashMap::new();
+            if self.visits == Visits::First {
+                for (i, (state, _)) in episode.iter().enumerate() {
+                    first.entry(state).or_insert(i);
+                }
+            }
+
+            let mut returns = Vec::with_capacity(episode.len());
+            let mut ret = 0.0;
+            for (_, reward) in episode.iter().rev() {
+                ret = reward + discount * ret;
+                returns.push(ret);
+            }
+            returns.reverse();
+
+            for (i, ((state, _), ret)) in episode.iter().zip(returns).enumerate() {
+                if self.visits == Visits::Every || first.get(state) == Some(&i) {
+                    estimates.record(state.clone(), ret);
+                }
+            }
+        }
+
+        Ok(estimates)
+    }
+}
+
+/// The values estimated by [`MonteCarlo::evaluate`], for the states the episodes went through
+#[derive(Debug, Clone)]
+pub struct Estimates<S> {
+    /// (mean return, number of returns) of every state
+    values: HashMap<S, (f64, usize)>,
+}
+
+impl<S> Default for Estimates<S> {
+    fn default() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+}
+
+impl<S> Estimates<S>
+where
+    S: Eq + Hash,
+{
+    fn record(&mut self, state: S, ret: f64) {
+        let (mean, count) = self.values.entry(state).or_insert((0.0, 0));
+        *count += 1;
+        *mean += (ret - *mean) / *count as f64;
+    }
+
+    /// The mean return from the state, or None if no episode went through it
+    pub fn value(&self, state: &S) -> Option<f64> {
+        self.values.get(state).map(|(mean, _)| *mean)
+    }
+
+    /// The number of returns the estimate of the state averages
+    pub fn visits(&self, state: &S) -> usize {
+        self.values.get(state).map_or(0, |(_, count)| *count)
+    }
+
+    /// The number of states the episodes went through
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Every state the episodes went through, with its estimated value
+    pub fn iter(&self) -> impl Iterator<Item = (&S, f64)> {
+        self.values.iter().map(|(state, (mean, _))| (state, *mean))
+    }
+}
+
+/// The states no episode went through are worth 0
+impl<S: Eq + Hash> ValueFunction<S> for Estimates<S> {
+    fn value(&self, state: &S) -> f64 {
+        Estimates::value(self, state).unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{policy::RandomRollout, rand::SplitMix64, tabular::TabularMDP};
+
+    use super::*;
+
+    #[test]
+    fn test_first_and_every_visit_estimates() {
+        // action 0 ends the episode with 1, action 1 pays 2 but only ends it half of the time.
+        // At random V(0) = 0.5 * 1 + 0.5 * (2 + 0.5 * V(0)) = 2
+        let mdp = TabularMDP::new(
+            vec![
+                vec![vec![0.0, 1.0], vec![0.5, 0.5]],
+                vec![vec![0.0, 1.0], vec![0.0, 0.0]],
+            ],
+            vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+        )
+        .with_terminal_states([1]);
+
+        let mut rng = SplitMix64::seed(5);
+        let policy = RandomRollout::new();
+        let first = MonteCarlo::new()
+            .with_episodes(5000)
+            .evaluate(&mdp, &policy, &mut rng);
+        let every = MonteCarlo::new()
+            .with_episodes(5000)
+            .with_visits(Visits::Every)
+            .evaluate(&mdp, &policy, &mut rng);
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(first.visits(&0), 5000);
+        assert!(every.visits(&0) > 5000);
+        assert!((first.value(&0).unwrap() - 2.0).abs() < 0.2);
+        assert!((every.value(&0).unwrap() - 2.0).abs() < 0.2);
+        assert_eq!(first.value(&1), None);
+
+        // a single step: the reward of one action at random
+        let short = MonteCarlo::new()
+            .with_episodes(5000)
+            .with_max_episode_length(1)
+            .evaluate(&mdp, &policy, &mut rng);
+        assert!((short.value(&0).unwrap() - 1.5).abs() < 0.1);
+    }
+}
@@ -0,0 +1,203 @@
+//! Prioritized sweeping (Moore & Atkeson, 1993) over explicit finite MDPs (see [`ExplicitMDP`]):
+//! the Bellman optimality update of value iteration, applied one state at a time in order of how much it would change it.
+//!
+//! States are kept in a priority queue keyed by their Bellman error `|max_a Q(s, a) - V(s)|`. Backing a state up
+//! changes the Q-values of its predecessors, which are queued again by their new error, so the updates follow
+//! the values as they spread out from the rewards rather than sweeping every state over and over.
+//! It stops once no state is off by more than the tolerance
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
+    hash::Hash,
+};
+
+use crate::{
+    mdp::ExplicitMDP,
+    solvers::value_iteration::{Model, Solution},
+};
+
+/// A state of the queue, by its Bellman error when it was queued
+struct Queued {
+    error: f64,
+    state: usize,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.error
+            .total_cmp(&other.error)
+            .then_with(|| other.state.cmp(&self.state))
+    }
+}
+
+/// Solves explicit MDPs, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{solvers::prioritized_sweeping::PrioritizedSweeping, tabular::TabularMDP};
+///
+/// // action 0 ends the episode with 1, action 1 pays 2 but only ends it half of the time
+/// let mdp = TabularMDP::new(
+///     vec![vec![vec![0.0, 1.0], vec![0.5, 0.5]], vec![vec![0.0, 1.0], vec![0.0, 0.0]]],
+///     vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+/// )
+/// .with_terminal_states([1]);
+///
+/// let solution = PrioritizedSweeping::new().solve(&mdp);
+/// assert!(solution.converged());
+/// assert_eq!(solution.action(&0), Some(&1));
+/// assert!((solution.value(&0).unwrap() - 4.0).abs() < 1e-6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrioritizedSweeping {
+    tolerance: f64,
+    max_backups: usize,
+}
+
+impl Default for PrioritizedSweeping {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-9,
+            max_backups: 1_000_000,
+        }
+    }
+}
+
+impl PrioritizedSweeping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves the states whose Bellman error is no more than this alone, 1e-9 by default
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Stops after this many single-state backups even if some values are still off, a million by default
+    pub fn with_max_backups(mut self, backups: usize) -> Self {
+        self.max_backups = backups;
+        self
+    }
+
+    /// Computes the optimal values of the states of the MDP, discounted by its discount factor.
+    /// The solution's [`Solution::sweeps`] are the number of single-state backups it took
+    pub fn solve<M, S, A>(&self, mdp: &M) -> Solution<S, A>
+    where
+        M: ExplicitMDP<S, A>,
+        S: Clone + Eq + Hash,
+        A: Clone,
+    {
+        let model = Model::new(mdp);
+        let states = model.states.len();
+
+        // the states every state can be reached from, in one step of any action
+        let mut predecessors = vec![HashSet::new(); states];
+        for (state, actions) in model.actions.iter().enumerate() {
+            for (_, outcomes) in actions {
+                for (next, p, _) in outcomes {
+                    if *p > 0.0 {
+                        predecessors[*next].insert(state);
+                    }
+                }
+            }
+        }
+
+        let mut values = vec![0.0; states];
+        let mut queue = BinaryHeap::new();
+        let error =
+            |values: &[f64], state: usize| (model.backup(values, state) - values[state]).abs();
+        for state in 0..states {
+            let error = error(&values, state);
+            if error > self.tolerance {
+                queue.push(Queued { error, state });
+            }
+        }
+
+        let mut backups = 0;
+        while backups < self.max_backups {
+            let Some(Queued { state, .. }) = queue.pop() else {
+                break;
+            };
+            // a state can be queued several times, only its current error matters
+            if error(&values, state) <= self.tolerance {
+                continue;
+            }
+
+            backups += 1;
+            values[state] = model.backup(&values, state);
+            for &predecessor in &predecessors[state] {
+                let error = error(&values, predecessor);
+                if error > self.tolerance {
+                    queue.push(Queued {
+                        error,
+                        state: predecessor,
+                    });
+                }
+            }
+        }
+
+        let converged = queue
+            .iter()
+            .all(|queued| error(&values, queued.state) <= self.tolerance);
+        model.solution(values, backups, converged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{solvers::value_iteration::ValueIteration, tabular::TabularMDP};
+
+    use super::*;
+
+    #[test]
+    fn test_prioritized_sweeping_agrees_with_value_iteration() {
+        // a line from 0 to the goal 9, where walking right costs 1, and slips back a fifth of the time
+        let transitions = (0..10)
+            .map(|s: usize| {
+                let mut right = vec![0.0; 10];
+                let mut left = vec![0.0; 10];
+                if s < 9 {
+                    right[s + 1] += 0.8;
+                    right[s.saturating_sub(1)] += 0.2;
+                    left[s.saturating_sub(1)] += 1.0;
+                }
+                vec![right, left]
+            })
+            .collect();
+        let rewards = (0..10).map(|_| vec![-1.0, -1.0]).collect();
+        let mdp = TabularMDP::new(transitions, rewards)
+            .with_terminal_states([9])
+            .with_discount_factor(0.95);
+
+        let expected = ValueIteration::new().solve(&mdp);
+        let solution = PrioritizedSweeping::new().solve(&mdp);
+        assert!(solution.converged());
+        for state in 0..10 {
+            assert!(
+                (solution.value(&state).unwrap() - expected.value(&state).unwrap()).abs() < 1e-6
+            );
+            assert_eq!(solution.action(&state), expected.action(&state));
+        }
+        // far fewer backups than value iteration's sweeps over every state
+        assert!(solution.sweeps() < expected.sweeps() * 10);
+
+        let capped = PrioritizedSweeping::new().with_max_backups(3).solve(&mdp);
+        assert!(!capped.converged());
+        assert_eq!(capped.sweeps(), 3);
+    }
+}
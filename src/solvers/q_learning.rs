@@ -0,0 +1,152 @@
+//! Tabular Q-learning over any [`GenerativeModel`], learning from episodes played against it rather than from its full model.
+//!
+//! Off-policy: the agent explores ε-greedily, but every update moves Q(s, a) towards `r + γ·max_a' Q(s', a')`,
+//! i.e. the return of acting greedily from the next state on
+
+use std::hash::Hash;
+
+use crate::{
+    action::Action,
+    error::McstError,
+    mdp::GenerativeModel,
+    rand::Rng,
+    solvers::{
+        episodes::{TdSettings, td_settings},
+        q_table::QTable,
+    },
+};
+
+/// Trains a [`QTable`], see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{mcts::MCTS, rand::SplitMix64, solvers::q_learning::QLearning, tabular::TabularMDP};
+///
+/// // action 0 ends the episode with 1, action 1 pays 2 but only ends it half of the time
+/// let mdp = TabularMDP::new(
+///     vec![vec![vec![0.0, 1.0], vec![0.5, 0.5]], vec![vec![0.0, 1.0], vec![0.0, 0.0]]],
+///     vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+/// )
+/// .with_terminal_states([1]);
+///
+/// let table = QLearning::new().with_episodes(2000).train(&mdp, &mut SplitMix64::seed(1));
+/// assert_eq!(table.best_action(&0), Some(&1));
+///
+/// // the table as the rollout policy, and its values for the states rollouts are cut short in
+/// let values = table.clone();
+/// let mut mcts = MCTS::new(mdp, table)
+///     .with_max_rollout_depth(2)
+///     .with_leaf_evaluator(move |state| values.value(state));
+/// mcts.run_iterations(100);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct QLearning {
+    settings: TdSettings,
+}
+
+impl QLearning {
+    /// Learns over 1000 episodes with α = 0.1, ε = 0.1 and the MDP's discount factor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    td_settings!();
+
+    /// Learns a table from scratch.
+    ///
+    /// Panics if the MDP fails, see [`QLearning::try_train`]
+    pub fn train<M, S, A>(&self, mdp: &M, rng: &mut dyn Rng) -> QTable<S, A>
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+    {
+        self.try_train(mdp, rng)
+            .unwrap_or_else(|error| panic!("training failed: {error}"))
+    }
+
+    /// Same as [`QLearning::train`], but stops at the first failure of the MDP and returns it
+    pub fn try_train<M, S, A>(&self, mdp: &M, rng: &mut dyn Rng) -> Result<QTable<S, A>, McstError>
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+    {
+        let mut table = QTable::new().with_initial_value(self.settings.initial_value);
+        self.try_improve(mdp, &mut table, rng)?;
+        Ok(table)
+    }
+
+    /// Goes on learning into an existing table, e.g. one trained earlier or loaded from disk.
+    ///
+    /// Panics if the MDP fails, see [`QLearning::try_improve`]
+    pub fn improve<M, S, A>(&self, mdp: &M, table: &mut QTable<S, A>, rng: &mut dyn Rng)
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+    {
+        self.try_improve(mdp, table, rng)
+            .unwrap_or_else(|error| panic!("training failed: {error}"))
+    }
+
+    /// Same as [`QLearning::improve`], but stops at the first failure of the MDP and returns it
+    pub fn try_improve<M, S, A>(
+        &self,
+        mdp: &M,
+        table: &mut QTable<S, A>,
+        rng: &mut dyn Rng,
+    ) -> Result<(), McstError>
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+    {
+        self.settings
+            .run(mdp, table, rng, |table, state, _action, actions| {
+                actions
+                    .iter()
+                    .map(|action| table.get(state, action))
+                    .fold(f64::NEG_INFINITY, f64::max)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{rand::SplitMix64, tabular::TabularMDP};
+
+    use super::*;
+
+    #[test]
+    fn test_q_learning_converges_on_the_optimal_values() {
+        // action 0 ends the episode with 1, action 1 pays 2 but only ends it half of the time: Q(0, 1) = 2 + 0.5 * 4 = 4
+        let mdp = TabularMDP::new(
+            vec![
+                vec![vec![0.0, 1.0], vec![0.5, 0.5]],
+                vec![vec![0.0, 1.0], vec![0.0, 0.0]],
+            ],
+            vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+        )
+        .with_terminal_states([1]);
+
+        let mut rng = SplitMix64::seed(7);
+        let table = QLearning::new()
+            .with_episodes(5000)
+            .with_learning_rate(0.05)
+            .with_exploration_rate(0.3)
+            .train(&mdp, &mut rng);
+        assert_eq!(table.best_action(&0), Some(&1));
+        // a constant learning rate keeps the estimate of the risky action noisy
+        assert!((table.get(&0, &1) - 4.0).abs() < 1.0);
+        assert!((table.get(&0, &0) - 1.0).abs() < 1e-3);
+        assert_eq!(table.len(), 1);
+
+        // discounting and cutting episodes short both lower the values
+        let short = QLearning::new()
+            .with_episodes(5000)
+            .with_max_episode_length(1)
+            .with_discount_factor(0.5)
+            .train(&mdp, &mut rng);
+        assert!(short.value(&0) < table.value(&0));
+    }
+}
@@ -0,0 +1,203 @@
+//! Tables of action values, as learned by [`crate::solvers::q_learning`] and [`crate::solvers::sarsa`]
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    action::Action,
+    policy::{Policy, RolloutPolicy},
+    rand::Rng,
+    value::ValueFunction,
+};
+
+/// The estimated return Q(s, a) of taking every action in every state, starting from the table's initial value for the pairs it hasn't seen.
+///
+/// Usable on its own through [`QTable::best_action`], and with the search:
+/// as a rollout policy playing the best known actions, and as a leaf evaluator through [`QTable::value`]
+/// (see [`crate::mcts::MCTS::with_leaf_evaluator`]).
+/// Tables trained offline can be saved and loaded with `mct_rs::persist`, behind the `serde` feature
+#[derive(Debug, Clone)]
+pub struct QTable<S, A> {
+    values: HashMap<S, Vec<(A, f64)>>,
+    initial: f64,
+}
+
+impl<S, A> Default for QTable<S, A> {
+    fn default() -> Self {
+        Self {
+            values: HashMap::new(),
+            initial: 0.0,
+        }
+    }
+}
+
+impl<S, A> QTable<S, A>
+where
+    S: Eq + Hash,
+    A: Action,
+{
+    /// An empty table, valuing every pair at 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Values the pairs the table hasn't seen at `value` rather than 0, e.g. optimistically to encourage exploration
+    pub fn with_initial_value(mut self, value: f64) -> Self {
+        self.initial = value;
+        self
+    }
+
+    /// Q(s, a)
+    pub fn get(&self, state: &S, action: &A) -> f64 {
+        self.values
+            .get(state)
+            .and_then(|actions| actions.iter().find(|(a, _)| a == action))
+            .map_or(self.initial, |(_, value)| *value)
+    }
+
+    pub fn set(&mut self, state: S, action: A, value: f64) {
+        let actions = self.values.entry(state).or_default();
+        match actions.iter_mut().find(|(a, _)| *a == action) {
+            Some((_, old)) => *old = value,
+            None => actions.push((action, value)),
+        }
+    }
+
+    /// The best of the given actions in the state, the first of them on ties
+    pub fn best_of<'a>(&self, state: &S, actions: &'a [A]) -> Option<&'a A> {
+        let mut best: Option<(&A, f64)> = None;
+        for action in actions {
+            let value = self.get(state, action);
+            if best.is_none_or(|(_, max)| value > max) {
+                best = Some((action, value));
+            }
+        }
+        best.map(|(action, _)| action)
+    }
+
+    /// The best action the table knows of in the state, or None if it saw none
+    pub fn best_action(&self, state: &S) -> Option<&A> {
+        self.values
+            .get(state)?
+            .iter()
+            .fold(None, |best: Option<&(A, f64)>, pair| match best {
+                Some(best) if best.1 >= pair.1 => Some(best),
+                _ => Some(pair),
+            })
+            .map(|(action, _)| action)
+    }
+
+    /// V(s) = max_a Q(s, a) over the actions the table knows of in the state, or the initial value if it knows none
+    pub fn value(&self, state: &S) -> f64 {
+        self.values
+            .get(state)
+            .and_then(|actions| actions.iter().map(|(_, value)| *value).reduce(f64::max))
+            .unwrap_or(self.initial)
+    }
+
+    /// The number of states the table knows actions of
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Every state the table knows of, with the values of its actions
+    pub fn iter(&self) -> impl Iterator<Item = (&S, &[(A, f64)])> {
+        self.values
+            .iter()
+            .map(|(state, actions)| (state, actions.as_slice()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: serde::Serialize, A: serde::Serialize> serde::Serialize for QTable<S, A> {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        // states as a list rather than the keys of an object, which JSON only allows strings as
+        #[derive(serde::Serialize)]
+        struct Table<'a, S, A> {
+            initial: f64,
+            values: Vec<(&'a S, &'a [(A, f64)])>,
+        }
+
+        let values = self
+            .values
+            .iter()
+            .map(|(state, actions)| (state, actions.as_slice()))
+            .collect();
+        Table {
+            initial: self.initial,
+            values,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, S, A> serde::Deserialize<'de> for QTable<S, A>
+where
+    S: serde::Deserialize<'de> + Eq + Hash,
+    A: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Table<S, A> {
+            initial: f64,
+            values: Vec<(S, Vec<(A, f64)>)>,
+        }
+
+        let table = Table::deserialize(deserializer)?;
+        Ok(Self {
+            values: table.values.into_iter().collect(),
+            initial: table.initial,
+        })
+    }
+}
+
+/// The best action the table knows of, see [`QTable::best_action`]
+impl<S, A> Policy<S, A> for QTable<S, A>
+where
+    S: Eq + Hash,
+    A: Action,
+{
+    fn action(&self, state: &S) -> Option<A> {
+        self.best_action(state).cloned()
+    }
+}
+
+/// See [`QTable::value`]
+impl<S, A> ValueFunction<S> for QTable<S, A>
+where
+    S: Eq + Hash,
+    A: Action,
+{
+    fn value(&self, state: &S) -> f64 {
+        QTable::value(self, state)
+    }
+}
+
+/// Plays the best action according to the table, breaking ties at random
+impl<M, S, A> RolloutPolicy<M, S, A> for QTable<S, A>
+where
+    S: Eq + Hash,
+    A: Action,
+{
+    fn pick(&self, state: &S, actions: &Vec<A>, rng: &mut dyn Rng) -> A {
+        let values = actions
+            .iter()
+            .map(|action| self.get(state, action))
+            .collect::<Vec<_>>();
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let best = actions
+            .iter()
+            .zip(&values)
+            .filter(|(_, value)| **value == max)
+            .map(|(action, _)| action)
+            .collect::<Vec<_>>();
+        match best.len() {
+            0 => actions[rng.gen_range(0, actions.len())].clone(),
+            n => best[rng.gen_range(0, n)].clone(),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Tabular SARSA and expected SARSA over any [`GenerativeModel`], the on-policy counterparts of [`crate::solvers::q_learning`].
+//!
+//! SARSA moves Q(s, a) towards `r + γ·Q(s', a')`, where a' is the action the agent actually takes next, exploration included.
+//! Expected SARSA uses the expectation of Q(s', ·) over the ε-greedy policy instead, which removes the noise of picking a'.
+//! Either way the table learns the value of the exploring policy rather than of the greedy one,
+//! so it steers clear of actions that only pay off when never followed by a random mistake
+
+use std::hash::Hash;
+
+use crate::{
+    action::Action,
+    error::McstError,
+    mdp::GenerativeModel,
+    rand::Rng,
+    solvers::{
+        episodes::{TdSettings, td_settings},
+        q_table::QTable,
+    },
+};
+
+/// Trains a [`QTable`] on-policy, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{rand::SplitMix64, solvers::sarsa::Sarsa, tabular::TabularMDP};
+///
+/// // action 0 ends the episode with 1, action 1 pays 2 but only ends it half of the time
+/// let mdp = TabularMDP::new(
+///     vec![vec![vec![0.0, 1.0], vec![0.5, 0.5]], vec![vec![0.0, 1.0], vec![0.0, 0.0]]],
+///     vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+/// )
+/// .with_terminal_states([1]);
+///
+/// let table = Sarsa::expected().with_episodes(2000).train(&mdp, &mut SplitMix64::seed(1));
+/// assert_eq!(table.best_action(&0), Some(&1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Sarsa {
+    settings: TdSettings,
+    expected: bool,
+}
+
+impl Sarsa {
+    /// SARSA over 1000 episodes with α = 0.1, ε = 0.1 and the MDP's discount factor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expected SARSA, with the same defaults as [`Sarsa::new`]
+    pub fn expected() -> Self {
+        Self {
+            expected: true,
+            ..Self::default()
+        }
+    }
+
+    td_settings!();
+
+    /// Learns a table from scratch.
+    ///
+    /// Panics if the MDP fails, see [`Sarsa::try_train`]
+    pub fn train<M, S, A>(&self, mdp: &M, rng: &mut dyn Rng) -> QTable<S, A>
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+    {
+        self.try_train(mdp, rng)
+            .unwrap_or_else(|error| panic!("training failed: {error}"))
+    }
+
+    /// Same as [`Sarsa::train`], but stops at the first failure of the MDP and returns it
+    pub fn try_train<M, S, A>(&self, mdp: &M, rng: &mut dyn Rng) -> Result<QTable<S, A>, McstError>
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+    {
+        let mut table = QTable::new().with_initial_value(self.settings.initial_value);
+        self.try_improve(mdp, &mut table, rng)?;
+        Ok(table)
+    }
+
+    /// Goes on learning into an existing table, e.g. one trained earlier or by another learner.
+    ///
+    /// Panics if the MDP fails, see [`Sarsa::try_improve`]
+    pub fn improve<M, S, A>(&self, mdp: &M, table: &mut QTable<S, A>, rng: &mut dyn Rng)
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+    {
+        self.try_improve(mdp, table, rng)
+            .unwrap_or_else(|error| panic!("training failed: {error}"))
+    }
+
+    /// Same as [`Sarsa::improve`], but stops at the first failure of the MDP and returns it
+    pub fn try_improve<M, S, A>(
+        &self,
+        mdp: &M,
+        table: &mut QTable<S, A>,
+        rng: &mut dyn Rng,
+    ) -> Result<(), McstError>
+    where
+        M: GenerativeModel<S, A>,
+        S: Clone + Eq + Hash,
+        A: Action,
+    {
+        let epsilon = self.settings.exploration_rate;
+        match self.expected {
+            false => self
+                .settings
+                .run(mdp, table, rng, |table, state, action, _actions| {
+                    table.get(state, action)
+                }),
+            // ε spread evenly over the actions, the rest on the best one
+            true => self
+                .settings
+                .run(mdp, table, rng, |table, state, _action, actions| {
+                    let values = actions.iter().map(|action| table.get(state, action));
+                    let mean = values.clone().sum::<f64>() / actions.len() as f64;
+                    let max = values.fold(f64::NEG_INFINITY, f64::max);
+                    epsilon * mean + (1.0 - epsilon) * max
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{rand::SplitMix64, solvers::q_learning::QLearning, tabular::TabularMDP};
+
+    use super::*;
+
+    #[test]
+    fn test_sarsa_learns_the_value_of_exploring() {
+        // from 0, action 0 ends the episode with 1, action 1 walks to the edge of a cliff (2) for nothing.
+        // There, action 0 ends it with 3 while action 1 falls for -100.
+        // Greedily the edge is worth 3, but an agent still exploring a third of the time falls often enough to avoid it
+        let mdp = TabularMDP::new(
+            vec![
+                vec![vec![0.0, 1.0, 0.0], vec![0.0, 0.0, 1.0]],
+                vec![vec![0.0; 3], vec![0.0; 3]],
+                vec![vec![0.0, 1.0, 0.0], vec![0.0, 1.0, 0.0]],
+            ],
+            vec![vec![1.0, 0.0], vec![0.0, 0.0], vec![3.0, -100.0]],
+        )
+        .with_terminal_states([1]);
+
+        let mut rng = SplitMix64::seed(3);
+        let q_learning = QLearning::new()
+            .with_episodes(3000)
+            .with_exploration_rate(0.3)
+            .train(&mdp, &mut rng);
+        assert_eq!(q_learning.best_action(&0), Some(&1));
+
+        for sarsa in [Sarsa::new(), Sarsa::expected()] {
+            let table = sarsa
+                .with_episodes(3000)
+                .with_exploration_rate(0.3)
+                .train(&mdp, &mut rng);
+            assert_eq!(table.best_action(&0), Some(&0));
+            assert_eq!(table.best_action(&2), Some(&0));
+        }
+
+        // the expectation of an ε-greedy policy: 0.85 * 3 + 0.15 * -100
+        let table = Sarsa::expected()
+            .with_episodes(5000)
+            .with_exploration_rate(0.3)
+            .with_learning_rate(0.05)
+            .train(&mdp, &mut rng);
+        assert!((table.get(&0, &1) - -12.45).abs() < 1.0);
+    }
+}
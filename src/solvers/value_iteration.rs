@@ -0,0 +1,326 @@
+//! Value iteration over explicit finite MDPs (see [`ExplicitMDP`]), which finds the optimal value of every state
+//! and a policy acting greedily on them.
+//!
+//! Every sweep applies the Bellman optimality update to every state:
+//! `V(s) = max_a Σ_s' P(s'|s, a) · (R(s, a, s') + γ·V(s'))`, terminal states and states without actions being worth 0.
+//! Sweeps go on until no value changes by more than the tolerance
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    mdp::ExplicitMDP,
+    policy::{Policy, TabularPolicy},
+    value::{TabularValues, ValueFunction},
+};
+
+/// Solves explicit MDPs, see the [module documentation](self)
+///
+/// ```
+/// use mct_rs::{solvers::value_iteration::ValueIteration, tabular::TabularMDP};
+///
+/// // action 0 ends the episode with 1, action 1 pays 2 but only ends it half of the time
+/// let mdp = TabularMDP::new(
+///     vec![vec![vec![0.0, 1.0], vec![0.5, 0.5]], vec![vec![0.0, 1.0], vec![0.0, 0.0]]],
+///     vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+/// )
+/// .with_terminal_states([1]);
+///
+/// let solution = ValueIteration::new().solve(&mdp);
+/// assert!(solution.converged());
+/// assert_eq!(solution.action(&0), Some(&1));
+/// assert!((solution.value(&0).unwrap() - 4.0).abs() < 1e-6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueIteration {
+    tolerance: f64,
+    max_sweeps: usize,
+}
+
+impl Default for ValueIteration {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-9,
+            max_sweeps: 10_000,
+        }
+    }
+}
+
+impl ValueIteration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops once no value changes by more than this in a sweep, 1e-9 by default
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Stops after this many sweeps even if the values are still changing, 10 000 by default.
+    /// Undiscounted MDPs where some policy never ends an episode may not converge at all
+    pub fn with_max_sweeps(mut self, sweeps: usize) -> Self {
+        self.max_sweeps = sweeps;
+        self
+    }
+
+    /// Computes the optimal values of the states of the MDP, discounted by its discount factor
+    pub fn solve<M, S, A>(&self, mdp: &M) -> Solution<S, A>
+    where
+        M: ExplicitMDP<S, A>,
+        S: Clone + Eq + Hash,
+        A: Clone,
+    {
+        let model = Model::new(mdp);
+
+        let mut values = vec![0.0; model.states.len()];
+        let mut sweeps = 0;
+        let mut converged = false;
+        while sweeps < self.max_sweeps {
+            sweeps += 1;
+            let updated = (0..values.len())
+                .map(|state| model.backup(&values, state))
+                .collect::<Vec<_>>();
+            let change = updated
+                .iter()
+                .zip(&values)
+                .map(|(new, old)| (new - old).abs())
+                .fold(0.0, f64::max);
+            values = updated;
+            if change <= self.tolerance {
+                converged = true;
+                break;
+            }
+        }
+
+        model.solution(values, sweeps, converged)
+    }
+}
+
+/// (next state, probability, reward) of every transition of an action
+pub(crate) type Outcomes = Vec<(usize, f64, f64)>;
+
+/// The outcomes of every action of every state of an explicit MDP, with the states numbered,
+/// so that solvers don't query the MDP again
+pub(crate) struct Model<S, A> {
+    pub(crate) states: Vec<S>,
+    /// (action, [(next state, probability, reward)]) of every state, none for terminal states
+    pub(crate) actions: Vec<Vec<(A, Outcomes)>>,
+    discount: f64,
+}
+
+impl<S, A> Model<S, A>
+where
+    S: Clone + Eq + Hash,
+    A: Clone,
+{
+    pub(crate) fn new<M: ExplicitMDP<S, A>>(mdp: &M) -> Self {
+        let states = mdp.get_states();
+        let index = states
+            .iter()
+            .enumerate()
+            .map(|(i, state)| (state.clone(), i))
+            .collect::<HashMap<_, _>>();
+
+        let actions = states
+            .iter()
+            .map(|state| {
+                if mdp.is_terminal(state) {
+                    return vec![];
+                }
+                mdp.get_actions(state)
+                    .into_iter()
+                    .map(|action| {
+                        let outcomes = mdp
+                            .get_transitions(state, &action)
+                            .into_iter()
+                            .map(|(next, p)| {
+                                let reward = mdp.get_reward(state, &action, &next);
+                                let next = *index
+                                    .get(&next)
+                                    .expect("transitions only lead to states of the MDP");
+                                (next, p, reward)
+                            })
+                            .collect::<Vec<_>>();
+                        (action, outcomes)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Self {
+            states,
+            actions,
+            discount: mdp.get_discount_factor(),
+        }
+    }
+
+    /// Q(s, a) = Σ_s' P(s'|s, a) · (R(s, a, s') + γ·V(s'))
+    pub(crate) fn q(&self, values: &[f64], outcomes: &[(usize, f64, f64)]) -> f64 {
+        outcomes
+            .iter()
+            .map(|(next, p, reward)| p * (reward + self.discount * values[*next]))
+            .sum()
+    }
+
+    /// max_a Q(s, a), 0 for terminal states and states without actions
+    pub(crate) fn backup(&self, values: &[f64], state: usize) -> f64 {
+        self.actions[state]
+            .iter()
+            .map(|(_, outcomes)| self.q(values, outcomes))
+            .reduce(f64::max)
+            .unwrap_or(0.0)
+    }
+
+    /// The values, and the greedy policy on them
+    pub(crate) fn solution(
+        self,
+        values: Vec<f64>,
+        sweeps: usize,
+        converged: bool,
+    ) -> Solution<S, A> {
+        // the first of the best actions, in the order the MDP lists them
+        let policy = self
+            .states
+            .iter()
+            .zip(&self.actions)
+            .filter_map(|(state, actions)| {
+                let mut best: Option<(&A, f64)> = None;
+                for (action, outcomes) in actions {
+                    let value = self.q(&values, outcomes);
+                    if best.is_none_or(|(_, max)| value > max) {
+                        best = Some((action, value));
+                    }
+                }
+                best.map(|(action, _)| (state.clone(), action.clone()))
+            })
+            .collect();
+
+        Solution {
+            values: self.states.into_iter().zip(values).collect(),
+            policy,
+            sweeps,
+            converged,
+        }
+    }
+}
+
+/// The values found by [`ValueIteration::solve`] (or [`crate::solvers::prioritized_sweeping`]), and the greedy policy on them.
+///
+/// Both a [`ValueFunction`] and a [`Policy`], e.g. to guide the search on an approximation of a larger problem
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "S: serde::Serialize, A: serde::Serialize",
+        deserialize = "S: serde::Deserialize<'de> + Eq + Hash, A: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Solution<S, A> {
+    values: TabularValues<S>,
+    policy: TabularPolicy<S, A>,
+    sweeps: usize,
+    converged: bool,
+}
+
+impl<S, A> Solution<S, A>
+where
+    S: Eq + Hash,
+{
+    /// The optimal value of the state, or None if the MDP doesn't list it
+    pub fn value(&self, state: &S) -> Option<f64> {
+        self.values.get(state)
+    }
+
+    /// The best action in the state, or None if it's terminal, has no actions or isn't a state of the MDP
+    pub fn action(&self, state: &S) -> Option<&A> {
+        self.policy.get(state)
+    }
+
+    pub fn values(&self) -> &TabularValues<S> {
+        &self.values
+    }
+
+    pub fn policy(&self) -> &TabularPolicy<S, A> {
+        &self.policy
+    }
+
+    pub fn into_values(self) -> TabularValues<S> {
+        self.values
+    }
+
+    pub fn into_policy(self) -> TabularPolicy<S, A> {
+        self.policy
+    }
+
+    /// The number of sweeps over the states it took, or of single-state backups with prioritized sweeping
+    pub fn sweeps(&self) -> usize {
+        self.sweeps
+    }
+
+    /// Whether the values settled within the tolerance, rather than the solver running out of sweeps
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+}
+
+impl<S: Eq + Hash, A> ValueFunction<S> for Solution<S, A> {
+    fn value(&self, state: &S) -> f64 {
+        self.values.value(state)
+    }
+}
+
+impl<S: Eq + Hash, A: Clone> Policy<S, A> for Solution<S, A> {
+    fn action(&self, state: &S) -> Option<A> {
+        self.policy.action(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, policy::RandomRollout, strategy::Strategy, tabular::TabularMDP};
+
+    use super::*;
+
+    #[test]
+    fn test_value_iteration_agrees_with_the_search() {
+        // a chain 0 -> 1 -> 2 -> 3, where action 0 walks right for nothing and action 1 ends the episode at once,
+        // paying the index of the state it's taken in. Discounted, the best is to walk to 2 and stop there
+        let transitions = (0..4)
+            .map(|s| {
+                let right = (0..4)
+                    .map(|t| f64::from(u8::from(t == (s + 1).min(3))))
+                    .collect();
+                let stop = (0..4).map(|t| f64::from(u8::from(t == 3))).collect();
+                if s == 3 {
+                    vec![vec![0.0; 4], vec![0.0; 4]]
+                } else {
+                    vec![right, stop]
+                }
+            })
+            .collect();
+        let rewards = (0..4).map(|s| vec![0.0, s as f64]).collect();
+        let mdp = TabularMDP::new(transitions, rewards)
+            .with_terminal_states([3])
+            .with_discount_factor(0.6);
+
+        let solution = ValueIteration::new().solve(&mdp);
+        assert!(solution.converged());
+        assert_eq!(solution.action(&2), Some(&1));
+        assert_eq!(solution.action(&1), Some(&0));
+        assert_eq!(solution.action(&3), None);
+        assert!((solution.value(&2).unwrap() - 2.0).abs() < 1e-9);
+        assert!((solution.value(&0).unwrap() - 0.72).abs() < 1e-9);
+
+        let capped = ValueIteration::new().with_max_sweeps(1).solve(&mdp);
+        assert!(!capped.converged());
+        assert_eq!(capped.sweeps(), 1);
+
+        let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+        mcts.run_iterations(1000);
+        assert_eq!(
+            mcts.best_action(Strategy::MostVisited).as_ref(),
+            solution.action(&0)
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+/// A snapshot of the shape and (approximate) size of the search tree
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeStats {
+    /// Total number of nodes in the tree, including the root
+    pub nodes: usize,
+    /// Approximate number of bytes held by the tree.
+    /// This accounts for the nodes themselves (and their reference counts) plus the children vectors,
+    /// but not for any heap memory owned by the states or actions stored in them
+    pub approx_bytes: usize,
+    /// Depth of the deepest node in the tree (the root is at depth 0)
+    pub max_depth: usize,
+    /// Average number of children of the expanded (non-leaf) nodes in the tree
+    pub avg_branching_factor: f64,
+}
+
+/// What the search knows about one of the root's actions
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionStats {
+    /// The number of times the action was taken
+    pub visits: usize,
+    /// Its mean return (Q-value), from the perspective of the player to move at the root,
+    /// or of the player set by [`crate::mcts::MCTS::for_player`]
+    pub value: f64,
+}
+
+/// What [`crate::mcts::MCTS::search`] found: the action it picked, and how confident it is in it
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchResult<A> {
+    /// The action picked by the searcher's strategy
+    pub action: A,
+    /// The estimated value of the action, from the perspective of the player to move at the root,
+    /// or of the player set by [`crate::mcts::MCTS::for_player`]. So are the values of `runner_ups`
+    pub value: f64,
+    /// The number of times the action was taken, over all the searches from this root
+    pub visits: usize,
+    /// The standard error of `value`, from the spread of the returns the action got (infinite until it was taken twice).
+    /// A wide error means more budget could still change the estimate, see [`SearchResult::confidence_interval`]
+    pub std_error: f64,
+    /// The number of iterations of this search
+    pub iterations: usize,
+    /// How long this search took. Only measured when the search reads the clock anyway,
+    /// i.e. with a time budget or profiling enabled (see [`crate::clock`])
+    pub elapsed: Option<Duration>,
+    /// The next best actions as `(action, value, visits)`, most visited first,
+    /// if the searcher was asked for them (see [`crate::mcts::MCTS::with_runner_ups`])
+    pub runner_ups: Vec<(A, f64, usize)>,
+    /// How the games through the action ended, if any ended with an outcome, see [`crate::mcts::MCTS::outcome_counts`]
+    pub outcomes: Option<OutcomeCounts>,
+}
+
+/// What the value of the root says about the game for the player to move, see [`crate::mcts::MCTS::verdict`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Verdict {
+    #[default]
+    PlayOn,
+    /// The value has stayed below the resignation threshold, see [`crate::mcts::MCTS::with_resignation`]
+    Resign,
+    /// The value has stayed above the win claim threshold, see [`crate::mcts::MCTS::with_win_claim`]
+    ClaimWin,
+}
+
+/// What a call to the search loop did, see [`crate::mcts::MCTS::run_iterations`]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchStats {
+    /// The number of select/expand/simulate/backpropagate iterations
+    pub iterations: usize,
+    /// The number of rollouts simulated, one per iteration that didn't end in a terminal state
+    pub simulations: usize,
+    /// The mean number of actions played by a rollout
+    pub avg_rollout_depth: f64,
+    /// The number of actions played by the longest rollout
+    pub max_rollout_depth: usize,
+    /// The number of nodes added to the tree
+    pub nodes_created: usize,
+    /// How long the search took. Only measured when the search reads the clock anyway,
+    /// i.e. with a time budget or profiling enabled (see [`crate::clock`])
+    pub elapsed: Option<Duration>,
+    /// Time spent in each phase, only measured when profiling is enabled (see [`crate::mcts::MCTS::with_profiling`])
+    pub phases: Option<PhaseTimings>,
+}
+
+impl<A> SearchResult<A> {
+    /// The range `value ± z·std_error`, e.g. with `z = 1.96` for a 95% confidence interval
+    /// (the returns of enough visits being roughly normally distributed around their mean)
+    pub fn confidence_interval(&self, z: f64) -> (f64, f64) {
+        let margin = z * self.std_error;
+        (self.value - margin, self.value + margin)
+    }
+
+    /// The chance of winning after the action, for the player `value` is reported for, as a share of the games through it that were won.
+    /// For models that don't report outcomes, `value` is taken for the difference between the chances of a win and of a loss,
+    /// as with rewards of 1 for a win, -1 for a loss and 0 for a draw (see [`crate::game::GameMdp`]), with no draws
+    pub fn win_probability(&self) -> f64 {
+        match self.outcomes.and_then(|counts| counts.frequencies()) {
+            Some((wins, _, _)) => wins,
+            None => ((self.value + 1.0) / 2.0).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// How the games through an action of the root ended, for the player values are reported for,
+/// counted over the iterations that reached the end of a game with an outcome (see [`crate::mdp::GenerativeModel::outcome`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutcomeCounts {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl OutcomeCounts {
+    pub fn total(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The shares of wins, draws and losses, `None` before any game ended
+    pub fn frequencies(&self) -> Option<(f64, f64, f64)> {
+        let total = self.total() as f64;
+        (self.total() > 0).then(|| {
+            (
+                self.wins as f64 / total,
+                self.draws as f64 / total,
+                self.losses as f64 / total,
+            )
+        })
+    }
+}
+
+/// Raw counts accumulated over a single search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct SearchCounters {
+    /// Number of select/expand/simulate/backpropagate iterations
+    pub(crate) iterations: usize,
+    /// Number of nodes expanded, each of which executed one action on the MDP
+    pub(crate) expansions: usize,
+    /// Number of rollouts simulated
+    pub(crate) rollouts: usize,
+    /// Total number of actions executed across all the rollouts
+    pub(crate) rollout_steps: usize,
+    /// Number of actions executed by the longest rollout
+    pub(crate) max_rollout_steps: usize,
+    /// Number of nodes added to the tree
+    pub(crate) nodes: usize,
+    /// Time spent in each phase, only measured when profiling is enabled
+    pub(crate) phases: Option<PhaseTimings>,
+}
+
+impl SearchCounters {
+    pub(crate) fn stats(&self, elapsed: Option<Duration>) -> SearchStats {
+        SearchStats {
+            iterations: self.iterations,
+            simulations: self.rollouts,
+            avg_rollout_depth: self.rollout_steps as f64 / self.rollouts.max(1) as f64,
+            max_rollout_depth: self.max_rollout_steps,
+            nodes_created: self.nodes,
+            elapsed,
+            phases: self.phases,
+        }
+    }
+}
+
+/// Time spent in each of the four MCTS phases, accumulated over a search.
+/// Tells whether the MDP (`get_actions`/`execute` dominate expansion and simulation) or the tree itself (selection and backpropagation) is the bottleneck
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhaseTimings {
+    pub select: Duration,
+    pub expand: Duration,
+    pub simulate: Duration,
+    pub backpropagate: Duration,
+}
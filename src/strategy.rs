@@ -1,8 +1,40 @@
+use crate::stats::ActionStats;
+
 /// Different strategies for selecting the final action after MCTS
 #[cfg_attr(feature = "uniffi", derive(uniffi::Enum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    any(feature = "config", feature = "serde"),
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Strategy {
+    #[default]
     MostVisited,
     HighestQValue,
     Probabilistic,
     HeuristicWin, // terminal/winning move aware
+    /// The action that is both the most visited and the highest valued (the "max-robust child").
+    /// When they differ, [`crate::mcts::MCTS::search`] keeps searching for a while, and falls back on another strategy if they still do,
+    /// see [`crate::mcts::MCTS::with_robust_child`]
+    RobustChild,
+}
+
+/// Picks the final action from the statistics of every explored action of the root,
+/// for selection rules the [`Strategy`] variants don't cover, e.g. blending visits and values, or domain-specific tie-breaks.
+/// Returns `None` to pick nothing.
+///
+/// Any `Fn(&[(A, ActionStats)]) -> Option<A>` closure is a selector, see [`crate::mcts::MCTS::with_final_selector`]
+pub trait FinalSelector<A> {
+    fn select(&self, actions: &[(A, ActionStats)]) -> Option<A>;
+}
+
+impl<A, F> FinalSelector<A> for F
+where
+    F: Fn(&[(A, ActionStats)]) -> Option<A>,
+{
+    fn select(&self, actions: &[(A, ActionStats)]) -> Option<A> {
+        self(actions)
+    }
 }
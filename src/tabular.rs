@@ -0,0 +1,247 @@
+//! MDPs given as explicit tables, with states and actions numbered from 0.
+//!
+//! Small enough to write out by hand, which makes them handy for teaching, for cross-checking the search against exact solvers,
+//! and as test fixtures
+
+use crate::{
+    error::MdpError,
+    mdp::{ExplicitMDP, GenerativeModel},
+    rand::Rng,
+};
+
+/// An MDP defined by a transition tensor and a reward matrix:
+/// `transitions[s][a][s']` is the probability of reaching `s'` by taking action `a` in state `s`,
+/// and `rewards[s][a]` the reward for taking action `a` in state `s`.
+///
+/// An action is available in a state if its transition probabilities aren't all zero
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabularMDP {
+    transitions: Vec<Vec<Vec<f64>>>,
+    rewards: Vec<Vec<f64>>,
+    terminal: Vec<bool>,
+    goals: Vec<usize>,
+    initial: usize,
+    discount: f64,
+}
+
+impl TabularMDP {
+    /// Starts in state 0, with no terminal or goal states and no discounting.
+    ///
+    /// Panics if the dimensions of the tables don't match, or if the probabilities of an available action don't sum to 1
+    pub fn new(transitions: Vec<Vec<Vec<f64>>>, rewards: Vec<Vec<f64>>) -> Self {
+        let states = transitions.len();
+        assert!(states > 0, "a tabular MDP needs at least one state");
+        assert_eq!(
+            rewards.len(),
+            states,
+            "rewards must have one row per state. states={states} -> rows={}",
+            rewards.len()
+        );
+
+        let actions = transitions[0].len();
+        for (s, (row, reward_row)) in transitions.iter().zip(&rewards).enumerate() {
+            assert_eq!(
+                row.len(),
+                actions,
+                "every state must have the same number of actions. state={s}"
+            );
+            assert_eq!(
+                reward_row.len(),
+                actions,
+                "rewards must have one column per action. state={s}"
+            );
+
+            for (a, probabilities) in row.iter().enumerate() {
+                assert_eq!(
+                    probabilities.len(),
+                    states,
+                    "transitions must give a probability for every next state. state={s} action={a}"
+                );
+
+                let total = probabilities.iter().sum::<f64>();
+                assert!(
+                    total == 0.0 || (total - 1.0).abs() < 1e-9,
+                    "transition probabilities must sum to 1 (or 0 for unavailable actions). state={s} action={a} -> sum={total}"
+                );
+            }
+        }
+
+        Self {
+            transitions,
+            rewards,
+            terminal: vec![false; states],
+            goals: vec![],
+            initial: 0,
+            discount: 1.0,
+        }
+    }
+
+    pub fn with_initial_state(mut self, state: usize) -> Self {
+        assert!(state < self.states(), "no such state. state={state}");
+        self.initial = state;
+        self
+    }
+
+    /// Episodes end on reaching any of these states
+    pub fn with_terminal_states(mut self, states: impl IntoIterator<Item = usize>) -> Self {
+        for state in states {
+            self.terminal[state] = true;
+        }
+        self
+    }
+
+    pub fn with_goal_states(mut self, states: impl IntoIterator<Item = usize>) -> Self {
+        self.goals = states.into_iter().collect();
+        self
+    }
+
+    pub fn with_discount_factor(mut self, discount: f64) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    /// Number of states
+    pub fn states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Number of actions, available or not, of every state
+    pub fn actions(&self) -> usize {
+        self.transitions[0].len()
+    }
+}
+
+impl GenerativeModel<usize, usize> for TabularMDP {
+    fn get_actions(&self, state: &usize) -> Vec<usize> {
+        (0..self.actions())
+            .filter(|a| self.transitions[*state][*a].iter().any(|p| *p > 0.0))
+            .collect()
+    }
+
+    fn execute(&self, state: &usize, action: &usize, rng: &mut dyn Rng) -> (usize, f64, bool) {
+        self.try_execute(state, action, rng)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    fn try_execute(
+        &self,
+        state: &usize,
+        action: &usize,
+        rng: &mut dyn Rng,
+    ) -> Result<(usize, f64, bool), MdpError> {
+        let probabilities = &self.transitions[*state][*action];
+
+        let r = rng.gen_f64();
+        let mut cumulative = 0.0;
+        // the last reachable state catches any rounding error in the probabilities
+        let next_state = probabilities
+            .iter()
+            .position(|p| {
+                cumulative += p;
+                *p > 0.0 && cumulative >= r
+            })
+            .or_else(|| probabilities.iter().rposition(|p| *p > 0.0))
+            .ok_or(MdpError::NoTransitions)?;
+
+        let reward = self.rewards[*state][*action];
+        Ok((next_state, reward, self.terminal[next_state]))
+    }
+
+    fn is_terminal(&self, state: &usize) -> bool {
+        self.terminal[*state]
+    }
+
+    fn get_initial_state(&self) -> usize {
+        self.initial
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.discount
+    }
+
+    fn state_key(&self, state: &usize) -> Option<u64> {
+        Some(*state as u64)
+    }
+
+    fn transition_probability(
+        &self,
+        state: &usize,
+        action: &usize,
+        next_state: &usize,
+    ) -> Option<f64> {
+        Some(self.transitions[*state][*action][*next_state])
+    }
+}
+
+impl ExplicitMDP<usize, usize> for TabularMDP {
+    fn get_states(&self) -> Vec<usize> {
+        (0..self.states()).collect()
+    }
+
+    fn get_transitions(&self, state: &usize, action: &usize) -> Vec<(usize, f64)> {
+        self.transitions[*state][*action]
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, p)| *p > 0.0)
+            .collect()
+    }
+
+    fn get_reward(&self, state: &usize, action: &usize, _next_state: &usize) -> f64 {
+        self.rewards[*state][*action]
+    }
+
+    fn get_goal_states(&self) -> Vec<usize> {
+        self.goals.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, policy::RandomRollout, strategy::Strategy};
+
+    use super::*;
+
+    /// From state 0, action 0 surely pays 1 and action 1 pays 2 but only gets to the end half of the time,
+    /// falling back into state 0 otherwise. Action 1 is only available in state 0
+    fn gamble() -> TabularMDP {
+        TabularMDP::new(
+            vec![
+                vec![vec![0.0, 1.0], vec![0.5, 0.5]],
+                vec![vec![0.0, 1.0], vec![0.0, 0.0]],
+            ],
+            vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+        )
+        .with_terminal_states([1])
+    }
+
+    #[test]
+    fn test_tabular_mdp_follows_its_tables() {
+        let mdp = gamble();
+        assert_eq!(mdp.get_actions(&0), vec![0, 1]);
+        assert_eq!(mdp.get_actions(&1), vec![0]);
+        assert_eq!(mdp.get_transitions(&0, &1), vec![(0, 0.5), (1, 0.5)]);
+
+        let mut rng = crate::rand::SplitMix64::seed(3);
+        let outcomes = (0..200)
+            .map(|_| mdp.execute(&0, &1, &mut rng))
+            .collect::<Vec<_>>();
+        assert!(outcomes.contains(&(0, 2.0, false)));
+        assert!(outcomes.contains(&(1, 2.0, true)));
+        assert_eq!(mdp.execute(&0, &0, &mut rng), (1, 1.0, true));
+    }
+
+    #[test]
+    fn test_search_prefers_the_better_tabular_action() {
+        // gambling is worth 2 + 0.5 * 2 + ... = 4 against 1 for action 0
+        let mut mcts = MCTS::new(gamble(), RandomRollout::new()).with_seed(5);
+        mcts.run_iterations(500);
+        assert_eq!(mcts.best_action(Strategy::MostVisited), Some(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "must sum to 1")]
+    fn test_rejects_invalid_probabilities() {
+        TabularMDP::new(vec![vec![vec![0.5]]], vec![vec![0.0]]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Counters and gauges about running searches (behind the `metrics` feature), emitted through the `metrics` crate
+//! so that services embedding the searcher monitor it with whatever recorder they installed, e.g. a Prometheus exporter.
+//!
+//! Every call to the search loop of [`crate::mcts::MCTS`] updates, once it's over:
+//! - [`ITERATIONS`] and [`NODES`], the work it did
+//! - [`ITERATIONS_PER_SECOND`], its speed
+//! - [`TRANSPOSITION_LOOKUPS`] and [`TRANSPOSITION_HITS`], with transpositions enabled, whose ratio is the hit rate of the table
+//! - [`BEST_MOVE_FLIPS`], how many times the most visited action at the root changed during it
+//! - [`SEARCH_FAILURES`], if the MDP failed
+//!
+//! Searches read their clock to measure their speed when the feature is enabled, see [`crate::clock`]
+
+use std::time::Duration;
+
+use crate::{action::Action, clock::Clock, error::McstError, node::Node, stats::SearchCounters};
+
+/// Counter of the iterations run
+pub const ITERATIONS: &str = "mcts_iterations_total";
+/// Counter of the nodes added to trees
+pub const NODES: &str = "mcts_nodes_total";
+/// Gauge of the number of iterations per second of the last search
+pub const ITERATIONS_PER_SECOND: &str = "mcts_iterations_per_second";
+/// Counter of the states looked up in transposition tables
+pub const TRANSPOSITION_LOOKUPS: &str = "mcts_transposition_lookups_total";
+/// Counter of the states found in transposition tables, whose nodes were shared
+pub const TRANSPOSITION_HITS: &str = "mcts_transposition_hits_total";
+/// Counter of the changes of the most visited action at the root
+pub const BEST_MOVE_FLIPS: &str = "mcts_best_move_flips_total";
+/// Counter of the searches stopped by a failure of the MDP
+pub const SEARCH_FAILURES: &str = "mcts_search_failures_total";
+
+/// What a call to the search loop has done so far, to be reported once it's over
+pub(crate) struct SearchMetrics {
+    start: Duration,
+    /// Lookups and hits of the transposition table when the search started
+    table: (usize, usize),
+    /// The index of the most visited child of the root, in the order the children were added
+    best: Option<usize>,
+    flips: u64,
+}
+
+impl SearchMetrics {
+    pub(crate) fn start(clock: &dyn Clock, table: (usize, usize)) -> Self {
+        Self {
+            start: clock.now(),
+            table,
+            best: None,
+            flips: 0,
+        }
+    }
+
+    pub(crate) fn iteration<S: Eq, A: Action>(&mut self, root: &Node<S, A>) {
+        let best = root
+            .children
+            .borrow()
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, child)| child.visits())
+            .map(|(index, _)| index);
+        // the first action to be expanded isn't a change of mind
+        if best != self.best && self.best.is_some() {
+            self.flips += 1;
+        }
+        self.best = best;
+    }
+
+    pub(crate) fn finished(
+        &self,
+        clock: &dyn Clock,
+        counters: &SearchCounters,
+        table: (usize, usize),
+        outcome: &Result<(), McstError>,
+    ) {
+        metrics::counter!(ITERATIONS).increment(counters.iterations as u64);
+        metrics::counter!(NODES).increment(counters.nodes as u64);
+        metrics::counter!(BEST_MOVE_FLIPS).increment(self.flips);
+        metrics::counter!(TRANSPOSITION_LOOKUPS).increment((table.0 - self.table.0) as u64);
+        metrics::counter!(TRANSPOSITION_HITS).increment((table.1 - self.table.1) as u64);
+        if outcome.is_err() {
+            metrics::counter!(SEARCH_FAILURES).increment(1);
+        }
+
+        let elapsed = clock.now().saturating_sub(self.start).as_secs_f64();
+        if elapsed > 0.0 {
+            metrics::gauge!(ITERATIONS_PER_SECOND).set(counters.iterations as f64 / elapsed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicU64, Ordering},
+        },
+    };
+
+    use metrics::{
+        Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+
+    use crate::{mcts::MCTS, mdp::GenerativeModel, policy::RandomRollout, rand::Rng};
+
+    use super::*;
+
+    /// Keeps the value of every counter and gauge, the gauges as the bits of their `f64`
+    #[derive(Default)]
+    struct Values(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+    impl Values {
+        fn metric(&self, key: &Key) -> Arc<AtomicU64> {
+            let mut values = self.0.lock().unwrap();
+            Arc::clone(values.entry(key.name().to_string()).or_default())
+        }
+
+        /// 0 for the metrics never emitted
+        fn get(&self, name: &str) -> u64 {
+            let values = self.0.lock().unwrap();
+            values
+                .get(name)
+                .map_or(0, |value| value.load(Ordering::Relaxed))
+        }
+    }
+
+    impl Recorder for Values {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.metric(key))
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.metric(key))
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    /// Adding 1 or 2 until 6, where 1 then 2 and 2 then 1 both lead to 3
+    struct Sums;
+
+    impl GenerativeModel<u32, usize> for Sums {
+        fn get_actions(&self, _state: &u32) -> Vec<usize> {
+            vec![1, 2]
+        }
+
+        fn execute(&self, state: &u32, step: &usize, _rng: &mut dyn Rng) -> (u32, f64, bool) {
+            let next = state + *step as u32;
+            (next, f64::from(next == 6), next >= 6)
+        }
+
+        fn is_terminal(&self, state: &u32) -> bool {
+            *state >= 6
+        }
+
+        fn get_initial_state(&self) -> u32 {
+            0
+        }
+
+        fn state_key(&self, state: &u32) -> Option<u64> {
+            Some(u64::from(*state))
+        }
+    }
+
+    #[test]
+    fn test_searches_report_metrics() {
+        let values = Values::default();
+        let mut mcts = MCTS::new(Sums, RandomRollout::new())
+            .with_seed(1)
+            .with_transpositions();
+        let nodes = metrics::with_local_recorder(&values, || {
+            mcts.run_iterations(200);
+            mcts.run_iterations(100);
+            mcts.tree_stats().nodes
+        });
+
+        assert_eq!(values.get(ITERATIONS), 300);
+        // the root isn't added by an iteration
+        assert_eq!(values.get(NODES) as usize, nodes - 1);
+        assert!(values.get(TRANSPOSITION_HITS) > 0);
+        assert!(values.get(TRANSPOSITION_LOOKUPS) > values.get(TRANSPOSITION_HITS));
+        assert!(f64::from_bits(values.get(ITERATIONS_PER_SECOND)) > 0.0);
+        assert_eq!(values.get(SEARCH_FAILURES), 0);
+    }
+}
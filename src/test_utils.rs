@@ -0,0 +1,235 @@
+//! Reference MDPs small enough that their optimal actions and values are known in closed form,
+//! and assertions over repeated searches, for regression-testing rollout policies, selection policies and search configurations.
+//!
+//! A search can find the optimal action by luck or miss it by bad luck, so a single run says little.
+//! [`assert_finds_optimal_action`] runs a search under many seeds, and fails if too few of the runs found an optimal action
+//!
+//! ```
+//! use mct_rs::{mcts::MCTS, policy::RandomRollout, strategy::Strategy, test_utils::Reference};
+//!
+//! for reference in Reference::all() {
+//!     reference.assert_solved_by(20, 0.95, |mdp, seed| {
+//!         let mut mcts = MCTS::new(mdp.clone(), RandomRollout::new())
+//!             .with_seed(seed)
+//!             .with_chance_nodes();
+//!         mcts.run_iterations(500);
+//!         mcts.best_action(Strategy::MostVisited)
+//!     });
+//! }
+//! ```
+
+use std::fmt::Debug;
+
+use crate::tabular::TabularMDP;
+
+/// A tiny MDP, started in state 0, with the optimal actions and value of that state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub name: &'static str,
+    pub mdp: TabularMDP,
+    pub optimal_actions: Vec<usize>,
+    pub optimal_value: f64,
+}
+
+impl Reference {
+    /// A single decision between rewards of 0.2, 0.5 and 0.8: action 2, worth 0.8
+    pub fn best_of_three() -> Self {
+        let end = vec![vec![0.0, 1.0]; 3];
+        Self {
+            name: "best of three",
+            mdp: TabularMDP::new(
+                vec![end, vec![vec![0.0; 2]; 3]],
+                vec![vec![0.2, 0.5, 0.8], vec![0.0; 3]],
+            )
+            .with_terminal_states([1]),
+            optimal_actions: vec![2],
+            optimal_value: 0.8,
+        }
+    }
+
+    /// Action 0 ends the episode with a reward of 1, action 1 pays nothing but leads to a reward of 2:
+    /// action 1, worth 2, which a search that doesn't look past the first reward misses
+    pub fn delayed_reward() -> Self {
+        Self {
+            name: "delayed reward",
+            mdp: TabularMDP::new(
+                vec![
+                    vec![vec![0.0, 0.0, 1.0], vec![0.0, 1.0, 0.0]],
+                    vec![vec![0.0, 0.0, 1.0], vec![0.0; 3]],
+                    vec![vec![0.0; 3]; 2],
+                ],
+                vec![vec![1.0, 0.0], vec![2.0, 0.0], vec![0.0; 2]],
+            )
+            .with_terminal_states([2]),
+            optimal_actions: vec![1],
+            optimal_value: 2.0,
+        }
+    }
+
+    /// Action 0 surely pays 0.5, action 1 pays 1 with probability 0.4 and nothing otherwise, a step later:
+    /// action 0, worth 0.5. Search it with chance nodes (see [`crate::mcts::MCTS::with_chance_nodes`]),
+    /// or the gamble is worth whatever its first outcome paid
+    pub fn safe_or_gamble() -> Self {
+        Self {
+            name: "safe or gamble",
+            mdp: TabularMDP::new(
+                vec![
+                    vec![vec![0.0, 0.0, 0.0, 1.0], vec![0.0, 0.4, 0.6, 0.0]],
+                    vec![vec![0.0, 0.0, 0.0, 1.0], vec![0.0; 4]],
+                    vec![vec![0.0, 0.0, 0.0, 1.0], vec![0.0; 4]],
+                    vec![vec![0.0; 4]; 2],
+                ],
+                vec![vec![0.5, 0.0], vec![1.0, 0.0], vec![0.0; 2], vec![0.0; 2]],
+            )
+            .with_terminal_states([3]),
+            optimal_actions: vec![0],
+            optimal_value: 0.5,
+        }
+    }
+
+    /// A corridor of `length` cells, discounted by 0.9, where action 0 steps back (or stays in the first cell)
+    /// and action 1 steps forward, paying 1 on reaching the last cell: action 1, worth 0.9^(length - 2).
+    ///
+    /// Panics if the corridor is shorter than 2 cells
+    pub fn discounted_corridor(length: usize) -> Self {
+        assert!(
+            length >= 2,
+            "a corridor needs at least 2 cells. length={length}"
+        );
+        let step =
+            |cell: usize| -> Vec<f64> { (0..length).map(|s| f64::from(s == cell)).collect() };
+        let mut transitions = (0..length - 1)
+            .map(|cell| vec![step(cell.saturating_sub(1)), step(cell + 1)])
+            .collect::<Vec<_>>();
+        transitions.push(vec![vec![0.0; length]; 2]);
+        let mut rewards = vec![vec![0.0; 2]; length];
+        rewards[length - 2][1] = 1.0;
+
+        Self {
+            name: "discounted corridor",
+            mdp: TabularMDP::new(transitions, rewards)
+                .with_terminal_states([length - 1])
+                .with_goal_states([length - 1])
+                .with_discount_factor(0.9),
+            optimal_actions: vec![1],
+            optimal_value: 0.9f64.powi(length as i32 - 2),
+        }
+    }
+
+    /// Every reference MDP, with a corridor of 5 cells
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::best_of_three(),
+            Self::delayed_reward(),
+            Self::safe_or_gamble(),
+            Self::discounted_corridor(5),
+        ]
+    }
+
+    /// Panics if `search`, given the MDP and a seed, finds an optimal action in less than `min_rate` of `runs` runs,
+    /// see [`assert_finds_optimal_action`]
+    pub fn assert_solved_by(
+        &self,
+        runs: u64,
+        min_rate: f64,
+        mut search: impl FnMut(&TabularMDP, u64) -> Option<usize>,
+    ) {
+        let rate = optimal_action_rate(runs, &self.optimal_actions, |seed| search(&self.mdp, seed));
+        assert!(
+            rate >= min_rate,
+            "{}: found an optimal action in {:.0}% of {runs} runs, below {:.0}%",
+            self.name,
+            rate * 100.0,
+            min_rate * 100.0
+        );
+    }
+}
+
+/// The share of `runs` runs of `search`, given the seeds 0 to `runs - 1`, that found one of the `optimal` actions
+pub fn optimal_action_rate<A: PartialEq>(
+    runs: u64,
+    optimal: &[A],
+    mut search: impl FnMut(u64) -> Option<A>,
+) -> f64 {
+    let found = (0..runs)
+        .filter(|seed| search(*seed).is_some_and(|action| optimal.contains(&action)))
+        .count();
+    found as f64 / runs as f64
+}
+
+/// Panics if `search`, given the seeds 0 to `runs - 1`, finds one of the `optimal` actions in less than `min_rate` of the runs,
+/// e.g. 0.95 for "in at least 95% of the runs"
+pub fn assert_finds_optimal_action<A: PartialEq + Debug>(
+    runs: u64,
+    min_rate: f64,
+    optimal: &[A],
+    search: impl FnMut(u64) -> Option<A>,
+) {
+    let rate = optimal_action_rate(runs, optimal, search);
+    assert!(
+        rate >= min_rate,
+        "found one of {optimal:?} in {:.0}% of {runs} runs, below {:.0}%",
+        rate * 100.0,
+        min_rate * 100.0
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        envs::blackjack::{Blackjack, Hand, optimal_decision},
+        mcts::MCTS,
+        mdp::GenerativeModel,
+        policy::RandomRollout,
+        solvers::value_iteration::ValueIteration,
+        strategy::Strategy,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_references_match_value_iteration() {
+        for reference in Reference::all() {
+            let solution = ValueIteration::new().solve(&reference.mdp);
+            let start = reference.mdp.get_initial_state();
+            let value = solution.value(&start).unwrap();
+            assert!(
+                (value - reference.optimal_value).abs() < 1e-6,
+                "{} {value}",
+                reference.name
+            );
+            assert!(
+                reference
+                    .optimal_actions
+                    .contains(solution.action(&start).unwrap()),
+                "{}",
+                reference.name
+            );
+        }
+        assert_eq!(Reference::discounted_corridor(2).optimal_value, 1.0);
+    }
+
+    #[test]
+    fn test_search_rates_count_optimal_actions() {
+        assert_eq!(optimal_action_rate(4, &[1, 2], Some), 0.5);
+        assert_eq!(optimal_action_rate(4, &[1], |_| None), 0.0);
+        let failed = std::panic::catch_unwind(|| {
+            assert_finds_optimal_action(10, 0.95, &[0], |seed| Some(seed % 2));
+        });
+        assert!(failed.is_err());
+
+        // hitting on 11 against the dealer's 6, as Blackjack's closed-form policy says
+        let hand = Hand {
+            sum: 11,
+            dealer: 6,
+            usable_ace: false,
+        };
+        assert_finds_optimal_action(10, 0.9, &[optimal_decision(&hand)], |seed| {
+            let mut mcts = MCTS::new(Blackjack::new(hand), RandomRollout::new())
+                .with_seed(seed)
+                .with_chance_nodes();
+            mcts.run_iterations(2000);
+            mcts.best_action(Strategy::MostVisited)
+        });
+    }
+}
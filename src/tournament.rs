@@ -0,0 +1,394 @@
+//! Round-robin tournaments between agents on a two-player [`Game`], rated on the Elo scale,
+//! to tell whether a change of parameters made an agent stronger, and by how much.
+//!
+//! Every pair of agents plays the same number of games, taking turns playing first (see [`crate::selfplay::Match`]).
+//! Ratings are fitted to all the games at once (the Bradley-Terry model, which the Elo scale is the logarithm of),
+//! with a 95% confidence interval each, so that a difference between two agents only means something
+//! when their intervals don't overlap. Between two agents, [`EloEstimate::from_results`] gives the difference directly
+//!
+//! ```
+//! use mct_rs::{
+//!     budget::Budget,
+//!     envs::nim::Nim,
+//!     mcts::MCTS,
+//!     policy::RandomRollout,
+//!     selfplay::PolicyAgent,
+//!     tournament::Tournament,
+//! };
+//!
+//! let result = Tournament::new(Nim::new([3, 4, 5]))
+//!     .with_games_per_pairing(4)
+//!     .with_agent("uct-500", Budget::Iterations(500), |mdp| {
+//!         MCTS::new(mdp, RandomRollout::new()).with_seed(1).with_transpositions()
+//!     })
+//!     .with_agent("uct-20", Budget::Iterations(20), |mdp| MCTS::new(mdp, RandomRollout::new()).with_seed(1))
+//!     .with_agent("random", Budget::Iterations(1), |_| PolicyAgent::new(RandomRollout).with_seed(1))
+//!     .run();
+//!
+//! println!("{result}");
+//! let ratings = result.ratings();
+//! assert!(ratings[0].elo.elo > ratings[2].elo.elo);
+//! ```
+
+use std::fmt::Display;
+
+use crate::{
+    budget::Budget,
+    compare::Planner,
+    error::McstError,
+    game::{Game, GameMdp},
+    selfplay::{Agent, PlayedGame, play},
+};
+
+/// The z-score of a two-sided 95% confidence interval
+const Z_95: f64 = 1.959963984540054;
+
+/// The Elo difference giving this expected score
+fn elo_of_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// A rating, or a difference of ratings, on the Elo scale, with its 95% confidence interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EloEstimate {
+    pub elo: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl EloEstimate {
+    /// How much stronger a player is than their opponent, from their wins, draws and losses against them.
+    ///
+    /// The interval comes from the normal approximation of the player's score. A player who scored every point
+    /// (or none) is infinitely stronger (or weaker), and without games the difference is 0, anywhere in between
+    pub fn from_results(wins: usize, draws: usize, losses: usize) -> Self {
+        let games = (wins + draws + losses) as f64;
+        if games == 0.0 {
+            return Self {
+                elo: 0.0,
+                lower: f64::NEG_INFINITY,
+                upper: f64::INFINITY,
+            };
+        }
+
+        let score = (wins as f64 + draws as f64 / 2.0) / games;
+        let variance = (wins as f64 * (1.0 - score).powi(2)
+            + draws as f64 * (0.5 - score).powi(2)
+            + losses as f64 * score.powi(2))
+            / games;
+        let margin = Z_95 * (variance / games).sqrt();
+        Self {
+            elo: elo_of_score(score),
+            lower: elo_of_score((score - margin).max(0.0)),
+            upper: elo_of_score((score + margin).min(1.0)),
+        }
+    }
+}
+
+impl Display for EloEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:+.1} [{:+.1}, {:+.1}]",
+            self.elo, self.lower, self.upper
+        )
+    }
+}
+
+/// How an agent did in the tournament, see [`TournamentResult::ratings`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rating {
+    pub agent: String,
+    pub games: usize,
+    /// The agent's share of the points of its games, a win counting 1 and a draw 1/2
+    pub score: f64,
+    /// Relative to the average agent of the tournament
+    pub elo: EloEstimate,
+}
+
+impl Display for Rating {
+    /// A single line of `key=value` pairs, like [`crate::bench::BenchReport`]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "agent={} games={} score={:.3} elo={:+.1} lower={:+.1} upper={:+.1}",
+            self.agent, self.games, self.score, self.elo.elo, self.elo.lower, self.elo.upper
+        )
+    }
+}
+
+/// The games of a tournament, see [`Tournament::run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TournamentResult<G: Game> {
+    /// The names of the agents, in the order they were added, which the seats of the games refer to
+    pub agents: Vec<String>,
+    pub games: Vec<PlayedGame<G>>,
+}
+
+impl<G: Game> TournamentResult<G> {
+    /// The wins, draws and losses of `agent` against `opponent`
+    pub fn results(&self, agent: usize, opponent: usize) -> (usize, usize, usize) {
+        let (mut wins, mut draws, mut losses) = (0, 0, 0);
+        for game in &self.games {
+            if !(game.seats.contains(&agent) && game.seats.contains(&opponent)) {
+                continue;
+            }
+            match game.winning_agent() {
+                Some(winner) if winner == agent => wins += 1,
+                Some(_) => losses += 1,
+                None => draws += 1,
+            }
+        }
+        (wins, draws, losses)
+    }
+
+    /// How much stronger `agent` is than `opponent`, from their games against each other only
+    pub fn head_to_head(&self, agent: usize, opponent: usize) -> EloEstimate {
+        let (wins, draws, losses) = self.results(agent, opponent);
+        EloEstimate::from_results(wins, draws, losses)
+    }
+
+    /// The ratings of the agents, in the order they were added, fitted to all the games by maximum likelihood
+    /// and centred on 0. A virtual draw is added to every pairing, so that the ratings of agents
+    /// who won or lost all their games stay finite
+    pub fn ratings(&self) -> Vec<Rating> {
+        let agents = self.agents.len();
+        // games and points of every agent against every other one, with the virtual draws
+        let mut games = vec![vec![0.0; agents]; agents];
+        let mut points = vec![0.0; agents];
+        for i in 0..agents {
+            for j in (0..agents).filter(|j| *j != i) {
+                let (wins, draws, losses) = self.results(i, j);
+                if wins + draws + losses > 0 {
+                    games[i][j] = (wins + draws + losses) as f64 + 1.0;
+                    points[i] += wins as f64 + (draws as f64 + 1.0) / 2.0;
+                }
+            }
+        }
+
+        // minorization-maximization (Hunter, 2004), on the strengths of the agents, their ratings' exponentials
+        let mut strengths = vec![1.0; agents];
+        for _ in 0..10_000 {
+            let mut next = (0..agents)
+                .map(|i| {
+                    let denominator = (0..agents)
+                        .filter(|j| games[i][*j] > 0.0)
+                        .map(|j| games[i][j] / (strengths[i] + strengths[j]))
+                        .sum::<f64>();
+                    match denominator > 0.0 {
+                        true => points[i] / denominator,
+                        false => strengths[i],
+                    }
+                })
+                .collect::<Vec<f64>>();
+            let mean_log = next.iter().map(|s| s.ln()).sum::<f64>() / agents as f64;
+            next.iter_mut().for_each(|s| *s /= mean_log.exp());
+            let change = next
+                .iter()
+                .zip(&strengths)
+                .map(|(a, b)| (a.ln() - b.ln()).abs())
+                .fold(0.0, f64::max);
+            strengths = next;
+            if change < 1e-10 {
+                break;
+            }
+        }
+
+        let scale = 400.0 / std::f64::consts::LN_10;
+        (0..agents)
+            .map(|i| {
+                let information = (0..agents)
+                    .filter(|j| games[i][*j] > 0.0)
+                    .map(|j| {
+                        let p = strengths[i] / (strengths[i] + strengths[j]);
+                        games[i][j] * p * (1.0 - p)
+                    })
+                    .sum::<f64>();
+                let elo = scale * strengths[i].ln();
+                let margin = Z_95 * scale / information.sqrt();
+                let played = self
+                    .games
+                    .iter()
+                    .filter(|game| game.seats.contains(&i))
+                    .count();
+                let won = self
+                    .games
+                    .iter()
+                    .filter(|game| game.seats.contains(&i))
+                    .map(|game| match game.winning_agent() {
+                        Some(winner) if winner == i => 1.0,
+                        Some(_) => 0.0,
+                        None => 0.5,
+                    })
+                    .sum::<f64>();
+                Rating {
+                    agent: self.agents[i].clone(),
+                    games: played,
+                    score: match played {
+                        0 => 0.5,
+                        _ => won / played as f64,
+                    },
+                    elo: EloEstimate {
+                        elo,
+                        lower: elo - margin,
+                        upper: elo + margin,
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+impl<G: Game> Display for TournamentResult<G> {
+    /// The ratings, one line per agent
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, rating) in self.ratings().iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{rating}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A round-robin tournament, see the [module documentation](self)
+pub struct Tournament<'a, G: Game> {
+    start: G,
+    agents: Vec<Agent<'a, G>>,
+    games_per_pairing: usize,
+    max_moves: usize,
+}
+
+impl<'a, G: Game + 'a> Tournament<'a, G> {
+    /// Games start from `start`. Every pair of agents plays 10 games, stopped as draws after 1000 moves
+    pub fn new(start: G) -> Self {
+        Self {
+            start,
+            agents: vec![],
+            games_per_pairing: 10,
+            max_moves: 1000,
+        }
+    }
+
+    /// Adds an agent, built by `create` for every game, which gets `budget` for every move
+    pub fn with_agent<P, F>(mut self, name: &str, budget: Budget, create: F) -> Self
+    where
+        P: Planner<G, G::Move> + 'a,
+        F: Fn(GameMdp<G>) -> P + 'a,
+    {
+        self.agents.push(Agent::new(name, budget, create));
+        self
+    }
+
+    /// The number of games every pair of agents plays, each of them playing first in half of them (the first added
+    /// of the two in the extra game of an odd number)
+    pub fn with_games_per_pairing(mut self, games: usize) -> Self {
+        self.games_per_pairing = games;
+        self
+    }
+
+    /// Stops games after this many moves, as draws
+    pub fn with_max_moves(mut self, moves: usize) -> Self {
+        self.max_moves = moves;
+        self
+    }
+
+    /// Plays every pairing, in the order the agents were added.
+    ///
+    /// Panics unless the game has two players, or if an agent fails, see [`Tournament::try_run`]
+    pub fn run(&self) -> TournamentResult<G> {
+        self.try_run()
+            .unwrap_or_else(|error| panic!("tournament failed: {error}"))
+    }
+
+    /// Same as [`Tournament::run`], but stops at the first failure of an agent and returns it, see [`crate::selfplay::Match::try_run`]
+    pub fn try_run(&self) -> Result<TournamentResult<G>, McstError> {
+        assert_eq!(
+            self.start.num_players(),
+            2,
+            "a tournament needs a two-player game"
+        );
+
+        let agents = self.agents.len();
+        let mut games = vec![];
+        for first in 0..agents {
+            for second in first + 1..agents {
+                for game in 0..self.games_per_pairing {
+                    let seats = match game % 2 {
+                        0 => [first, second],
+                        _ => [second, first],
+                    };
+                    games.push(play(&self.start, &self.agents, seats, self.max_moves)?);
+                }
+            }
+        }
+        Ok(TournamentResult {
+            agents: self.agents.iter().map(|agent| agent.name.clone()).collect(),
+            games,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{envs::nim::Nim, mcts::MCTS, policy::RandomRollout, selfplay::PolicyAgent};
+
+    use super::*;
+
+    #[test]
+    fn test_elo_follows_the_score() {
+        let even = EloEstimate::from_results(3, 4, 3);
+        assert_eq!(even.elo, 0.0);
+        assert!(even.lower < 0.0 && even.upper > 0.0);
+        assert!((even.lower + even.upper).abs() < 1e-9);
+
+        // two thirds of the points are worth 120 Elo
+        let ahead = EloEstimate::from_results(3, 2, 1);
+        assert!((ahead.elo - 120.41).abs() < 0.01, "{ahead}");
+        let behind = EloEstimate::from_results(1, 2, 3);
+        assert!((ahead.elo + behind.elo).abs() < 1e-9);
+        assert!(ahead.lower < ahead.elo && ahead.elo < ahead.upper);
+
+        // more games, narrower intervals
+        let more = EloEstimate::from_results(30, 20, 10);
+        assert!(more.upper - more.lower < ahead.upper - ahead.lower);
+        assert_eq!(EloEstimate::from_results(5, 0, 0).elo, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_tournaments_rate_stronger_agents_higher() {
+        let result = Tournament::new(Nim::new([3, 4, 5]))
+            .with_games_per_pairing(20)
+            .with_agent("uct", Budget::Iterations(1000), |mdp| {
+                MCTS::new(mdp, RandomRollout::new())
+                    .with_seed(1)
+                    .with_transpositions()
+            })
+            .with_agent("random", Budget::Iterations(1), |_| {
+                PolicyAgent::new(RandomRollout).with_seed(1)
+            })
+            .with_agent("also random", Budget::Iterations(1), |_| {
+                PolicyAgent::new(RandomRollout).with_seed(2)
+            })
+            .run();
+
+        assert_eq!(result.games.len(), 3 * 20);
+        assert_eq!(result.results(0, 1), (20, 0, 0));
+        assert_eq!(result.head_to_head(1, 0).elo, f64::NEG_INFINITY);
+
+        let ratings = result.ratings();
+        assert_eq!(ratings.len(), 3);
+        assert!(ratings.iter().all(|rating| rating.games == 40));
+        assert_eq!(ratings[0].score, 1.0);
+        // finite, centred, and the search clearly ahead of both random players
+        let total = ratings.iter().map(|rating| rating.elo.elo).sum::<f64>();
+        assert!(total.abs() < 1e-6);
+        for random in &ratings[1..] {
+            assert!(ratings[0].elo.lower > random.elo.upper, "{result}");
+        }
+        assert_eq!(result.to_string().lines().count(), 3);
+    }
+}
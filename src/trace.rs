@@ -0,0 +1,67 @@
+//! Structured events about a running search (behind the `tracing` feature), for services that already collect `tracing` data.
+//!
+//! Every call to the search loop runs in an `mcts_search` span, and within it:
+//! - a `DEBUG` event every [`crate::mcts::MCTS::with_trace_interval`] iterations, with the root's visits so far
+//! - a `DEBUG` event whenever the most visited action at the root changes
+//! - an `INFO` event when the budget is used up, or a `WARN` event when the MDP fails
+
+use tracing::Level;
+
+use crate::{action::Action, error::McstError, node::Node};
+
+/// What the search reported so far
+pub(crate) struct SearchTrace {
+    interval: usize,
+    /// The index of the most visited child of the root, in the order the children were added
+    best: Option<usize>,
+}
+
+impl SearchTrace {
+    pub(crate) fn new(interval: usize) -> Self {
+        Self {
+            interval,
+            best: None,
+        }
+    }
+
+    pub(crate) fn iteration<S: Eq, A: Action>(&mut self, iteration: usize, root: &Node<S, A>) {
+        if !tracing::enabled!(Level::DEBUG) {
+            return;
+        }
+
+        if self.interval > 0 && iteration.is_multiple_of(self.interval) {
+            tracing::debug!(
+                iteration,
+                root_visits = *root.visits.borrow(),
+                "search progress"
+            );
+        }
+
+        let children = root.children.borrow();
+        let best = children
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, child)| child.visits())
+            .map(|(index, _)| index);
+        if best != self.best {
+            self.best = best;
+            if let Some(index) = best {
+                let child = &children[index];
+                tracing::debug!(
+                    iteration,
+                    action = ?child.action,
+                    visits = child.visits(),
+                    value = child.q_value(),
+                    "best move changed"
+                );
+            }
+        }
+    }
+
+    pub(crate) fn finished(&self, iterations: usize, outcome: &Result<(), McstError>) {
+        match outcome {
+            Ok(()) => tracing::info!(iterations, "budget exhausted"),
+            Err(error) => tracing::warn!(iterations, %error, "search failed"),
+        }
+    }
+}
@@ -0,0 +1,541 @@
+//! Training examples for value and policy networks, as self-play produces them:
+//! the encoded state searched from, how the search spread its visits over the actions, and how the game ended.
+//!
+//! Examples are streamed into an [`ExampleSink`] as they're produced rather than kept until the end of a run.
+//! [`JsonLinesWriter`] (behind the `serde` feature) writes them one JSON object per line, which dataframe libraries
+//! read directly; columnar formats such as Parquet plug in as other sinks.
+//!
+//! [`SelfPlayGenerator`] closes the loop on a two-player [`Game`]: a searcher plays itself, picking early moves
+//! in proportion to their visits for variety (see [`SelfPlayGenerator::with_temperature`]), resigning lost games
+//! to save time (see [`SelfPlayGenerator::with_resignation`]), and every position becomes an example once the game is over
+//!
+//! ```
+//! use mct_rs::{
+//!     budget::Budget,
+//!     envs::nim::Nim,
+//!     mcts::MCTS,
+//!     neural::StateEncoder,
+//!     policy::RandomRollout,
+//!     training::{SelfPlayGenerator, TrainingExample},
+//! };
+//!
+//! struct Heaps;
+//!
+//! impl StateEncoder<Nim> for Heaps {
+//!     fn shape(&self) -> Vec<usize> {
+//!         vec![3]
+//!     }
+//!
+//!     fn encode(&self, nim: &Nim, features: &mut Vec<f32>) {
+//!         features.extend(nim.heaps().iter().map(|heap| *heap as f32));
+//!     }
+//! }
+//!
+//! let mut generator = SelfPlayGenerator::new(Nim::new([3, 4, 5]), Heaps, Budget::Iterations(200), |mdp| {
+//!     MCTS::new(mdp, RandomRollout::new()).with_seed(1)
+//! })
+//! .with_seed(1);
+//!
+//! let mut examples: Vec<TrainingExample<_>> = vec![];
+//! let report = generator.run(4, &mut examples);
+//! assert_eq!(report.examples, examples.len());
+//! ```
+
+use std::{error::Error, fmt::Display, io};
+
+use crate::{
+    budget::Budget,
+    compare::Planner,
+    error::{McstError, MdpError},
+    game::{Game, GameMdp},
+    mdp::PlayerId,
+    neural::StateEncoder,
+    rand::{Rng, SplitMix64},
+    selfplay::Agent,
+    stats::ActionStats,
+};
+
+/// What a position taught the search, see the [module documentation](self)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrainingExample<A> {
+    /// The state, as encoded for the network (see [`crate::neural::StateEncoder`])
+    pub features: Vec<f32>,
+    /// The share of the visits of every action searched, see [`crate::mcts::MCTS::visit_distribution`]
+    pub policy: Vec<(A, f64)>,
+    /// The final result of the game, from the perspective of the player to move in the state
+    pub outcome: f64,
+}
+
+/// Where training examples are streamed to
+pub trait ExampleSink<A> {
+    fn write(&mut self, example: &TrainingExample<A>) -> io::Result<()>;
+
+    /// Makes sure every example written so far is stored, e.g. before the run ends
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps the examples in memory
+impl<A: Clone> ExampleSink<A> for Vec<TrainingExample<A>> {
+    fn write(&mut self, example: &TrainingExample<A>) -> io::Result<()> {
+        self.push(example.clone());
+        Ok(())
+    }
+}
+
+/// Writes examples as JSON lines, e.g. to a buffered file
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct JsonLinesWriter<W> {
+    writer: W,
+    written: usize,
+}
+
+#[cfg(feature = "serde")]
+impl<W: io::Write> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, written: 0 }
+    }
+
+    /// The number of examples written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<A, W> ExampleSink<A> for JsonLinesWriter<W>
+where
+    A: serde::Serialize,
+    W: io::Write,
+{
+    fn write(&mut self, example: &TrainingExample<A>) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, example)?;
+        self.writer.write_all(b"\n")?;
+        self.written += 1;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Why self-play stopped generating examples
+#[derive(Debug)]
+pub enum GenerationError {
+    /// The searcher failed, or passed or played an illegal move
+    Search(McstError),
+    /// The sink couldn't take an example
+    Sink(io::Error),
+}
+
+impl Display for GenerationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Search(error) => write!(f, "self-play failed: {error}"),
+            Self::Sink(error) => write!(f, "couldn't write an example: {error}"),
+        }
+    }
+}
+
+impl Error for GenerationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Search(error) => Some(error),
+            Self::Sink(error) => Some(error),
+        }
+    }
+}
+
+/// What a run of [`SelfPlayGenerator`] produced
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenerationReport {
+    pub games: usize,
+    pub examples: usize,
+    /// The games a player resigned
+    pub resignations: usize,
+    /// The games played out to check resignations, in which a player would have resigned
+    pub resignation_checks: usize,
+    /// Those of `resignation_checks` that the player who would have resigned didn't lose after all.
+    /// Their share estimates how often resigning throws away a game, to tune the threshold by
+    pub false_resignations: usize,
+}
+
+impl Display for GenerationReport {
+    /// A single line of `key=value` pairs, like [`crate::bench::BenchReport`]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "games={} examples={} resignations={} resignation_checks={} false_resignations={}",
+            self.games,
+            self.examples,
+            self.resignations,
+            self.resignation_checks,
+            self.false_resignations
+        )
+    }
+}
+
+/// A position waiting for the end of its game to become an example
+struct Pending<A> {
+    features: Vec<f32>,
+    policy: Vec<(A, f64)>,
+    player: PlayerId,
+}
+
+/// Generates training examples by self-play, see the [module documentation](self)
+pub struct SelfPlayGenerator<'a, G: Game, E> {
+    start: G,
+    encoder: E,
+    agent: Agent<'a, G>,
+    temperature: Box<dyn Fn(usize) -> f64 + 'a>,
+    resignation: Option<f64>,
+    resignation_checks: f64,
+    max_moves: usize,
+    rng: Box<dyn Rng>,
+}
+
+impl<'a, G: Game + 'a, E: StateEncoder<G>> SelfPlayGenerator<'a, G, E> {
+    /// Games start from `start`, and both sides are played by a planner built by `create` for every game,
+    /// with `budget` for every move. The policy of an example is the share of the visits of every move
+    /// (see [`Planner::root_stats`]), so the planner should be a searcher.
+    ///
+    /// Samples the first 30 moves of every game at a temperature of 1, never resigns, and stops games after 1000 moves, as draws
+    pub fn new<P, F>(start: G, encoder: E, budget: Budget, create: F) -> Self
+    where
+        P: Planner<G, G::Move> + 'a,
+        F: Fn(GameMdp<G>) -> P + 'a,
+    {
+        Self {
+            start,
+            encoder,
+            agent: Agent::new("self-play", budget, create),
+            temperature: Box::new(|moves| match moves < 30 {
+                true => 1.0,
+                false => 0.0,
+            }),
+            resignation: None,
+            resignation_checks: 0.1,
+            max_moves: 1000,
+            rng: Box::new(SplitMix64::from_entropy()),
+        }
+    }
+
+    /// The temperature of every move, by the number of moves played before it. At a temperature `t`, moves are sampled
+    /// with probabilities proportional to their visits to the power of `1/t`, so 1 follows the visits and higher temperatures
+    /// flatten them. At 0, the planner's own pick is played
+    pub fn with_temperature<F: Fn(usize) -> f64 + 'a>(mut self, schedule: F) -> Self {
+        self.temperature = Box::new(schedule);
+        self
+    }
+
+    /// Resigns for the player to move once the value of their best move falls below `threshold`
+    /// (e.g. -0.9, with values between -1 and 1), ending the game as their loss. The position they resign in is still an example
+    pub fn with_resignation(mut self, threshold: f64) -> Self {
+        self.resignation = Some(threshold);
+        self
+    }
+
+    /// The share of the games played out to the end despite a resignation, to count how many of those would have been lost anyway
+    /// (see [`GenerationReport::false_resignations`]). 0.1 by default
+    pub fn with_resignation_checks(mut self, share: f64) -> Self {
+        self.resignation_checks = share;
+        self
+    }
+
+    /// Stops games after this many moves, as draws
+    pub fn with_max_moves(mut self, moves: usize) -> Self {
+        self.max_moves = moves;
+        self
+    }
+
+    /// Draws the randomness of the sampled moves and of the resignation checks from the given generator,
+    /// see [`crate::mcts::MCTS::with_rng`]
+    pub fn with_rng<R: Rng + 'static>(mut self, rng: R) -> Self {
+        self.rng = Box::new(rng);
+        self
+    }
+
+    /// Makes the moves sampled deterministic, see [`crate::mcts::MCTS::with_seed`]
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(SplitMix64::seed(seed))
+    }
+
+    /// Plays `games` games, writing the examples of every game to `sink` once it's over, and flushing it at the end.
+    ///
+    /// Panics unless the game has two players, or if the planner or the sink fails, see [`SelfPlayGenerator::try_run`]
+    pub fn run<S: ExampleSink<G::Move>>(&mut self, games: usize, sink: &mut S) -> GenerationReport {
+        self.try_run(games, sink)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Same as [`SelfPlayGenerator::run`], but stops at the first failure and returns it
+    pub fn try_run<S: ExampleSink<G::Move>>(
+        &mut self,
+        games: usize,
+        sink: &mut S,
+    ) -> Result<GenerationReport, GenerationError> {
+        assert_eq!(
+            self.start.num_players(),
+            2,
+            "self-play needs a two-player game"
+        );
+        let mut report = GenerationReport::default();
+        for _ in 0..games {
+            self.play(sink, &mut report)?;
+        }
+        sink.flush().map_err(GenerationError::Sink)?;
+        Ok(report)
+    }
+
+    /// Plays a game, and writes its examples
+    fn play<S: ExampleSink<G::Move>>(
+        &mut self,
+        sink: &mut S,
+        report: &mut GenerationReport,
+    ) -> Result<(), GenerationError> {
+        let checked = self.resignation.is_some() && self.rng.gen_f64() < self.resignation_checks;
+        let mut planner = (self.agent.create)(GameMdp::new(self.start.clone()));
+        let mut position = self.start.clone();
+        let mut pending = vec![];
+        let mut moves = 0;
+        // the player who resigned, or would have in a checked game
+        let mut resigned = None;
+
+        while moves < self.max_moves
+            && position.winner().is_none()
+            && !position.legal_moves().is_empty()
+        {
+            let player = position.current_player();
+            let picked = planner
+                .plan(&position, self.agent.budget)
+                .map_err(GenerationError::Search)?;
+            let stats = planner.root_stats();
+
+            let mut features = vec![];
+            self.encoder.encode(&position, &mut features);
+            let total = stats
+                .iter()
+                .map(|(_, stats)| stats.visits)
+                .sum::<usize>()
+                .max(1);
+            pending.push(Pending {
+                features,
+                policy: stats
+                    .iter()
+                    .map(|(mv, stats)| (mv.clone(), stats.visits as f64 / total as f64))
+                    .collect(),
+                player,
+            });
+
+            let best = stats.iter().map(|(_, stats)| stats.value).reduce(f64::max);
+            let hopeless = self
+                .resignation
+                .zip(best)
+                .is_some_and(|(threshold, best)| best < threshold);
+            if resigned.is_none() && hopeless {
+                resigned = Some(player);
+                if !checked {
+                    break;
+                }
+            }
+
+            let mv = match self.sample(&stats, (self.temperature)(moves)) {
+                Some(mv) => mv,
+                None => picked.ok_or_else(|| {
+                    GenerationError::Search(
+                        MdpError::ContractViolation("the planner passed in a game not over".into())
+                            .into(),
+                    )
+                })?,
+            };
+            position.make_move(&mv);
+            moves += 1;
+        }
+
+        let winner = match (resigned, checked) {
+            (Some(player), false) => Some(1 - player),
+            _ => position.winner(),
+        };
+        report.games += 1;
+        match (resigned, checked) {
+            (Some(_), false) => report.resignations += 1,
+            (Some(player), true) => {
+                report.resignation_checks += 1;
+                if winner != Some(1 - player) {
+                    report.false_resignations += 1;
+                }
+            }
+            (None, _) => {}
+        }
+
+        for example in pending {
+            let outcome = match winner {
+                Some(winner) if winner == example.player => 1.0,
+                Some(_) => -1.0,
+                None => 0.0,
+            };
+            sink.write(&TrainingExample {
+                features: example.features,
+                policy: example.policy,
+                outcome,
+            })
+            .map_err(GenerationError::Sink)?;
+            report.examples += 1;
+        }
+        Ok(())
+    }
+
+    /// A move sampled in proportion to its visits to the power of `1/temperature`, None at a temperature of 0
+    /// (or without visits) for the planner's own pick
+    fn sample(&mut self, stats: &[(G::Move, ActionStats)], temperature: f64) -> Option<G::Move> {
+        let most = stats.iter().map(|(_, stats)| stats.visits).max()?;
+        if temperature <= 0.0 || most == 0 {
+            return None;
+        }
+        // relative to the most visited move, so that low temperatures don't overflow
+        let weights = stats
+            .iter()
+            .map(|(_, stats)| (stats.visits as f64 / most as f64).powf(1.0 / temperature))
+            .collect::<Vec<_>>();
+        let mut target = self.rng.gen_f64() * weights.iter().sum::<f64>();
+        for ((mv, _), weight) in stats.iter().zip(&weights) {
+            if target < *weight {
+                return Some(mv.clone());
+            }
+            target -= weight;
+        }
+        stats.last().map(|(mv, _)| mv.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{envs::nim::Nim, mcts::MCTS, mdp::FnMdp, policy::RandomRollout};
+
+    use super::*;
+
+    #[test]
+    fn test_searches_become_training_examples() {
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_: &u32| vec![1usize, 2],
+            |state, step, _rng| {
+                let next = state + *step as u32;
+                (next, f64::from(next == 3), next >= 3)
+            },
+            |state| *state >= 3,
+        );
+        let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+        assert!(mcts.visit_distribution().is_empty());
+        mcts.run_iterations(200);
+
+        let policy = mcts.visit_distribution();
+        assert_eq!(policy.len(), 2);
+        assert!((policy.iter().map(|(_, share)| share).sum::<f64>() - 1.0).abs() < 1e-9);
+        let example = TrainingExample {
+            features: vec![0.0],
+            policy,
+            outcome: 1.0,
+        };
+
+        let mut examples = vec![];
+        examples.write(&example).unwrap();
+        assert_eq!(examples, vec![example.clone()]);
+
+        #[cfg(feature = "serde")]
+        {
+            let mut writer = JsonLinesWriter::new(vec![]);
+            writer.write(&example).unwrap();
+            writer.write(&example).unwrap();
+            assert_eq!(writer.written(), 2);
+            let text = String::from_utf8(writer.into_inner()).unwrap();
+            let lines = text.lines().collect::<Vec<_>>();
+            assert_eq!(lines.len(), 2);
+            let read: TrainingExample<usize> = serde_json::from_str(lines[1]).unwrap();
+            assert_eq!(read, example);
+        }
+    }
+
+    struct Heaps;
+
+    impl StateEncoder<Nim> for Heaps {
+        fn shape(&self) -> Vec<usize> {
+            vec![3]
+        }
+
+        fn encode(&self, nim: &Nim, features: &mut Vec<f32>) {
+            features.extend(nim.heaps().iter().map(|heap| *heap as f32));
+        }
+    }
+
+    fn generator<'a>() -> SelfPlayGenerator<'a, Nim, Heaps> {
+        SelfPlayGenerator::new(Nim::new([3, 4, 5]), Heaps, Budget::Iterations(300), |mdp| {
+            MCTS::new(mdp, RandomRollout::new()).with_seed(1)
+        })
+        .with_seed(1)
+    }
+
+    #[test]
+    fn test_self_play_labels_positions_with_the_outcome() {
+        let mut examples = vec![];
+        let report = generator().with_temperature(|_| 0.0).run(2, &mut examples);
+        assert_eq!(report.games, 2);
+        assert_eq!(report.examples, examples.len());
+        assert_eq!(report.resignations, 0);
+
+        // the same searcher both sides without sampling, so the same game twice
+        let moves = examples.len() / 2;
+        assert_eq!(examples[..moves], examples[moves..]);
+        assert_eq!(examples[0].features, vec![3.0, 4.0, 5.0]);
+        for (i, example) in examples[..moves].iter().enumerate() {
+            assert!((example.policy.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+            // whoever made the last move won, and the players alternate
+            let mover = (moves - 1 - i) % 2 == 0;
+            assert_eq!(example.outcome, if mover { 1.0 } else { -1.0 });
+        }
+
+        // sampling at a high temperature varies the games
+        let mut sampled = vec![];
+        generator().with_temperature(|_| 10.0).run(4, &mut sampled);
+        assert_ne!(sampled[..moves], examples[..moves]);
+    }
+
+    #[test]
+    fn test_self_play_resigns_lost_games() {
+        let mut played_out = vec![];
+        generator()
+            .with_temperature(|_| 0.0)
+            .run(1, &mut played_out);
+
+        let mut resigned = vec![];
+        let report = generator()
+            .with_temperature(|_| 0.0)
+            .with_resignation(-0.5)
+            .with_resignation_checks(0.0)
+            .run(1, &mut resigned);
+        assert_eq!(report.resignations, 1);
+        assert!(resigned.len() < played_out.len());
+        // the last position is the resigning player's, who lost
+        assert_eq!(resigned.last().unwrap().outcome, -1.0);
+        assert_eq!(resigned[..], played_out[..resigned.len()]);
+
+        let mut checked = vec![];
+        let report = generator()
+            .with_temperature(|_| 0.0)
+            .with_resignation(-0.5)
+            .with_resignation_checks(1.0)
+            .run(1, &mut checked);
+        assert_eq!((report.resignations, report.resignation_checks), (0, 1));
+        assert_eq!(report.false_resignations, 0);
+        assert_eq!(checked, played_out);
+    }
+}
@@ -0,0 +1,105 @@
+//! Transposition table, which turns the search tree into a DAG.
+//!
+//! Many MDPs reach the same state through different orders of actions, and a plain tree searches each of those copies separately.
+//! When enabled (see [`crate::mcts::MCTS::with_transpositions`]) and the MDP provides [`crate::mdp::GenerativeModel::state_key`],
+//! newly reached states are first looked up in this table, and an existing node for that state is shared rather than a new one created.
+//!
+//! How statistics are combined across paths:
+//! - Nodes are only shared between states with the same key, that compare equal, and that are the same number of actions away from the root.
+//!   The last condition keeps the graph acyclic, so selection always terminates.
+//! - The visits and value of a node live on the node, so a shared node's statistics are the sum over every path that reached it,
+//!   and every parent selects among its children using those combined statistics.
+//! - Backpropagation only updates the nodes on the path taken during that iteration;
+//!   parents on the other paths to a shared node are not updated.
+//! - Rewards live on the edges rather than on the nodes: every edge into a shared node keeps the reward of its own transition,
+//!   and an iteration backs up the reward of the edge it went through.
+//!   A shared node's value is therefore the combined value of the edges into it, each weighed by how often it was taken.
+
+#[cfg(feature = "metrics")]
+use std::cell::Cell;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+use crate::node::{Copies, Node};
+
+/// (state key, depth) -> node.
+/// Nodes are held weakly, so that the table never keeps a discarded part of the tree alive
+type Entries<S, A> = HashMap<(u64, usize), Weak<Node<S, A>>>;
+
+#[derive(Debug)]
+pub(crate) struct TranspositionTable<S, A> {
+    nodes: RefCell<Entries<S, A>>,
+    /// The number of lookups, and of those that found a node, see [`crate::telemetry`]
+    #[cfg(feature = "metrics")]
+    lookups: Cell<(usize, usize)>,
+}
+
+impl<S, A> Default for TranspositionTable<S, A> {
+    fn default() -> Self {
+        Self {
+            nodes: RefCell::new(HashMap::new()),
+            #[cfg(feature = "metrics")]
+            lookups: Cell::default(),
+        }
+    }
+}
+
+impl<S: PartialEq, A> TranspositionTable<S, A> {
+    /// Returns the node already created for this state at this depth, if any
+    pub(crate) fn get(&self, key: u64, depth: usize, state: &S) -> Option<Rc<Node<S, A>>> {
+        let mut nodes = self.nodes.borrow_mut();
+        #[cfg(feature = "metrics")]
+        let (lookups, hits) = self.lookups.get();
+        #[cfg(feature = "metrics")]
+        self.lookups.set((lookups + 1, hits));
+        let node = nodes.get(&(key, depth)).map(Weak::upgrade)?;
+
+        match node {
+            // equal keys don't guarantee equal states
+            Some(node) if node.state == *state => {
+                #[cfg(feature = "metrics")]
+                self.lookups.set((lookups + 1, hits + 1));
+                Some(node)
+            }
+            Some(_) => None,
+            None => {
+                nodes.remove(&(key, depth));
+                None
+            }
+        }
+    }
+
+    /// The number of lookups so far, and of those that found a node
+    #[cfg(feature = "metrics")]
+    pub(crate) fn lookups(&self) -> (usize, usize) {
+        self.lookups.get()
+    }
+
+    /// The same table over a copy of the tree, see [`Node::deep_clone`]. Entries for nodes outside the copy are dropped
+    pub(crate) fn remapped(&self, copies: &Copies<S, A>) -> Self {
+        let nodes = self
+            .nodes
+            .borrow()
+            .iter()
+            .filter_map(|(entry, node)| {
+                let copy = copies.get(&node.as_ptr())?;
+                Some((*entry, Rc::downgrade(copy)))
+            })
+            .collect();
+
+        Self {
+            nodes: RefCell::new(nodes),
+            #[cfg(feature = "metrics")]
+            lookups: self.lookups.clone(),
+        }
+    }
+
+    pub(crate) fn insert(&self, key: u64, node: &Rc<Node<S, A>>) {
+        self.nodes
+            .borrow_mut()
+            .insert((key, node.depth), Rc::downgrade(node));
+    }
+}
@@ -0,0 +1,292 @@
+//! Read-only views of a search tree, see [`crate::mcts::MCTS::root`], for analysis tools, visualizers and tests.
+//!
+//! The views share the nodes of the tree rather than copying them, so they're cheap to make,
+//! but they also see the statistics change if the search runs again while they're held
+
+use std::{
+    any::Any,
+    cell::{Ref, RefMut},
+    collections::HashSet,
+    rc::Rc,
+};
+
+use crate::{
+    action::Action,
+    mdp::PlayerId,
+    node::{Child, Node},
+    node_stats::NodeStats,
+    stats::ActionStats,
+};
+
+/// A node of the search tree, i.e. a state the search reached
+pub struct TreeNode<S, A> {
+    node: Rc<Node<S, A>>,
+}
+
+impl<S, A> Clone for TreeNode<S, A> {
+    fn clone(&self) -> Self {
+        Self {
+            node: Rc::clone(&self.node),
+        }
+    }
+}
+
+impl<S, A> TreeNode<S, A>
+where
+    S: Eq,
+    A: Action,
+{
+    pub(crate) fn new(node: Rc<Node<S, A>>) -> Self {
+        Self { node }
+    }
+
+    /// Identifies the node: two views of the same node have the same address
+    pub(crate) fn address(&self) -> *const Node<S, A> {
+        Rc::as_ptr(&self.node)
+    }
+
+    pub fn state(&self) -> &S {
+        &self.node.state
+    }
+
+    /// The player to move in this node's state
+    pub fn player(&self) -> PlayerId {
+        self.node.player
+    }
+
+    /// The number of actions between the root and this node
+    pub fn depth(&self) -> usize {
+        self.node.depth
+    }
+
+    /// The number of times the search went through this node
+    pub fn visits(&self) -> usize {
+        *self.node.visits.borrow()
+    }
+
+    /// The mean return of the visits, from the perspective of the player who moved into this node
+    pub fn value(&self) -> f64 {
+        self.node.q_value()
+    }
+
+    /// The exact return after this node's state, from the perspective of the first player,
+    /// if the endgame hook solved it (see [`crate::mcts::MCTS::with_endgame`])
+    pub fn exact_value(&self) -> Option<f64> {
+        self.node.exact.get()
+    }
+
+    /// The statistics the selection policy keeps for this node (see [`crate::mcts::MCTS::with_selection`]),
+    /// or None if it keeps none or they aren't a `T`
+    pub fn stats<T: NodeStats>(&self) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.node.stats.borrow(), |stats| {
+            (stats.as_deref()? as &dyn Any).downcast_ref()
+        })
+        .ok()
+    }
+
+    /// The payload attached to this node (see [`crate::mcts::MCTS::with_node_metadata`]),
+    /// or None if it has none or it isn't a `T`
+    pub fn metadata<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.node.metadata.borrow(), |metadata| {
+            metadata.as_ref()?.downcast_ref()
+        })
+        .ok()
+    }
+
+    /// Same as [`TreeNode::metadata`], to update the payload, e.g. to tighten solver bounds as the search goes.
+    /// The payload mustn't be borrowed while the search runs
+    pub fn metadata_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.node.metadata.borrow_mut(), |metadata| {
+            metadata.as_mut()?.downcast_mut()
+        })
+        .ok()
+    }
+
+    /// Returns true if no action was explored from this node yet
+    pub fn is_leaf(&self) -> bool {
+        self.node.children.borrow().is_empty()
+    }
+
+    /// The actions explored from this node, in the order they were first tried
+    pub fn edges(&self) -> Vec<TreeEdge<S, A>> {
+        self.node
+            .children
+            .borrow()
+            .iter()
+            .map(TreeEdge::new)
+            .collect()
+    }
+
+    /// The principal variation from this node: the most visited action, then the most visited one after it, down to a leaf
+    pub fn principal_variation(&self) -> Vec<A> {
+        let mut variation = vec![];
+        let mut node = self.clone();
+        let mut seen = HashSet::new();
+        // transpositions can lead back to a node of the variation
+        while seen.insert(node.address()) {
+            let edges = node.edges();
+            let Some(edge) = edges.iter().max_by_key(|edge| edge.stats().visits) else {
+                break;
+            };
+            variation.push(edge.action().clone());
+            node = edge.child().clone();
+        }
+        variation
+    }
+
+    /// Walks the subtree rooted at this node depth-first, parents before their children.
+    /// The visitor returns whether to walk the children of the node it's given.
+    /// Nodes shared through transpositions (see [`crate::mcts::MCTS::with_transpositions`]) are only visited once
+    pub fn walk<F>(&self, mut visitor: F)
+    where
+        F: FnMut(&TreeNode<S, A>) -> bool,
+    {
+        let mut seen = HashSet::new();
+
+        // iterative, so that very deep trees don't overflow the stack
+        let mut stack = vec![self.clone()];
+        while let Some(node) = stack.pop() {
+            if !seen.insert(Rc::as_ptr(&node.node)) || !visitor(&node) {
+                continue;
+            }
+
+            let children = node.node.children.borrow();
+            let outcomes = children.iter().flat_map(Child::outcomes);
+            // reversed, so that the first child is walked first
+            let start = stack.len();
+            stack.extend(outcomes.map(|o| TreeNode::new(Rc::clone(&o.node))));
+            stack[start..].reverse();
+        }
+    }
+}
+
+/// An action explored from a node, and the node(s) it led to
+pub struct TreeEdge<S, A> {
+    action: A,
+    stats: ActionStats,
+    outcomes: Vec<(TreeNode<S, A>, f64)>,
+}
+
+impl<S, A> TreeEdge<S, A>
+where
+    S: Eq,
+    A: Action,
+{
+    fn new(child: &Child<S, A>) -> Self {
+        Self {
+            action: child.action.clone(),
+            stats: child.stats(),
+            outcomes: child
+                .outcomes()
+                .map(|o| (TreeNode::new(Rc::clone(&o.node)), o.reward))
+                .collect(),
+        }
+    }
+
+    pub fn action(&self) -> &A {
+        &self.action
+    }
+
+    /// The statistics of the action when this view was made, from the perspective of the player taking it
+    pub fn stats(&self) -> ActionStats {
+        self.stats
+    }
+
+    /// The node the action led to (the first one it led to, with chance nodes)
+    pub fn child(&self) -> &TreeNode<S, A> {
+        &self.outcomes[0].0
+    }
+
+    /// Every node the action led to with the reward of getting there,
+    /// which is more than one only with chance nodes (see [`crate::mcts::MCTS::with_chance_nodes`])
+    pub fn outcomes(&self) -> &[(TreeNode<S, A>, f64)] {
+        &self.outcomes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mcts::MCTS, mdp::FnMdp, policy::RandomRollout, strategy::Strategy};
+
+    #[test]
+    fn test_walk_visits_every_node_once() {
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_state: &u32| vec![1usize, 2],
+            |state, step, _rng| (state + *step as u32, 1.0, state + *step as u32 >= 6),
+            |state| *state >= 6,
+        );
+        let mut mcts = MCTS::new(mdp, RandomRollout::new()).with_seed(1);
+        mcts.run_iterations(200);
+
+        let root = mcts.root();
+        assert_eq!(root.visits(), 200);
+        assert_eq!(root.depth(), 0);
+
+        let edges = root.edges();
+        assert_eq!(
+            edges.iter().map(|e| e.stats().visits).sum::<usize>(),
+            root.visits()
+        );
+        let best = edges.iter().max_by_key(|e| e.stats().visits).unwrap();
+        assert_eq!(
+            Some(*best.action()),
+            mcts.best_action(Strategy::MostVisited)
+        );
+        assert_eq!(best.child().depth(), 1);
+        assert_eq!(best.outcomes()[0].1, 1.0);
+
+        let pv = root.principal_variation();
+        assert_eq!(pv.first(), Some(best.action()));
+        assert_eq!(pv[1..], best.child().principal_variation());
+        assert!(pv.iter().sum::<usize>() >= 6);
+
+        let mut nodes = 0;
+        let mut deepest = 0;
+        root.walk(|node| {
+            nodes += 1;
+            deepest = deepest.max(node.depth());
+            true
+        });
+        let stats = mcts.tree_stats();
+        assert_eq!(nodes, stats.nodes);
+        assert_eq!(deepest, stats.max_depth);
+
+        // pruned at the root's children
+        let mut shallow = 0;
+        root.walk(|node| {
+            shallow += 1;
+            node.depth() < 1
+        });
+        assert_eq!(shallow, 1 + edges.len());
+    }
+
+    #[test]
+    fn test_every_node_gets_metadata() {
+        let mdp = FnMdp::new(
+            || 0u32,
+            |_state: &u32| vec![1usize, 2],
+            |state, step, _rng| (state + *step as u32, 1.0, state + *step as u32 >= 6),
+            |state| *state >= 6,
+        );
+        let mut mcts = MCTS::new(mdp, RandomRollout::new())
+            .with_seed(1)
+            .with_node_metadata(|node| format!("{} at depth {}", node.state(), node.depth()));
+        mcts.run_iterations(200);
+
+        let root = mcts.root();
+        assert_eq!(
+            root.metadata::<String>().as_deref(),
+            Some(&"0 at depth 0".to_string())
+        );
+        assert!(root.metadata::<u32>().is_none());
+        root.walk(|node| {
+            assert!(node.metadata::<String>().is_some());
+            true
+        });
+
+        root.metadata_mut::<String>().unwrap().push_str(", seen");
+        mcts.run_iterations(10);
+        assert_eq!(*root.metadata::<String>().unwrap(), "0 at depth 0, seen");
+    }
+}
@@ -1,49 +1,114 @@
 use core::f64;
 
 use crate::action::Action;
-use crate::node::Node;
-use crate::rand::genrand;
+use crate::node::{Child, Node};
+use crate::node_stats::{ErasedSelection, Selection};
+use crate::rand::Rng;
 
 /// Given that this node is fully expanded i.e all the direct children of this node have been explored
 /// This method helps us calculate the best child of this node to exploit further
 /// Selects an action for the state from a list given a Q-function(???) (https://gibberblot.github.io/rl-notes/single-agent/multi-armed-bandits.html#id5)
 /// this can be: Softmax strategy, UCB1 e.t.c
-#[derive(Debug, Default)]
-pub struct UCB1;
+#[derive(Clone)]
+pub struct UCB1 {
+    /// Weight of the exploration term
+    c: f64,
+    /// Replaces the UCB1 scores when set, see [`crate::mcts::MCTS::with_selection`]
+    pub(crate) selection: Option<Selection>,
+}
+
+impl Default for UCB1 {
+    fn default() -> Self {
+        Self::new(Self::C)
+    }
+}
 
 impl UCB1 {
-    const C: f64 = 1.4142135623730951;
+    pub(crate) const C: f64 = f64::consts::SQRT_2;
+
+    pub(crate) fn new(c: f64) -> Self {
+        Self { c, selection: None }
+    }
+
+    pub(crate) fn set_exploration_constant(&mut self, c: f64) {
+        self.c = c;
+    }
 
-    pub(crate) fn select<S, A>(&self, node: &Node<S, A>, actions: Vec<A>) -> A
+    pub(crate) fn select<S, A>(&self, node: &Node<S, A>, actions: Vec<A>, rng: &mut dyn Rng) -> A
     where
         A: Action,
         S: PartialEq + Eq,
     {
         let children = node.children.borrow();
-        let child_actions = children.iter().flat_map(|c| c.action).collect::<Vec<_>>();
 
-        for action in actions.iter() {
-            if !child_actions.contains(action) {
-                return *action;
-            }
+        // Every child holds a distinct action, so there can only be unexplored actions if there are fewer children than actions
+        if children.len() < actions.len()
+            && let Some(action) = actions.iter().find(|a| !node.has_child(a))
+        {
+            return action.clone();
         }
 
-        let mut max_actions = Vec::new();
-        let mut max_value = f64::NEG_INFINITY;
-
-        for child in children.iter() {
-            let value = child.ucb1(Self::C);
+        // Pruned actions (see `crate::mcts::MCTS::with_pruning`) leave some of the children out
+        let pruned = actions.len() < children.len();
+        let parent_visits = *node.visits.borrow();
+        let exploration = self.c * (parent_visits.max(1) as f64).ln().sqrt();
+        let candidates = children
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| !pruned || actions.contains(&child.action));
 
-            if value > max_value {
-                max_actions = vec![child.action.unwrap()];
+        let mut max_children = Vec::new();
+        let mut max_value = f64::NEG_INFINITY;
+        for (index, child) in candidates {
+            let value = match &self.selection {
+                Some(selection) => selected_score(&**selection, child, parent_visits),
+                None => {
+                    let (q, inv_sqrt_visits) = child.ucb1_terms();
+                    q + exploration * inv_sqrt_visits
+                }
+            };
+            // the first candidate is taken even with a NaN score, so that there's always one
+            if max_children.is_empty() || value > max_value {
+                max_children.clear();
+                max_children.push(index);
                 max_value = value;
             } else if value == max_value {
-                max_actions.push(child.action.unwrap());
+                max_children.push(index);
             }
         }
 
         //  if there are multiple actions with the highest value choose one randomly
-        let index = genrand(0, max_actions.len());
-        return max_actions[index];
+        let index = max_children[rng.gen_range(0, max_children.len())];
+        children[index].action.clone()
     }
 }
+
+/// The score of the action under a custom selection policy, weighing its outcomes by their visits with chance nodes
+fn selected_score<S, A>(
+    selection: &dyn ErasedSelection,
+    child: &Child<S, A>,
+    parent_visits: usize,
+) -> f64
+where
+    A: Action,
+    S: Eq,
+{
+    let visits = child.visits();
+    let score = |node: &Node<S, A>, visits| {
+        selection.score(node.stats.borrow().as_deref(), visits, parent_visits)
+    };
+    let mut outcomes = child.outcomes().peekable();
+    let first = outcomes.next().expect("an action has at least one outcome");
+    if outcomes.peek().is_none() {
+        return score(&first.node, visits);
+    }
+
+    std::iter::once(first)
+        .chain(outcomes)
+        .map(|o| {
+            let n = *o.node.visits.borrow();
+            score(&o.node, n) * n as f64
+        })
+        .sum::<f64>()
+        / visits.max(1) as f64
+}
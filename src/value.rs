@@ -0,0 +1,157 @@
+//! Estimates of the return after a state, as solvers compute them and as the search can be given them
+//! through [`crate::mcts::MCTS::with_value_function`]
+
+use std::{collections::HashMap, hash::Hash};
+
+/// V(s), the expected return after a state.
+///
+/// Implemented for closures, for the tables of [`TabularValues`], and for what the solvers of [`crate::solvers`] return
+pub trait ValueFunction<S> {
+    fn value(&self, state: &S) -> f64;
+}
+
+impl<S, F> ValueFunction<S> for F
+where
+    F: Fn(&S) -> f64,
+{
+    fn value(&self, state: &S) -> f64 {
+        self(state)
+    }
+}
+
+/// Values states in batches, e.g. on an inference server, and may fail to, see [`crate::mcts::MCTS::mcts_batched`]
+pub trait BatchEvaluator<S> {
+    type Error;
+
+    /// One value per state, in order, from the perspective of the first player like [`ValueFunction`]'s
+    fn try_value_batch(&self, states: &[S]) -> Result<Vec<f64>, Self::Error>;
+}
+
+/// The values of finitely many states, worth 0 unless listed
+#[derive(Debug, Clone)]
+pub struct TabularValues<S> {
+    values: HashMap<S, f64>,
+}
+
+impl<S> Default for TabularValues<S> {
+    fn default() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+}
+
+impl<S> TabularValues<S>
+where
+    S: Eq + Hash,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of the state, or None if it isn't listed
+    pub fn get(&self, state: &S) -> Option<f64> {
+        self.values.get(state).copied()
+    }
+
+    pub fn insert(&mut self, state: S, value: f64) {
+        self.values.insert(state, value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&S, f64)> {
+        self.values.iter().map(|(state, value)| (state, *value))
+    }
+}
+
+impl<S: Eq + Hash> FromIterator<(S, f64)> for TabularValues<S> {
+    fn from_iter<T: IntoIterator<Item = (S, f64)>>(iter: T) -> Self {
+        Self {
+            values: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<S> From<HashMap<S, f64>> for TabularValues<S> {
+    fn from(values: HashMap<S, f64>) -> Self {
+        Self { values }
+    }
+}
+
+/// As a list of (state, value) pairs, whatever the type of the states
+#[cfg(feature = "serde")]
+impl<S: serde::Serialize> serde::Serialize for TabularValues<S> {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(&self.values)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, S> serde::Deserialize<'de> for TabularValues<S>
+where
+    S: serde::Deserialize<'de> + Eq + Hash,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values = <Vec<(S, f64)> as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(values.into_iter().collect())
+    }
+}
+
+impl<S: Eq + Hash> ValueFunction<S> for TabularValues<S> {
+    fn value(&self, state: &S) -> f64 {
+        self.get(state).unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mcts::MCTS,
+        policy::{Policy, PolicyRollout},
+        solvers::{q_table::QTable, value_iteration::ValueIteration},
+        strategy::Strategy,
+        tabular::TabularMDP,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_solutions_guide_the_search() {
+        // action 0 ends the episode with 1, action 1 pays 2 but only ends it half of the time
+        let mdp = TabularMDP::new(
+            vec![
+                vec![vec![0.0, 1.0], vec![0.5, 0.5]],
+                vec![vec![0.0, 1.0], vec![0.0, 0.0]],
+            ],
+            vec![vec![1.0, 2.0], vec![0.0, 0.0]],
+        )
+        .with_terminal_states([1]);
+        let solution = ValueIteration::new().solve(&mdp);
+        assert!((ValueFunction::value(&solution, &0) - 4.0).abs() < 1e-6);
+        assert_eq!(Policy::action(&solution, &0), Some(1));
+
+        let mut table = QTable::new();
+        table.set(0, 1, 3.0);
+        let solvers: [&dyn ValueFunction<usize>; 3] = [&solution, &table, &|_: &usize| 4.0];
+        assert!(solvers.iter().all(|v| v.value(&0) >= 3.0));
+
+        let values = solution.values().clone();
+        let policy = solution.into_policy();
+        let mut mcts = MCTS::new(mdp, PolicyRollout::new(policy.clone()))
+            .with_seed(1)
+            .with_max_rollout_depth(1)
+            .with_value_function(values);
+        mcts.run_iterations(200);
+        assert_eq!(
+            mcts.best_action(Strategy::MostVisited).as_ref(),
+            policy.get(&0)
+        );
+    }
+}
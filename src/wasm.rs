@@ -0,0 +1,354 @@
+//! A searcher for JavaScript, e.g. to run the search client-side in a browser game (behind the `wasm` feature).
+//!
+//! The MDP is a JavaScript object whose methods the search calls back:
+//!
+//! ```js
+//! import { Searcher } from "./pkg/mct_rs.js";
+//!
+//! // adding 1 or 2 at a time until the total reaches 10, which pays 1
+//! const searcher = new Searcher({
+//!   initialState: () => 0,
+//!   actions: (total) => [1, 2],
+//!   // `random` is uniform in [0, 1), drawn from the searcher's generator so that seeded searches are reproducible
+//!   execute: (total, step, random) => [total + step, total + step >= 10 ? 1 : 0, total + step >= 10],
+//!   isTerminal: (total) => total >= 10,
+//!   // optional: the player to move and the number of players of a game, and the discount factor
+//!   playerToMove: (total) => 0,
+//!   players: 1,
+//!   discount: 1,
+//! });
+//! searcher.setSeed(7n);
+//! searcher.setIterations(2000);
+//! const { action, value, visits } = searcher.search();
+//! ```
+//!
+//! States and actions are any JavaScript values, compared with `===`: objects are only equal to themselves,
+//! so states that are objects merge in the tree (e.g. with chance nodes) only if `execute` returns the same objects.
+//! Whatever a callback throws is thrown back by the call to the searcher that ran into it.
+//! Time limits read `Date.now()`, see [`crate::clock`]
+
+use std::{cell::RefCell, time::Duration};
+
+use js_sys::{Array, Function, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    action::Action,
+    budget::Budget,
+    error::{McstError, MdpError},
+    mcts::MCTS,
+    mdp::{GenerativeModel, PlayerId},
+    policy::RandomRollout,
+    rand::Rng,
+    strategy::Strategy,
+};
+
+/// A state of a [`JsMdp`], compared with `===`
+#[derive(Debug, Clone)]
+pub struct JsState(pub JsValue);
+
+impl PartialEq for JsState {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for JsState {}
+
+/// An action of a [`JsMdp`], compared with `===`
+#[derive(Debug, Clone)]
+pub struct JsAction(pub JsValue);
+
+impl PartialEq for JsAction {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for JsAction {}
+
+impl Action for JsAction {}
+
+/// An MDP implemented by the methods of a JavaScript object, see the [module documentation](self).
+///
+/// Only `execute` can report a failure to the search. What the other callbacks throw is kept, the search goes on
+/// as if the state had no actions or were terminal, and [`JsMdp::take_error`] hands it back once it's over
+pub struct JsMdp {
+    initial_state: Function,
+    actions: Function,
+    execute: Function,
+    is_terminal: Function,
+    player_to_move: Option<Function>,
+    players: usize,
+    discount: f64,
+    error: RefCell<Option<JsValue>>,
+}
+
+impl JsMdp {
+    /// Reads the callbacks off `mdp`, and fails if one of the required ones is missing
+    pub fn new(mdp: &Object) -> Result<Self, JsValue> {
+        let function = |name: &str| -> Result<Option<Function>, JsValue> {
+            let value = Reflect::get(mdp, &JsValue::from_str(name))?;
+            match value.is_undefined() {
+                true => Ok(None),
+                false => value
+                    .dyn_into::<Function>()
+                    .map(Some)
+                    .map_err(|_| JsError::new(&format!("`{name}` isn't a function")).into()),
+            }
+        };
+        let required = |name: &str| {
+            function(name)?
+                .ok_or_else(|| JsValue::from(JsError::new(&format!("`{name}` is missing"))))
+        };
+        let number = |name: &str| -> Result<Option<f64>, JsValue> {
+            let value = Reflect::get(mdp, &JsValue::from_str(name))?;
+            match value.is_undefined() {
+                true => Ok(None),
+                false => value
+                    .as_f64()
+                    .map(Some)
+                    .ok_or_else(|| JsError::new(&format!("`{name}` isn't a number")).into()),
+            }
+        };
+
+        Ok(Self {
+            initial_state: required("initialState")?,
+            actions: required("actions")?,
+            execute: required("execute")?,
+            is_terminal: required("isTerminal")?,
+            player_to_move: function("playerToMove")?,
+            players: number("players")?.map_or(1, |players| players as usize),
+            discount: number("discount")?.unwrap_or(1.0),
+            error: RefCell::new(None),
+        })
+    }
+
+    /// The first exception a callback other than `execute` threw since the last call, if any
+    pub fn take_error(&self) -> Option<JsValue> {
+        self.error.borrow_mut().take()
+    }
+
+    /// Keeps the first of the exceptions thrown until the next [`JsMdp::take_error`]
+    fn record(&self, error: JsValue) {
+        self.error.borrow_mut().get_or_insert(error);
+    }
+}
+
+impl GenerativeModel<JsState, JsAction> for JsMdp {
+    fn get_actions(&self, state: &JsState) -> Vec<JsAction> {
+        match self.actions.call1(&JsValue::NULL, &state.0) {
+            Ok(actions) => Array::from(&actions).iter().map(JsAction).collect(),
+            Err(error) => {
+                self.record(error);
+                vec![]
+            }
+        }
+    }
+
+    fn execute(
+        &self,
+        state: &JsState,
+        action: &JsAction,
+        rng: &mut dyn Rng,
+    ) -> (JsState, f64, bool) {
+        self.try_execute(state, action, rng)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Expects `[nextState, reward, done]` back
+    fn try_execute(
+        &self,
+        state: &JsState,
+        action: &JsAction,
+        rng: &mut dyn Rng,
+    ) -> Result<(JsState, f64, bool), MdpError> {
+        let random = JsValue::from_f64(rng.gen_f64());
+        let outcome = self
+            .execute
+            .call3(&JsValue::NULL, &state.0, &action.0, &random)
+            .map_err(|error| {
+                let message = format!("`execute` threw {error:?}");
+                self.record(error);
+                MdpError::Custom(message)
+            })?;
+
+        let outcome = Array::from(&outcome);
+        let reward = outcome.get(1).as_f64();
+        match (outcome.length(), reward) {
+            (3, Some(reward)) => Ok((JsState(outcome.get(0)), reward, outcome.get(2).is_truthy())),
+            _ => Err(MdpError::Custom(
+                "`execute` must return [nextState, reward, done]".to_string(),
+            )),
+        }
+    }
+
+    fn is_terminal(&self, state: &JsState) -> bool {
+        match self.is_terminal.call1(&JsValue::NULL, &state.0) {
+            Ok(terminal) => terminal.is_truthy(),
+            Err(error) => {
+                self.record(error);
+                true
+            }
+        }
+    }
+
+    fn get_initial_state(&self) -> JsState {
+        match self.initial_state.call0(&JsValue::NULL) {
+            Ok(state) => JsState(state),
+            Err(error) => {
+                self.record(error);
+                JsState(JsValue::UNDEFINED)
+            }
+        }
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.discount
+    }
+
+    fn player_to_move(&self, state: &JsState) -> PlayerId {
+        let Some(player_to_move) = &self.player_to_move else {
+            return 0;
+        };
+        match player_to_move.call1(&JsValue::NULL, &state.0) {
+            Ok(player) => player.as_f64().map_or(0, |player| player as PlayerId),
+            Err(error) => {
+                self.record(error);
+                0
+            }
+        }
+    }
+
+    fn num_players(&self) -> usize {
+        self.players
+    }
+}
+
+type JsSearch = MCTS<JsMdp, JsState, JsAction, RandomRollout>;
+
+/// Milliseconds since the epoch, from `Date.now()`, which every JavaScript runtime has
+fn now() -> Duration {
+    Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+}
+
+/// MCTS with random rollouts over a [`JsMdp`], see the [module documentation](self)
+#[wasm_bindgen]
+pub struct Searcher {
+    /// Only ever None while a setter rebuilds it
+    mcts: Option<JsSearch>,
+}
+
+impl Searcher {
+    fn mcts(&mut self) -> &mut JsSearch {
+        self.mcts.as_mut().expect("the searcher is configured")
+    }
+
+    fn configure(&mut self, configure: impl FnOnce(JsSearch) -> JsSearch) {
+        self.mcts = self.mcts.take().map(configure);
+    }
+
+    /// Throws what a callback threw first, or the error of the search
+    fn fail(&mut self, error: McstError) -> JsValue {
+        let thrown = self.mcts().mdp().take_error();
+        thrown.unwrap_or_else(|| JsError::new(&error.to_string()).into())
+    }
+
+    /// What a callback threw, even if the search went on regardless
+    fn check(&mut self) -> Result<(), JsValue> {
+        match self.mcts().mdp().take_error() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Searcher {
+    /// Searches 1000 iterations per call by default, with a generator seeded from `crypto.getRandomValues`
+    #[wasm_bindgen(constructor)]
+    pub fn new(mdp: &Object) -> Result<Searcher, JsValue> {
+        let mdp = JsMdp::new(mdp)?;
+        let mcts = MCTS::try_new(mdp, RandomRollout::new())
+            .map_err(|error| JsValue::from(JsError::new(&error.to_string())))?
+            .with_clock(now);
+        let mut searcher = Self { mcts: Some(mcts) };
+        searcher.check()?;
+        Ok(searcher)
+    }
+
+    /// Makes the searches reproducible, given an MDP whose callbacks only draw from the `random` they're handed
+    #[wasm_bindgen(js_name = setSeed)]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.configure(|mcts| mcts.with_seed(seed));
+    }
+
+    /// Makes every search run this many iterations
+    #[wasm_bindgen(js_name = setIterations)]
+    pub fn set_iterations(&mut self, iterations: usize) {
+        self.configure(|mcts| mcts.with_budget(Budget::Iterations(iterations)));
+    }
+
+    /// Makes every search run for this many milliseconds
+    #[wasm_bindgen(js_name = setTimeLimit)]
+    pub fn set_time_limit(&mut self, milliseconds: f64) {
+        let budget = Budget::Time(milliseconds.max(0.0) as u128);
+        self.configure(|mcts| mcts.with_budget(budget));
+    }
+
+    /// The exploration constant of UCB1
+    #[wasm_bindgen(js_name = setExplorationConstant)]
+    pub fn set_exploration_constant(&mut self, c: f64) {
+        self.configure(|mcts| mcts.with_exploration_constant(c));
+    }
+
+    /// Picks the final action by its value rather than by its visits
+    #[wasm_bindgen(js_name = setPickHighestValue)]
+    pub fn set_pick_highest_value(&mut self, highest_value: bool) {
+        let strategy = match highest_value {
+            true => Strategy::HighestQValue,
+            false => Strategy::MostVisited,
+        };
+        self.configure(|mcts| mcts.with_strategy(strategy));
+    }
+
+    /// Searches from `state` from now on, discarding the tree built so far
+    #[wasm_bindgen(js_name = setRootState)]
+    pub fn set_root_state(&mut self, state: JsValue) -> Result<(), JsValue> {
+        self.mcts().set_root_state(JsState(state));
+        self.check()
+    }
+
+    /// Runs a search, and returns `{ action, value, visits, stdError, iterations, winProbability }` for the action it picked,
+    /// or `undefined` if it explored none, e.g. because the root is terminal or has no actions
+    pub fn search(&mut self) -> Result<JsValue, JsValue> {
+        let result = match self.mcts().try_search() {
+            Ok(result) => result,
+            Err(McstError::TerminalRoot { .. } | McstError::NoLegalActions) => None,
+            Err(error) => return Err(self.fail(error)),
+        };
+        self.check()?;
+
+        let Some(result) = result else {
+            return Ok(JsValue::UNDEFINED);
+        };
+        let win_probability = result.win_probability();
+        let object = Object::new();
+        for (key, value) in [
+            ("action", result.action.0),
+            ("value", result.value.into()),
+            ("visits", result.visits.into()),
+            ("stdError", result.std_error.into()),
+            ("iterations", result.iterations.into()),
+            ("winProbability", win_probability.into()),
+        ] {
+            Reflect::set(&object, &JsValue::from_str(key), &value)?;
+        }
+        Ok(object.into())
+    }
+
+    /// The total number of iterations run by every search so far
+    pub fn iterations(&self) -> usize {
+        self.mcts.as_ref().map_or(0, |mcts| mcts.iterations())
+    }
+}
@@ -0,0 +1,764 @@
+//! Models that wrap another model, changing or checking its behaviour without touching its implementation
+
+use std::fmt::Debug;
+
+use crate::{
+    error::MdpError,
+    mdp::{ExplicitMDP, GenerativeModel, Outcome, PlayerId},
+    rand::Rng,
+};
+
+/// Checks, on every step, that the wrapped model keeps to the rules the search relies on:
+/// - non-terminal states offer at least one action
+/// - the executed action is one of the actions of the state it's executed from, which isn't terminal
+/// - the terminal flag returned by `execute` agrees with `is_terminal`
+///
+/// Violations are reported as [`MdpError::ContractViolation`] (see [`crate::mcts::MCTS::try_mcts`]), naming the offending state and action,
+/// instead of failing in confusing ways deep inside the search.
+/// Every step executes `get_actions` and `is_terminal` a few more times, so this is meant for debugging and tests
+#[derive(Debug, Clone, Default)]
+pub struct Checked<M> {
+    inner: M,
+}
+
+impl<M> Checked<M> {
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+}
+
+impl<M, S, A> GenerativeModel<S, A> for Checked<M>
+where
+    M: GenerativeModel<S, A>,
+    S: Debug,
+    A: Debug + PartialEq,
+{
+    fn get_actions(&self, state: &S) -> Vec<A> {
+        self.inner.get_actions(state)
+    }
+
+    fn execute(&self, state: &S, action: &A, rng: &mut dyn Rng) -> (S, f64, bool) {
+        self.try_execute(state, action, rng)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    fn try_execute(
+        &self,
+        state: &S,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, f64, bool), MdpError> {
+        let violation = |message: String| Err(MdpError::ContractViolation(message));
+
+        if self.inner.is_terminal(state) {
+            return violation(format!("{action:?} executed from terminal state {state:?}"));
+        }
+
+        let actions = self.inner.get_actions(state);
+        if !actions.contains(action) {
+            return violation(format!(
+                "{action:?} executed from {state:?}, which only offers {actions:?}"
+            ));
+        }
+
+        let (next_state, reward, done) = self.inner.try_execute(state, action, rng)?;
+
+        let terminal = self.inner.is_terminal(&next_state);
+        if done != terminal {
+            return violation(format!(
+                "{action:?} from {state:?} returned done={done} for {next_state:?}, but is_terminal returns {terminal}"
+            ));
+        }
+
+        if !terminal && self.inner.get_actions(&next_state).is_empty() {
+            return violation(format!("non-terminal state {next_state:?} has no actions"));
+        }
+
+        Ok((next_state, reward, done))
+    }
+
+    fn is_terminal(&self, state: &S) -> bool {
+        self.inner.is_terminal(state)
+    }
+
+    fn get_initial_state(&self) -> S {
+        self.inner.get_initial_state()
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.inner.get_discount_factor()
+    }
+
+    fn player_to_move(&self, state: &S) -> PlayerId {
+        self.inner.player_to_move(state)
+    }
+
+    fn num_players(&self) -> usize {
+        self.inner.num_players()
+    }
+
+    fn state_key(&self, state: &S) -> Option<u64> {
+        self.inner.state_key(state)
+    }
+
+    fn canonicalize(&self, state: &S) -> Option<S> {
+        self.inner.canonicalize(state)
+    }
+
+    fn transition_probability(&self, state: &S, action: &A, next_state: &S) -> Option<f64> {
+        self.inner.transition_probability(state, action, next_state)
+    }
+
+    fn action_cost(&self, state: &S, action: &A) -> f64 {
+        self.inner.action_cost(state, action)
+    }
+
+    fn outcome(&self, state: &S) -> Option<Outcome> {
+        self.inner.outcome(state)
+    }
+}
+
+/// Rescales the rewards of the wrapped model to `reward * scale + shift`, e.g. to bring them into the range the exploration constant is tuned for
+#[derive(Debug, Clone)]
+pub struct ScaledReward<M> {
+    inner: M,
+    scale: f64,
+    shift: f64,
+}
+
+impl<M> ScaledReward<M> {
+    pub fn new(inner: M, scale: f64, shift: f64) -> Self {
+        Self {
+            inner,
+            scale,
+            shift,
+        }
+    }
+
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+
+    fn rescale<S>(&self, (state, reward, done): (S, f64, bool)) -> (S, f64, bool) {
+        (state, reward * self.scale + self.shift, done)
+    }
+}
+
+impl<M, S, A> GenerativeModel<S, A> for ScaledReward<M>
+where
+    M: GenerativeModel<S, A>,
+{
+    fn get_actions(&self, state: &S) -> Vec<A> {
+        self.inner.get_actions(state)
+    }
+
+    fn execute(&self, state: &S, action: &A, rng: &mut dyn Rng) -> (S, f64, bool) {
+        self.rescale(self.inner.execute(state, action, rng))
+    }
+
+    fn try_execute(
+        &self,
+        state: &S,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, f64, bool), MdpError> {
+        self.inner
+            .try_execute(state, action, rng)
+            .map(|step| self.rescale(step))
+    }
+
+    fn is_terminal(&self, state: &S) -> bool {
+        self.inner.is_terminal(state)
+    }
+
+    fn get_initial_state(&self) -> S {
+        self.inner.get_initial_state()
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.inner.get_discount_factor()
+    }
+
+    fn player_to_move(&self, state: &S) -> PlayerId {
+        self.inner.player_to_move(state)
+    }
+
+    fn num_players(&self) -> usize {
+        self.inner.num_players()
+    }
+
+    fn state_key(&self, state: &S) -> Option<u64> {
+        self.inner.state_key(state)
+    }
+
+    fn canonicalize(&self, state: &S) -> Option<S> {
+        self.inner.canonicalize(state)
+    }
+
+    fn transition_probability(&self, state: &S, action: &A, next_state: &S) -> Option<f64> {
+        self.inner.transition_probability(state, action, next_state)
+    }
+
+    /// Costs are in the units of the rewards, so they're rescaled too (but not shifted)
+    fn action_cost(&self, state: &S, action: &A) -> f64 {
+        self.inner.action_cost(state, action) * self.scale
+    }
+
+    fn outcome(&self, state: &S) -> Option<Outcome> {
+        self.inner.outcome(state)
+    }
+}
+
+impl<M, S, A> ExplicitMDP<S, A> for ScaledReward<M>
+where
+    M: ExplicitMDP<S, A>,
+{
+    fn get_states(&self) -> Vec<S> {
+        self.inner.get_states()
+    }
+
+    fn get_transitions(&self, state: &S, action: &A) -> Vec<(S, f64)> {
+        self.inner.get_transitions(state, action)
+    }
+
+    fn get_reward(&self, state: &S, action: &A, next_state: &S) -> f64 {
+        self.inner.get_reward(state, action, next_state) * self.scale + self.shift
+    }
+
+    fn get_goal_states(&self) -> Vec<S> {
+        self.inner.get_goal_states()
+    }
+}
+
+/// Subtracts the cost of every action (see [`GenerativeModel::action_cost`]), times `weight`, from the reward of taking it,
+/// so that the search trades rewards off against costs rather than only breaking ties with them
+#[derive(Debug, Clone)]
+pub struct CostPenalty<M> {
+    inner: M,
+    weight: f64,
+}
+
+impl<M> CostPenalty<M> {
+    pub fn new(inner: M, weight: f64) -> Self {
+        Self { inner, weight }
+    }
+
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+}
+
+impl<M, S, A> GenerativeModel<S, A> for CostPenalty<M>
+where
+    M: GenerativeModel<S, A>,
+{
+    fn get_actions(&self, state: &S) -> Vec<A> {
+        self.inner.get_actions(state)
+    }
+
+    fn execute(&self, state: &S, action: &A, rng: &mut dyn Rng) -> (S, f64, bool) {
+        let cost = self.inner.action_cost(state, action);
+        let (next_state, reward, done) = self.inner.execute(state, action, rng);
+        (next_state, reward - self.weight * cost, done)
+    }
+
+    fn try_execute(
+        &self,
+        state: &S,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, f64, bool), MdpError> {
+        let cost = self.inner.action_cost(state, action);
+        let (next_state, reward, done) = self.inner.try_execute(state, action, rng)?;
+        Ok((next_state, reward - self.weight * cost, done))
+    }
+
+    fn is_terminal(&self, state: &S) -> bool {
+        self.inner.is_terminal(state)
+    }
+
+    fn get_initial_state(&self) -> S {
+        self.inner.get_initial_state()
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.inner.get_discount_factor()
+    }
+
+    fn player_to_move(&self, state: &S) -> PlayerId {
+        self.inner.player_to_move(state)
+    }
+
+    fn num_players(&self) -> usize {
+        self.inner.num_players()
+    }
+
+    fn state_key(&self, state: &S) -> Option<u64> {
+        self.inner.state_key(state)
+    }
+
+    fn canonicalize(&self, state: &S) -> Option<S> {
+        self.inner.canonicalize(state)
+    }
+
+    fn transition_probability(&self, state: &S, action: &A, next_state: &S) -> Option<f64> {
+        self.inner.transition_probability(state, action, next_state)
+    }
+
+    fn action_cost(&self, state: &S, action: &A) -> f64 {
+        self.inner.action_cost(state, action)
+    }
+
+    fn outcome(&self, state: &S) -> Option<Outcome> {
+        self.inner.outcome(state)
+    }
+}
+
+/// Truncates the episodes of the wrapped model after `limit` steps.
+/// The states of this model are `(state, steps taken so far)` pairs, and a state becomes terminal once `limit` steps have been taken
+#[derive(Debug, Clone)]
+pub struct StepLimit<M> {
+    inner: M,
+    limit: usize,
+}
+
+impl<M> StepLimit<M> {
+    pub fn new(inner: M, limit: usize) -> Self {
+        Self { inner, limit }
+    }
+
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+}
+
+impl<M, S, A> GenerativeModel<(S, usize), A> for StepLimit<M>
+where
+    M: GenerativeModel<S, A>,
+{
+    fn get_actions(&self, (state, _): &(S, usize)) -> Vec<A> {
+        self.inner.get_actions(state)
+    }
+
+    fn execute(
+        &self,
+        (state, steps): &(S, usize),
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> ((S, usize), f64, bool) {
+        let (next_state, reward, done) = self.inner.execute(state, action, rng);
+        let steps = steps + 1;
+        ((next_state, steps), reward, done || steps >= self.limit)
+    }
+
+    fn try_execute(
+        &self,
+        (state, steps): &(S, usize),
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<((S, usize), f64, bool), MdpError> {
+        let (next_state, reward, done) = self.inner.try_execute(state, action, rng)?;
+        let steps = steps + 1;
+        Ok(((next_state, steps), reward, done || steps >= self.limit))
+    }
+
+    fn is_terminal(&self, (state, steps): &(S, usize)) -> bool {
+        *steps >= self.limit || self.inner.is_terminal(state)
+    }
+
+    fn get_initial_state(&self) -> (S, usize) {
+        (self.inner.get_initial_state(), 0)
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.inner.get_discount_factor()
+    }
+
+    fn player_to_move(&self, (state, _): &(S, usize)) -> PlayerId {
+        self.inner.player_to_move(state)
+    }
+
+    fn num_players(&self) -> usize {
+        self.inner.num_players()
+    }
+
+    fn state_key(&self, (state, _): &(S, usize)) -> Option<u64> {
+        // states are only ever shared at the same depth, i.e. after the same number of steps
+        self.inner.state_key(state)
+    }
+
+    fn canonicalize(&self, (state, steps): &(S, usize)) -> Option<(S, usize)> {
+        self.inner
+            .canonicalize(state)
+            .map(|canonical| (canonical, *steps))
+    }
+
+    fn transition_probability(
+        &self,
+        (state, _): &(S, usize),
+        action: &A,
+        (next_state, _): &(S, usize),
+    ) -> Option<f64> {
+        self.inner.transition_probability(state, action, next_state)
+    }
+
+    fn action_cost(&self, (state, _): &(S, usize), action: &A) -> f64 {
+        self.inner.action_cost(state, action)
+    }
+
+    fn outcome(&self, (state, _): &(S, usize)) -> Option<Outcome> {
+        self.inner.outcome(state)
+    }
+}
+
+/// Presents the states of the wrapped model as another type, e.g. a more compact encoding.
+/// `into` and `from` must be inverses of each other: the state is converted back to the wrapped model's type on every call
+#[derive(Debug, Clone)]
+pub struct MapState<M, Into, From> {
+    inner: M,
+    into: Into,
+    from: From,
+}
+
+impl<M, Into, From> MapState<M, Into, From> {
+    pub fn new<S, T>(inner: M, into: Into, from: From) -> Self
+    where
+        Into: Fn(S) -> T,
+        From: Fn(&T) -> S,
+    {
+        Self { inner, into, from }
+    }
+
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+}
+
+impl<M, Into, From, S, T, A> GenerativeModel<T, A> for MapState<M, Into, From>
+where
+    M: GenerativeModel<S, A>,
+    Into: Fn(S) -> T,
+    From: Fn(&T) -> S,
+{
+    fn get_actions(&self, state: &T) -> Vec<A> {
+        self.inner.get_actions(&(self.from)(state))
+    }
+
+    fn execute(&self, state: &T, action: &A, rng: &mut dyn Rng) -> (T, f64, bool) {
+        let (next_state, reward, done) = self.inner.execute(&(self.from)(state), action, rng);
+        ((self.into)(next_state), reward, done)
+    }
+
+    fn try_execute(
+        &self,
+        state: &T,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(T, f64, bool), MdpError> {
+        let (next_state, reward, done) =
+            self.inner.try_execute(&(self.from)(state), action, rng)?;
+        Ok(((self.into)(next_state), reward, done))
+    }
+
+    fn is_terminal(&self, state: &T) -> bool {
+        self.inner.is_terminal(&(self.from)(state))
+    }
+
+    fn get_initial_state(&self) -> T {
+        (self.into)(self.inner.get_initial_state())
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.inner.get_discount_factor()
+    }
+
+    fn player_to_move(&self, state: &T) -> PlayerId {
+        self.inner.player_to_move(&(self.from)(state))
+    }
+
+    fn num_players(&self) -> usize {
+        self.inner.num_players()
+    }
+
+    fn state_key(&self, state: &T) -> Option<u64> {
+        self.inner.state_key(&(self.from)(state))
+    }
+
+    fn canonicalize(&self, state: &T) -> Option<T> {
+        self.inner
+            .canonicalize(&(self.from)(state))
+            .map(|canonical| (self.into)(canonical))
+    }
+
+    fn transition_probability(&self, state: &T, action: &A, next_state: &T) -> Option<f64> {
+        self.inner
+            .transition_probability(&(self.from)(state), action, &(self.from)(next_state))
+    }
+
+    fn action_cost(&self, state: &T, action: &A) -> f64 {
+        self.inner.action_cost(&(self.from)(state), action)
+    }
+
+    fn outcome(&self, state: &T) -> Option<Outcome> {
+        self.inner.outcome(&(self.from)(state))
+    }
+}
+
+/// Makes the wrapped model noisier, to check that plans hold up when the environment doesn't behave exactly as modelled:
+/// - with probability `action_noise`, a uniformly random action of the state is executed instead of the chosen one
+/// - every reward is perturbed by a uniformly random amount in `-reward_noise..reward_noise`
+///
+/// The noise is drawn from the search's generator, so seeded searches stay reproducible
+#[derive(Debug, Clone)]
+pub struct Noisy<M> {
+    inner: M,
+    action_noise: f64,
+    reward_noise: f64,
+}
+
+impl<M> Noisy<M> {
+    /// Starts without any noise, see [`Noisy::with_action_noise`] and [`Noisy::with_reward_noise`]
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            action_noise: 0.0,
+            reward_noise: 0.0,
+        }
+    }
+
+    pub fn with_action_noise(mut self, probability: f64) -> Self {
+        self.action_noise = probability;
+        self
+    }
+
+    pub fn with_reward_noise(mut self, amplitude: f64) -> Self {
+        self.reward_noise = amplitude;
+        self
+    }
+
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+
+    /// Returns the action to actually execute, if it isn't the chosen one
+    fn slip<S, A>(&self, state: &S, rng: &mut dyn Rng) -> Option<A>
+    where
+        M: GenerativeModel<S, A>,
+    {
+        if self.action_noise <= 0.0 || rng.gen_f64() >= self.action_noise {
+            return None;
+        }
+
+        let mut actions = self.inner.get_actions(state);
+        if actions.is_empty() {
+            return None;
+        }
+        Some(actions.swap_remove(rng.gen_range(0, actions.len())))
+    }
+
+    fn perturb(&self, reward: f64, rng: &mut dyn Rng) -> f64 {
+        if self.reward_noise <= 0.0 {
+            return reward;
+        }
+        reward + (rng.gen_f64() * 2.0 - 1.0) * self.reward_noise
+    }
+}
+
+impl<M, S, A> GenerativeModel<S, A> for Noisy<M>
+where
+    M: GenerativeModel<S, A>,
+{
+    fn get_actions(&self, state: &S) -> Vec<A> {
+        self.inner.get_actions(state)
+    }
+
+    fn execute(&self, state: &S, action: &A, rng: &mut dyn Rng) -> (S, f64, bool) {
+        let slipped = self.slip(state, rng);
+        let action = slipped.as_ref().unwrap_or(action);
+
+        let (next_state, reward, done) = self.inner.execute(state, action, rng);
+        (next_state, self.perturb(reward, rng), done)
+    }
+
+    fn try_execute(
+        &self,
+        state: &S,
+        action: &A,
+        rng: &mut dyn Rng,
+    ) -> Result<(S, f64, bool), MdpError> {
+        let slipped = self.slip(state, rng);
+        let action = slipped.as_ref().unwrap_or(action);
+
+        let (next_state, reward, done) = self.inner.try_execute(state, action, rng)?;
+        Ok((next_state, self.perturb(reward, rng), done))
+    }
+
+    fn is_terminal(&self, state: &S) -> bool {
+        self.inner.is_terminal(state)
+    }
+
+    fn get_initial_state(&self) -> S {
+        self.inner.get_initial_state()
+    }
+
+    fn get_discount_factor(&self) -> f64 {
+        self.inner.get_discount_factor()
+    }
+
+    fn player_to_move(&self, state: &S) -> PlayerId {
+        self.inner.player_to_move(state)
+    }
+
+    fn num_players(&self) -> usize {
+        self.inner.num_players()
+    }
+
+    fn state_key(&self, state: &S) -> Option<u64> {
+        self.inner.state_key(state)
+    }
+
+    fn canonicalize(&self, state: &S) -> Option<S> {
+        self.inner.canonicalize(state)
+    }
+
+    fn transition_probability(&self, state: &S, action: &A, next_state: &S) -> Option<f64> {
+        // slipping onto another action changes the probabilities of every outcome
+        if self.action_noise > 0.0 {
+            return None;
+        }
+        self.inner.transition_probability(state, action, next_state)
+    }
+
+    fn action_cost(&self, state: &S, action: &A) -> f64 {
+        self.inner.action_cost(state, action)
+    }
+
+    fn outcome(&self, state: &S) -> Option<Outcome> {
+        self.inner.outcome(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mdp::FnMdp, rand::SplitMix64};
+
+    use super::*;
+
+    #[test]
+    fn test_checked_reports_contract_violations() {
+        let mut rng = SplitMix64::seed(1);
+
+        // claims every step ends the episode, but only 3 is terminal
+        let mdp = Checked::new(FnMdp::new(
+            || 0,
+            |_state: &u32| vec![1u32, 2],
+            |state, step: &u32, _rng| (state + step, 0.0, true),
+            |state| *state >= 3,
+        ));
+
+        assert!(matches!(
+            mdp.try_execute(&0, &7, &mut rng),
+            Err(MdpError::ContractViolation(_))
+        ));
+        assert!(matches!(
+            mdp.try_execute(&3, &1, &mut rng),
+            Err(MdpError::ContractViolation(_))
+        ));
+        assert!(matches!(
+            mdp.try_execute(&0, &1, &mut rng),
+            Err(MdpError::ContractViolation(_))
+        ));
+        assert_eq!(mdp.try_execute(&1, &2, &mut rng), Ok((3, 0.0, true)));
+    }
+
+    /// Counts up by the chosen step, ending at 10 or more
+    fn counter() -> impl GenerativeModel<u32, u32> {
+        FnMdp::new(
+            || 0,
+            |_state: &u32| vec![1u32, 2],
+            |state, step: &u32, _rng| (state + step, 1.0, state + step >= 10),
+            |state| *state >= 10,
+        )
+    }
+
+    #[test]
+    fn test_step_limit_and_scaled_reward() {
+        let mut rng = SplitMix64::seed(1);
+        let mdp = StepLimit::new(ScaledReward::new(counter(), 2.0, -0.5), 2);
+
+        let state = mdp.get_initial_state();
+        assert_eq!(state, (0, 0));
+
+        let (state, reward, done) = mdp.execute(&state, &1, &mut rng);
+        assert_eq!((state, reward, done), ((1, 1), 1.5, false));
+
+        let (state, _, done) = mdp.execute(&state, &1, &mut rng);
+        assert_eq!(state, (2, 2));
+        assert!(done);
+        assert!(mdp.is_terminal(&state));
+    }
+
+    #[test]
+    fn test_map_state_and_noise() {
+        let mut rng = SplitMix64::seed(1);
+        let mdp = MapState::new(
+            counter(),
+            |state: u32| state as i64,
+            |state: &i64| *state as u32,
+        );
+        assert_eq!(mdp.execute(&8i64, &2, &mut rng), (10i64, 1.0, true));
+
+        // always slips to a random action, and perturbs rewards by up to 0.1
+        let noisy = Noisy::new(counter())
+            .with_action_noise(1.0)
+            .with_reward_noise(0.1);
+        let steps = (0..50)
+            .map(|_| noisy.execute(&0, &1, &mut rng))
+            .collect::<Vec<_>>();
+
+        assert!(steps.iter().any(|(state, ..)| *state == 2));
+        assert!(
+            steps
+                .iter()
+                .all(|(_, reward, _)| (reward - 1.0).abs() <= 0.1)
+        );
+    }
+
+    #[test]
+    fn test_cost_penalty_subtracts_action_costs() {
+        let mut rng = SplitMix64::seed(1);
+
+        struct Priced;
+
+        impl GenerativeModel<u32, u32> for Priced {
+            fn get_actions(&self, _state: &u32) -> Vec<u32> {
+                vec![1, 2]
+            }
+
+            fn execute(&self, state: &u32, step: &u32, _rng: &mut dyn Rng) -> (u32, f64, bool) {
+                (state + step, 1.0, false)
+            }
+
+            fn is_terminal(&self, _state: &u32) -> bool {
+                false
+            }
+
+            fn get_initial_state(&self) -> u32 {
+                0
+            }
+
+            fn action_cost(&self, _state: &u32, step: &u32) -> f64 {
+                *step as f64
+            }
+        }
+
+        let mdp = CostPenalty::new(Priced, 0.25);
+        assert_eq!(mdp.execute(&0, &1, &mut rng), (1, 0.75, false));
+        assert_eq!(mdp.execute(&0, &2, &mut rng), (2, 0.5, false));
+        assert_eq!(mdp.action_cost(&0, &2), 2.0);
+    }
+}
@@ -0,0 +1,107 @@
+//! Zobrist hashing, the usual way to provide [`crate::mdp::GenerativeModel::state_key`] for board games.
+//!
+//! Every (position, piece) pair gets a random 64-bit key, and the hash of a board is the XOR of the keys of its occupied positions.
+//! Since XOR is its own inverse, placing, removing or moving a piece only takes one or two XORs to update the hash,
+//! so states can carry their hash along and update it incrementally in `execute`.
+
+use crate::rand::{Rng, SplitMix64};
+
+/// Random keys for every (position, piece) pair on a board, plus one for the side to move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZobristTable {
+    pieces: usize,
+    keys: Vec<u64>,
+    side: u64,
+}
+
+impl ZobristTable {
+    /// The seed used by [`ZobristTable::new`]
+    const SEED: u64 = 0x5EED_2B0B_5157_0001;
+
+    /// Creates the keys for a board of `positions` squares/cells, each of which can hold one of `pieces` kinds of pieces.
+    /// The keys are always the same for the same dimensions, so hashes are stable across runs
+    pub fn new(positions: usize, pieces: usize) -> Self {
+        Self::with_seed(positions, pieces, Self::SEED)
+    }
+
+    /// Same as [`ZobristTable::new`] but with keys generated from the given seed
+    pub fn with_seed(positions: usize, pieces: usize, seed: u64) -> Self {
+        let mut rng = SplitMix64::seed(seed);
+        let keys = (0..positions * pieces).map(|_| rng.next_u64()).collect();
+
+        Self {
+            pieces,
+            keys,
+            side: rng.next_u64(),
+        }
+    }
+
+    /// Returns the key of `piece` standing on `position`
+    pub fn key(&self, position: usize, piece: usize) -> u64 {
+        assert!(
+            piece < self.pieces,
+            "piece must be less than {}. piece={piece}",
+            self.pieces
+        );
+        self.keys[position * self.pieces + piece]
+    }
+
+    /// Adds or removes `piece` at `position` to/from the hash (both are the same operation)
+    pub fn toggle(&self, hash: u64, position: usize, piece: usize) -> u64 {
+        hash ^ self.key(position, piece)
+    }
+
+    /// Updates the hash for `piece` moving from one position to another
+    pub fn relocate(&self, hash: u64, from: usize, to: usize, piece: usize) -> u64 {
+        hash ^ self.key(from, piece) ^ self.key(to, piece)
+    }
+
+    /// Flips the side to move in the hash
+    pub fn toggle_side(&self, hash: u64) -> u64 {
+        hash ^ self.side
+    }
+
+    /// Computes the hash of a whole board from its (position, piece) pairs, e.g. for the initial state
+    pub fn hash<I>(&self, pieces: I) -> u64
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        pieces.into_iter().fold(0, |hash, (position, piece)| {
+            self.toggle(hash, position, piece)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incremental_updates_match_full_hash() {
+        let table = ZobristTable::new(9, 2);
+
+        let mut hash = 0;
+        hash = table.toggle(hash, 4, 0);
+        hash = table.toggle(hash, 0, 1);
+        hash = table.relocate(hash, 4, 8, 0);
+
+        assert_eq!(hash, table.hash([(0, 1), (8, 0)]));
+
+        // toggling is its own inverse
+        assert_eq!(table.toggle(table.toggle(hash, 3, 1), 3, 1), hash);
+        assert_eq!(table.toggle_side(table.toggle_side(hash)), hash);
+        assert_ne!(table.toggle_side(hash), hash);
+    }
+
+    #[test]
+    fn test_keys_are_stable_and_distinct() {
+        let table = ZobristTable::new(64, 12);
+        assert_eq!(table, ZobristTable::new(64, 12));
+        assert_ne!(table, ZobristTable::with_seed(64, 12, 7));
+
+        let mut keys = table.keys.clone();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), 64 * 12);
+    }
+}